@@ -0,0 +1,131 @@
+// Copyright 2016 Jonathan Anderson <jonathan.anderson@mun.ca>
+//
+// This software was developed by BAE Systems, the University of Cambridge
+// Computer Laboratory, and Memorial University under DARPA/AFRL contract
+// FA8650-15-C-7558 ("CADETS"), as part of the DARPA Transparent Computing
+// (TC) research program.
+//
+// Licensed under the Apache License, Version 2.0,
+// <LICENSE-APACHE or http://apache.org/licenses/LICENSE-2.0>
+// or the MIT license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+
+// Compare the throughput of sequential and parallel trace decoding.
+//
+// Run with `cargo bench --bench parallel`; set `KTRACE_BENCH_RECORDS` to
+// change the size of the synthetic trace.
+
+extern crate byteorder;
+extern crate ktrace;
+
+use byteorder::{LittleEndian,WriteBytesExt};
+use std::io::{Cursor,Write};
+use std::time::{Duration,Instant};
+
+
+fn main() {
+    let count = std::env::var("KTRACE_BENCH_RECORDS").ok()
+                         .and_then(|s| s.parse().ok())
+                         .unwrap_or(500_000);
+
+    let trace = synthetic_trace(count);
+    let megabytes = trace.len() as f64 / (1024.0 * 1024.0);
+    println!["{} records, {:.1} MiB", count, megabytes];
+
+    let (records, t) = time(|| {
+        ktrace::parse::<LittleEndian>(&mut Cursor::new(&trace))
+               .expect("failed to parse trace")
+               .len()
+    });
+    assert_eq![records, count];
+    report("sequential", megabytes, t);
+
+    let cpus = std::thread::available_parallelism()
+                           .map(|n| n.get())
+                           .unwrap_or(4);
+
+    let mut threads = 1;
+    while threads <= cpus {
+        let input = Cursor::new(trace.clone());
+        let (records, t) = time(|| {
+            ktrace::parse_parallel::<LittleEndian, _>(input, threads)
+                   .collect::<Result<Vec<_>, _>>()
+                   .expect("failed to parse trace")
+                   .len()
+        });
+        assert_eq![records, count];
+        report(&format!["parallel ({} threads)", threads], megabytes, t);
+
+        threads *= 2;
+    }
+}
+
+fn time<F, T>(f: F) -> (T, Duration)
+    where F: FnOnce() -> T
+{
+    let start = Instant::now();
+    let result = f();
+    (result, start.elapsed())
+}
+
+fn report(name: &str, megabytes: f64, t: Duration) {
+    let seconds = t.as_secs_f64();
+    println!["{:24} {:8.3} s {:8.1} MiB/s", name, seconds, megabytes / seconds];
+}
+
+/// A trace of `read(2)` calls, each with a `KTR_GENIO` record and a return.
+fn synthetic_trace(count: usize) -> Vec<u8> {
+    let mut trace = vec![];
+    let data = [0x61; 64];
+
+    for i in 0..count {
+        let usec = i as i64;
+
+        match i % 3 {
+            0 => {
+                let mut payload = vec![];
+                payload.write_u16::<LittleEndian>(3).unwrap();
+                payload.write_u16::<LittleEndian>(3).unwrap();
+                payload.write_u32::<LittleEndian>(0).unwrap();
+                for arg in &[3, 0x2000, 64] {
+                    payload.write_u64::<LittleEndian>(*arg).unwrap();
+                }
+                record(&mut trace, 1, usec, &payload);
+            },
+            1 => {
+                let mut payload = vec![];
+                payload.write_i32::<LittleEndian>(3).unwrap();
+                payload.write_i32::<LittleEndian>(0).unwrap();
+                payload.write_all(&data).unwrap();
+                record(&mut trace, 4, usec, &payload);
+            },
+            _ => {
+                let mut payload = vec![];
+                payload.write_u16::<LittleEndian>(3).unwrap();
+                payload.write_u16::<LittleEndian>(0).unwrap();
+                payload.write_u32::<LittleEndian>(0).unwrap();
+                payload.write_u64::<LittleEndian>(64).unwrap();
+                record(&mut trace, 2, usec, &payload);
+            },
+        }
+    }
+
+    trace
+}
+
+fn record(trace: &mut Vec<u8>, record_type: u16, usec: i64, payload: &[u8]) {
+    let mut command = [0; 20];
+    command[..3].copy_from_slice(b"cat");
+
+    trace.write_u32::<LittleEndian>(payload.len() as u32).unwrap();
+    trace.write_u16::<LittleEndian>(record_type).unwrap();
+    trace.write_u16::<LittleEndian>(0).unwrap();
+    trace.write_u32::<LittleEndian>(100).unwrap();
+    trace.write_all(&command).unwrap();
+    trace.write_i64::<LittleEndian>(1000 + usec / 1_000_000).unwrap();
+    trace.write_i64::<LittleEndian>(usec % 1_000_000).unwrap();
+    trace.write_i64::<LittleEndian>(100100).unwrap();
+    trace.write_all(payload).unwrap();
+}
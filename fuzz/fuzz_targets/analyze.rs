@@ -0,0 +1,40 @@
+// Copyright 2016 Jonathan Anderson <jonathan.anderson@mun.ca>
+//
+// This software was developed by BAE Systems, the University of Cambridge
+// Computer Laboratory, and Memorial University under DARPA/AFRL contract
+// FA8650-15-C-7558 ("CADETS"), as part of the DARPA Transparent Computing
+// (TC) research program.
+//
+// Licensed under the Apache License, Version 2.0,
+// <LICENSE-APACHE or http://apache.org/licenses/LICENSE-2.0>
+// or the MIT license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+
+#![no_main]
+
+use byteorder::NativeEndian;
+use ktrace::Os;
+use libfuzzer_sys::fuzz_target;
+use std::io;
+
+#[path = "../../tests/common/analyses.rs"]
+mod analyses;
+
+// Analyses of whatever records can be decoded, from a FreeBSD trace or
+// from any of the foreign formats that we import
+fuzz_target!(|data: &[u8]| {
+    if let Ok(records) = ktrace::parse::<NativeEndian>(&mut &data[..]) {
+        analyses::analyze::<NativeEndian>(records);
+    }
+
+    for &os in &[Os::OpenBSD, Os::NetBSD, Os::Darwin, Os::Linux] {
+        if let Ok(entries) = ktrace::parse_os::<NativeEndian>(data, os) {
+            analyses::analyze::<NativeEndian>(analyses::common(entries));
+        }
+    }
+
+    let mut redactor = ktrace::Redactor::<NativeEndian>::new(Default::default());
+    let _ = ktrace::redact(&mut &data[..], &mut io::sink(), &mut redactor);
+});
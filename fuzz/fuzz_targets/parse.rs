@@ -0,0 +1,33 @@
+// Copyright 2016 Jonathan Anderson <jonathan.anderson@mun.ca>
+//
+// This software was developed by BAE Systems, the University of Cambridge
+// Computer Laboratory, and Memorial University under DARPA/AFRL contract
+// FA8650-15-C-7558 ("CADETS"), as part of the DARPA Transparent Computing
+// (TC) research program.
+//
+// Licensed under the Apache License, Version 2.0,
+// <LICENSE-APACHE or http://apache.org/licenses/LICENSE-2.0>
+// or the MIT license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+
+#![no_main]
+
+use byteorder::NativeEndian;
+use libfuzzer_sys::fuzz_target;
+
+// Whole traces: parsing must fail cleanly, and the streaming and
+// zero-copy parsers must agree.
+fuzz_target!(|data: &[u8]| {
+    let parsed = ktrace::parse::<NativeEndian>(&mut &data[..]);
+    let sliced: Vec<_> = ktrace::parse_slice::<NativeEndian>(data).collect();
+
+    if let Ok(records) = parsed {
+        assert_eq!(records.len(), sliced.len());
+        for (r, s) in records.iter().zip(&sliced) {
+            let (_, ref s) = *s.as_ref().unwrap();
+            assert_eq!(r.1.is_ok(), s.is_ok());
+        }
+    }
+});
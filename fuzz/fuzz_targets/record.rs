@@ -0,0 +1,41 @@
+// Copyright 2016 Jonathan Anderson <jonathan.anderson@mun.ca>
+//
+// This software was developed by BAE Systems, the University of Cambridge
+// Computer Laboratory, and Memorial University under DARPA/AFRL contract
+// FA8650-15-C-7558 ("CADETS"), as part of the DARPA Transparent Computing
+// (TC) research program.
+//
+// Licensed under the Apache License, Version 2.0,
+// <LICENSE-APACHE or http://apache.org/licenses/LICENSE-2.0>
+// or the MIT license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+
+#![no_main]
+
+use byteorder::{BigEndian,LittleEndian};
+use ktrace::{Header,Record,RecordRef,RecordType};
+use libfuzzer_sys::fuzz_target;
+
+// Individual headers and record payloads of every type, in both byte orders
+fuzz_target!(|data: &[u8]| {
+    if data.len() >= 56 {
+        let mut raw = [0; 56];
+        raw.copy_from_slice(&data[..56]);
+        let _ = Header::parse::<LittleEndian>(&raw);
+        let _ = Header::parse::<BigEndian>(&raw);
+    }
+
+    let (t, payload) = match data.split_first() {
+        Some((&t, payload)) => (t as u16 % 14 + 1, payload),
+        None => return,
+    };
+    let t = RecordType::from_u16(t).unwrap();
+
+    if let Ok(r) = Record::parse::<LittleEndian>(payload, &t) {
+        let _ = r.to_string();
+    }
+    let _ = Record::parse::<BigEndian>(payload, &t);
+    let _ = RecordRef::parse::<LittleEndian>(payload, &t);
+});
@@ -0,0 +1,305 @@
+// Copyright 2016 Jonathan Anderson <jonathan.anderson@mun.ca>
+//
+// This software was developed by BAE Systems, the University of Cambridge
+// Computer Laboratory, and Memorial University under DARPA/AFRL contract
+// FA8650-15-C-7558 ("CADETS"), as part of the DARPA Transparent Computing
+// (TC) research program.
+//
+// Licensed under the Apache License, Version 2.0,
+// <LICENSE-APACHE or http://apache.org/licenses/LICENSE-2.0>
+// or the MIT license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+
+use std::collections::HashMap;
+use std::fmt;
+use ::{Header,Record,Result,linux,syscalls};
+
+pub const SV_ABI_MASK: u32 = 0xff;
+pub const SV_ABI_LINUX: u32 = 3;
+pub const SV_ABI_FREEBSD: u32 = 9;
+pub const SV_ABI_CLOUDABI: u32 = 17;
+pub const SV_ABI_UNDEF: u32 = 255;
+
+pub const SV_ILP32: u32 = 0x000100;
+pub const SV_LP64: u32 = 0x000200;
+pub const SV_IA32: u32 = 0x004000;
+pub const SV_AOUT: u32 = 0x008000;
+pub const SV_SHP: u32 = 0x010000;
+pub const SV_CAPSICUM: u32 = 0x020000;
+pub const SV_TIMEKEEP: u32 = 0x040000;
+pub const SV_ASLR: u32 = 0x080000;
+pub const SV_RNG_SEED_VER: u32 = 0x100000;
+pub const SV_SIG_DISCIGN: u32 = 0x200000;
+pub const SV_SIG_WAITNDQ: u32 = 0x400000;
+pub const SV_DSO_SIG: u32 = 0x800000;
+
+/// Linux `open(2)` flags (other than the access mode) and their FreeBSD
+/// equivalents
+const LINUX_OPEN_FLAGS: [(u64, u64); 14] = [
+    (0x0000_0040, 0x0000_0200),     // O_CREAT
+    (0x0000_0080, 0x0000_0800),     // O_EXCL
+    (0x0000_0100, 0x0000_8000),     // O_NOCTTY
+    (0x0000_0200, 0x0000_0400),     // O_TRUNC
+    (0x0000_0400, 0x0000_0008),     // O_APPEND
+    (0x0000_0800, 0x0000_0004),     // O_NONBLOCK
+    (0x0000_1000, 0x0100_0000),     // O_DSYNC
+    (0x0000_2000, 0x0000_0040),     // FASYNC
+    (0x0000_4000, 0x0001_0000),     // O_DIRECT
+    (0x0001_0000, 0x0002_0000),     // O_DIRECTORY
+    (0x0002_0000, 0x0000_0100),     // O_NOFOLLOW
+    (0x0008_0000, 0x0010_0000),     // O_CLOEXEC
+    (0x0010_0000, 0x0000_0080),     // __O_SYNC
+    (0x0020_0000, 0x0040_0000),     // O_PATH
+];
+
+const O_ACCMODE: u64 = 0x0003;
+
+/// Linux socket type flags and their FreeBSD equivalents
+const LINUX_SOCK_FLAGS: [(u64, u64); 2] = [
+    (0x0008_0000, 0x1000_0000),     // SOCK_CLOEXEC
+    (0x0000_0800, 0x2000_0000),     // SOCK_NONBLOCK
+];
+
+const LINUX_SOCK_TYPE_MASK: u64 = 0xf;
+
+/// Linux `fcntl(2)` commands and their FreeBSD equivalents
+const LINUX_FCNTL: [(u64, u64); 11] = [
+    (0, 0),                         // F_DUPFD
+    (1, 1),                         // F_GETFD
+    (2, 2),                         // F_SETFD
+    (3, 3),                         // F_GETFL
+    (4, 4),                         // F_SETFL
+    (5, 11),                        // F_GETLK
+    (6, 12),                        // F_SETLK
+    (7, 13),                        // F_SETLKW
+    (8, 6),                         // F_SETOWN
+    (9, 5),                         // F_GETOWN
+    (1030, 17),                     // F_DUPFD_CLOEXEC
+];
+
+const FLAG_NAMES: [(u32, &str); 12] = [
+    (SV_ILP32, "SV_ILP32"),
+    (SV_LP64, "SV_LP64"),
+    (SV_IA32, "SV_IA32"),
+    (SV_AOUT, "SV_AOUT"),
+    (SV_SHP, "SV_SHP"),
+    (SV_CAPSICUM, "SV_CAPSICUM"),
+    (SV_TIMEKEEP, "SV_TIMEKEEP"),
+    (SV_ASLR, "SV_ASLR"),
+    (SV_RNG_SEED_VER, "SV_RNG_SEED_VER"),
+    (SV_SIG_DISCIGN, "SV_SIG_DISCIGN"),
+    (SV_SIG_WAITNDQ, "SV_SIG_WAITNDQ"),
+    (SV_DSO_SIG, "SV_DSO_SIG"),
+];
+
+
+/// A system call convention that a traced process can run under
+///
+/// FreeBSD records each new process' ABI (the `sv_flags` of its
+/// `sysentvec`) in a `KTR_PROCCTOR` record; system call numbers must be
+/// interpreted accordingly.
+#[derive(Clone,Copy,Debug,Default,Eq,Hash,PartialEq)]
+pub enum Abi {
+    #[default]
+    FreeBSD,
+    Linux,
+    LinuxI386,
+}
+
+/// The `sv_flags` of a process' `sysentvec`, from a `KTR_PROCCTOR` record
+///
+/// The low byte identifies the ABI family (an `ELFOSABI_*` value) and the
+/// rest are `SV_*` flags such as `SV_LP64` or `SV_CAPSICUM`.
+#[derive(Clone,Copy,Debug,Default,Eq,Hash,PartialEq)]
+pub struct AbiFlags(pub u32);
+
+/// The operating system whose system calls a process makes
+#[derive(Clone,Copy,Debug,Eq,Hash,PartialEq)]
+pub enum AbiFamily {
+    FreeBSD,
+    Linux,
+    CloudABI,
+    Undefined,
+    Other(u8),
+}
+
+/// Tracks the ABI of each process in a trace
+#[derive(Default)]
+pub struct AbiTracker {
+    processes: HashMap<u32, Abi>,
+}
+
+
+impl Abi {
+    /// The ABI described by a `ProcessCreation` record's flags.
+    pub fn from_flags(flags: AbiFlags) -> Abi {
+        match flags.family() {
+            AbiFamily::Linux if flags.pointer_width() == Some(32) => Abi::LinuxI386,
+            AbiFamily::Linux => Abi::Linux,
+            _ => Abi::FreeBSD,
+        }
+    }
+
+    /// `sv_flags` describing this ABI, as in a `ProcessCreation` record.
+    pub fn flags(self) -> AbiFlags {
+        AbiFlags(match self {
+            Abi::FreeBSD => SV_ABI_FREEBSD | SV_LP64,
+            Abi::Linux => SV_ABI_LINUX | SV_LP64,
+            Abi::LinuxI386 => SV_ABI_LINUX | SV_ILP32 | SV_IA32,
+        })
+    }
+
+    /// Translate `open(2)` flags passed under this ABI into FreeBSD's `O_*`
+    /// flags, dropping any that FreeBSD doesn't have.
+    pub fn open_flags(self, flags: u64) -> u64 {
+        match self {
+            Abi::FreeBSD => flags,
+            Abi::Linux | Abi::LinuxI386 => {
+                LINUX_OPEN_FLAGS.iter()
+                                .filter(|&&(linux, _)| flags & linux != 0)
+                                .fold(flags & O_ACCMODE, |f, &(_, freebsd)| f | freebsd)
+            },
+        }
+    }
+
+    /// Translate a socket type passed to `socket(2)`, `socketpair(2)` or
+    /// `accept4(2)` under this ABI into FreeBSD's, including its
+    /// `SOCK_CLOEXEC` and `SOCK_NONBLOCK` flags.
+    pub fn sock_type(self, sock_type: u64) -> u64 {
+        match self {
+            Abi::FreeBSD => sock_type,
+            Abi::Linux | Abi::LinuxI386 => {
+                LINUX_SOCK_FLAGS.iter()
+                                .filter(|&&(linux, _)| sock_type & linux != 0)
+                                .fold(sock_type & LINUX_SOCK_TYPE_MASK,
+                                      |t, &(_, freebsd)| t | freebsd)
+            },
+        }
+    }
+
+    /// Translate an `fcntl(2)` command passed under this ABI into FreeBSD's,
+    /// if FreeBSD has an equivalent.
+    pub fn fcntl_command(self, command: u64) -> Option<u64> {
+        match self {
+            Abi::FreeBSD => Some(command),
+            Abi::Linux | Abi::LinuxI386 => {
+                LINUX_FCNTL.iter()
+                           .find(|&&(linux, _)| linux == command)
+                           .map(|&(_, freebsd)| freebsd)
+            },
+        }
+    }
+
+    pub fn syscall_name(self, number: u16) -> Result<String> {
+        match self {
+            Abi::FreeBSD => syscalls::name(number as usize),
+            Abi::Linux => linux::syscall_name(number as usize),
+            Abi::LinuxI386 => linux::syscall_name_i386(number as usize),
+        }
+    }
+}
+
+impl AbiFlags {
+    pub fn bits(self) -> u32 {
+        self.0
+    }
+
+    /// Whether all of the `SV_*` flags in `flags` are set.
+    pub fn contains(self, flags: u32) -> bool {
+        self.0 & flags == flags
+    }
+
+    pub fn family(self) -> AbiFamily {
+        match self.0 & SV_ABI_MASK {
+            SV_ABI_FREEBSD => AbiFamily::FreeBSD,
+            SV_ABI_LINUX => AbiFamily::Linux,
+            SV_ABI_CLOUDABI => AbiFamily::CloudABI,
+            SV_ABI_UNDEF => AbiFamily::Undefined,
+            x => AbiFamily::Other(x as u8),
+        }
+    }
+
+    /// The size of a pointer (in bits), if the flags specify one.
+    pub fn pointer_width(self) -> Option<u8> {
+        if self.contains(SV_LP64) {
+            Some(64)
+        } else if self.contains(SV_ILP32) {
+            Some(32)
+        } else {
+            None
+        }
+    }
+
+    /// The ABI whose system call table the process uses.
+    pub fn abi(self) -> Abi {
+        Abi::from_flags(self)
+    }
+
+    /// The names of the `SV_*` flags that are set, with any unknown bits
+    /// given in hex.
+    pub fn names(self) -> Vec<String> {
+        let mut names: Vec<String> = FLAG_NAMES.iter()
+                                               .filter(|&&(bit, _)| self.contains(bit))
+                                               .map(|&(_, name)| name.to_string())
+                                               .collect();
+
+        let known = FLAG_NAMES.iter().fold(SV_ABI_MASK, |all, &(bit, _)| all | bit);
+        if self.0 & !known != 0 {
+            names.push(format!["0x{:x}", self.0 & !known]);
+        }
+
+        names
+    }
+}
+
+impl From<u32> for AbiFlags {
+    fn from(flags: u32) -> AbiFlags {
+        AbiFlags(flags)
+    }
+}
+
+impl fmt::Display for AbiFamily {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            AbiFamily::FreeBSD => write![f, "SV_ABI_FREEBSD"],
+            AbiFamily::Linux => write![f, "SV_ABI_LINUX"],
+            AbiFamily::CloudABI => write![f, "SV_ABI_CLOUDABI"],
+            AbiFamily::Undefined => write![f, "SV_ABI_UNDEF"],
+            AbiFamily::Other(x) => write![f, "SV_ABI({})", x],
+        }
+    }
+}
+
+/// e.g., `SV_ABI_FREEBSD|SV_LP64|SV_SHP`
+impl fmt::Display for AbiFlags {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write![f, "{}", self.family()]?;
+        for name in self.names() {
+            write![f, "|{}", name]?;
+        }
+
+        Ok(())
+    }
+}
+
+impl AbiTracker {
+    pub fn new() -> AbiTracker {
+        AbiTracker::default()
+    }
+
+    /// Account for the next record, returning the ABI of its process.
+    pub fn record(&mut self, header: &Header, record: &Record) -> Abi {
+        if let Record::ProcessCreation{flags} = *record {
+            self.processes.insert(header.pid, flags.abi());
+        }
+
+        self.abi(header.pid)
+    }
+
+    /// The ABI of a process (FreeBSD's, unless we have seen otherwise).
+    pub fn abi(&self, pid: u32) -> Abi {
+        self.processes.get(&pid).cloned().unwrap_or_default()
+    }
+}
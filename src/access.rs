@@ -0,0 +1,288 @@
+// Copyright 2016 Jonathan Anderson <jonathan.anderson@mun.ca>
+//
+// This software was developed by BAE Systems, the University of Cambridge
+// Computer Laboratory, and Memorial University under DARPA/AFRL contract
+// FA8650-15-C-7558 ("CADETS"), as part of the DARPA Transparent Computing
+// (TC) research program.
+//
+// Licensed under the Apache License, Version 2.0,
+// <LICENSE-APACHE or http://apache.org/licenses/LICENSE-2.0>
+// or the MIT license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::collections::{BTreeMap,BTreeSet};
+use std::fmt;
+use std::io::{self,Write};
+use ::{ByteString,Call,CallTracker,FdTracker,Header,Record,json};
+
+const O_ACCMODE: u64 = 0x0003;
+const O_WRONLY: u64 = 0x0001;
+const O_RDWR: u64 = 0x0002;
+const O_CREAT: u64 = 0x0200;
+const O_TRUNC: u64 = 0x0400;
+
+
+/// Something that a system call did with a file
+#[derive(Clone,Copy,Debug,Eq,Hash,Ord,PartialEq,PartialOrd)]
+pub enum Access {
+    Read,
+    Write,
+    Create,
+
+    /// looked up metadata or checked access (`stat`, `access`, `readlink`…)
+    Stat,
+
+    /// changed metadata (`chmod`, `chown`, `utimes`…)
+    Attributes,
+
+    Exec,
+    Unlink,
+    Rename,
+    Chdir,
+
+    /// any other call that looks up a path
+    Other,
+}
+
+/// Everything that one process did with one file
+#[derive(Clone,Debug,Default)]
+pub struct FileAccess {
+    pub accesses: BTreeSet<Access>,
+
+    /// the system calls that accessed the file
+    pub syscalls: BTreeSet<String>,
+
+    pub calls: u64,
+    pub failures: u64,
+}
+
+/// The files that one process accessed
+#[derive(Clone,Debug,Default)]
+pub struct ProcessAccess {
+    pub pid: u32,
+    pub command: ByteString,
+
+    /// accesses, by resolved path
+    pub files: BTreeMap<ByteString, FileAccess>,
+}
+
+/// Summarises the files that each process in a trace touched, and how
+///
+/// Paths are resolved against the process' working directory or the
+/// directory descriptor passed to `*at` calls (see `FdTracker::resolve`).
+/// Failed lookups are included, since a policy (e.g., a jail, `unveil`
+/// list or Capsicum sandbox) must account for the paths that a program
+/// tries to use as well as those that it finds.
+#[derive(Default)]
+pub struct FileAccesses {
+    pub processes: BTreeMap<u32, ProcessAccess>,
+
+    calls: CallTracker,
+    fds: FdTracker,
+}
+
+
+impl Access {
+    /// The accesses made by a call to the path in its `n`th `KTR_NAMEI`
+    /// record.
+    pub fn of(call: &Call, n: usize) -> Vec<Access> {
+        let arg = |n: usize| call.args.get(n).cloned().unwrap_or(0);
+
+        let open = |flags: u64| {
+            let flags = call.abi.open_flags(flags);
+            let mut v = match flags & O_ACCMODE {
+                O_WRONLY => vec![Access::Write],
+                O_RDWR => vec![Access::Read, Access::Write],
+                _ => vec![Access::Read],
+            };
+            if flags & O_CREAT != 0 {
+                v.push(Access::Create);
+            }
+            if flags & O_TRUNC != 0 && !v.contains(&Access::Write) {
+                v.push(Access::Write);
+            }
+            v
+        };
+
+        match call.name().as_str() {
+            "open" => open(arg(1)),
+            "openat" => open(arg(2)),
+            // openat2(2)'s flags are in a struct open_how, which isn't traced
+            "openat2" => vec![Access::Other],
+            "creat" => vec![Access::Write, Access::Create],
+
+            "stat" | "lstat" | "fstatat" | "nstat" | "nlstat" | "access" |
+            "eaccess" | "faccessat" | "readlink" | "readlinkat" | "pathconf" |
+            "lpathconf" | "statfs" | "getfh" | "lgetfh" | "getfhat" |
+            "__realpathat" | "__acl_get_file" | "__acl_get_link" |
+            "extattr_get_file" | "extattr_get_link" | "extattr_list_file" |
+            "extattr_list_link" | "newfstatat" | "fstatat64" | "statx" |
+            "faccessat2" => vec![Access::Stat],
+
+            "chmod" | "lchmod" | "fchmodat" | "chown" | "lchown" |
+            "fchownat" | "utimes" | "lutimes" | "futimesat" | "utimensat" |
+            "chflags" | "lchflags" | "chflagsat" | "__acl_set_file" |
+            "__acl_set_link" | "extattr_set_file" | "extattr_set_link" |
+            "extattr_delete_file" | "extattr_delete_link" |
+            "fchmodat2" => vec![Access::Attributes],
+
+            "truncate" => vec![Access::Write],
+
+            "execve" | "__mac_execve" => vec![Access::Exec],
+
+            "unlink" | "unlinkat" | "funlinkat" | "rmdir" => vec![Access::Unlink],
+            "rename" | "renameat" | "renameat2" => vec![Access::Rename],
+
+            "mkdir" | "mkdirat" | "mkfifo" | "mkfifoat" | "mknod" |
+            "mknodat" | "symlink" | "symlinkat" => vec![Access::Create],
+
+            // link(2) looks up an existing file and creates a new name
+            "link" | "linkat" if n == 0 => vec![Access::Stat],
+            "link" | "linkat" => vec![Access::Create],
+
+            "chdir" | "chroot" => vec![Access::Chdir],
+
+            _ => vec![Access::Other],
+        }
+    }
+}
+
+impl fmt::Display for Access {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write![f, "{}", match *self {
+            Access::Read => "read",
+            Access::Write => "write",
+            Access::Create => "create",
+            Access::Stat => "stat",
+            Access::Attributes => "attributes",
+            Access::Exec => "exec",
+            Access::Unlink => "unlink",
+            Access::Rename => "rename",
+            Access::Chdir => "chdir",
+            Access::Other => "other",
+        }]
+    }
+}
+
+
+impl FileAccesses {
+    pub fn new() -> FileAccesses {
+        FileAccesses::default()
+    }
+
+    /// Account for the next record in a trace.
+    pub fn record(&mut self, header: &Header, record: &Record) {
+        // Resolve a call's paths before the descriptor tracker applies its
+        // effects (e.g., a chdir(2) doesn't change how its own path resolves).
+        if let Some(call) = self.calls.record(header, record) {
+            self.call(header, &call);
+        }
+
+        self.fds.record(header, record);
+    }
+
+    /// Write the files accessed by each process, with the accesses that
+    /// it made (and the number of calls that failed).
+    pub fn write_text(&self, out: &mut dyn Write) -> io::Result<()> {
+        for (i, p) in self.processes.values().enumerate() {
+            if i > 0 {
+                writeln![out]?;
+            }
+
+            writeln![out, "PID {} ({}):", p.pid, p.command]?;
+            for (path, f) in &p.files {
+                write![out, "  {} [{}]", path, join(&f.accesses, ",")]?;
+                if f.failures > 0 {
+                    write![out, " ({} of {} calls failed)", f.failures, f.calls]?;
+                }
+                writeln![out]?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Write one JSON object per process and file.
+    pub fn write_json_lines(&self, out: &mut dyn Write) -> io::Result<()> {
+        for p in self.processes.values() {
+            for (path, f) in &p.files {
+                let list = |items: Vec<String>| items.iter()
+                                                     .map(|s| json::string(s))
+                                                     .collect::<Vec<_>>()
+                                                     .join(",");
+
+                writeln![out, "{{\"pid\":{},\"command\":{},\"path\":{},\
+                               \"accesses\":[{}],\"syscalls\":[{}],\
+                               \"calls\":{},\"failures\":{}}}",
+                         p.pid, json::string(&p.command.to_string()),
+                         json::string(&path.to_string()),
+                         list(f.accesses.iter().map(Access::to_string).collect()),
+                         list(f.syscalls.iter().cloned().collect()),
+                         f.calls, f.failures]?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn call(&mut self, header: &Header, call: &Call) {
+        if call.paths.is_empty() {
+            return;
+        }
+
+        let name = call.name();
+        let p = self.processes.entry(call.pid).or_insert_with(|| ProcessAccess {
+            pid: call.pid,
+            ..ProcessAccess::default()
+        });
+
+        // A process' command changes when it calls execve(2).
+        if p.command != header.command {
+            p.command = header.command.clone();
+        }
+
+        for (n, path) in call.paths.iter().enumerate() {
+            let path = self.fds.resolve(call.pid, dirfd(&name, call, n), path);
+            let f = p.files.entry(path).or_default();
+
+            f.accesses.extend(Access::of(call, n));
+            f.syscalls.insert(name.clone());
+            f.calls += 1;
+            if !call.succeeded() {
+                f.failures += 1;
+            }
+        }
+    }
+}
+
+
+/// The directory descriptor that the `n`th path looked up by a call is
+/// relative to (`None` for calls that use the working directory).
+pub(crate) fn dirfd(name: &str, call: &Call, n: usize) -> Option<i32> {
+    match name {
+        "openat" | "fstatat" | "faccessat" | "readlinkat" | "fchmodat" |
+        "fchownat" | "futimesat" | "utimensat" | "chflagsat" | "mkdirat" |
+        "mkfifoat" | "mknodat" | "unlinkat" | "funlinkat" | "getfhat" |
+        "__realpathat" => call.fd(0),
+
+        // Linux (e.g., newfstatat(dirfd, path, buf, flags))
+        "newfstatat" | "fstatat64" | "statx" | "faccessat2" | "openat2" |
+        "fchmodat2" => call.fd(0),
+
+        // renameat(fromfd, from, tofd, to), linkat(fd1, path1, fd2, path2)
+        "renameat" | "renameat2" | "linkat" => call.fd(if n == 0 { 0 } else { 2 }),
+
+        // symlinkat(target, fd, path): only the new link is looked up
+        "symlinkat" => call.fd(1),
+
+        _ => None,
+    }
+}
+
+fn join<T: fmt::Display>(items: &BTreeSet<T>, separator: &str) -> String {
+    items.iter()
+         .map(T::to_string)
+         .collect::<Vec<_>>()
+         .join(separator)
+}
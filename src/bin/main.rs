@@ -14,44 +14,739 @@
 extern crate byteorder;
 extern crate clap;
 extern crate ktrace;
+extern crate nix;
 
-use byteorder::NativeEndian;
-use ktrace::Error;
+use byteorder::{BigEndian,NativeEndian};
+use ktrace::{Entry,Error,Header,Os,Record};
 use std::fs::File;
+use std::io::{Read,Write};
+
+type Records = Vec<(Header, Result<Record, Error>)>;
 
 
 fn main() {
     let version = option_env!["CARGO_PKG_VERSION"].unwrap_or("unknown");
 
+    let input = || clap::Arg::with_name("INPUT")
+                             .help("Binary ktrace dump file")
+                             .required(true);
+    let jobs = || clap::Arg::with_name("jobs")
+                            .short("j")
+                            .long("jobs")
+                            .takes_value(true)
+                            .help("Decode records using this many threads");
+    let os = || clap::Arg::with_name("os")
+                          .long("os")
+                          .takes_value(true)
+                          .possible_values(&["auto", "freebsd", "openbsd", "netbsd", "darwin",
+                                            "linux"])
+                          .help("Operating system that wrote the traces");
+
     let args = clap::App::new("ktrace")
                              .version(version)
-                             .arg(clap::Arg::with_name("INPUT")
-                                  .help("Binary ktrace dump file")
-                                  .required(true))
+                             .setting(clap::AppSettings::SubcommandsNegateReqs)
+                             .arg(input())
+                             .arg(clap::Arg::with_name("fds")
+                                  .long("fds")
+                                  .help("Show what file descriptors refer to"))
+                             .arg(clap::Arg::with_name("follow")
+                                  .short("f")
+                                  .long("follow")
+                                  .help("Wait for more records at the end of the trace"))
+                             .arg(jobs())
+                             .arg(os())
+                             .arg(clap::Arg::with_name("pid")
+                                  .long("pid")
+                                  .takes_value(true)
+                                  .help("Only show records from this process"))
+                             .arg(clap::Arg::with_name("since")
+                                  .long("since")
+                                  .takes_value(true)
+                                  .help("Start at this time (seconds since the epoch)"))
+                             .arg(clap::Arg::with_name("start")
+                                  .long("start")
+                                  .takes_value(true)
+                                  .help("Start at this record number"))
+                             .arg(clap::Arg::with_name("count")
+                                  .long("count")
+                                  .takes_value(true)
+                                  .help("Show at most this many records"))
+                             .subcommand(clap::SubCommand::with_name("index")
+                                  .about("Write an index for random access to a trace")
+                                  .arg(input())
+                                  .arg(clap::Arg::with_name("OUTPUT")
+                                       .help("Index file to write (default: INPUT.idx)")))
+                             .subcommand(clap::SubCommand::with_name("extract")
+                                  .about("Reassemble I/O into per-descriptor streams")
+                                  .arg(input())
+                                  .arg(clap::Arg::with_name("DIR")
+                                       .help("Directory to write streams to")
+                                       .required(true)))
+                             .subcommand(clap::SubCommand::with_name("stats")
+                                  .about("Summarize system call counts, errors and latencies")
+                                  .arg(input())
+                                  .arg(clap::Arg::with_name("json")
+                                       .long("json")
+                                       .help("Write statistics as JSON"))
+                                  .arg(clap::Arg::with_name("per-process")
+                                       .short("p")
+                                       .long("per-process")
+                                       .help("Also show statistics for each process")))
+                             .subcommand(clap::SubCommand::with_name("offcpu")
+                                  .about("Summarize the time that threads spend off the CPU")
+                                  .arg(input())
+                                  .arg(clap::Arg::with_name("folded")
+                                       .long("folded")
+                                       .help("Write folded syscall;wmesg stacks \
+                                              (for flame graphs) instead of tables")))
+                             .subcommand(clap::SubCommand::with_name("faults")
+                                  .about("Summarize page faults by mapped file or region")
+                                  .arg(input()))
+                             .subcommand(clap::SubCommand::with_name("sysctls")
+                                  .about("List the sysctl OIDs that each program queried or set")
+                                  .arg(input()))
+                             .subcommand(clap::SubCommand::with_name("files")
+                                  .about("List the files that each process accessed, and how")
+                                  .arg(input())
+                                  .arg(clap::Arg::with_name("json")
+                                       .long("json")
+                                       .help("Write one JSON object per process and file")))
+                             .subcommand(clap::SubCommand::with_name("diff")
+                                  .about("Compare the system calls made in two traces")
+                                  .arg(clap::Arg::with_name("keep-addresses")
+                                       .long("keep-addresses")
+                                       .help("Compare values that look like addresses"))
+                                  .arg(clap::Arg::with_name("keep-fds")
+                                       .long("keep-fds")
+                                       .help("Compare file descriptor numbers"))
+                                  .arg(clap::Arg::with_name("keep-pids")
+                                       .long("keep-pids")
+                                       .help("Compare returned process IDs"))
+                                  .arg(clap::Arg::with_name("ignore-args")
+                                       .long("ignore-args")
+                                       .help("Ignore all system call arguments"))
+                                  .arg(clap::Arg::with_name("ignore-paths")
+                                       .long("ignore-paths")
+                                       .help("Ignore the paths looked up by system calls"))
+                                  .arg(jobs())
+                                  .arg(os())
+                                  .arg(clap::Arg::with_name("BEFORE")
+                                       .help("Trace of the original run")
+                                       .required(true))
+                                  .arg(clap::Arg::with_name("AFTER")
+                                       .help("Trace to compare it with")
+                                       .required(true)))
+                             .subcommand(clap::SubCommand::with_name("export")
+                                  .about("Convert a trace for use by other tools")
+                                  .arg(clap::Arg::with_name("format")
+                                       .short("f")
+                                       .long("format")
+                                       .takes_value(true)
+                                       .possible_values(&["chrome", "dot", "jsonl", "pcapng"])
+                                       .required(true)
+                                       .help("Output format (chrome: Chrome Trace Event JSON, \
+                                              for Perfetto or chrome://tracing; \
+                                              dot: provenance graph for GraphViz; \
+                                              jsonl: provenance graph as JSON lines; \
+                                              pcapng: socket I/O as synthetic packets \
+                                              for Wireshark)"))
+                                  .arg(input())
+                                  .arg(clap::Arg::with_name("OUTPUT")
+                                       .help("File to write to")
+                                       .required(true)))
+                             .subcommand(clap::SubCommand::with_name("merge")
+                                  .about("Interleave several traces in timestamp order")
+                                  .arg(clap::Arg::with_name("output")
+                                       .short("o")
+                                       .long("output")
+                                       .takes_value(true)
+                                       .help("Write a binary trace here instead of \
+                                              printing records"))
+                                  .arg(clap::Arg::with_name("INPUT")
+                                       .help("Binary ktrace dump files")
+                                       .required(true)
+                                       .multiple(true)))
+                             .subcommand(clap::SubCommand::with_name("redact")
+                                  .about("Remove paths, data, commands and IDs from a trace")
+                                  .arg(clap::Arg::with_name("paths")
+                                       .long("paths")
+                                       .takes_value(true)
+                                       .possible_values(&["hash", "map", "keep"])
+                                       .help("How to redact paths (default: hash)"))
+                                  .arg(clap::Arg::with_name("salt")
+                                       .long("salt")
+                                       .takes_value(true)
+                                       .help("Secret to mix into path hashes"))
+                                  .arg(clap::Arg::with_name("data")
+                                       .long("data")
+                                       .takes_value(true)
+                                       .help("How to redact I/O and user data: zero, keep \
+                                              or a number of bytes to keep (default: zero)"))
+                                  .arg(clap::Arg::with_name("keep-commands")
+                                       .long("keep-commands")
+                                       .help("Don't replace command names"))
+                                  .arg(clap::Arg::with_name("keep-pids")
+                                       .long("keep-pids")
+                                       .help("Don't replace process and thread IDs"))
+                                  .arg(input())
+                                  .arg(clap::Arg::with_name("OUTPUT")
+                                       .help("Redacted trace to write")
+                                       .required(true)))
                              .get_matches();
 
-    let parsed = args.value_of("INPUT")
+    let result = match args.subcommand() {
+        ("index", Some(sub)) => index(sub),
+        ("extract", Some(sub)) => extract(sub),
+        ("stats", Some(sub)) => stats(sub),
+        ("offcpu", Some(sub)) => offcpu(sub),
+        ("faults", Some(sub)) => faults(sub),
+        ("sysctls", Some(sub)) => sysctls(sub),
+        ("files", Some(sub)) => files(sub),
+        ("diff", Some(sub)) => diff(sub),
+        ("export", Some(sub)) => export(sub),
+        ("merge", Some(sub)) => merge(sub),
+        ("redact", Some(sub)) => redact(sub),
+        _ => dump(&args),
+    };
+
+    if let Err(e) = result {
+        println!["Error: {}", e];
+        std::process::exit(1);
+    }
+}
+
+/// Which OS wrote the input trace, according to `--os` or the trace itself.
+fn os(args: &clap::ArgMatches, input: &str) -> Result<Os, Error> {
+    if let Some(name) = args.value_of("os").filter(|&name| name != "auto") {
+        return Os::from_name(name)
+                  .ok_or(Error::msg(format!["unknown OS: {}", name]));
+    }
+
+    let mut start = vec![];
+    args.value_of(input)
         .ok_or(Error::msg("missing required argument"))
-        .and_then(|name| File::open(name).map_err(Error::IO))
-        .and_then(|mut file| ktrace::parse::<NativeEndian>(&mut file))
-        ;
-
-    match parsed {
-        Err(e) => {
-            println!["Error: {}", e];
-            std::process::exit(1);
+        .and_then(|name| File::open(name).map_err(Error::io))
+        .and_then(|f| f.take(4096).read_to_end(&mut start).map_err(Error::io))?;
+
+    Ok(Os::detect(&start))
+}
+
+/// Parse a trace from a non-FreeBSD system.
+fn parse_foreign(args: &clap::ArgMatches, input: &str, os: Os)
+    -> Result<Vec<Entry>, Error>
+{
+    let data = args.value_of(input)
+                   .ok_or(Error::msg("missing required argument"))
+                   .and_then(|name| std::fs::read(name).map_err(Error::io))?;
+
+    if os == Os::Darwin && ktrace::darwin::big_endian(&data) {
+        return ktrace::parse_os::<BigEndian>(&data, os);
+    }
+
+    ktrace::parse_os::<NativeEndian>(&data, os)
+}
+
+fn parse(args: &clap::ArgMatches) -> Result<Records, Error> {
+    parse_input(args, "INPUT")
+}
+
+/// Parse the trace named by argument `input`.
+fn parse_input(args: &clap::ArgMatches, input: &str) -> Result<Records, Error> {
+    let os = os(args, input)?;
+    if os != Os::FreeBSD {
+        // Analyses only understand records with FreeBSD equivalents.
+        return Ok(parse_foreign(args, input, os)?
+                      .into_iter()
+                      .filter_map(|entry| match entry {
+                          Entry::Common(header, record) => Some((header, record)),
+                          Entry::Specific{..} => None,
+                      })
+                      .collect());
+    }
+
+    let mut file = args.value_of(input)
+                       .ok_or(Error::msg("missing required argument"))
+                       .and_then(|name| File::open(name).map_err(Error::io))?;
+
+    match args.value_of("jobs") {
+        Some(jobs) => {
+            let threads = jobs.parse()
+                              .map_err(|_| Error::msg("invalid thread count"))?;
+
+            ktrace::parse_parallel::<NativeEndian, _>(file, threads).collect()
         },
+        None => ktrace::parse::<NativeEndian>(&mut file),
+    }
+}
 
-        Ok(records) => {
-            println!["Parsed {} records:", records.len()];
-            for (header, record) in records.into_iter() {
-                print!["{:6} {:8} ", header.pid, header.command];
+fn dump(args: &clap::ArgMatches) -> Result<(), Error> {
+    let os = os(args, "INPUT")?;
+    if os != Os::FreeBSD {
+        return dump_foreign(args, os);
+    }
+
+    if args.is_present("follow") {
+        return follow(args);
+    }
+
+    if ["pid", "since", "start", "count"].iter().any(|a| args.is_present(a)) {
+        return dump_indexed(args);
+    }
+
+    let records = parse(args)?;
+    let show_fds = args.is_present("fds");
+    let mut abis = ktrace::AbiTracker::new();
+
+    println!["Parsed {} records:", records.len()];
+    for (header, record, fd) in ktrace::annotate::<NativeEndian, _>(records) {
+        print!["{:6} {:8} ", header.pid, header.command];
+
+        match record {
+            Ok(ref rec) => print!["{}", rec.display(abis.record(&header, rec))],
+            Err(ref e) => print!["<error: {}>", e],
+        };
+
+        match fd {
+            Some(ref descriptor) if show_fds => {
+                println![" [{}]", descriptor]
+            },
+            _ => println![],
+        };
+    }
+
+    Ok(())
+}
+
+/// Dump a non-FreeBSD trace, including records that FreeBSD doesn't have.
+fn dump_foreign(args: &clap::ArgMatches, os: Os) -> Result<(), Error> {
+    let unsupported = ["follow", "jobs", "pid", "since", "start", "count"];
+    if let Some(arg) = unsupported.iter().find(|a| args.is_present(a)) {
+        return Err(Error::msg(format!["--{} is not supported for {} traces",
+                                      arg, os]));
+    }
+
+    let entries = parse_foreign(args, "INPUT", os)?;
+    let mut abis = ktrace::AbiTracker::new();
 
+    println!["Parsed {} records ({}):", entries.len(), os];
+    for entry in entries {
+        match entry {
+            Entry::Common(header, record) => {
+                print!["{:6} {:8} ", header.pid, header.command];
                 match record {
+                    Ok(ref rec) => {
+                        println!["{}", rec.display(abis.record(&header, rec))]
+                    },
+                    Err(ref e) => println!["<error: {}>", e],
+                };
+            },
+            Entry::Specific{pid, command, record, ..} => {
+                print!["{:6} {:8} ", pid, command];
+                match record {
+                    Ok(ref extra) => println!["{}", extra],
+                    Err(ref e) => println!["<error: {}>", e],
+                };
+            },
+        }
+    }
+
+    Ok(())
+}
+
+/// Dump selected records, using (or building) an index to find them.
+fn dump_indexed(args: &clap::ArgMatches) -> Result<(), Error> {
+    let name = args.value_of("INPUT")
+                   .ok_or(Error::msg("missing required argument"))?;
+    let file = File::open(name)?;
+
+    // Use a sidecar index if there is a current one, or rebuild the index
+    // if the trace has changed since the sidecar was written.
+    let length = file.metadata()?.len();
+    let sidecar = File::open(format!["{}.idx", name])
+                      .ok()
+                      .and_then(|mut idx| ktrace::Index::read(&mut idx).ok())
+                      .filter(|index| index.trace_length() == length);
+
+    let mut reader = match sidecar {
+        Some(index) => ktrace::IndexedReader::<_, NativeEndian>::new(file, index)?,
+        None => ktrace::IndexedReader::open(file)?,
+    };
+
+    let number = |arg: &str| -> Result<Option<u64>, Error> {
+        match args.value_of(arg) {
+            Some(x) => x.parse().map(Some).map_err(|_| {
+                Error::msg(format!["invalid --{}: '{}'", arg, x])
+            }),
+            None => Ok(None),
+        }
+    };
+
+    let pid = number("pid")?;
+    let count = number("count")?.map(|c| c as usize).unwrap_or(usize::MAX);
+
+    let mut start = number("start")?.unwrap_or(0) as usize;
+    if let Some(since) = args.value_of("since") {
+        let seconds: f64 = since.parse().map_err(|_| {
+            Error::msg(format!["invalid --since: '{}'", since])
+        })?;
+
+        let time = nix::sys::time::TimeVal::microseconds((seconds * 1e6).round() as i64);
+        start = start.max(reader.index().find_time(time));
+    }
+
+    let records = reader.select(start, |e| pid.is_none_or(|p| e.pid == p as u32));
+
+    for r in records.take(count) {
+        let (header, record) = r?;
+        print!["{:6} {:8} ", header.pid, header.command];
+
+        match record {
+            Ok(ref rec) => println!["{}", rec],
+            Err(ref e) => println!["<error: {}>", e],
+        };
+    }
+
+    Ok(())
+}
+
+/// Dump records as they are appended to a trace, like `tail -f`.
+fn follow(args: &clap::ArgMatches) -> Result<(), Error> {
+    let name = args.value_of("INPUT")
+                   .ok_or(Error::msg("missing required argument"))?;
+    let show_fds = args.is_present("fds");
+
+    let mut fds = ktrace::FdTracker::<NativeEndian>::new();
+    let mut abis = ktrace::AbiTracker::new();
+
+    for event in ktrace::Follower::<NativeEndian>::open(name)? {
+        let (header, record) = match event? {
+            ktrace::FollowEvent::Record(header, record) => (header, record),
+            ktrace::FollowEvent::Truncated => {
+                println!["--- {} truncated; reading from the start", name];
+                fds = ktrace::FdTracker::new();
+                continue;
+            },
+            ktrace::FollowEvent::Rotated => {
+                println!["--- {} replaced; reading the new file", name];
+                fds = ktrace::FdTracker::new();
+                continue;
+            },
+        };
+
+        print!["{:6} {:8} ", header.pid, header.command];
+
+        let fd = match record {
+            Ok(ref rec) => {
+                print!["{}", rec.display(abis.record(&header, rec))];
+                fds.record(&header, rec)
+            },
+            Err(ref e) => {
+                print!["<error: {}>", e];
+                None
+            },
+        };
+
+        match fd {
+            Some(ref descriptor) if show_fds => {
+                println![" [{}]", descriptor]
+            },
+            _ => println![],
+        };
+    }
+
+    Ok(())
+}
+
+fn index(args: &clap::ArgMatches) -> Result<(), Error> {
+    let name = args.value_of("INPUT")
+                   .ok_or(Error::msg("missing required argument"))?;
+
+    let index = ktrace::Index::build::<NativeEndian, _>(&mut File::open(name)?)?;
+    let output = args.value_of("OUTPUT")
+                     .map(str::to_string)
+                     .unwrap_or(format!["{}.idx", name]);
+
+    let mut out = std::io::BufWriter::new(File::create(&output)?);
+    index.write(&mut out)?;
+
+    println!["Indexed {} records in {}", index.len(), output];
+    Ok(())
+}
+
+fn extract(args: &clap::ArgMatches) -> Result<(), Error> {
+    let records = parse(args)?;
+    let dir = args.value_of("DIR")
+                  .ok_or(Error::msg("missing required argument"))?;
+
+    let mut extractor = ktrace::StreamExtractor::new();
+    for (header, record) in &records {
+        if let Ok(ref record) = *record {
+            extractor.record(header, record);
+        }
+    }
+
+    let streams = extractor.into_streams();
+    ktrace::write_streams(&streams, dir)?;
+
+    for stream in &streams {
+        let truncated = stream.truncated();
+        println!["{:6} {:8} fd {:3} {:5?} {:8} B{} {}",
+                 stream.pid, stream.command, stream.fd, stream.direction,
+                 stream.data.len(),
+                 if truncated > 0 {
+                     format![" ({} B truncated)", truncated]
+                 } else {
+                     String::new()
+                 },
+                 stream.descriptor.as_ref()
+                       .map(|d| d.to_string())
+                       .unwrap_or_default()];
+    }
+
+    Ok(())
+}
+
+fn stats(args: &clap::ArgMatches) -> Result<(), Error> {
+    let records = parse(args)?;
+
+    let mut stats = ktrace::Stats::new();
+    for (header, record) in &records {
+        if let Ok(ref record) = *record {
+            stats.record(header, record);
+        }
+    }
+
+    let stdout = std::io::stdout();
+    let mut out = stdout.lock();
+
+    if args.is_present("json") {
+        stats.write_json(&mut out)
+    } else {
+        stats.write_table(&mut out, args.is_present("per-process"))
+    }
+    .map_err(Error::io)
+}
+
+fn offcpu(args: &clap::ArgMatches) -> Result<(), Error> {
+    let records = parse(args)?;
+
+    let mut offcpu = ktrace::OffCpu::new();
+    for (header, record) in &records {
+        if let Ok(ref record) = *record {
+            offcpu.record(header, record);
+        }
+    }
+
+    let stdout = std::io::stdout();
+    let mut out = stdout.lock();
+
+    if args.is_present("folded") {
+        offcpu.write_folded(&mut out)
+    } else {
+        offcpu.write_table(&mut out)
+    }
+    .map_err(Error::io)
+}
+
+fn faults(args: &clap::ArgMatches) -> Result<(), Error> {
+    let records = parse(args)?;
+
+    let mut faults = ktrace::PageFaults::new();
+    for (header, record) in &records {
+        if let Ok(ref record) = *record {
+            faults.record(header, record);
+        }
+    }
+
+    faults.write_table(&mut std::io::stdout()).map_err(Error::io)
+}
+
+fn sysctls(args: &clap::ArgMatches) -> Result<(), Error> {
+    let records = parse(args)?;
+
+    let mut sysctls = ktrace::Sysctls::new();
+    for (header, record) in &records {
+        if let Ok(ref record) = *record {
+            sysctls.record(header, record);
+        }
+    }
+
+    sysctls.write_table(&mut std::io::stdout()).map_err(Error::io)
+}
+
+fn files(args: &clap::ArgMatches) -> Result<(), Error> {
+    let records = parse(args)?;
+
+    let mut files = ktrace::FileAccesses::new();
+    for (header, record) in &records {
+        if let Ok(ref record) = *record {
+            files.record(header, record);
+        }
+    }
+
+    let stdout = std::io::stdout();
+    let mut out = stdout.lock();
+
+    if args.is_present("json") {
+        files.write_json_lines(&mut out)
+    } else {
+        files.write_text(&mut out)
+    }
+    .map_err(Error::io)
+}
+
+fn diff(args: &clap::ArgMatches) -> Result<(), Error> {
+    let before = parse_input(args, "BEFORE")?;
+    let after = parse_input(args, "AFTER")?;
+
+    let options = ktrace::DiffOptions {
+        addresses: !args.is_present("keep-addresses"),
+        fds: !args.is_present("keep-fds"),
+        pids: !args.is_present("keep-pids"),
+        args: args.is_present("ignore-args"),
+        paths: args.is_present("ignore-paths"),
+    };
+
+    let diff = ktrace::TraceDiff::new(decoded(&before), decoded(&after), &options);
+    diff.write_text(&mut std::io::stdout())?;
+
+    Ok(())
+}
+
+/// The records in a trace that could be decoded.
+fn decoded(records: &Records) -> impl Iterator<Item = (&Header, &Record)> {
+    records.iter().filter_map(|(h, r)| r.as_ref().ok().map(|r| (h, r)))
+}
+
+fn export(args: &clap::ArgMatches) -> Result<(), Error> {
+    let records = parse(args)?;
+    let output = args.value_of("OUTPUT")
+                     .ok_or(Error::msg("missing required argument"))
+                     .and_then(|name| File::create(name).map_err(Error::io))
+                     .map(std::io::BufWriter::new)?;
+
+    match args.value_of("format") {
+        Some("chrome") => {
+            let mut trace = ktrace::ChromeTrace::new(output).map_err(Error::io)?;
+            for (header, record) in &records {
+                if let Ok(ref record) = *record {
+                    trace.record(header, record).map_err(Error::io)?;
+                }
+            }
+            trace.finish().map_err(Error::io)?;
+        },
+
+        Some("pcapng") => {
+            let mut capture = ktrace::PcapNg::new(output).map_err(Error::io)?;
+            for (header, record) in &records {
+                if let Ok(ref record) = *record {
+                    capture.record(header, record).map_err(Error::io)?;
+                }
+            }
+            capture.finish().map_err(Error::io)?;
+        },
+
+        Some(format @ "dot") | Some(format @ "jsonl") => {
+            let mut graph = ktrace::ProvenanceGraph::new();
+            for (header, record) in &records {
+                if let Ok(ref record) = *record {
+                    graph.record(header, record);
+                }
+            }
+
+            let mut output = output;
+            if format == "dot" {
+                graph.write_dot(&mut output)
+            } else {
+                graph.write_json_lines(&mut output)
+            }
+            .map_err(Error::io)?;
+        },
+
+        format => {
+            return Err(Error::msg(format!["unknown output format: {}",
+                                          format.unwrap_or("")]));
+        },
+    }
+
+    Ok(())
+}
+
+fn merge(args: &clap::ArgMatches) -> Result<(), Error> {
+    let names: Vec<_> = args.values_of("INPUT")
+                            .ok_or(Error::msg("missing required argument"))?
+                            .collect();
+
+    let mut merged = ktrace::Merge::<NativeEndian>::new();
+    for name in &names {
+        merged.add(std::io::BufReader::new(File::open(name)?));
+    }
+
+    match args.value_of("output") {
+        Some(output) => {
+            let mut out = std::io::BufWriter::new(File::create(output)?);
+            for r in merged {
+                out.write_all(&r?.to_bytes::<NativeEndian>())?;
+            }
+            out.flush()?;
+        },
+
+        None => {
+            let width = names.iter().map(|n| n.len()).max().unwrap_or(0);
+
+            for r in merged {
+                let r = r?;
+                let t = r.header.timestamp;
+
+                print!["{:width$} {}.{:06} {:6} {:8} ",
+                       names[r.source], t.tv_sec, t.tv_usec,
+                       r.header.pid, r.header.command];
+
+                match r.record {
                     Ok(ref rec) => println!["{}", rec],
                     Err(ref e) => println!["<error: {}>", e],
                 };
             }
         },
     }
+
+    Ok(())
+}
+
+fn redact(args: &clap::ArgMatches) -> Result<(), Error> {
+    let input = args.value_of("INPUT")
+                    .ok_or(Error::msg("missing required argument"))?;
+    let output = args.value_of("OUTPUT")
+                     .ok_or(Error::msg("missing required argument"))?;
+
+    let paths = match args.value_of("paths") {
+        Some("map") => ktrace::PathPolicy::Map,
+        Some("keep") => ktrace::PathPolicy::Keep,
+        _ => ktrace::PathPolicy::Hash,
+    };
+
+    let data = match args.value_of("data") {
+        None | Some("zero") => ktrace::DataPolicy::Zero,
+        Some("keep") => ktrace::DataPolicy::Keep,
+        Some(n) => ktrace::DataPolicy::Truncate(n.parse().map_err(|_| {
+            Error::msg(format!["invalid --data: '{}'", n])
+        })?),
+    };
+
+    let options = ktrace::RedactOptions {
+        paths,
+        data,
+        commands: !args.is_present("keep-commands"),
+        pids: !args.is_present("keep-pids"),
+        salt: args.value_of("salt").unwrap_or("").to_string(),
+    };
+
+    let mut redactor = ktrace::Redactor::<NativeEndian>::new(options);
+    let mut input = std::io::BufReader::new(File::open(input)?);
+    let mut output = std::io::BufWriter::new(File::create(output)?);
+
+    ktrace::redact(&mut input, &mut output, &mut redactor)?;
+    output.flush()?;
+
+    Ok(())
 }
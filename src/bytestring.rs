@@ -0,0 +1,150 @@
+// Copyright 2016 Jonathan Anderson <jonathan.anderson@mun.ca>
+//
+// This software was developed by BAE Systems, the University of Cambridge
+// Computer Laboratory, and Memorial University under DARPA/AFRL contract
+// FA8650-15-C-7558 ("CADETS"), as part of the DARPA Transparent Computing
+// (TC) research program.
+//
+// Licensed under the Apache License, Version 2.0,
+// <LICENSE-APACHE or http://apache.org/licenses/LICENSE-2.0>
+// or the MIT license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::borrow::Cow;
+use std::fmt;
+use std::ops::Deref;
+use std::str;
+
+
+/// A string from the kernel (e.g., a path or command name) that is usually,
+/// but not necessarily, UTF-8
+///
+/// FreeBSD paths and command names are arbitrary bytes, so they are kept
+/// as bytes and only interpreted when displayed: invalid UTF-8 is shown as
+/// `\xNN` escapes (and backslashes as `\\`).
+#[derive(Clone,Default,Eq,Hash,Ord,PartialEq,PartialOrd)]
+pub struct ByteString(Vec<u8>);
+
+/// Displays bytes as UTF-8, escaping backslashes and any bytes that aren't
+/// valid UTF-8
+pub struct Escaped<'a>(pub &'a [u8]);
+
+
+impl ByteString {
+    pub fn new(bytes: Vec<u8>) -> ByteString {
+        ByteString(bytes)
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.0
+    }
+
+    /// The string, if it is valid UTF-8.
+    pub fn to_str(&self) -> Option<&str> {
+        str::from_utf8(&self.0).ok()
+    }
+
+    /// The string, with invalid UTF-8 replaced by U+FFFD.
+    pub fn to_string_lossy(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(&self.0)
+    }
+
+    /// The string as displayed, with invalid UTF-8 escaped.
+    pub fn escaped(&self) -> Escaped<'_> {
+        Escaped(&self.0)
+    }
+}
+
+impl Deref for ByteString {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl From<Vec<u8>> for ByteString {
+    fn from(bytes: Vec<u8>) -> ByteString {
+        ByteString(bytes)
+    }
+}
+
+impl<'a> From<&'a [u8]> for ByteString {
+    fn from(bytes: &'a [u8]) -> ByteString {
+        ByteString(bytes.to_vec())
+    }
+}
+
+impl From<String> for ByteString {
+    fn from(s: String) -> ByteString {
+        ByteString(s.into_bytes())
+    }
+}
+
+impl<'a> From<&'a str> for ByteString {
+    fn from(s: &'a str) -> ByteString {
+        ByteString(s.as_bytes().to_vec())
+    }
+}
+
+impl PartialEq<str> for ByteString {
+    fn eq(&self, other: &str) -> bool {
+        self.0 == other.as_bytes()
+    }
+}
+
+impl<'a> PartialEq<&'a str> for ByteString {
+    fn eq(&self, other: &&'a str) -> bool {
+        self.0 == other.as_bytes()
+    }
+}
+
+impl PartialEq<String> for ByteString {
+    fn eq(&self, other: &String) -> bool {
+        self.0 == other.as_bytes()
+    }
+}
+
+impl PartialEq<ByteString> for String {
+    fn eq(&self, other: &ByteString) -> bool {
+        self.as_bytes() == other.as_bytes()
+    }
+}
+
+impl fmt::Display for ByteString {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.escaped().fmt(f)
+    }
+}
+
+impl fmt::Debug for ByteString {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write![f, "\"{}\"", self.escaped()]
+    }
+}
+
+impl<'a> fmt::Display for Escaped<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // Without escapes, honour width and alignment (e.g., in tables).
+        match str::from_utf8(self.0) {
+            Ok(s) if !s.contains('\\') => return f.pad(s),
+            _ => {},
+        }
+
+        // Backslashes are escaped too, so that `\xNN` is unambiguous.
+        let mut s = String::new();
+        for chunk in self.0.utf8_chunks() {
+            s.push_str(&chunk.valid().replace('\\', "\\\\"));
+            for b in chunk.invalid() {
+                s.push_str(&format!["\\x{:02x}", b]);
+            }
+        }
+
+        f.pad(&s)
+    }
+}
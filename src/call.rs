@@ -0,0 +1,177 @@
+// Copyright 2016 Jonathan Anderson <jonathan.anderson@mun.ca>
+//
+// This software was developed by BAE Systems, the University of Cambridge
+// Computer Laboratory, and Memorial University under DARPA/AFRL contract
+// FA8650-15-C-7558 ("CADETS"), as part of the DARPA Transparent Computing
+// (TC) research program.
+//
+// Licensed under the Apache License, Version 2.0,
+// <LICENSE-APACHE or http://apache.org/licenses/LICENSE-2.0>
+// or the MIT license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+extern crate nix;
+
+use self::nix::sys::time::TimeVal;
+use std::collections::HashMap;
+use ::{Abi,AbiTracker,ByteString,Header,Record};
+
+/// Pseudo-errno returned by a successful `execve(2)`
+pub const EJUSTRETURN: i32 = -2;
+
+
+/// A system call paired with its return and the records emitted while it ran
+#[derive(Clone,Debug)]
+pub struct Call {
+    pub pid: u32,
+    pub tid: usize,
+
+    /// the ABI of the calling process, which gives `number` its meaning
+    pub abi: Abi,
+
+    /// system call number
+    pub number: u16,
+
+    /// user arguments (empty if the call was not seen, e.g., in a new child)
+    pub args: Vec<u64>,
+
+    /// time of the `KTR_SYSCALL` record, if there was one
+    pub start: Option<TimeVal>,
+
+    /// time of the `KTR_SYSRET` record
+    pub end: TimeVal,
+
+    pub error: i32,
+    pub retval: u64,
+
+    /// paths looked up by the call (`KTR_NAMEI`)
+    pub paths: Vec<ByteString>,
+
+    /// structures copied in or out by the call (`KTR_STRUCT`)
+    pub structs: Vec<(String, Vec<u8>)>,
+
+    /// MIB names accessed by `__sysctl` or `__sysctlbyname` (`KTR_SYSCTL`)
+    pub sysctls: Vec<ByteString>,
+}
+
+impl Call {
+    pub fn name(&self) -> String {
+        self.abi.syscall_name(self.number)
+                .unwrap_or(format!["<<bad syscall: {}>>", self.number])
+    }
+
+    /// The call succeeded (including an `execve` that never returns).
+    pub fn succeeded(&self) -> bool {
+        self.error == 0 || self.error == EJUSTRETURN
+    }
+
+    /// The `n`th argument, interpreted as a file descriptor.
+    pub fn fd(&self, n: usize) -> Option<i32> {
+        self.args.get(n).map(|x| *x as i32)
+    }
+
+    /// The first structure with a given name (e.g., "sockaddr").
+    pub fn find_struct(&self, name: &str) -> Option<&[u8]> {
+        self.structs.iter()
+                    .find(|&(n, _)| n == name)
+                    .map(|(_, content)| content.as_slice())
+    }
+}
+
+
+/// Pairs `SystemCall` and `SystemCallReturn` records on each thread
+#[derive(Default)]
+pub struct CallTracker {
+    pending: HashMap<(u32, usize), Call>,
+    abis: AbiTracker,
+}
+
+impl CallTracker {
+    pub fn new() -> CallTracker {
+        CallTracker::default()
+    }
+
+    /// Account for the next record, returning a `Call` if the record
+    /// completes one.
+    pub fn record(&mut self, header: &Header, record: &Record) -> Option<Call> {
+        let key = (header.pid, header.tid);
+        let abi = self.abis.record(header, record);
+
+        match *record {
+            Record::SystemCall{number, ref args} => {
+                self.pending.insert(key, Call {
+                    pid: header.pid,
+                    tid: header.tid,
+                    abi,
+                    number,
+                    args: args.clone(),
+                    start: Some(header.timestamp),
+                    end: header.timestamp,
+                    error: 0,
+                    retval: 0,
+                    paths: vec![],
+                    structs: vec![],
+                    sysctls: vec![],
+                });
+                None
+            },
+
+            Record::SystemCallReturn{code, error, retval, ..} => {
+                let mut call = match self.pending.remove(&key) {
+                    Some(call) if call.number == code => call,
+
+                    // We didn't see this call begin (e.g., fork in the child)
+                    _ => Call {
+                        pid: header.pid,
+                        tid: header.tid,
+                        abi,
+                        number: code,
+                        args: vec![],
+                        start: None,
+                        end: header.timestamp,
+                        error: 0,
+                        retval: 0,
+                        paths: vec![],
+                        structs: vec![],
+                        sysctls: vec![],
+                    },
+                };
+
+                call.end = header.timestamp;
+                call.error = error as i32;
+                call.retval = retval;
+
+                Some(call)
+            },
+
+            Record::Namei(ref path) => {
+                if let Some(call) = self.pending.get_mut(&key) {
+                    call.paths.push(path.clone());
+                }
+                None
+            },
+
+            Record::Struct{ref name, ref content} => {
+                if let Some(call) = self.pending.get_mut(&key) {
+                    call.structs.push((name.to_string(), content.clone()));
+                }
+                None
+            },
+
+            Record::Sysctl(ref name) => {
+                if let Some(call) = self.pending.get_mut(&key) {
+                    call.sysctls.push(name.clone());
+                }
+                None
+            },
+
+            _ => None,
+        }
+    }
+
+    /// The call currently in progress on a thread, if any.
+    pub fn pending(&self, pid: u32, tid: usize) -> Option<&Call> {
+        self.pending.get(&(pid, tid))
+    }
+}
@@ -0,0 +1,457 @@
+// Copyright 2016 Jonathan Anderson <jonathan.anderson@mun.ca>
+//
+// This software was developed by BAE Systems, the University of Cambridge
+// Computer Laboratory, and Memorial University under DARPA/AFRL contract
+// FA8650-15-C-7558 ("CADETS"), as part of the DARPA Transparent Computing
+// (TC) research program.
+//
+// Licensed under the Apache License, Version 2.0,
+// <LICENSE-APACHE or http://apache.org/licenses/LICENSE-2.0>
+// or the MIT license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+
+use std::collections::{BTreeMap,HashMap};
+use std::fmt;
+use std::io::{self,Write};
+use fd::takes_fd;
+use redact::returns_pid;
+use ::{ByteString,Call,CallTracker,Header,Record,errno};
+
+
+/// Which details of system calls to ignore when comparing traces
+///
+/// Timings are always ignored.
+#[derive(Clone,Debug)]
+pub struct DiffOptions {
+    /// ignore arguments and return values that look like user-space
+    /// addresses (at least 0x100000)
+    pub addresses: bool,
+
+    /// ignore file descriptor arguments and return values
+    pub fds: bool,
+
+    /// ignore process IDs returned by `fork`, `getpid`, etc.
+    pub pids: bool,
+
+    /// ignore all arguments
+    pub args: bool,
+
+    /// ignore the paths looked up by system calls
+    pub paths: bool,
+}
+
+/// A system call, normalised for comparison with calls in another trace
+#[derive(Clone,Debug,PartialEq)]
+pub struct DiffCall {
+    pub name: String,
+
+    /// arguments, with ignored values replaced by placeholders
+    pub args: Vec<String>,
+    pub paths: Vec<ByteString>,
+
+    /// return value or error name
+    pub result: String,
+}
+
+/// A difference between the system calls made by a thread in two traces
+#[derive(Clone,Debug)]
+pub enum Change {
+    Removed(DiffCall),
+    Inserted(DiffCall),
+
+    /// The same system call was made with different arguments or results.
+    Changed(DiffCall, DiffCall),
+}
+
+/// The differences between the system calls made by one thread
+#[derive(Clone,Debug)]
+pub struct ThreadDiff {
+    /// the thread's command and its position among threads running the
+    /// same command (e.g., `cat#0`), which identifies it in both traces
+    pub thread: String,
+
+    pub changes: Vec<Change>,
+}
+
+/// The differences between two traces of the same program
+#[derive(Clone,Debug,Default)]
+pub struct TraceDiff {
+    /// threads whose system calls differ
+    pub threads: Vec<ThreadDiff>,
+
+    /// (system call, errno) failures that only happen in the second trace,
+    /// with the number of times that they happen
+    pub new_errors: BTreeMap<(String, i32), u64>,
+
+    /// capability failures that only happen in the second trace
+    pub new_capability_failures: BTreeMap<String, u64>,
+}
+
+/// System calls and failures gathered from one trace
+#[derive(Default)]
+struct Run {
+    /// each thread's label and normalised calls, in order of appearance
+    threads: Vec<(String, Vec<DiffCall>)>,
+    errors: BTreeMap<(String, i32), u64>,
+    capability_failures: BTreeMap<String, u64>,
+}
+
+
+impl Default for DiffOptions {
+    fn default() -> DiffOptions {
+        DiffOptions {
+            addresses: true,
+            fds: true,
+            pids: true,
+            args: false,
+            paths: false,
+        }
+    }
+}
+
+impl DiffOptions {
+    /// Describe a call in terms of the details that we don't ignore.
+    pub fn normalize(&self, call: &Call) -> DiffCall {
+        let name = call.name();
+        let args = call.args.iter().enumerate().map(|(i, &arg)| {
+            if self.args {
+                "_".to_string()
+            } else if self.fds && i == 0 && takes_fd(&name) {
+                "fd".to_string()
+            } else if self.addresses && arg >= 0x100000 {
+                "addr".to_string()
+            } else {
+                format!["0x{:x}", arg]
+            }
+        }).collect();
+
+        let result = if !call.succeeded() {
+            errno::name(call.error).unwrap_or(format!["errno {}", call.error])
+        } else if self.fds && returns_fd(&name) {
+            "fd".to_string()
+        } else if self.pids && returns_pid(&name) {
+            "pid".to_string()
+        } else if self.addresses && call.retval >= 0x100000 {
+            "addr".to_string()
+        } else {
+            format!["0x{:x}", call.retval]
+        };
+
+        DiffCall {
+            name,
+            args,
+            paths: if self.paths { vec![] } else { call.paths.clone() },
+            result,
+        }
+    }
+}
+
+impl fmt::Display for DiffCall {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write![f, "{}({})", self.name, self.args.join(", ")]?;
+        for path in &self.paths {
+            write![f, " \"{}\"", path]?;
+        }
+        write![f, " = {}", self.result]
+    }
+}
+
+
+impl TraceDiff {
+    /// Compare the system calls in two traces, thread by thread.
+    pub fn new<'a, I, J>(before: I, after: J, options: &DiffOptions) -> TraceDiff
+        where I: IntoIterator<Item = (&'a Header, &'a Record)>,
+              J: IntoIterator<Item = (&'a Header, &'a Record)>
+    {
+        let before = Run::new(before, options);
+        let mut after = Run::new(after, options);
+
+        let mut threads = vec![];
+
+        for (thread, calls) in &before.threads {
+            let later = after.threads.iter()
+                             .position(|(t, _)| t == thread)
+                             .map(|i| after.threads.remove(i).1)
+                             .unwrap_or_default();
+
+            threads.push(ThreadDiff::new(thread, calls, &later));
+        }
+
+        // Threads that only appear in the second trace
+        for (thread, calls) in &after.threads {
+            threads.push(ThreadDiff::new(thread, &[], calls));
+        }
+
+        threads.retain(|t| !t.changes.is_empty());
+
+        TraceDiff {
+            threads,
+            new_errors: only_after(&before.errors, after.errors),
+            new_capability_failures: only_after(&before.capability_failures,
+                                                after.capability_failures),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.threads.is_empty() && self.new_errors.is_empty()
+            && self.new_capability_failures.is_empty()
+    }
+
+    /// Describe the differences in a `diff -c`-like format, where changed
+    /// calls are marked with `!`.
+    pub fn write_text(&self, out: &mut dyn Write) -> io::Result<()> {
+        for t in &self.threads {
+            writeln![out, "thread {}:", t.thread]?;
+
+            for change in &t.changes {
+                match *change {
+                    Change::Removed(ref call) => writeln![out, "- {}", call]?,
+                    Change::Inserted(ref call) => writeln![out, "+ {}", call]?,
+                    Change::Changed(ref before, ref after) => {
+                        writeln![out, "! {}", before]?;
+                        writeln![out, "! {}", after]?;
+                    },
+                }
+            }
+        }
+
+        if !self.new_errors.is_empty() {
+            writeln![out, "new errors:"]?;
+            for (&(ref call, error), count) in &self.new_errors {
+                writeln![out, "  {} {} ({}x)", call,
+                         errno::name(error).unwrap_or(format!["errno {}", error]),
+                         count]?;
+            }
+        }
+
+        if !self.new_capability_failures.is_empty() {
+            writeln![out, "new capability failures:"]?;
+            for (fail, count) in &self.new_capability_failures {
+                writeln![out, "  {} ({}x)", fail, count]?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+
+impl ThreadDiff {
+    fn new(thread: &str, before: &[DiffCall], after: &[DiffCall]) -> ThreadDiff {
+        let changes = align(before, after).into_iter().filter_map(|e| match e {
+            Edit::Same(i, j) if before[i] == after[j] => None,
+            Edit::Same(i, j) => Some(Change::Changed(before[i].clone(),
+                                                     after[j].clone())),
+            Edit::Delete(i) => Some(Change::Removed(before[i].clone())),
+            Edit::Insert(j) => Some(Change::Inserted(after[j].clone())),
+        }).collect();
+
+        ThreadDiff {
+            thread: thread.to_string(),
+            changes,
+        }
+    }
+}
+
+
+impl Run {
+    fn new<'a, I>(records: I, options: &DiffOptions) -> Run
+        where I: IntoIterator<Item = (&'a Header, &'a Record)>
+    {
+        let mut run = Run::default();
+        let mut calls = CallTracker::new();
+
+        // Index into `threads` and number of threads running each command
+        let mut threads = HashMap::new();
+        let mut commands = HashMap::new();
+
+        for (header, record) in records {
+            let i = *threads.entry((header.pid, header.tid)).or_insert_with(|| {
+                let n = commands.entry(header.command.clone()).or_insert(0);
+                run.threads.push((format!["{}#{}", header.command, n], vec![]));
+                *n += 1;
+
+                run.threads.len() - 1
+            });
+
+            if let Record::CapabilityFailure(ref fail) = *record {
+                let call = calls.pending(header.pid, header.tid)
+                                .map(Call::name)
+                                .unwrap_or("<unknown>".to_string());
+
+                *run.capability_failures.entry(format!["{}: {}", call, fail])
+                                        .or_insert(0) += 1;
+            }
+
+            if let Some(call) = calls.record(header, record) {
+                if !call.succeeded() {
+                    *run.errors.entry((call.name(), call.error))
+                               .or_insert(0) += 1;
+                }
+
+                run.threads[i].1.push(options.normalize(&call));
+            }
+        }
+
+        run
+    }
+}
+
+
+/// The entries of `after` whose keys don't appear in `before`.
+fn only_after<K>(before: &BTreeMap<K, u64>, mut after: BTreeMap<K, u64>)
+    -> BTreeMap<K, u64>
+    where K: Ord
+{
+    after.retain(|k, _| !before.contains_key(k));
+    after
+}
+
+/// System calls that return a new file descriptor.
+fn returns_fd(name: &str) -> bool {
+    matches!(name,
+        "open" | "openat" | "creat" | "fhopen" | "shm_open" |
+        "posix_openpt" | "socket" | "accept" | "accept4" | "dup" |
+        "dup2" | "kqueue" | "pipe")
+}
+
+
+/// A step in aligning two sequences of system calls
+enum Edit {
+    Same(usize, usize),
+    Delete(usize),
+    Insert(usize),
+}
+
+/// Align two call sequences by system call name, using Myers' algorithm
+/// to find the smallest number of insertions and deletions.
+///
+/// This is the linear-space variant, which splits the problem at the
+/// middle of an optimal path and recurses on either side, so that long
+/// threads that have diverged don't need quadratic memory.
+fn align(a: &[DiffCall], b: &[DiffCall]) -> Vec<Edit> {
+    let mut edits = Vec::with_capacity(a.len().max(b.len()));
+    align_into(a, b, 0, 0, &mut edits);
+    edits
+}
+
+/// Align `a` and `b`, which start at `a[i]` and `b[j]` in the sequences
+/// being aligned.
+fn align_into(a: &[DiffCall], b: &[DiffCall], i: usize, j: usize,
+              edits: &mut Vec<Edit>)
+{
+    let same = |x: &DiffCall, y: &DiffCall| x.name == y.name;
+
+    let prefix = a.iter().zip(b).take_while(|&(x, y)| same(x, y)).count();
+    edits.extend((0..prefix).map(|p| Edit::Same(i + p, j + p)));
+    let (a, b, i, j) = (&a[prefix..], &b[prefix..], i + prefix, j + prefix);
+
+    let suffix = a.iter().rev().zip(b.iter().rev())
+                  .take_while(|&(x, y)| same(x, y))
+                  .count();
+    let (a, b) = (&a[..a.len() - suffix], &b[..b.len() - suffix]);
+
+    match middle(a, b) {
+        Some((x, y)) if !a.is_empty() && !b.is_empty() => {
+            align_into(&a[..x], &b[..y], i, j, edits);
+            align_into(&a[x..], &b[y..], i + x, j + y, edits);
+        },
+        _ => {
+            edits.extend((i..i + a.len()).map(Edit::Delete));
+            edits.extend((j..j + b.len()).map(Edit::Insert));
+        },
+    }
+
+    edits.extend((0..suffix).map(|s| Edit::Same(i + a.len() + s, j + b.len() + s)));
+}
+
+/// A point on an optimal path from the start of `a` and `b` to their ends,
+/// found by searching forward from the start and backward from the end
+/// until the searches meet (or `None` if the sequences have nothing in
+/// common).
+fn middle(a: &[DiffCall], b: &[DiffCall]) -> Option<(usize, usize)> {
+    let (n, m) = (a.len() as isize, b.len() as isize);
+    let max_d = (n + m + 1) / 2;
+    let offset = max_d;
+    let len = 2 * max_d + 2;
+
+    // Furthest-reaching x on each diagonal, searching forward and backward
+    let mut forward = vec![-1; len as usize];
+    let mut backward = vec![-1; len as usize];
+    forward[(offset + 1) as usize] = 0;
+    backward[(offset + 1) as usize] = 0;
+
+    let delta = n - m;
+    let odd = delta % 2 != 0;
+
+    // Diagonals to skip at either end, because they have left the grid
+    let (mut f_start, mut f_end, mut b_start, mut b_end) = (0, 0, 0, 0);
+
+    for d in 0..max_d {
+        for k in (-d + f_start..d + 1 - f_end).step_by(2) {
+            let ki = (offset + k) as usize;
+            let mut x = if k == -d || (k != d && forward[ki - 1] < forward[ki + 1]) {
+                forward[ki + 1]
+            } else {
+                forward[ki - 1] + 1
+            };
+            let mut y = x - k;
+
+            while x < n && y < m && a[x as usize].name == b[y as usize].name {
+                x += 1;
+                y += 1;
+            }
+            forward[ki] = x;
+
+            if x > n {
+                f_end += 2;
+            } else if y > m {
+                f_start += 2;
+            } else if odd {
+                let bi = offset + delta - k;
+                if bi >= 0 && bi < len && backward[bi as usize] != -1
+                    && x >= n - backward[bi as usize]
+                {
+                    return Some((x as usize, y as usize));
+                }
+            }
+        }
+
+        for k in (-d + b_start..d + 1 - b_end).step_by(2) {
+            let ki = (offset + k) as usize;
+            let mut x = if k == -d || (k != d && backward[ki - 1] < backward[ki + 1]) {
+                backward[ki + 1]
+            } else {
+                backward[ki - 1] + 1
+            };
+            let mut y = x - k;
+
+            while x < n && y < m
+                  && a[(n - x - 1) as usize].name == b[(m - y - 1) as usize].name
+            {
+                x += 1;
+                y += 1;
+            }
+            backward[ki] = x;
+
+            if x > n {
+                b_end += 2;
+            } else if y > m {
+                b_start += 2;
+            } else if !odd {
+                let fi = offset + delta - k;
+                if fi >= 0 && fi < len && forward[fi as usize] != -1 {
+                    let fx = forward[fi as usize];
+                    let fy = offset + fx - fi;
+                    if fx >= n - x {
+                        return Some((fx as usize, fy as usize));
+                    }
+                }
+            }
+        }
+    }
+
+    None
+}
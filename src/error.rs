@@ -11,50 +11,229 @@
 // at your option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
-use ::std::{fmt,io,string};
 
+use ::std::{error,fmt,io,str,string};
+use ::{Header,RecordType};
+
+
+/// An error encountered while reading or parsing a trace, along with
+/// where in the trace it happened (if known)
+#[derive(Debug)]
+pub struct Error {
+    kind: ErrorKind,
+    trace: Option<usize>,
+    index: Option<usize>,
+    offset: Option<u64>,
+    record_type: Option<RecordType>,
+    header: Option<Box<Header>>,
+}
+
+/// The kinds of errors that can be encountered
+#[derive(Debug)]
+pub enum ErrorKind {
+    /// The input ended partway through a header or record.
+    Truncated { expected: usize, got: usize },
+
+    /// A record's payload is the wrong size for its type.
+    BadLength { expected: String, got: usize },
+
+    /// A header's `ktr_type` isn't a known record type.
+    UnknownType(u16),
+
+    /// A string in the trace isn't valid UTF-8.
+    InvalidUtf8(str::Utf8Error),
+
+    /// A field's value doesn't correspond to any known constant.
+    UnknownValue { field: String, value: i64 },
 
-pub enum Error {
-    BadValue{ expected: String, got: String },
     IO(io::Error),
     Message(String),
-    UTF8(string::FromUtf8Error),
 }
 
 impl Error {
-    pub fn bad_value<S1, S2>(expected: S1, got: S2) -> Error
-        where S1: Into<String>, S2: Into<String>
+    pub fn new(kind: ErrorKind) -> Error {
+        Error {
+            kind,
+            trace: None,
+            index: None,
+            offset: None,
+            record_type: None,
+            header: None,
+        }
+    }
+
+    pub fn truncated(expected: usize, got: usize) -> Error {
+        Error::new(ErrorKind::Truncated { expected, got })
+    }
+
+    pub fn bad_length<S>(expected: S, got: usize) -> Error
+        where S: Into<String>
     {
-        Error::BadValue{
+        Error::new(ErrorKind::BadLength {
             expected: expected.into(),
-            got: got.into(),
-        }
+            got,
+        })
+    }
+
+    pub fn unknown_type(value: u16) -> Error {
+        Error::new(ErrorKind::UnknownType(value))
+    }
+
+    pub fn unknown_value<S, V>(field: S, value: V) -> Error
+        where S: Into<String>, V: Into<i64>
+    {
+        Error::new(ErrorKind::UnknownValue {
+            field: field.into(),
+            value: value.into(),
+        })
+    }
+
+    pub fn utf8(e: str::Utf8Error) -> Error {
+        Error::new(ErrorKind::InvalidUtf8(e))
+    }
+
+    pub fn io(e: io::Error) -> Error {
+        Error::new(ErrorKind::IO(e))
     }
 
     pub fn msg<Str>(s: Str) -> Error
         where Str: Into<String>
     {
-        Error::Message(s.into())
+        Error::new(ErrorKind::Message(s.into()))
+    }
+
+    /// Record the index and byte offset of the header or record that
+    /// caused the error.
+    pub fn at(mut self, index: usize, offset: u64) -> Error {
+        self.index = Some(index);
+        self.offset = Some(offset);
+        self
+    }
+
+    /// Record which of several traces (e.g., the sources of a `Merge`)
+    /// the error occurred in.
+    pub fn in_trace(mut self, trace: usize) -> Error {
+        self.trace = Some(trace);
+        self
+    }
+
+    /// Record the header of the record that caused the error.
+    pub fn in_record(mut self, header: &Header) -> Error {
+        self.record_type = Some(header.record_type.clone());
+        self.header = Some(Box::new(header.clone()));
+        self
+    }
+
+    /// Record the type of record that was being parsed.
+    pub fn of_type(mut self, t: &RecordType) -> Error {
+        self.record_type = Some(t.clone());
+        self
+    }
+
+    pub fn kind(&self) -> &ErrorKind {
+        &self.kind
+    }
+
+    /// Which of several traces the error occurred in, if known.
+    pub fn trace(&self) -> Option<usize> {
+        self.trace
+    }
+
+    /// Index of the offending record within the trace, if known.
+    pub fn index(&self) -> Option<usize> {
+        self.index
+    }
+
+    /// Byte offset of the offending header within the trace, if known.
+    pub fn offset(&self) -> Option<u64> {
+        self.offset
+    }
+
+    pub fn record_type(&self) -> Option<&RecordType> {
+        self.record_type.as_ref()
+    }
+
+    pub fn header(&self) -> Option<&Header> {
+        self.header.as_deref()
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Error {
+        Error::io(e)
+    }
+}
+
+impl From<string::FromUtf8Error> for Error {
+    fn from(e: string::FromUtf8Error) -> Error {
+        Error::utf8(e.utf8_error())
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self.kind {
+            ErrorKind::IO(ref e) => Some(e),
+            ErrorKind::InvalidUtf8(ref e) => Some(e),
+            _ => None,
+        }
     }
 }
 
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
-        match self {
-            &Error::BadValue{ref expected, ref got} => {
-                write![f, "bad value: expected {}, got {}", expected, got]
+        if let Some(trace) = self.trace {
+            write![f, "trace #{}: ", trace]?;
+        }
+
+        match (self.index, self.offset) {
+            (Some(i), Some(offset)) => {
+                write![f, "record #{} at offset 0x{:x}: ", i, offset]?
             },
+            (Some(i), None) => write![f, "record #{}: ", i]?,
+            (None, Some(offset)) => write![f, "offset 0x{:x}: ", offset]?,
+            (None, None) => {},
+        }
 
-            &Error::IO(ref e) => {
-                write![f, "I/O error: {}", e]
+        match self.kind {
+            ErrorKind::Truncated{expected, got} => {
+                write![f, "truncated {}: expected {} B, got {}",
+                       self.record_type.as_ref()
+                                       .map(|t| t.to_string())
+                                       .unwrap_or("header".to_string()),
+                       expected, got]
             },
 
-            &Error::Message(ref message) => {
-                write![f, "{}", message]
+            ErrorKind::BadLength{ref expected, got} => {
+                match self.record_type {
+                    Some(ref t) => write![f, "{} length {}", t, got]?,
+                    None => write![f, "length {}", got]?,
+                }
+
+                write![f, ", expected {}", expected]
+            },
+
+            ErrorKind::UnknownType(t) => {
+                write![f, "unknown record type {}", t]
             },
 
-            &Error::UTF8(ref e) => {
-                write![f, "UTF8 error: {}", e]
+            ErrorKind::InvalidUtf8(ref e) => {
+                match self.record_type {
+                    Some(ref t) => write![f, "invalid UTF-8 in {}: {}", t, e],
+                    None => write![f, "invalid UTF-8: {}", e],
+                }
+            },
+
+            ErrorKind::UnknownValue{ref field, value} => {
+                write![f, "unknown {} value {}", field, value]
+            },
+
+            ErrorKind::IO(ref e) => {
+                write![f, "I/O error: {}", e]
+            },
+
+            ErrorKind::Message(ref message) => {
+                write![f, "{}", message]
             },
         }
     }
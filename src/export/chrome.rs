@@ -0,0 +1,201 @@
+// Copyright 2016 Jonathan Anderson <jonathan.anderson@mun.ca>
+//
+// This software was developed by BAE Systems, the University of Cambridge
+// Computer Laboratory, and Memorial University under DARPA/AFRL contract
+// FA8650-15-C-7558 ("CADETS"), as part of the DARPA Transparent Computing
+// (TC) research program.
+//
+// Licensed under the Apache License, Version 2.0,
+// <LICENSE-APACHE or http://apache.org/licenses/LICENSE-2.0>
+// or the MIT license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+
+extern crate nix;
+
+use self::nix::sys::time::TimeVal;
+use std::collections::{HashMap,HashSet};
+use std::io::{self,Write};
+use ::{ByteString,CallTracker,Header,Record,errno,fault,json};
+
+
+/// Writes a trace in the Chrome Trace Event format
+///
+/// The output can be loaded by ui.perfetto.dev or chrome://tracing.
+/// Each thread is a track, system calls are duration slices and context
+/// switches and page faults are slices nested within them.
+pub struct ChromeTrace<W: Write> {
+    out: W,
+    calls: CallTracker,
+    events: usize,
+
+    /// when each thread was switched out, and why
+    off_cpu: HashMap<(u32, usize), (TimeVal, String)>,
+
+    /// the start, address and type of each thread's current page fault
+    faults: HashMap<(u32, usize), (TimeVal, u64, u32)>,
+
+    commands: HashMap<u32, ByteString>,
+    threads: HashSet<(u32, usize)>,
+}
+
+
+impl<W: Write> ChromeTrace<W> {
+    pub fn new(mut out: W) -> io::Result<ChromeTrace<W>> {
+        writeln![out, "{{\"displayTimeUnit\":\"ns\",\"traceEvents\":["]?;
+
+        Ok(ChromeTrace {
+            out,
+            calls: CallTracker::new(),
+            events: 0,
+            off_cpu: HashMap::new(),
+            faults: HashMap::new(),
+            commands: HashMap::new(),
+            threads: HashSet::new(),
+        })
+    }
+
+    /// Convert the next record in a trace into trace events.
+    pub fn record(&mut self, header: &Header, record: &Record) -> io::Result<()> {
+        let key = (header.pid, header.tid);
+        self.name_track(header)?;
+
+        if let Some(call) = self.calls.record(header, record) {
+            let args = format!["{{\"args\":{},\"retval\":{},\"error\":{}}}",
+                json::string(&call.args.iter()
+                                       .map(|x| format!["0x{:x}", x])
+                                       .collect::<Vec<_>>()
+                                       .join(", ")),
+                json::string(&format!["0x{:x}", call.retval]),
+                json::string(&if call.error == 0 {
+                    "0".to_string()
+                } else {
+                    errno::name(call.error).unwrap_or(call.error.to_string())
+                })];
+
+            match call.start {
+                Some(start) => {
+                    self.slice(header, &call.name(), "syscall", start, call.end,
+                               &args)?;
+                },
+                None => {
+                    self.instant(header, &format!["{} (return)", call.name()],
+                                 "syscall", &args)?;
+                },
+            }
+        }
+
+        match *record {
+            Record::ContextSwitch{out: true, ref message, ..} => {
+                self.off_cpu.insert(key, (header.timestamp, message.to_string()));
+            },
+
+            Record::ContextSwitch{out: false, ref message, ..} => {
+                if let Some((start, wmesg)) = self.off_cpu.remove(&key) {
+                    let name = if wmesg.is_empty() { message.to_string() } else { wmesg };
+                    self.slice(header, &format!["off-CPU: {}", name], "sched",
+                               start, header.timestamp, "{}")?;
+                }
+            },
+
+            Record::PageFault{virtual_address, fault_type} => {
+                self.faults.insert(key, (header.timestamp, virtual_address,
+                                         fault_type));
+            },
+
+            Record::PageFaultEnd{result} => {
+                if let Some((start, addr, fault_type)) = self.faults.remove(&key) {
+                    self.slice(header, "page fault", "fault", start,
+                               header.timestamp,
+                               &format!["{{\"address\":\"0x{:x}\",\"type\":{},\
+                                          \"result\":{}}}",
+                                        addr, json::string(&fault::protection(fault_type)),
+                                        json::string(&fault::result(result)
+                                                          .unwrap_or(result.to_string()))])?;
+                }
+            },
+
+            Record::Signal{signo, code, ..} => {
+                self.instant(header, &format!["signal {}", signo], "signal",
+                             &format!["{{\"signo\":{},\"code\":{}}}", signo, code])?;
+            },
+
+            Record::CapabilityFailure(ref failure) => {
+                self.instant(header, "capability failure", "capability",
+                             &format!["{{\"failure\":{}}}",
+                                      json::string(&failure.to_string())])?;
+            },
+
+            _ => {},
+        }
+
+        Ok(())
+    }
+
+    /// Finish the JSON document, returning the underlying writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        write![self.out, "\n]}}\n"]?;
+        self.out.flush()?;
+        Ok(self.out)
+    }
+
+    /// Name the process and thread tracks that a record belongs to.
+    fn name_track(&mut self, header: &Header) -> io::Result<()> {
+        let renamed = self.commands.get(&header.pid)
+                                   .map(|c| *c != header.command)
+                                   .unwrap_or(true);
+
+        if renamed {
+            self.commands.insert(header.pid, header.command.clone());
+            self.event(&format!["\"name\":\"process_name\",\"ph\":\"M\",\
+                                 \"pid\":{},\"tid\":{},\
+                                 \"args\":{{\"name\":{}}}",
+                                header.pid, header.tid,
+                                json::string(&format!["{} ({})", header.command,
+                                                      header.pid])])?;
+        }
+
+        if self.threads.insert((header.pid, header.tid)) {
+            self.event(&format!["\"name\":\"thread_name\",\"ph\":\"M\",\
+                                 \"pid\":{},\"tid\":{},\
+                                 \"args\":{{\"name\":{}}}",
+                                header.pid, header.tid,
+                                json::string(&format!["{} {}", header.command,
+                                                      header.tid])])?;
+        }
+
+        Ok(())
+    }
+
+    fn slice(&mut self, header: &Header, name: &str, category: &str,
+             start: TimeVal, end: TimeVal, args: &str) -> io::Result<()>
+    {
+        self.event(&format!["\"name\":{},\"cat\":\"{}\",\"ph\":\"X\",\
+                             \"ts\":{},\"dur\":{},\"pid\":{},\"tid\":{},\
+                             \"args\":{}",
+                            json::string(name), category,
+                            ::microseconds(&start),
+                            ::microseconds(&end).saturating_sub(::microseconds(&start)),
+                            header.pid, header.tid, args])
+    }
+
+    fn instant(&mut self, header: &Header, name: &str, category: &str,
+               args: &str) -> io::Result<()>
+    {
+        self.event(&format!["\"name\":{},\"cat\":\"{}\",\"ph\":\"i\",\"s\":\"t\",\
+                             \"ts\":{},\"pid\":{},\"tid\":{},\"args\":{}",
+                            json::string(name), category,
+                            ::microseconds(&header.timestamp),
+                            header.pid, header.tid, args])
+    }
+
+    fn event(&mut self, body: &str) -> io::Result<()> {
+        if self.events > 0 {
+            writeln![self.out, ","]?;
+        }
+
+        self.events += 1;
+        write![self.out, "{{{}}}", body]
+    }
+}
@@ -0,0 +1,600 @@
+// Copyright 2016 Jonathan Anderson <jonathan.anderson@mun.ca>
+//
+// This software was developed by BAE Systems, the University of Cambridge
+// Computer Laboratory, and Memorial University under DARPA/AFRL contract
+// FA8650-15-C-7558 ("CADETS"), as part of the DARPA Transparent Computing
+// (TC) research program.
+//
+// Licensed under the Apache License, Version 2.0,
+// <LICENSE-APACHE or http://apache.org/licenses/LICENSE-2.0>
+// or the MIT license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use byteorder::{BigEndian,ByteOrder,LittleEndian,NativeEndian,WriteBytesExt};
+use std::collections::HashMap;
+use std::io::{self,Write};
+use std::net::{IpAddr,Ipv4Addr,SocketAddr};
+use ::{ByteString,CallTracker,Descriptor,FdTracker,Header,IODir,Record,SockAddr};
+
+const BLOCK_SECTION_HEADER: u32 = 0x0a0d_0d0a;
+const BLOCK_INTERFACE: u32 = 1;
+const BLOCK_ENHANCED_PACKET: u32 = 6;
+const BYTE_ORDER_MAGIC: u32 = 0x1a2b_3c4d;
+
+const OPT_END: u16 = 0;
+const OPT_COMMENT: u16 = 1;
+const OPT_SHB_USERAPPL: u16 = 4;
+const OPT_IF_NAME: u16 = 2;
+const OPT_IF_DESCRIPTION: u16 = 3;
+
+/// Raw IPv4 or IPv6 packets, without link-layer framing
+const LINKTYPE_RAW: u16 = 101;
+
+/// Interfaces that packets are "captured" on
+const INTERFACE_INET: u32 = 0;
+const INTERFACE_UNIX: u32 = 1;
+
+const SOCK_STREAM: i32 = 1;
+const SOCK_DGRAM: i32 = 2;
+const SOCK_SEQPACKET: i32 = 5;
+
+const IPPROTO_TCP: u8 = 6;
+const IPPROTO_UDP: u8 = 17;
+
+const TCP_FIN: u16 = 0x01;
+const TCP_SYN: u16 = 0x02;
+const TCP_PSH: u16 = 0x08;
+const TCP_ACK: u16 = 0x10;
+
+/// The most data that we put in one synthetic packet
+const MAX_SEGMENT: usize = 65000;
+
+/// Ports for synthetic addresses (Unix sockets, or unbound local ends)
+const FIRST_EPHEMERAL_PORT: u16 = 49152;
+const FIRST_UNIX_PORT: u16 = 1024;
+
+
+/// Writes the data sent and received on sockets as a pcapng capture
+///
+/// Each connection's `GenericIO` records are wrapped in synthetic TCP or
+/// UDP (and IPv4 or IPv6) headers, so that Wireshark can follow streams
+/// as if the traffic had been captured from the network. TCP streams get
+/// a synthetic handshake when first seen and a FIN when closed. Unix
+/// sockets are framed as TCP or UDP over 127.0.0.0/8 on their own
+/// interface, with one port for each socket path. Unbound local addresses
+/// are given ephemeral ports. Every packet is annotated with the process,
+/// descriptor and socket it came from.
+///
+/// Only sockets whose creation was traced can be identified, so I/O on
+/// descriptors inherited from before tracing began is ignored.
+pub struct PcapNg<W: Write> {
+    out: W,
+    fds: FdTracker,
+    calls: CallTracker,
+
+    /// connections, by the process and descriptor they were used through
+    connections: HashMap<(u32, i32), Connection>,
+    next_port: u16,
+    unix_ports: HashMap<ByteString, u16>,
+    ip_id: u16,
+    packets: usize,
+
+    /// data received on unconnected sockets by calls that haven't returned,
+    /// by process and thread
+    received: HashMap<(u32, usize), Vec<Received>>,
+}
+
+/// Data received before we know who sent it
+struct Received {
+    header: Header,
+    fd: i32,
+    descriptor: Descriptor,
+    data: Vec<u8>,
+}
+
+/// The synthetic framing of one socket's traffic
+struct Connection {
+    descriptor: Descriptor,
+    tcp: bool,
+    interface: u32,
+    local: SocketAddr,
+    peer: SocketAddr,
+
+    /// the next TCP sequence numbers to send from each end
+    local_seq: u32,
+    peer_seq: u32,
+
+    comment: String,
+}
+
+
+impl<W: Write> PcapNg<W> {
+    pub fn new(mut out: W) -> io::Result<PcapNg<W>> {
+        let mut options = vec![];
+        option(&mut options, OPT_SHB_USERAPPL, b"ktrace-rs");
+        end_options(&mut options);
+
+        let mut body = vec![];
+        body.write_u32::<LittleEndian>(BYTE_ORDER_MAGIC)?;
+        body.write_u16::<LittleEndian>(1)?;
+        body.write_u16::<LittleEndian>(0)?;
+        body.write_i64::<LittleEndian>(-1)?;    // section length: unknown
+        body.extend(options);
+        block(&mut out, BLOCK_SECTION_HEADER, &body)?;
+
+        for &(name, description) in &[
+            ("inet", "IPv4 and IPv6 sockets"),
+            ("unix", "Unix sockets (synthetic loopback addresses)"),
+        ] {
+            let mut body = vec![];
+            body.write_u16::<LittleEndian>(LINKTYPE_RAW)?;
+            body.write_u16::<LittleEndian>(0)?;
+            body.write_u32::<LittleEndian>(0)?;     // no snapshot length
+            option(&mut body, OPT_IF_NAME, name.as_bytes());
+            option(&mut body, OPT_IF_DESCRIPTION, description.as_bytes());
+            end_options(&mut body);
+            block(&mut out, BLOCK_INTERFACE, &body)?;
+        }
+
+        Ok(PcapNg {
+            out,
+            fds: FdTracker::new(),
+            calls: CallTracker::new(),
+            connections: HashMap::new(),
+            next_port: FIRST_EPHEMERAL_PORT,
+            unix_ports: HashMap::new(),
+            ip_id: 0,
+            packets: 0,
+            received: HashMap::new(),
+        })
+    }
+
+    /// Convert the next record in a trace into packets (if it is socket I/O).
+    pub fn record(&mut self, header: &Header, record: &Record) -> io::Result<()> {
+        let descriptor = self.fds.record(header, record);
+        let call = self.calls.record(header, record);
+        let thread = (header.pid, header.tid);
+
+        match *record {
+            Record::GenericIO{fd, ref rw, ref data} if !data.is_empty() => {
+                let descriptor = match descriptor {
+                    Some(d @ Descriptor::Socket{..}) => d,
+                    _ => return Ok(()),
+                };
+
+                // Unconnected datagram sockets name their peer in each call,
+                // but recvfrom(2) copies the sender's address out after the
+                // data, so received data waits for the call to return.
+                let unconnected = matches!(descriptor, Descriptor::Socket{peer: None, ..});
+                let pending = self.calls.pending(header.pid, header.tid);
+                if unconnected && matches!(*rw, IODir::Read) && pending.is_some() {
+                    self.received.entry(thread).or_default().push(Received {
+                        header: header.clone(),
+                        fd,
+                        descriptor,
+                        data: data.clone(),
+                    });
+                    return Ok(());
+                }
+
+                let peer = pending.and_then(|c| c.find_struct("sockaddr"))
+                                  .and_then(|s| SockAddr::parse::<NativeEndian>(s).ok());
+
+                self.data(header, fd, descriptor, peer, rw, data)
+            },
+
+            Record::SystemCallReturn{..} => {
+                let peer = call.as_ref()
+                               .and_then(|c| c.find_struct("sockaddr"))
+                               .and_then(|s| SockAddr::parse::<NativeEndian>(s).ok());
+
+                self.flush(thread, peer)
+            },
+
+            Record::SystemCall{ref args, ..} => {
+                let is_close = self.calls
+                                   .pending(header.pid, header.tid)
+                                   .is_some_and(|c| c.name() == "close");
+
+                match args.first() {
+                    Some(&fd) if is_close => self.close(header, fd as i32),
+                    _ => Ok(()),
+                }
+            },
+
+            _ => Ok(()),
+        }
+    }
+
+    /// The number of packets written so far.
+    pub fn packets(&self) -> usize {
+        self.packets
+    }
+
+    /// Finish the capture, returning the underlying writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        // Calls still in progress when the trace ended
+        let threads = self.received.keys().cloned().collect::<Vec<_>>();
+        for thread in threads {
+            self.flush(thread, None)?;
+        }
+
+        self.out.flush()?;
+        Ok(self.out)
+    }
+
+    /// Write the data that a thread has received from `peer`.
+    fn flush(&mut self, thread: (u32, usize), peer: Option<SockAddr>)
+        -> io::Result<()>
+    {
+        for r in self.received.remove(&thread).unwrap_or_default() {
+            self.data(&r.header, r.fd, r.descriptor, peer.clone(), &IODir::Read,
+                      &r.data)?;
+        }
+
+        Ok(())
+    }
+
+    fn data(&mut self, header: &Header, fd: i32, descriptor: Descriptor,
+            peer: Option<SockAddr>, rw: &IODir, data: &[u8]) -> io::Result<()>
+    {
+        let key = (header.pid, fd);
+
+        // A descriptor number that now refers to something else is a new
+        // connection.
+        let stale = self.connections.get(&key)
+                                    .map(|c| c.descriptor != descriptor)
+                                    .unwrap_or(false);
+        if stale {
+            self.close(header, fd)?;
+        }
+
+        if !self.connections.contains_key(&key) {
+            let connection = match self.connection(header, fd, &descriptor, peer) {
+                Some(c) => c,
+                None => return Ok(()),
+            };
+
+            let tcp = connection.tcp;
+            self.connections.insert(key, connection);
+
+            if tcp {
+                self.handshake(header, key)?;
+            }
+        }
+
+        for chunk in data.chunks(MAX_SEGMENT) {
+            let outbound = matches!(*rw, IODir::Write);
+            self.packet(header, key, outbound, TCP_PSH | TCP_ACK, chunk)?;
+        }
+
+        Ok(())
+    }
+
+    /// Describe a socket's traffic, if it can be framed as TCP or UDP.
+    fn connection(&mut self, header: &Header, fd: i32, descriptor: &Descriptor,
+                  call_peer: Option<SockAddr>) -> Option<Connection>
+    {
+        let (sock_type, local, peer) = match *descriptor {
+            Descriptor::Socket{sock_type, ref local, ref peer, ..} => {
+                (sock_type, local.clone(), peer.clone().or(call_peer))
+            },
+            _ => return None,
+        };
+
+        let tcp = match sock_type {
+            SOCK_STREAM | SOCK_SEQPACKET => true,
+            SOCK_DGRAM => false,
+            _ => return None,
+        };
+
+        let describe = |a: &Option<SockAddr>| {
+            a.as_ref().map(|a| a.to_string()).unwrap_or("?".to_string())
+        };
+        let comment = format!["{} (pid {}) fd {}: {} -> {}", header.command,
+                              header.pid, fd, describe(&local), describe(&peer)];
+
+        let unix = matches!(local, Some(SockAddr::Unix(_)))
+                   || matches!(peer, Some(SockAddr::Unix(_)));
+
+        let (interface, local, peer) = if unix {
+            let path = |a: &Option<SockAddr>| match *a {
+                Some(SockAddr::Unix(ref path)) => Some(path.clone()),
+                _ => None,
+            };
+
+            // The named end of the socket gets a port of its own.
+            let (name, named_is_local) = match (path(&local), path(&peer)) {
+                (_, Some(p)) if !p.is_empty() => (p, false),
+                (Some(p), _) => (p, true),
+                (_, Some(p)) => (p, false),
+                (None, None) => return None,
+            };
+
+            let next = FIRST_UNIX_PORT + self.unix_ports.len() as u16;
+            let named_port = *self.unix_ports.entry(name).or_insert(next);
+            let named = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 2)),
+                                        named_port);
+            let other = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)),
+                                        self.ephemeral_port());
+
+            if named_is_local {
+                (INTERFACE_UNIX, named, other)
+            } else {
+                (INTERFACE_UNIX, other, named)
+            }
+        } else {
+            let inet = |a: &Option<SockAddr>| match *a {
+                Some(SockAddr::Inet(a)) => Some(SocketAddr::V4(a)),
+                Some(SockAddr::Inet6(a)) => Some(SocketAddr::V6(a)),
+                _ => None,
+            };
+
+            let peer = inet(&peer)?;
+            let local = match inet(&local) {
+                Some(ref a) if a.port() != 0 && !a.ip().is_unspecified() => *a,
+                Some(ref a) if a.port() != 0 => {
+                    SocketAddr::new(unspecified(&peer), a.port())
+                },
+                _ => SocketAddr::new(unspecified(&peer), self.ephemeral_port()),
+            };
+
+            // Both ends of the synthetic packets must be the same family.
+            if local.is_ipv4() != peer.is_ipv4() {
+                return None;
+            }
+
+            (INTERFACE_INET, local, peer)
+        };
+
+        Some(Connection {
+            descriptor: descriptor.clone(),
+            tcp,
+            interface,
+            local,
+            peer,
+            local_seq: 1,
+            peer_seq: 1,
+            comment,
+        })
+    }
+
+    fn ephemeral_port(&mut self) -> u16 {
+        let port = self.next_port;
+        self.next_port = self.next_port.checked_add(1).unwrap_or(FIRST_EPHEMERAL_PORT);
+        port
+    }
+
+    fn handshake(&mut self, header: &Header, key: (u32, i32)) -> io::Result<()> {
+        self.packet(header, key, true, TCP_SYN, &[])?;
+        self.packet(header, key, false, TCP_SYN | TCP_ACK, &[])?;
+        self.packet(header, key, true, TCP_ACK, &[])
+    }
+
+    /// Forget a connection, closing it (from the local end) if it's TCP.
+    fn close(&mut self, header: &Header, fd: i32) -> io::Result<()> {
+        let key = (header.pid, fd);
+
+        if self.connections.get(&key).map(|c| c.tcp).unwrap_or(false) {
+            self.packet(header, key, true, TCP_FIN | TCP_ACK, &[])?;
+        }
+
+        self.connections.remove(&key);
+        Ok(())
+    }
+
+    /// Write one synthetic packet on a connection.
+    fn packet(&mut self, header: &Header, key: (u32, i32), outbound: bool,
+              flags: u16, data: &[u8]) -> io::Result<()>
+    {
+        self.ip_id = self.ip_id.wrapping_add(1);
+        let ip_id = self.ip_id;
+
+        let c = match self.connections.get_mut(&key) {
+            Some(c) => c,
+            None => return Ok(()),
+        };
+
+        let (src, dst) = if outbound { (c.local, c.peer) } else { (c.peer, c.local) };
+
+        let transport = if c.tcp {
+            let (seq, ack) = if outbound {
+                (c.local_seq, c.peer_seq)
+            } else {
+                (c.peer_seq, c.local_seq)
+            };
+
+            // SYN and FIN each consume a sequence number.
+            let consumed = data.len() as u32 + (flags & (TCP_SYN | TCP_FIN) != 0) as u32;
+            if outbound {
+                c.local_seq = c.local_seq.wrapping_add(consumed);
+            } else {
+                c.peer_seq = c.peer_seq.wrapping_add(consumed);
+            }
+
+            // Only the first SYN doesn't acknowledge anything.
+            let ack = if flags & TCP_ACK != 0 { ack } else { 0 };
+            tcp(src.port(), dst.port(), seq, ack, flags, data)
+        } else {
+            udp(src.port(), dst.port(), data)
+        };
+
+        let protocol = if c.tcp { IPPROTO_TCP } else { IPPROTO_UDP };
+        let packet = ip(src.ip(), dst.ip(), protocol, ip_id, transport);
+
+        let comment = format!["{} {}", c.comment,
+                              if outbound { "(sent)" } else { "(received)" }];
+        let interface = c.interface;
+
+        self.write_packet(header, interface, &packet, &comment)
+    }
+
+    fn write_packet(&mut self, header: &Header, interface: u32, packet: &[u8],
+                    comment: &str) -> io::Result<()>
+    {
+        let time = ::microseconds(&header.timestamp).max(0) as u64;
+
+        let mut body = vec![];
+        body.write_u32::<LittleEndian>(interface)?;
+        body.write_u32::<LittleEndian>((time >> 32) as u32)?;
+        body.write_u32::<LittleEndian>(time as u32)?;
+        body.write_u32::<LittleEndian>(packet.len() as u32)?;
+        body.write_u32::<LittleEndian>(packet.len() as u32)?;
+        body.extend(packet);
+        pad(&mut body);
+
+        option(&mut body, OPT_COMMENT, comment.as_bytes());
+        end_options(&mut body);
+
+        self.packets += 1;
+        block(&mut self.out, BLOCK_ENHANCED_PACKET, &body)
+    }
+}
+
+
+/// The unspecified address of the same family as `peer`.
+fn unspecified(peer: &SocketAddr) -> IpAddr {
+    match *peer {
+        SocketAddr::V4(_) => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+        SocketAddr::V6(_) => IpAddr::V6(::std::net::Ipv6Addr::UNSPECIFIED),
+    }
+}
+
+/// Write a block: type, length, body (already padded) and length again.
+fn block(out: &mut dyn Write, block_type: u32, body: &[u8]) -> io::Result<()> {
+    let length = 12 + body.len() as u32;
+
+    out.write_u32::<LittleEndian>(block_type)?;
+    out.write_u32::<LittleEndian>(length)?;
+    out.write_all(body)?;
+    out.write_u32::<LittleEndian>(length)
+}
+
+fn option(out: &mut Vec<u8>, code: u16, value: &[u8]) {
+    let mut header = [0; 4];
+    LittleEndian::write_u16(&mut header[0..2], code);
+    LittleEndian::write_u16(&mut header[2..4], value.len() as u16);
+
+    out.extend(&header);
+    out.extend(value);
+    pad(out);
+}
+
+fn end_options(out: &mut Vec<u8>) {
+    option(out, OPT_END, &[]);
+}
+
+/// Pad to a multiple of 32 bits.
+fn pad(out: &mut Vec<u8>) {
+    while !out.len().is_multiple_of(4) {
+        out.push(0);
+    }
+}
+
+fn tcp(src: u16, dst: u16, seq: u32, ack: u32, flags: u16, data: &[u8]) -> Vec<u8> {
+    let mut segment = vec![0; 20];
+    BigEndian::write_u16(&mut segment[0..2], src);
+    BigEndian::write_u16(&mut segment[2..4], dst);
+    BigEndian::write_u32(&mut segment[4..8], seq);
+    BigEndian::write_u32(&mut segment[8..12], ack);
+    BigEndian::write_u16(&mut segment[12..14], (5 << 12) | flags);
+    BigEndian::write_u16(&mut segment[14..16], 65535);
+    segment.extend(data);
+    segment
+}
+
+fn udp(src: u16, dst: u16, data: &[u8]) -> Vec<u8> {
+    let mut datagram = vec![0; 8];
+    BigEndian::write_u16(&mut datagram[0..2], src);
+    BigEndian::write_u16(&mut datagram[2..4], dst);
+    BigEndian::write_u16(&mut datagram[4..6], (8 + data.len()).min(65535) as u16);
+    datagram.extend(data);
+    datagram
+}
+
+/// Wrap a TCP segment or UDP datagram in an IP header, filling in the
+/// transport checksum (which covers a pseudo-header of IP addresses).
+fn ip(src: IpAddr, dst: IpAddr, protocol: u8, id: u16, mut transport: Vec<u8>)
+    -> Vec<u8>
+{
+    let checksum_at = if protocol == IPPROTO_TCP { 16 } else { 6 };
+    let length = transport.len();
+
+    let mut pseudo = vec![];
+    match (src, dst) {
+        (IpAddr::V4(s), IpAddr::V4(d)) => {
+            pseudo.extend(&s.octets());
+            pseudo.extend(&d.octets());
+            pseudo.extend(&[0, protocol]);
+            pseudo.extend(&(length as u16).to_be_bytes());
+        },
+        (s, d) => {
+            pseudo.extend(&v6(s).octets());
+            pseudo.extend(&v6(d).octets());
+            pseudo.extend(&(length as u32).to_be_bytes());
+            pseudo.extend(&[0, 0, 0, protocol]);
+        },
+    }
+
+    let mut sum = checksum(&pseudo, &transport);
+    if sum == 0 && protocol == IPPROTO_UDP {
+        sum = 0xffff;
+    }
+    BigEndian::write_u16(&mut transport[checksum_at..checksum_at + 2], sum);
+
+    match (src, dst) {
+        (IpAddr::V4(s), IpAddr::V4(d)) => {
+            let mut packet = vec![0; 20];
+            packet[0] = 0x45;
+            BigEndian::write_u16(&mut packet[2..4], (20 + length).min(65535) as u16);
+            BigEndian::write_u16(&mut packet[4..6], id);
+            BigEndian::write_u16(&mut packet[6..8], 0x4000);   // don't fragment
+            packet[8] = 64;
+            packet[9] = protocol;
+            packet[12..16].copy_from_slice(&s.octets());
+            packet[16..20].copy_from_slice(&d.octets());
+
+            let sum = checksum(&packet, &[]);
+            BigEndian::write_u16(&mut packet[10..12], sum);
+
+            packet.extend(transport);
+            packet
+        },
+        (s, d) => {
+            let mut packet = vec![0; 40];
+            packet[0] = 0x60;
+            BigEndian::write_u16(&mut packet[4..6], length.min(65535) as u16);
+            packet[6] = protocol;
+            packet[7] = 64;
+            packet[8..24].copy_from_slice(&v6(s).octets());
+            packet[24..40].copy_from_slice(&v6(d).octets());
+
+            packet.extend(transport);
+            packet
+        },
+    }
+}
+
+fn v6(a: IpAddr) -> ::std::net::Ipv6Addr {
+    match a {
+        IpAddr::V4(a) => a.to_ipv6_mapped(),
+        IpAddr::V6(a) => a,
+    }
+}
+
+/// The Internet checksum of two buffers, taken together.
+fn checksum(a: &[u8], b: &[u8]) -> u16 {
+    let mut sum = a.iter()
+                   .chain(b)
+                   .collect::<Vec<_>>()
+                   .chunks(2)
+                   .map(|w| ((*w[0] as u32) << 8) | w.get(1).map(|&&x| x as u32).unwrap_or(0))
+                   .sum::<u32>();
+
+    while sum > 0xffff {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+
+    !(sum as u16)
+}
@@ -0,0 +1,379 @@
+// Copyright 2016 Jonathan Anderson <jonathan.anderson@mun.ca>
+//
+// This software was developed by BAE Systems, the University of Cambridge
+// Computer Laboratory, and Memorial University under DARPA/AFRL contract
+// FA8650-15-C-7558 ("CADETS"), as part of the DARPA Transparent Computing
+// (TC) research program.
+//
+// Licensed under the Apache License, Version 2.0,
+// <LICENSE-APACHE or http://apache.org/licenses/LICENSE-2.0>
+// or the MIT license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+
+extern crate nix;
+
+use self::nix::sys::time::TimeVal;
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self,Write};
+use access::dirfd;
+use ::{ByteString,Call,CallTracker,Descriptor,FdTracker,Header,Record,json};
+
+
+/// An entity in a provenance graph
+#[derive(Clone,Debug,Eq,Hash,PartialEq)]
+pub enum Node {
+    Process { pid: u32 },
+    File(ByteString),
+    Socket(String),
+
+    /// a socket without an address (e.g., from `socketpair(2)`)
+    UnnamedSocket { pid: u32, fd: i32 },
+
+    Pipe(usize),
+}
+
+/// A causal relationship between two entities
+#[derive(Clone,Copy,Debug,Eq,Hash,PartialEq)]
+pub enum EdgeKind {
+    Read,
+    Write,
+    Exec,
+    Fork,
+    Connect,
+    Accept,
+    Rename,
+    Unlink,
+}
+
+/// All of the events of one kind between the same two nodes
+#[derive(Clone,Debug)]
+pub struct Edge {
+    pub from: usize,
+    pub to: usize,
+    pub kind: EdgeKind,
+    pub count: u64,
+    pub first: TimeVal,
+    pub last: TimeVal,
+}
+
+/// Builds a causal graph of processes, files, sockets and pipes
+#[derive(Default)]
+pub struct ProvenanceGraph {
+    nodes: Vec<Node>,
+    ids: HashMap<Node, usize>,
+
+    /// commands run by each process, in the order they were run
+    commands: HashMap<u32, Vec<ByteString>>,
+
+    edges: Vec<Edge>,
+    edge_ids: HashMap<(usize, usize, EdgeKind), usize>,
+
+    calls: CallTracker,
+    fds: FdTracker,
+}
+
+
+impl fmt::Display for EdgeKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match *self {
+            EdgeKind::Read => "read",
+            EdgeKind::Write => "write",
+            EdgeKind::Exec => "exec",
+            EdgeKind::Fork => "fork",
+            EdgeKind::Connect => "connect",
+            EdgeKind::Accept => "accept",
+            EdgeKind::Rename => "rename",
+            EdgeKind::Unlink => "unlink",
+        };
+
+        write![f, "{}", name]
+    }
+}
+
+
+impl ProvenanceGraph {
+    pub fn new() -> ProvenanceGraph {
+        ProvenanceGraph::default()
+    }
+
+    pub fn nodes(&self) -> &[Node] {
+        &self.nodes
+    }
+
+    pub fn edges(&self) -> &[Edge] {
+        &self.edges
+    }
+
+    /// Account for the next record in a trace.
+    pub fn record(&mut self, header: &Header, record: &Record) {
+        let process = self.process(header);
+
+        // Look up the descriptor that a call operates on before the tracker
+        // applies the call's effects (e.g., closing a close-on-exec
+        // descriptor passed to fexecve(2)). The tracker's view afterwards
+        // includes new descriptors and connect(2)'s peer address.
+        // Paths are resolved beforehand for the same reason.
+        let call = self.calls.record(header, record);
+        let operand = call.as_ref()
+                          .and_then(|c| c.fd(0))
+                          .and_then(|fd| self.fds.lookup(header.pid, fd)
+                                             .map(|d| (fd, d.clone())));
+        let paths = call.as_ref()
+                        .map(|c| self.resolve(c))
+                        .unwrap_or_default();
+        let descriptor = self.fds.record(header, record);
+
+        let call = match call {
+            Some(ref c) if c.succeeded() => c,
+            _ => return,
+        };
+
+        let name = call.name();
+        match name.as_str() {
+            "read" | "readv" | "pread" | "preadv" | "recv" | "recvfrom" |
+            "recvmsg" if call.retval > 0 => {
+                if let Some(node) = self.operand_node(call.pid, operand) {
+                    self.edge(node, process, EdgeKind::Read, call);
+                }
+            },
+
+            "write" | "writev" | "pwrite" | "pwritev" | "send" | "sendto" |
+            "sendmsg" if call.retval > 0 => {
+                if let Some(node) = self.operand_node(call.pid, operand) {
+                    self.edge(process, node, EdgeKind::Write, call);
+                }
+            },
+
+            // sendfile(fd, s, ...) returns 0, with the byte count in *sbytes.
+            "sendfile" => {
+                if let Some(file) = self.operand_node(call.pid, operand) {
+                    self.edge(file, process, EdgeKind::Read, call);
+                }
+                if let Some(socket) = self.fd_node(call, 1) {
+                    self.edge(process, socket, EdgeKind::Write, call);
+                }
+            },
+
+            "execve" | "__mac_execve" => {
+                if let Some(path) = paths.first() {
+                    let file = self.node(Node::File(path.clone()));
+                    self.edge(file, process, EdgeKind::Exec, call);
+                }
+            },
+
+            "fexecve" => {
+                if let Some(node) = self.operand_node(call.pid, operand) {
+                    self.edge(node, process, EdgeKind::Exec, call);
+                }
+            },
+
+            // Only the parent knows the child's PID.
+            "fork" | "vfork" | "rfork" | "pdfork" if call.retval != 0 => {
+                let child = self.node(Node::Process { pid: call.retval as u32 });
+                self.edge(process, child, EdgeKind::Fork, call);
+            },
+
+            "connect" | "connectat" => {
+                let n = if name == "connect" { 0 } else { 1 };
+                if let Some(node) = self.fd_node(call, n) {
+                    self.edge(process, node, EdgeKind::Connect, call);
+                }
+            },
+
+            "accept" | "accept4" => {
+                let accepted = descriptor.map(|d| (call.retval as i32, d));
+                if let Some(node) = self.operand_node(call.pid, accepted) {
+                    self.edge(node, process, EdgeKind::Accept, call);
+                }
+            },
+
+            "rename" | "renameat" if paths.len() >= 2 => {
+                let from = self.node(Node::File(paths[0].clone()));
+                let to = self.node(Node::File(paths[1].clone()));
+                self.edge(from, to, EdgeKind::Rename, call);
+            },
+
+            "unlink" | "unlinkat" | "rmdir" => {
+                if let Some(path) = paths.first() {
+                    let file = self.node(Node::File(path.clone()));
+                    self.edge(process, file, EdgeKind::Unlink, call);
+                }
+            },
+
+            _ => {},
+        }
+    }
+
+    /// Write the graph in GraphViz DOT format.
+    pub fn write_dot(&self, out: &mut dyn Write) -> io::Result<()> {
+        writeln![out, "digraph provenance {{"]?;
+
+        for (id, node) in self.nodes.iter().enumerate() {
+            let shape = match *node {
+                Node::Process{..} => "ellipse",
+                Node::File(_) => "box",
+                Node::Socket(_) | Node::UnnamedSocket{..} => "diamond",
+                Node::Pipe(_) => "hexagon",
+            };
+
+            writeln![out, "    n{} [label={} shape={}];",
+                     id, json::string(&self.label(node)), shape]?;
+        }
+
+        for edge in &self.edges {
+            let label = if edge.count > 1 {
+                format!["{} ×{}", edge.kind, edge.count]
+            } else {
+                edge.kind.to_string()
+            };
+
+            writeln![out, "    n{} -> n{} [label={}];",
+                     edge.from, edge.to, json::string(&label)]?;
+        }
+
+        writeln![out, "}}"]
+    }
+
+    /// Write the graph as JSON objects, one node or edge per line.
+    pub fn write_json_lines(&self, out: &mut dyn Write) -> io::Result<()> {
+        for (id, node) in self.nodes.iter().enumerate() {
+            let properties = match *node {
+                Node::Process{pid} => {
+                    let commands = self.commands.get(&pid)
+                                       .map(|c| c.iter()
+                                                 .map(|s| json::string(&s.to_string()))
+                                                 .collect::<Vec<_>>()
+                                                 .join(","))
+                                       .unwrap_or_default();
+
+                    format!["\"kind\":\"process\",\"pid\":{},\"commands\":[{}]",
+                            pid, commands]
+                },
+                Node::File(ref path) => {
+                    format!["\"kind\":\"file\",\"path\":{}",
+                            json::string(&path.to_string())]
+                },
+                Node::Socket(ref addr) => {
+                    format!["\"kind\":\"socket\",\"address\":{}",
+                            json::string(addr)]
+                },
+                Node::UnnamedSocket{pid, fd} => {
+                    format!["\"kind\":\"socket\",\"pid\":{},\"fd\":{}", pid, fd]
+                },
+                Node::Pipe(n) => format!["\"kind\":\"pipe\",\"pipe\":{}", n],
+            };
+
+            writeln![out, "{{\"type\":\"node\",\"id\":{},{}}}", id, properties]?;
+        }
+
+        for e in &self.edges {
+            writeln![out, "{{\"type\":\"edge\",\"from\":{},\"to\":{},\
+                         \"kind\":\"{}\",\"count\":{},\
+                         \"first_us\":{},\"last_us\":{}}}",
+                     e.from, e.to, e.kind, e.count,
+                     ::microseconds(&e.first), ::microseconds(&e.last)]?;
+        }
+
+        Ok(())
+    }
+
+    fn label(&self, node: &Node) -> String {
+        match *node {
+            Node::Process{pid} => {
+                match self.commands.get(&pid).and_then(|c| c.last()) {
+                    Some(command) => format!["{} ({})", command, pid],
+                    None => format!["PID {}", pid],
+                }
+            },
+            Node::File(ref path) => path.to_string(),
+            Node::Socket(ref addr) => addr.clone(),
+            Node::UnnamedSocket{pid, fd} => format!["socket (PID {} fd {})", pid, fd],
+            Node::Pipe(n) => format!["pipe #{}", n],
+        }
+    }
+
+    fn node(&mut self, node: Node) -> usize {
+        let nodes = &mut self.nodes;
+        *self.ids.entry(node.clone()).or_insert_with(|| {
+            nodes.push(node);
+            nodes.len() - 1
+        })
+    }
+
+    fn process(&mut self, header: &Header) -> usize {
+        let commands = self.commands.entry(header.pid).or_default();
+        if commands.last().is_none_or(|c| *c != header.command) {
+            commands.push(header.command.clone());
+        }
+
+        self.node(Node::Process { pid: header.pid })
+    }
+
+    /// The paths that a call looked up, resolved against its directory
+    /// descriptor or working directory.
+    fn resolve(&self, call: &Call) -> Vec<ByteString> {
+        let name = call.name();
+        call.paths.iter()
+                  .enumerate()
+                  .map(|(n, path)| {
+                      self.fds.resolve(call.pid, dirfd(&name, call, n), path)
+                  })
+                  .collect()
+    }
+
+    fn fd_node(&mut self, call: &Call, n: usize) -> Option<usize> {
+        let fd = call.fd(n)?;
+        let descriptor = self.fds.lookup(call.pid, fd).cloned()?;
+        self.descriptor_node(call.pid, fd, &descriptor)
+    }
+
+    /// The node for an `(fd, descriptor)` pair, if there is one.
+    fn operand_node(&mut self, pid: u32, operand: Option<(i32, Descriptor)>)
+        -> Option<usize>
+    {
+        let (fd, descriptor) = operand?;
+        self.descriptor_node(pid, fd, &descriptor)
+    }
+
+    /// The node for the descriptor `fd` in process `pid`.
+    fn descriptor_node(&mut self, pid: u32, fd: i32, descriptor: &Descriptor)
+        -> Option<usize>
+    {
+        let node = match *descriptor {
+            Descriptor::File(ref path) => Node::File(path.clone()),
+            Descriptor::Socket{ref peer, ref local, ..} => {
+                match peer.as_ref().or(local.as_ref()) {
+                    Some(addr) => Node::Socket(addr.to_string()),
+                    None => Node::UnnamedSocket { pid, fd },
+                }
+            },
+            Descriptor::Pipe(n) => Node::Pipe(n),
+            Descriptor::SharedMemory(_) | Descriptor::Pty | Descriptor::Kqueue => return None,
+        };
+
+        Some(self.node(node))
+    }
+
+    fn edge(&mut self, from: usize, to: usize, kind: EdgeKind, call: &Call) {
+        let time = call.start.unwrap_or(call.end);
+        let edges = &mut self.edges;
+        let id = *self.edge_ids.entry((from, to, kind)).or_insert_with(|| {
+            edges.push(Edge {
+                from,
+                to,
+                kind,
+                count: 0,
+                first: time,
+                last: time,
+            });
+            edges.len() - 1
+        });
+
+        let edge = &mut self.edges[id];
+        edge.count += 1;
+        edge.last = time;
+    }
+}
@@ -0,0 +1,295 @@
+// Copyright 2016 Jonathan Anderson <jonathan.anderson@mun.ca>
+//
+// This software was developed by BAE Systems, the University of Cambridge
+// Computer Laboratory, and Memorial University under DARPA/AFRL contract
+// FA8650-15-C-7558 ("CADETS"), as part of the DARPA Transparent Computing
+// (TC) research program.
+//
+// Licensed under the Apache License, Version 2.0,
+// <LICENSE-APACHE or http://apache.org/licenses/LICENSE-2.0>
+// or the MIT license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+extern crate nix;
+
+use self::nix::sys::time::TimeVal;
+use std::collections::{BTreeMap,HashMap};
+use std::io::{self,Write};
+use ::{ByteString,Call,CallTracker,FdTracker,Header,Record,fault};
+
+
+/// A page fault, from its `KTR_FAULT` record to its `KTR_FAULTEND`
+#[derive(Clone,Debug)]
+pub struct PageFaultInterval {
+    pub pid: u32,
+    pub tid: usize,
+    pub command: ByteString,
+
+    pub address: u64,
+
+    /// `VM_PROT_*` flags describing the access that faulted
+    pub fault_type: u32,
+
+    /// `KERN_*` result
+    pub result: u32,
+
+    pub start: TimeVal,
+    pub end: TimeVal,
+
+    /// the system call that the thread was in when it faulted
+    pub syscall: Option<String>,
+
+    /// the name of the mapping that contains `address` (see `Mapping::name`)
+    pub region: String,
+}
+
+/// A region of a process' address space that was mapped with `mmap(2)`
+#[derive(Clone,Debug)]
+pub struct Mapping {
+    pub start: u64,
+    pub length: u64,
+
+    /// the file that was mapped, or `None` for anonymous memory
+    pub path: Option<ByteString>,
+
+    /// the descriptor that was mapped, if any
+    pub fd: Option<i32>,
+}
+
+/// Page fault counts and times for one region
+#[derive(Clone,Debug,Default)]
+pub struct RegionFaults {
+    pub faults: u64,
+    pub reads: u64,
+    pub writes: u64,
+    pub executes: u64,
+
+    /// faults whose result wasn't `KERN_SUCCESS`
+    pub failures: u64,
+
+    /// total and longest time spent handling faults (µs)
+    pub total: i64,
+    pub max: i64,
+}
+
+/// Pairs page fault records and attributes faults to the files (or
+/// anonymous memory) mapped at the faulting addresses
+///
+/// Only mappings created while tracing are known; faults elsewhere (e.g.,
+/// in a program's text, stack or heap) are attributed to `[unknown]`.
+#[derive(Default)]
+pub struct PageFaults {
+    /// faults by region name
+    pub regions: BTreeMap<String, RegionFaults>,
+
+    /// number of faults by result
+    pub results: BTreeMap<u32, u64>,
+
+    calls: CallTracker,
+    fds: FdTracker,
+    mappings: HashMap<u32, Vec<Mapping>>,
+
+    /// each thread's current page fault, without its end
+    pending: HashMap<(u32, usize), PageFaultInterval>,
+}
+
+
+impl PageFaultInterval {
+    /// The time taken to handle the fault (µs).
+    pub fn duration(&self) -> i64 {
+        ::microseconds(&self.end).saturating_sub(::microseconds(&self.start))
+    }
+
+    pub fn succeeded(&self) -> bool {
+        self.result == fault::KERN_SUCCESS
+    }
+}
+
+impl Mapping {
+    pub fn contains(&self, address: u64) -> bool {
+        address >= self.start && address - self.start < self.length
+    }
+
+    /// The mapped file's path, `[anonymous]` or `[fd N]` for descriptors
+    /// opened before tracing began.
+    pub fn name(&self) -> String {
+        match (&self.path, self.fd) {
+            (Some(path), _) => path.to_string(),
+            (None, Some(fd)) => format!["[fd {}]", fd],
+            (None, None) => "[anonymous]".to_string(),
+        }
+    }
+}
+
+impl RegionFaults {
+    fn add(&mut self, f: &PageFaultInterval) {
+        let duration = f.duration();
+
+        self.faults += 1;
+        self.reads += (f.fault_type & fault::VM_PROT_READ != 0) as u64;
+        self.writes += (f.fault_type & fault::VM_PROT_WRITE != 0) as u64;
+        self.executes += (f.fault_type & fault::VM_PROT_EXECUTE != 0) as u64;
+        self.failures += (!f.succeeded()) as u64;
+        self.total = self.total.saturating_add(duration);
+        self.max = self.max.max(duration);
+    }
+}
+
+
+impl PageFaults {
+    pub fn new() -> PageFaults {
+        PageFaults::default()
+    }
+
+    /// The mappings that are known for a process, oldest first.
+    pub fn mappings(&self, pid: u32) -> &[Mapping] {
+        self.mappings.get(&pid).map(|m| &m[..]).unwrap_or(&[])
+    }
+
+    /// The name of the most recent mapping that contains an address.
+    pub fn region(&self, pid: u32, address: u64) -> String {
+        self.mappings(pid)
+            .iter()
+            .rev()
+            .find(|m| m.contains(address))
+            .map(Mapping::name)
+            .unwrap_or("[unknown]".to_string())
+    }
+
+    /// Account for the next record in a trace, returning the page fault
+    /// that it ends (if it is a `KTR_FAULTEND` record).
+    pub fn record(&mut self, header: &Header, record: &Record)
+        -> Option<PageFaultInterval>
+    {
+        self.fds.record(header, record);
+        if let Some(call) = self.calls.record(header, record) {
+            self.update(&call);
+        }
+
+        let key = (header.pid, header.tid);
+
+        match *record {
+            Record::PageFault{virtual_address, fault_type} => {
+                let syscall = self.calls.pending(header.pid, header.tid)
+                                        .map(Call::name);
+
+                self.pending.insert(key, PageFaultInterval {
+                    pid: header.pid,
+                    tid: header.tid,
+                    command: header.command.clone(),
+                    address: virtual_address,
+                    fault_type,
+                    result: fault::KERN_SUCCESS,
+                    start: header.timestamp,
+                    end: header.timestamp,
+                    syscall,
+                    region: self.region(header.pid, virtual_address),
+                });
+
+                None
+            },
+
+            Record::PageFaultEnd{result} => {
+                let mut f = self.pending.remove(&key)?;
+                f.result = result;
+                f.end = header.timestamp;
+
+                self.regions.entry(f.region.clone()).or_default().add(&f);
+                *self.results.entry(result).or_insert(0) += 1;
+
+                Some(f)
+            },
+
+            _ => None,
+        }
+    }
+
+    /// Write a table of faults by region (most faults first), followed by
+    /// the number of faults with each result.
+    pub fn write_table(&self, out: &mut dyn Write) -> io::Result<()> {
+        writeln![out, "{:<40} {:>7} {:>7} {:>7} {:>7} {:>7} {:>12} {:>9}",
+                 "region", "faults", "read", "write", "exec", "failed",
+                 "total (µs)", "max"]?;
+
+        let mut regions: Vec<_> = self.regions.iter().collect();
+        regions.sort_by(|a, b| b.1.faults.cmp(&a.1.faults).then(a.0.cmp(b.0)));
+
+        for (name, r) in regions {
+            writeln![out, "{:<40} {:>7} {:>7} {:>7} {:>7} {:>7} {:>12} {:>9}",
+                     name, r.faults, r.reads, r.writes, r.executes,
+                     r.failures, r.total, r.max]?;
+        }
+
+        writeln![out]?;
+        writeln![out, "{:<40} {:>7}", "result", "faults"]?;
+        for (&result, count) in &self.results {
+            writeln![out, "{:<40} {:>7}",
+                     fault::result(result).unwrap_or(result.to_string()), count]?;
+        }
+
+        Ok(())
+    }
+
+    /// Apply the effects of a completed system call to the address space.
+    fn update(&mut self, call: &Call) {
+        if !call.succeeded() {
+            return;
+        }
+
+        let arg = |n: usize| call.args.get(n).cloned().unwrap_or(0);
+
+        match call.name().as_str() {
+            "mmap" => {
+                let fd = arg(4) as i32;
+                let (fd, path) = if fd < 0 {
+                    (None, None)
+                } else {
+                    let path = self.fds.lookup(call.pid, fd)
+                                       .and_then(|d| d.path())
+                                       .cloned();
+                    (Some(fd), path)
+                };
+
+                self.mappings.entry(call.pid).or_default().push(Mapping {
+                    start: call.retval,
+                    length: arg(1),
+                    path,
+                    fd,
+                });
+            },
+
+            "munmap" => {
+                let (start, end) = (arg(0), arg(0).saturating_add(arg(1)));
+                if let Some(mappings) = self.mappings.get_mut(&call.pid) {
+                    // Keep whatever is left on either side of the hole.
+                    *mappings = mappings.drain(..).flat_map(|m| {
+                        let m_end = m.start.saturating_add(m.length);
+                        let before = Mapping {
+                            length: m_end.min(start).saturating_sub(m.start),
+                            ..m.clone()
+                        };
+                        let after = Mapping {
+                            start: m.start.max(end),
+                            length: m_end.saturating_sub(m.start.max(end)),
+                            ..m
+                        };
+
+                        vec![before, after].into_iter().filter(|m| m.length > 0)
+                    }).collect();
+                }
+            },
+
+            "fork" | "vfork" | "rfork" | "pdfork" if call.retval != 0 => {
+                let parent = self.mappings(call.pid).to_vec();
+                self.mappings.insert(call.retval as u32, parent);
+            },
+
+            "execve" | "fexecve" => {
+                self.mappings.remove(&call.pid);
+            },
+
+            _ => {},
+        }
+    }
+}
@@ -0,0 +1,602 @@
+// Copyright 2016 Jonathan Anderson <jonathan.anderson@mun.ca>
+//
+// This software was developed by BAE Systems, the University of Cambridge
+// Computer Laboratory, and Memorial University under DARPA/AFRL contract
+// FA8650-15-C-7558 ("CADETS"), as part of the DARPA Transparent Computing
+// (TC) research program.
+//
+// Licensed under the Apache License, Version 2.0,
+// <LICENSE-APACHE or http://apache.org/licenses/LICENSE-2.0>
+// or the MIT license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use byteorder::{ByteOrder,NativeEndian};
+use std::collections::{BTreeMap,HashMap};
+use std::fmt;
+use std::marker::PhantomData;
+use ::{ByteString,Call,CallTracker,Header,Record,Result,SockAddr};
+
+const AT_FDCWD: i32 = -100;
+const EINPROGRESS: i32 = 36;
+const O_CLOEXEC: u64 = 0x0010_0000;
+const SOCK_CLOEXEC: u64 = 0x1000_0000;
+const SOCK_NONBLOCK: u64 = 0x2000_0000;
+const RFCFDG: u64 = 0x1000;
+const F_DUPFD: u64 = 0;
+const F_SETFD: u64 = 2;
+const F_DUPFD_CLOEXEC: u64 = 17;
+const FD_CLOEXEC: u64 = 1;
+const CLOSE_RANGE_CLOEXEC: u64 = 1 << 2;
+
+
+/// What a file descriptor refers to
+#[derive(Clone,Debug,PartialEq)]
+pub enum Descriptor {
+    /// A file opened by path, resolved against the process' working
+    /// directory or directory descriptor (see `FdTracker::resolve`); the
+    /// path stays relative if that directory isn't known
+    File(ByteString),
+
+    Socket {
+        domain: i32,
+        sock_type: i32,
+        protocol: i32,
+        local: Option<SockAddr>,
+        peer: Option<SockAddr>,
+    },
+
+    /// Either end of a pipe (identified by the order pipes were created in)
+    Pipe(usize),
+
+    /// A POSIX shared memory object (`None` if anonymous)
+    SharedMemory(Option<ByteString>),
+
+    /// The master side of a pseudo-terminal, from `posix_openpt(2)`
+    Pty,
+
+    Kqueue,
+}
+
+/// Tracks the file descriptor table and working directory of every
+/// process in a trace
+///
+/// Descriptors that were open before tracing began, and descriptors that
+/// are returned via memory rather than a return value (e.g., from
+/// `pipe2(2)` or `socketpair(2)`) unless the kernel logs them in an `fds`
+/// structure, are not known to the tracker. Neither
+/// is the working directory that a process started with, so it is only
+/// known after a `chdir(2)` to an absolute path.
+///
+/// Structures that calls copy in or out (socket addresses and `fds`) are
+/// decoded in the trace's byte order, `E`.
+pub struct FdTracker<E = NativeEndian> {
+    calls: CallTracker,
+    processes: HashMap<u32, BTreeMap<i32, Entry>>,
+    pipes: usize,
+
+    /// working directories (relative to the initial one, if unknown)
+    cwds: HashMap<u32, ByteString>,
+
+    byte_order: PhantomData<E>,
+}
+
+#[derive(Clone,Debug)]
+struct Entry {
+    descriptor: Descriptor,
+    cloexec: bool,
+}
+
+
+impl Descriptor {
+    /// A path describing this descriptor, if it was opened by path.
+    pub fn path(&self) -> Option<&ByteString> {
+        match *self {
+            Descriptor::File(ref path) => Some(path),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Descriptor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Descriptor::File(ref path) => write![f, "{}", path],
+            Descriptor::Socket{ref local, ref peer, domain, sock_type, ..} => {
+                write![f, "socket({}, {})", domain, sock_type]?;
+                if let Some(ref local) = *local {
+                    write![f, " {}", local]?;
+                }
+                if let Some(ref peer) = *peer {
+                    write![f, " -> {}", peer]?;
+                }
+                Ok(())
+            },
+            Descriptor::Pipe(id) => write![f, "pipe #{}", id],
+            Descriptor::SharedMemory(Some(ref name)) => write![f, "shm {}", name],
+            Descriptor::SharedMemory(None) => write![f, "anonymous shm"],
+            Descriptor::Pty => write![f, "pty"],
+            Descriptor::Kqueue => write![f, "kqueue"],
+        }
+    }
+}
+
+
+impl<E> Default for FdTracker<E> {
+    fn default() -> FdTracker<E> {
+        FdTracker {
+            calls: CallTracker::new(),
+            processes: HashMap::new(),
+            pipes: 0,
+            cwds: HashMap::new(),
+            byte_order: PhantomData,
+        }
+    }
+}
+
+
+impl<E> FdTracker<E>
+    where E: ByteOrder
+{
+    pub fn new() -> FdTracker<E> {
+        FdTracker::default()
+    }
+
+    /// Look up a descriptor in a process' table.
+    pub fn lookup(&self, pid: u32, fd: i32) -> Option<&Descriptor> {
+        self.processes.get(&pid)
+                      .and_then(|table| table.get(&fd))
+                      .map(|entry| &entry.descriptor)
+    }
+
+    /// A process' working directory, if it has changed since tracing began.
+    pub fn cwd(&self, pid: u32) -> Option<&ByteString> {
+        self.cwds.get(&pid)
+    }
+
+    /// Resolve a path looked up by a process, relative to a directory
+    /// descriptor (for `*at` calls) or to the process' working directory.
+    ///
+    /// `.` and `..` components are removed lexically, without regard to
+    /// symbolic links. The result is relative if the directory that the
+    /// path is relative to isn't known.
+    pub fn resolve(&self, pid: u32, dirfd: Option<i32>, path: &[u8]) -> ByteString {
+        let base = if path.starts_with(b"/") {
+            None
+        } else {
+            match dirfd {
+                Some(AT_FDCWD) | None => self.cwd(pid),
+                Some(fd) => self.lookup(pid, fd).and_then(Descriptor::path),
+            }
+        };
+
+        match base {
+            Some(dir) => normalize(&[dir.as_bytes(), b"/", path].concat()),
+            None => normalize(path),
+        }
+    }
+
+    /// All known descriptors in a process' table.
+    pub fn table(&self, pid: u32) -> Vec<(i32, &Descriptor)> {
+        self.processes.get(&pid)
+                      .map(|table| table.iter()
+                                        .map(|(fd, e)| (*fd, &e.descriptor))
+                                        .collect())
+                      .unwrap_or_default()
+    }
+
+    /// Account for the next record, returning the descriptor that it
+    /// refers to (if any).
+    ///
+    /// For I/O records and calls that take a descriptor, this is the
+    /// descriptor being operated on; for returns from calls that create a
+    /// descriptor, it is the new descriptor.
+    pub fn record(&mut self, header: &Header, record: &Record)
+        -> Option<Descriptor>
+    {
+        let call = self.calls.record(header, record);
+
+        match *record {
+            Record::GenericIO{fd, ..} => {
+                self.lookup(header.pid, fd).cloned()
+            },
+
+            Record::SystemCall{ref args, ..} => {
+                let call = self.calls.pending(header.pid, header.tid);
+                if call.is_some_and(|c| takes_fd(&c.name())) {
+                    args.first()
+                        .and_then(|fd| self.lookup(header.pid, *fd as i32))
+                        .cloned()
+                } else {
+                    None
+                }
+            },
+
+            Record::SystemCallReturn{..} => {
+                call.and_then(|c| self.update(&c))
+            },
+
+            _ => None,
+        }
+    }
+
+    /// Apply the effects of a completed system call to the descriptor
+    /// tables, returning any newly-created descriptor.
+    fn update(&mut self, call: &Call) -> Option<Descriptor> {
+        let name = call.name();
+
+        // A non-blocking connect(2) has a peer even if it hasn't finished.
+        let in_progress = call.error == EINPROGRESS && name.starts_with("connect");
+        if !call.succeeded() && !in_progress {
+            return None;
+        }
+
+        let pid = call.pid;
+        let ret = call.retval as i32;
+        let arg = |n: usize| call.args.get(n).cloned().unwrap_or(0);
+
+        // Flags are translated from the caller's ABI into FreeBSD's.
+        let abi = call.abi;
+        let o_cloexec = |flags: u64| abi.open_flags(flags) & O_CLOEXEC != 0;
+
+        match name.as_str() {
+            "open" | "creat" | "openat" => {
+                let path = call.paths.first().cloned().unwrap_or_default();
+                let (path, flags) = match name.as_str() {
+                    "openat" => (self.resolve(pid, call.fd(0), &path), arg(2)),
+                    "creat" => (self.resolve(pid, None, &path), 0),
+                    _ => (self.resolve(pid, None, &path), arg(1)),
+                };
+
+                self.insert(pid, ret, Descriptor::File(path), o_cloexec(flags))
+            },
+
+            // Shared memory names aren't paths (and SHM_ANON has no name).
+            "shm_open" | "shm_open2" => {
+                let name = call.paths.first().cloned();
+                self.insert(pid, ret, Descriptor::SharedMemory(name),
+                            o_cloexec(arg(1)))
+            },
+
+            "posix_openpt" => self.insert(pid, ret, Descriptor::Pty, o_cloexec(arg(0))),
+
+            // We don't know which file a handle refers to, but we do know
+            // that the descriptor no longer refers to what it used to.
+            "fhopen" => {
+                self.forget(pid, ret);
+                None
+            },
+
+            "socket" => {
+                let sock_type = abi.sock_type(arg(1));
+                self.insert(pid, ret, Descriptor::Socket {
+                    domain: arg(0) as i32,
+                    sock_type: (sock_type & !(SOCK_CLOEXEC | SOCK_NONBLOCK)) as i32,
+                    protocol: arg(2) as i32,
+                    local: None,
+                    peer: None,
+                }, sock_type & SOCK_CLOEXEC != 0)
+            },
+
+            "accept" | "accept4" => {
+                let listener = call.fd(0).and_then(|fd| self.lookup(pid, fd));
+                let peer = sockaddr::<E>(call);
+
+                let accepted = match listener {
+                    Some(&Descriptor::Socket{domain, sock_type, protocol,
+                                             ref local, ..}) => {
+                        Descriptor::Socket {
+                            domain,
+                            sock_type,
+                            protocol,
+                            local: local.clone(),
+                            peer,
+                        }
+                    },
+                    _ => Descriptor::Socket {
+                        domain: 0,
+                        sock_type: 0,
+                        protocol: 0,
+                        local: None,
+                        peer,
+                    },
+                };
+
+                let cloexec = name == "accept4"
+                              && abi.sock_type(arg(3)) & SOCK_CLOEXEC != 0;
+                self.insert(pid, ret, accepted, cloexec)
+            },
+
+            "connect" | "bind" | "connectat" | "bindat" => {
+                let (fd, is_connect) = match name.as_str() {
+                    "connect" => (call.fd(0), true),
+                    "bind" => (call.fd(0), false),
+                    "connectat" => (call.fd(1), true),
+                    _ => (call.fd(1), false),
+                };
+
+                let addr = sockaddr::<E>(call);
+                let entry = fd.and_then(|fd| {
+                    self.processes.get_mut(&pid).and_then(|t| t.get_mut(&fd))
+                });
+
+                if let Some(&mut Entry {
+                    descriptor: Descriptor::Socket{ref mut local, ref mut peer, ..},
+                    ..
+                }) = entry {
+                    if is_connect {
+                        *peer = addr;
+                    } else {
+                        *local = addr;
+                    }
+                }
+
+                None
+            },
+
+            "pipe" | "pipe2" | "socketpair" => {
+                let (descriptor, cloexec) = if name == "socketpair" {
+                    let sock_type = abi.sock_type(arg(1));
+                    let socket = Descriptor::Socket {
+                        domain: arg(0) as i32,
+                        sock_type: (sock_type & !(SOCK_CLOEXEC | SOCK_NONBLOCK)) as i32,
+                        protocol: arg(2) as i32,
+                        local: None,
+                        peer: None,
+                    };
+                    (socket, sock_type & SOCK_CLOEXEC != 0)
+                } else {
+                    let cloexec = name == "pipe2" && o_cloexec(arg(1));
+                    self.pipes += 1;
+                    (Descriptor::Pipe(self.pipes - 1), cloexec)
+                };
+
+                // Both descriptors are known if the kernel logged them
+                // (OpenBSD does); otherwise, only pipe(2) returns one
+                // (the read end) in a register.
+                let fds = fds::<E>(call);
+                let fds = match (fds.len(), name.as_str()) {
+                    (2, _) => fds,
+                    (_, "pipe") => vec![ret],
+                    _ => vec![],
+                };
+
+                for &fd in &fds {
+                    self.insert(pid, fd, descriptor.clone(), cloexec);
+                }
+
+                fds.first().map(|_| descriptor)
+            },
+
+            "kqueue" => self.insert(pid, ret, Descriptor::Kqueue, false),
+
+            "dup" | "dup2" => self.duplicate(pid, call.fd(0), ret, false),
+            "dup3" => self.duplicate(pid, call.fd(0), ret, o_cloexec(arg(2))),
+
+            "fcntl" => {
+                match abi.fcntl_command(arg(1)).unwrap_or(u64::MAX) {
+                    F_DUPFD => self.duplicate(pid, call.fd(0), ret, false),
+                    F_DUPFD_CLOEXEC => self.duplicate(pid, call.fd(0), ret, true),
+                    F_SETFD => {
+                        let fd = call.fd(0).unwrap_or(-1);
+                        if let Some(entry) = self.processes.get_mut(&pid)
+                                                           .and_then(|t| t.get_mut(&fd)) {
+                            entry.cloexec = arg(2) & FD_CLOEXEC != 0;
+                        }
+                        None
+                    },
+                    _ => None,
+                }
+            },
+
+            "chdir" | "fchdir" => {
+                let dir = if name == "chdir" {
+                    call.paths.first().map(|p| self.resolve(pid, None, p))
+                } else {
+                    call.fd(0).and_then(|fd| self.lookup(pid, fd))
+                              .and_then(Descriptor::path)
+                              .cloned()
+                };
+
+                if let Some(dir) = dir {
+                    self.cwds.insert(pid, dir);
+                }
+                None
+            },
+
+            "close" => {
+                if let (Some(table), Some(fd)) = (self.processes.get_mut(&pid),
+                                                  call.fd(0)) {
+                    table.remove(&fd);
+                }
+                None
+            },
+
+            "closefrom" => {
+                if let (Some(table), Some(low)) = (self.processes.get_mut(&pid),
+                                                   call.fd(0)) {
+                    let low = low.max(0);
+                    let _ = table.split_off(&low);
+                }
+                None
+            },
+
+            "close_range" => {
+                // The range is inclusive, and its end is often ~0U.
+                let (low, high) = (arg(0) as u32, arg(1) as u32);
+                let range = |fd: &i32| *fd >= 0 && (low..=high).contains(&(*fd as u32));
+
+                if let Some(table) = self.processes.get_mut(&pid) {
+                    if arg(2) & CLOSE_RANGE_CLOEXEC != 0 {
+                        for (_, entry) in table.iter_mut().filter(|(fd, _)| range(fd)) {
+                            entry.cloexec = true;
+                        }
+                    } else {
+                        table.retain(|fd, _| !range(fd));
+                    }
+                }
+                None
+            },
+
+            "fork" | "vfork" | "rfork" | "pdfork" => {
+                // Only the parent knows the child's PID.
+                if call.retval == 0 {
+                    return None;
+                }
+
+                if let Some(cwd) = self.cwds.get(&pid).cloned() {
+                    self.cwds.entry(call.retval as u32).or_insert(cwd);
+                }
+
+                let inherited = if name == "rfork" && arg(0) & RFCFDG != 0 {
+                    BTreeMap::new()
+                } else {
+                    self.processes.get(&pid).cloned().unwrap_or_default()
+                };
+                let child = self.processes.entry(call.retval as u32)
+                                          .or_default();
+
+                // The child may already have run: don't overwrite anything
+                // it has done itself.
+                for (fd, entry) in inherited {
+                    child.entry(fd).or_insert(entry);
+                }
+
+                None
+            },
+
+            "execve" | "fexecve" | "__mac_execve" => {
+                if let Some(table) = self.processes.get_mut(&pid) {
+                    table.retain(|_, entry| !entry.cloexec);
+                }
+                None
+            },
+
+            _ => None,
+        }
+    }
+
+    fn insert(&mut self, pid: u32, fd: i32, descriptor: Descriptor,
+              cloexec: bool) -> Option<Descriptor>
+    {
+        self.processes.entry(pid)
+                      .or_default()
+                      .insert(fd, Entry {
+                          descriptor: descriptor.clone(),
+                          cloexec,
+                      });
+
+        Some(descriptor)
+    }
+
+    fn duplicate(&mut self, pid: u32, from: Option<i32>, to: i32,
+                 cloexec: bool) -> Option<Descriptor>
+    {
+        match from.and_then(|fd| self.lookup(pid, fd)).cloned() {
+            Some(descriptor) => self.insert(pid, to, descriptor, cloexec),
+            None => {
+                // We don't know what the old descriptor was, but we do know
+                // that the new one no longer refers to what it used to.
+                self.forget(pid, to);
+                None
+            },
+        }
+    }
+
+    /// Remove a descriptor that now refers to something unknown.
+    fn forget(&mut self, pid: u32, fd: i32) {
+        if let Some(table) = self.processes.get_mut(&pid) {
+            table.remove(&fd);
+        }
+    }
+}
+
+
+/// An iterator that annotates parsed records with file descriptor information
+pub struct Annotated<I, E> {
+    records: I,
+    tracker: FdTracker<E>,
+}
+
+impl<I, E> Iterator for Annotated<I, E>
+    where I: Iterator<Item = (Header, Result<Record>)>, E: ByteOrder
+{
+    type Item = (Header, Result<Record>, Option<Descriptor>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.records.next().map(|(header, record)| {
+            let descriptor = match record {
+                Ok(ref r) => self.tracker.record(&header, r),
+                Err(_) => None,
+            };
+
+            (header, record, descriptor)
+        })
+    }
+}
+
+/// Annotate a stream of records, parsed from a trace in byte order `E`,
+/// with the descriptors they refer to.
+pub fn annotate<E, I>(records: I) -> Annotated<I::IntoIter, E>
+    where E: ByteOrder, I: IntoIterator<Item = (Header, Result<Record>)>
+{
+    Annotated {
+        records: records.into_iter(),
+        tracker: FdTracker::new(),
+    }
+}
+
+
+/// Remove empty, `.` and `..` components from a path.
+fn normalize(path: &[u8]) -> ByteString {
+    let absolute = path.starts_with(b"/");
+    let mut components: Vec<&[u8]> = vec![];
+
+    for c in path.split(|&b| b == b'/') {
+        match c {
+            b"" | b"." => {},
+            b".." if components.last().is_some_and(|&last| last != b"..") => {
+                components.pop();
+            },
+            b".." if absolute => {},
+            c => components.push(c),
+        }
+    }
+
+    match (absolute, components.is_empty()) {
+        (true, _) => [&b"/"[..], &components.join(&b'/')].concat().into(),
+        (false, true) => ".".into(),
+        (false, false) => components.join(&b'/').into(),
+    }
+}
+
+/// System calls whose first argument is a file descriptor.
+pub(crate) fn takes_fd(name: &str) -> bool {
+    matches!(name,
+        "read" | "write" | "readv" | "writev" | "pread" | "pwrite" |
+        "preadv" | "pwritev" | "close" | "fstat" | "fstatfs" | "lseek" |
+        "ftruncate" | "fsync" | "fdatasync" | "fchmod" | "fchown" |
+        "fchflags" | "fcntl" | "ioctl" | "dup" | "dup2" | "flock" |
+        "fchdir" | "futimes" | "futimens" | "fpathconf" | "getdirentries" |
+        "getdents" | "sendto" | "recvfrom" | "sendmsg" | "recvmsg" |
+        "send" | "recv" | "connect" | "bind" | "listen" | "accept" |
+        "accept4" | "shutdown" | "getsockopt" | "setsockopt" |
+        "getsockname" | "getpeername" | "kevent" | "sendfile" |
+        "fexecve" | "posix_fallocate" | "posix_fadvise" |
+        "cap_rights_limit" | "cap_ioctls_limit" | "cap_fcntls_limit" |
+        "extattr_set_fd" | "extattr_get_fd" | "extattr_delete_fd" |
+        "extattr_list_fd")
+}
+
+/// The socket address copied in or out by a call, if any.
+fn sockaddr<E: ByteOrder>(call: &Call) -> Option<SockAddr> {
+    call.find_struct("sockaddr")
+        .and_then(|data| SockAddr::parse::<E>(data).ok())
+}
+
+/// The descriptors that a call copied out in an `fds` structure.
+fn fds<E: ByteOrder>(call: &Call) -> Vec<i32> {
+    call.find_struct("fds")
+        .map(|data| data.chunks_exact(4).map(E::read_i32).collect())
+        .unwrap_or_default()
+}
@@ -0,0 +1,218 @@
+// Copyright 2016 Jonathan Anderson <jonathan.anderson@mun.ca>
+//
+// This software was developed by BAE Systems, the University of Cambridge
+// Computer Laboratory, and Memorial University under DARPA/AFRL contract
+// FA8650-15-C-7558 ("CADETS"), as part of the DARPA Transparent Computing
+// (TC) research program.
+//
+// Licensed under the Apache License, Version 2.0,
+// <LICENSE-APACHE or http://apache.org/licenses/LICENSE-2.0>
+// or the MIT license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+
+use byteorder::ByteOrder;
+use std::fs::{self,File};
+use std::io::{self,Read,Seek,SeekFrom};
+use std::marker::PhantomData;
+use std::os::unix::fs::{FileExt,MetadataExt};
+use std::path::{Path,PathBuf};
+use std::thread;
+use std::time::Duration;
+use ::{Error,Header,Record,Result};
+
+
+/// Something that happened to a trace file being followed
+#[derive(Debug)]
+pub enum FollowEvent {
+    /// A new record was appended to the trace.
+    Record(Header, Result<Record>),
+
+    /// The trace was truncated: records are now read from the beginning.
+    Truncated,
+
+    /// The trace was replaced by a new file (e.g., by log rotation):
+    /// records are now read from the beginning of the new file.
+    Rotated,
+}
+
+/// Reads records from a trace that is still being written, like `kdump -l`
+///
+/// At the end of the file, `Follower` waits for more data rather than
+/// stopping, so a partially-written record at the end of the trace is
+/// retried rather than reported as truncated. Iteration only stops after
+/// an error that makes the rest of the trace unreadable.
+///
+/// A trace is considered replaced when its path names a different file
+/// (device and inode) from the one being read, and truncated when it is
+/// shorter than what has been read or its first header has changed (e.g.,
+/// because `ktrace` started writing it again from the beginning).
+pub struct Follower<E> {
+    path: PathBuf,
+    file: File,
+    interval: Duration,
+
+    /// bytes read from the file that do not yet make up a complete record
+    buffer: Vec<u8>,
+
+    /// number of records read and file offset of `buffer`
+    index: usize,
+    offset: u64,
+
+    /// the first header in the file, once it has been read
+    first: Option<[u8; 56]>,
+
+    failed: bool,
+    byte_order: PhantomData<E>,
+}
+
+impl<E> Follower<E>
+    where E: ByteOrder
+{
+    /// Follow a trace file, starting from its first record.
+    pub fn open<P>(path: P) -> Result<Follower<E>>
+        where P: AsRef<Path>
+    {
+        let path = path.as_ref().to_path_buf();
+        let file = File::open(&path)?;
+
+        Ok(Follower {
+            path,
+            file,
+            interval: Duration::from_millis(250),
+            buffer: vec![],
+            index: 0,
+            offset: 0,
+            first: None,
+            failed: false,
+            byte_order: PhantomData,
+        })
+    }
+
+    /// Set how long to wait at the end of the trace before checking again.
+    pub fn with_interval(mut self, interval: Duration) -> Follower<E> {
+        self.interval = interval;
+        self
+    }
+
+    /// Wait for the next event in the trace.
+    pub fn next_event(&mut self) -> Result<FollowEvent> {
+        loop {
+            if let Some(event) = self.take_record()? {
+                return Ok(event);
+            }
+
+            // Before reading more, check that we're still reading the
+            // same trace (it may have been replaced or rewritten since).
+            if let Some(event) = self.check_file()? {
+                return Ok(event);
+            }
+
+            let mut data = [0; 64 * 1024];
+            let n = match self.file.read(&mut data) {
+                Ok(n) => n,
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(Error::io(e).at(self.index, self.offset)),
+            };
+
+            if n > 0 {
+                self.buffer.extend_from_slice(&data[..n]);
+            } else {
+                thread::sleep(self.interval);
+            }
+        }
+    }
+
+    /// Decode the next record from the buffer, if it is complete.
+    fn take_record(&mut self) -> Result<Option<FollowEvent>> {
+        if self.buffer.len() < 56 {
+            return Ok(None);
+        }
+
+        let mut raw = [0; 56];
+        raw.copy_from_slice(&self.buffer[..56]);
+
+        let header = Header::parse::<E>(&raw)
+                            .map_err(|e| e.at(self.index, self.offset))?;
+
+        if self.offset == 0 {
+            self.first = Some(raw);
+        }
+
+        let end = 56 + header.length;
+        if self.buffer.len() < end {
+            return Ok(None);
+        }
+
+        let record = Record::parse::<E>(&self.buffer[56..end],
+                                        &header.record_type)
+                           .map_err(|e| e.in_record(&header)
+                                         .at(self.index, self.offset));
+
+        self.buffer.drain(..end);
+        self.index += 1;
+        self.offset += end as u64;
+
+        Ok(Some(FollowEvent::Record(header, record)))
+    }
+
+    /// Check whether the trace has been replaced or truncated.
+    fn check_file(&mut self) -> Result<Option<FollowEvent>> {
+        let current = self.file.metadata()?;
+
+        // The path may briefly not exist while a trace is being rotated.
+        let replaced = match fs::metadata(&self.path) {
+            Ok(ref m) => m.dev() != current.dev() || m.ino() != current.ino(),
+            Err(_) => false,
+        };
+
+        if replaced {
+            self.file = File::open(&self.path)?;
+            self.restart();
+            return Ok(Some(FollowEvent::Rotated));
+        }
+
+        let read = self.offset + self.buffer.len() as u64;
+        let rewritten = match self.first {
+            Some(ref first) if current.len() >= read => {
+                let mut header = [0; 56];
+                self.file.read_exact_at(&mut header, 0)?;
+                header != *first
+            },
+            _ => false,
+        };
+
+        if current.len() < read || rewritten {
+            self.file.seek(SeekFrom::Start(0))?;
+            self.restart();
+            return Ok(Some(FollowEvent::Truncated));
+        }
+
+        Ok(None)
+    }
+
+    fn restart(&mut self) {
+        self.buffer.clear();
+        self.index = 0;
+        self.offset = 0;
+        self.first = None;
+    }
+}
+
+impl<E> Iterator for Follower<E>
+    where E: ByteOrder
+{
+    type Item = Result<FollowEvent>;
+
+    fn next(&mut self) -> Option<Result<FollowEvent>> {
+        if self.failed {
+            return None;
+        }
+
+        let event = self.next_event();
+        self.failed = event.is_err();
+
+        Some(event)
+    }
+}
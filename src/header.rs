@@ -11,13 +11,11 @@
 // at your option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
-extern crate libc;
 extern crate nix;
 
-use std::ffi::CStr;
+use byteorder::ByteOrder;
 use std::fmt;
-use std::mem::transmute;
-use ::Error;
+use ::{ByteString,Error};
 
 const MAXCOMLEN: usize = 19;
 
@@ -28,42 +26,87 @@ pub struct Header {
     pub length: usize,
     pub record_type: RecordType,
     pub pid: u32,
-    pub command: String,
+    pub command: ByteString,
     pub timestamp: nix::sys::time::TimeVal,
     pub tid: usize,
 }
 
-#[repr(C)]
-struct RawHeader {
-    ktr_len: u32,
-    ktr_type: u16,
-    ktr_pid: u32,
-    ktr_comm: [i8; MAXCOMLEN + 1],
-    ktr_time: libc::timeval,
-    ktr_tid: libc::intptr_t,
+/// A `Header` that borrows its command name from the buffer it was parsed
+/// from (e.g., by `parse_slice`)
+#[derive(Clone, Debug)]
+pub struct HeaderRef<'a> {
+    pub length: usize,
+    pub record_type: RecordType,
+    pub pid: u32,
+    pub command: &'a [u8],
+    pub timestamp: nix::sys::time::TimeVal,
+    pub tid: usize,
 }
 
 impl Header {
-    pub fn parse(buffer: &[u8;56]) -> Result<Header, Error> {
-        let raw = unsafe { transmute::<&[u8; 56],&RawHeader>(&buffer)};
-
-        let command = unsafe { CStr::from_ptr(&raw.ktr_comm as *const i8) }
-            .to_str()
-            .or(Err(Error::msg("invalid 'command' in ktrace record header")))
-            ;
-
-        Ok(Header{
-            length: raw.ktr_len as usize,
-            record_type: try![RecordType::from_u16(raw.ktr_type)],
-            pid: raw.ktr_pid as u32,
-            command: try![command].to_string(),
+    /// Parse a C `struct ktr_header` written with byte order `E`.
+    pub fn parse<E>(buffer: &[u8;56]) -> Result<Header, Error>
+        where E: ByteOrder
+    {
+        HeaderRef::parse::<E>(buffer).map(HeaderRef::into_owned)
+    }
+
+    /// Convert the header back into a C `struct ktr_header` with byte
+    /// order `E`, truncating the command name if necessary.
+    pub fn to_bytes<E>(&self) -> [u8;56]
+        where E: ByteOrder
+    {
+        let mut buffer = [0; 56];
+
+        E::write_u32(&mut buffer[0..4], self.length as u32);
+        E::write_u16(&mut buffer[4..6], self.record_type.clone() as u16);
+        E::write_u32(&mut buffer[8..12], self.pid);
+
+        let len = self.command.len().min(MAXCOMLEN);
+        buffer[12..12 + len].copy_from_slice(&self.command.as_bytes()[..len]);
+
+        E::write_i64(&mut buffer[32..40], self.timestamp.tv_sec);
+        E::write_i64(&mut buffer[40..48], self.timestamp.tv_usec);
+        E::write_u64(&mut buffer[48..56], self.tid as u64);
+
+        buffer
+    }
+}
+
+impl<'a> HeaderRef<'a> {
+    /// Parse a C `struct ktr_header` written with byte order `E`, without
+    /// copying the command name.
+    pub fn parse<E>(buffer: &'a [u8;56]) -> Result<HeaderRef<'a>, Error>
+        where E: ByteOrder
+    {
+        // The command may fill ktr_comm without a terminating NUL.
+        let comm = &buffer[12..12 + MAXCOMLEN + 1];
+        let len = comm.iter().position(|&c| c == 0).unwrap_or(comm.len());
+
+        Ok(HeaderRef{
+            length: E::read_u32(&buffer[0..4]) as usize,
+            record_type: RecordType::from_u16(E::read_u16(&buffer[4..6]))?,
+            pid: E::read_u32(&buffer[8..12]),
+            command: &comm[..len],
             timestamp: nix::sys::time::TimeVal{
-                tv_sec: raw.ktr_time.tv_sec,
-                tv_usec: raw.ktr_time.tv_usec,
+                tv_sec: E::read_i64(&buffer[32..40]),
+                tv_usec: E::read_i64(&buffer[40..48]),
             },
-            tid: raw.ktr_tid as usize,
+            tid: E::read_u64(&buffer[48..56]) as usize,
         })
     }
+
+    /// Copy the command name into an owned `Header`.
+    pub fn into_owned(self) -> Header {
+        Header {
+            length: self.length,
+            record_type: self.record_type,
+            pid: self.pid,
+            command: ByteString::from(self.command),
+            timestamp: self.timestamp,
+            tid: self.tid,
+        }
+    }
 }
 
 impl fmt::Display for Header {
@@ -111,7 +154,7 @@ impl RecordType {
             12 => Ok(RecordType::CapabilityFailure),
             13 => Ok(RecordType::PageFault),
             14 => Ok(RecordType::PageFaultEnd),
-            _ => Err(Error::bad_value("ktr_type", val.to_string())),
+            _ => Err(Error::unknown_type(val)),
         }
     }
 }
@@ -0,0 +1,283 @@
+// Copyright 2016 Jonathan Anderson <jonathan.anderson@mun.ca>
+//
+// This software was developed by BAE Systems, the University of Cambridge
+// Computer Laboratory, and Memorial University under DARPA/AFRL contract
+// FA8650-15-C-7558 ("CADETS"), as part of the DARPA Transparent Computing
+// (TC) research program.
+//
+// Licensed under the Apache License, Version 2.0,
+// <LICENSE-APACHE or http://apache.org/licenses/LICENSE-2.0>
+// or the MIT license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+
+extern crate nix;
+
+use byteorder::{ByteOrder,LittleEndian,ReadBytesExt,WriteBytesExt};
+use self::nix::sys::time::TimeVal;
+use std::io::{self,Read,Seek,SeekFrom,Write};
+use std::marker::PhantomData;
+use ::{Error,Header,Record,RecordType,Result};
+
+const MAGIC: &[u8; 8] = b"KTRIDX01";
+
+
+/// The location and identity of one record in a trace file
+#[derive(Clone,Debug)]
+pub struct IndexEntry {
+    /// byte offset of the record's header
+    pub offset: u64,
+    pub length: u32,
+    pub record_type: RecordType,
+    pub pid: u32,
+    pub tid: usize,
+    pub timestamp: TimeVal,
+}
+
+/// An index of every header in a trace file, allowing random access
+#[derive(Clone,Debug,Default)]
+pub struct Index {
+    entries: Vec<IndexEntry>,
+
+    /// size of the indexed trace, used to detect stale indices
+    trace_length: u64,
+}
+
+/// Reads records from a trace file at arbitrary positions using an `Index`
+pub struct IndexedReader<R, E> {
+    reader: R,
+    index: Index,
+    byte_order: PhantomData<E>,
+}
+
+
+impl Index {
+    /// Index a trace by reading each header and skipping over its payload.
+    pub fn build<E, R>(r: &mut R) -> Result<Index>
+        where E: ByteOrder, R: Read + Seek
+    {
+        let mut entries = vec![];
+        let mut offset = r.seek(SeekFrom::Start(0))?;
+        let end = r.seek(SeekFrom::End(0))?;
+        r.seek(SeekFrom::Start(0))?;
+
+        while offset < end {
+            let index = entries.len();
+            if end - offset < 56 {
+                return Err(Error::truncated(56, (end - offset) as usize)
+                                 .at(index, offset));
+            }
+
+            let mut data = [0; 56];
+            r.read_exact(&mut data)?;
+            let header = Header::parse::<E>(&data).map_err(|e| e.at(index, offset))?;
+
+            let next = offset + 56 + header.length as u64;
+            if next > end {
+                return Err(Error::truncated(header.length,
+                                            (end - offset - 56) as usize)
+                                 .in_record(&header)
+                                 .at(index, offset));
+            }
+
+            entries.push(IndexEntry {
+                offset,
+                length: header.length as u32,
+                record_type: header.record_type,
+                pid: header.pid,
+                tid: header.tid,
+                timestamp: header.timestamp,
+            });
+
+            offset = r.seek(SeekFrom::Start(next))?;
+        }
+
+        Ok(Index { entries, trace_length: end })
+    }
+
+    /// Load an index previously saved with `write`.
+    pub fn read(r: &mut dyn Read) -> Result<Index> {
+        let mut magic = [0; 8];
+        r.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(Error::msg("not a ktrace index file"));
+        }
+
+        let trace_length = r.read_u64::<LittleEndian>()?;
+        let count = r.read_u64::<LittleEndian>()? as usize;
+
+        let mut entries = Vec::with_capacity(count.min(1 << 20));
+        for _ in 0..count {
+            let mut data = [0; 40];
+            r.read_exact(&mut data)?;
+
+            entries.push(IndexEntry {
+                offset: LittleEndian::read_u64(&data[0..8]),
+                length: LittleEndian::read_u32(&data[8..12]),
+                record_type: RecordType::from_u16(
+                    LittleEndian::read_u16(&data[12..14]))?,
+                pid: LittleEndian::read_u32(&data[16..20]),
+                tid: LittleEndian::read_u64(&data[20..28]) as usize,
+                timestamp: TimeVal {
+                    tv_sec: LittleEndian::read_i64(&data[28..36]),
+                    tv_usec: LittleEndian::read_i32(&data[36..40]) as i64,
+                },
+            });
+        }
+
+        Ok(Index { entries, trace_length })
+    }
+
+    /// Save the index (e.g., as a sidecar file next to the trace).
+    pub fn write(&self, w: &mut dyn Write) -> io::Result<()> {
+        w.write_all(MAGIC)?;
+        w.write_u64::<LittleEndian>(self.trace_length)?;
+        w.write_u64::<LittleEndian>(self.entries.len() as u64)?;
+
+        for e in &self.entries {
+            w.write_u64::<LittleEndian>(e.offset)?;
+            w.write_u32::<LittleEndian>(e.length)?;
+            w.write_u16::<LittleEndian>(e.record_type.clone() as u16)?;
+            w.write_u16::<LittleEndian>(0)?;
+            w.write_u32::<LittleEndian>(e.pid)?;
+            w.write_u64::<LittleEndian>(e.tid as u64)?;
+            w.write_i64::<LittleEndian>(e.timestamp.tv_sec)?;
+            w.write_i32::<LittleEndian>(e.timestamp.tv_usec as i32)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn entries(&self) -> &[IndexEntry] {
+        &self.entries
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Size of the trace file that was indexed.
+    pub fn trace_length(&self) -> u64 {
+        self.trace_length
+    }
+
+    /// The number of the first record at or after a given time.
+    ///
+    /// This assumes that the trace is in time order, as written by the
+    /// kernel.
+    pub fn find_time(&self, time: TimeVal) -> usize {
+        self.entries.partition_point(|e| e.timestamp < time)
+    }
+}
+
+
+impl<R, E> IndexedReader<R, E>
+    where R: Read + Seek, E: ByteOrder
+{
+    /// Wrap a trace with an existing index, checking that the index
+    /// describes a file of the same size.
+    pub fn new(mut reader: R, index: Index) -> Result<IndexedReader<R, E>> {
+        let length = reader.seek(SeekFrom::End(0))?;
+        if length != index.trace_length {
+            return Err(Error::msg(format![
+                "stale index: describes a {} B trace, but the trace is {} B",
+                index.trace_length, length]));
+        }
+
+        Ok(IndexedReader { reader, index, byte_order: PhantomData })
+    }
+
+    /// Index a trace and wrap it.
+    pub fn open(mut reader: R) -> Result<IndexedReader<R, E>> {
+        let index = Index::build::<E, _>(&mut reader)?;
+        Ok(IndexedReader { reader, index, byte_order: PhantomData })
+    }
+
+    pub fn index(&self) -> &Index {
+        &self.index
+    }
+
+    /// Read and parse the `n`th record in the trace.
+    pub fn record(&mut self, n: usize) -> Result<(Header, Result<Record>)> {
+        let offset = match self.index.entries.get(n) {
+            Some(entry) => entry.offset,
+            None => return Err(Error::msg(format![
+                "no record #{} (trace has {})", n, self.index.len()])),
+        };
+
+        self.reader.seek(SeekFrom::Start(offset))?;
+
+        let mut data = [0; 56];
+        self.reader.read_exact(&mut data)?;
+        let header = Header::parse::<E>(&data).map_err(|e| e.at(n, offset))?;
+
+        // Don't trust the header's length enough to allocate it all up front.
+        let mut data = vec![];
+        let got = Read::take(&mut self.reader, header.length as u64)
+                       .read_to_end(&mut data)
+                       .map_err(|e| Error::io(e).at(n, offset))?;
+        if got < header.length {
+            return Err(Error::truncated(header.length, got)
+                             .in_record(&header)
+                             .at(n, offset));
+        }
+
+        let record = Record::parse::<E>(&data, &header.record_type)
+                           .map_err(|e| e.in_record(&header).at(n, offset));
+
+        Ok((header, record))
+    }
+
+    /// Iterate over records starting from the `n`th.
+    pub fn records_from(&mut self, n: usize) -> Records<'_, R, E> {
+        let numbers = Box::new(n..self.index.len());
+        Records { reader: self, numbers }
+    }
+
+    /// Iterate over records starting at a given time.
+    pub fn records_since(&mut self, time: TimeVal) -> Records<'_, R, E> {
+        let n = self.index.find_time(time);
+        self.records_from(n)
+    }
+
+    /// Iterate over all of the records from one process.
+    pub fn records_for_pid(&mut self, pid: u32) -> Records<'_, R, E> {
+        self.select(0, |e| e.pid == pid)
+    }
+
+    /// Iterate over the records, starting from the `n`th, whose index
+    /// entries satisfy a predicate.
+    pub fn select<F>(&mut self, n: usize, predicate: F) -> Records<'_, R, E>
+        where F: Fn(&IndexEntry) -> bool
+    {
+        let numbers = self.index.entries.iter()
+                                        .enumerate()
+                                        .skip(n)
+                                        .filter(|&(_, e)| predicate(e))
+                                        .map(|(i, _)| i)
+                                        .collect::<Vec<_>>();
+
+        Records { reader: self, numbers: Box::new(numbers.into_iter()) }
+    }
+}
+
+/// An iterator over selected records of an `IndexedReader`
+pub struct Records<'r, R: 'r, E: 'r> {
+    reader: &'r mut IndexedReader<R, E>,
+    numbers: Box<dyn Iterator<Item = usize>>,
+}
+
+impl<'r, R, E> Iterator for Records<'r, R, E>
+    where R: Read + Seek, E: ByteOrder
+{
+    type Item = Result<(Header, Result<Record>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.numbers.next().map(|n| self.reader.record(n))
+    }
+}
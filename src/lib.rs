@@ -12,45 +12,128 @@
 // except according to those terms.
 
 extern crate byteorder;
+extern crate nix;
 
+mod abi;
+mod access;
+mod bytestring;
+mod call;
+mod diff;
 mod error;
+mod export;
+mod faults;
+mod fd;
+mod follow;
 mod header;
+mod index;
+mod json;
+mod merge;
+mod offcpu;
+mod os;
+mod parallel;
 mod record;
+mod redact;
+mod slice;
+mod stats;
+mod strace;
+mod streams;
+mod sysctls;
 
+pub use abi::*;
+pub use access::*;
+pub use bytestring::*;
+pub use call::*;
+pub use diff::*;
 pub use error::*;
+pub use export::*;
+pub use faults::*;
+pub use fd::*;
+pub use follow::*;
 pub use header::*;
+pub use index::*;
+pub use merge::*;
+pub use offcpu::*;
+pub use os::*;
+pub use parallel::*;
 pub use record::*;
+pub use redact::*;
+pub use slice::*;
+pub use stats::*;
+pub use strace::*;
+pub use streams::*;
+pub use sysctls::*;
 
 use std::io;
-use std::io::ErrorKind::UnexpectedEof;
+use std::io::Read;
+use std::io::ErrorKind::Interrupted;
 
 type Result<T> = std::result::Result<T, self::Error>;
 
 
-pub fn parse<E>(mut r: &mut io::Read) -> Result<Vec<(Header,Result<Record>)>>
+pub fn parse<E>(r: &mut dyn io::Read) -> Result<Vec<(Header,Result<Record>)>>
     where E: byteorder::ByteOrder
 {
     let mut v = Vec::new();
+    let mut offset = 0;
 
-    loop {
-        let mut data = [0; 56];
-        match r.read_exact(&mut data) {
-            Err(ref e) if e.kind() == UnexpectedEof => {
-                break;
-            },
+    while let Some((header, data)) = read_raw::<E>(r, v.len(), offset)? {
+        let index = v.len();
+        let record = Record::parse::<E>(&data, &header.record_type)
+                           .map_err(|e| e.in_record(&header).at(index, offset));
 
-            Err(e) => { return Err(Error::IO(e)); },
-            Ok(()) => {},
-        };
+        offset += (56 + header.length) as u64;
+        v.push((header, record));
+    }
+
+    Ok(v)
+}
 
-        let header = try![Header::parse(&data)];
+/// Read the next record's header and (undecoded) payload, if there is one.
+fn read_raw<E>(r: &mut dyn io::Read, index: usize, offset: u64)
+    -> Result<Option<(Header, Vec<u8>)>>
+    where E: byteorder::ByteOrder
+{
+    let mut data = [0; 56];
+    match read_fully(r, &mut data).map_err(|e| e.at(index, offset))? {
+        0 => return Ok(None),
+        56 => {},
+        n => return Err(Error::truncated(56, n).at(index, offset)),
+    };
 
-        let mut data = vec![0; header.length];
-        try![r.read_exact(&mut data).map_err(Error::IO)];
-        let record = Record::parse::<E>(&data, &header.record_type);
+    let header = Header::parse::<E>(&data).map_err(|e| e.at(index, offset))?;
 
-        v.push((header, record));
+    // Don't trust the header's length enough to allocate it all up front.
+    let mut data = vec![];
+    let n = Read::take(&mut *r, header.length as u64)
+             .read_to_end(&mut data)
+             .map_err(|e| Error::io(e).at(index, offset))?;
+    if n < header.length {
+        return Err(Error::truncated(header.length, n)
+                         .in_record(&header)
+                         .at(index, offset));
     }
 
-    Ok(v)
+    Ok(Some((header, data)))
+}
+
+/// Read as much of `buf` as possible, stopping early only at EOF.
+fn read_fully(r: &mut dyn io::Read, buf: &mut [u8]) -> Result<usize> {
+    let mut n = 0;
+
+    while n < buf.len() {
+        match r.read(&mut buf[n..]) {
+            Ok(0) => break,
+            Ok(count) => n += count,
+            Err(ref e) if e.kind() == Interrupted => {},
+            Err(e) => return Err(Error::io(e)),
+        }
+    }
+
+    Ok(n)
+}
+
+/// A time in microseconds, saturating rather than overflowing if a
+/// (malformed) trace contains a nonsensical timestamp.
+fn microseconds(t: &nix::sys::time::TimeVal) -> i64 {
+    t.tv_sec.saturating_mul(1_000_000).saturating_add(t.tv_usec)
 }
@@ -0,0 +1,175 @@
+// Copyright 2016 Jonathan Anderson <jonathan.anderson@mun.ca>
+//
+// This software was developed by BAE Systems, the University of Cambridge
+// Computer Laboratory, and Memorial University under DARPA/AFRL contract
+// FA8650-15-C-7558 ("CADETS"), as part of the DARPA Transparent Computing
+// (TC) research program.
+//
+// Licensed under the Apache License, Version 2.0,
+// <LICENSE-APACHE or http://apache.org/licenses/LICENSE-2.0>
+// or the MIT license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+
+use byteorder::ByteOrder;
+use std::io::Read;
+use std::marker::PhantomData;
+use ::{Header,Record,Result};
+
+
+/// A record from one of several merged traces
+#[derive(Debug)]
+pub struct MergedRecord {
+    /// the trace that the record came from, as returned by `Merge::add`
+    pub source: usize,
+
+    pub header: Header,
+    pub record: Result<Record>,
+
+    /// the record's undecoded payload
+    pub payload: Vec<u8>,
+}
+
+/// Interleaves the records of several traces in timestamp order
+///
+/// Records with the same timestamp are ordered by source (in the order
+/// that sources were added), and the records of each source are kept in
+/// their original order. If a trace cannot be framed, an `Err` identifying
+/// the trace (see `Error::trace`) is yielded and no more records are read
+/// from that trace.
+pub struct Merge<E> {
+    sources: Vec<Source>,
+    byte_order: PhantomData<E>,
+}
+
+struct Source {
+    reader: Box<dyn Read>,
+    next: Option<(Header, Vec<u8>)>,
+    done: bool,
+
+    /// number of records read and offset of the next one
+    index: usize,
+    offset: u64,
+}
+
+
+impl MergedRecord {
+    /// Convert the record back into its binary representation, with the
+    /// byte order `E` of the trace that it came from.
+    pub fn to_bytes<E>(&self) -> Vec<u8>
+        where E: ByteOrder
+    {
+        let mut v = Vec::with_capacity(56 + self.payload.len());
+        v.extend_from_slice(&self.header.to_bytes::<E>());
+        v.extend_from_slice(&self.payload);
+        v
+    }
+}
+
+
+impl<E> Merge<E>
+    where E: ByteOrder
+{
+    pub fn new() -> Merge<E> {
+        Merge {
+            sources: vec![],
+            byte_order: PhantomData,
+        }
+    }
+
+    /// Add a trace to the merge, returning the number that will identify
+    /// its records.
+    pub fn add<R>(&mut self, reader: R) -> usize
+        where R: Read + 'static
+    {
+        self.sources.push(Source {
+            reader: Box::new(reader),
+            next: None,
+            done: false,
+            index: 0,
+            offset: 0,
+        });
+
+        self.sources.len() - 1
+    }
+}
+
+impl<E> Default for Merge<E>
+    where E: ByteOrder
+{
+    fn default() -> Merge<E> {
+        Merge::new()
+    }
+}
+
+impl<E> Iterator for Merge<E>
+    where E: ByteOrder
+{
+    type Item = Result<MergedRecord>;
+
+    fn next(&mut self) -> Option<Result<MergedRecord>> {
+        let mut earliest = None;
+
+        for (i, source) in self.sources.iter_mut().enumerate() {
+            if let Err(e) = source.fill::<E>() {
+                return Some(Err(e.in_trace(i)));
+            }
+
+            if let Some((ref header, _)) = source.next {
+                match earliest {
+                    Some((_, t)) if t <= header.timestamp => {},
+                    _ => earliest = Some((i, header.timestamp)),
+                }
+            }
+        }
+
+        let (source, _) = earliest?;
+        let (index, offset) = (self.sources[source].index,
+                               self.sources[source].offset);
+        let (header, payload) = self.sources[source].take()?;
+
+        let record = Record::parse::<E>(&payload, &header.record_type)
+                           .map_err(|e| e.in_record(&header)
+                                         .at(index, offset)
+                                         .in_trace(source));
+
+        Some(Ok(MergedRecord { source, header, record, payload }))
+    }
+}
+
+
+impl Source {
+    /// Read the source's next record, if we haven't already.
+    fn fill<E>(&mut self) -> Result<()>
+        where E: ByteOrder
+    {
+        if self.done || self.next.is_some() {
+            return Ok(());
+        }
+
+        match ::read_raw::<E>(&mut self.reader, self.index, self.offset) {
+            Ok(Some(next)) => {
+                self.next = Some(next);
+                Ok(())
+            },
+            Ok(None) => {
+                self.done = true;
+                Ok(())
+            },
+            Err(e) => {
+                self.done = true;
+                Err(e)
+            },
+        }
+    }
+
+    fn take(&mut self) -> Option<(Header, Vec<u8>)> {
+        let (header, payload) = self.next.take()?;
+
+        self.index += 1;
+        self.offset += (56 + header.length) as u64;
+
+        Some((header, payload))
+    }
+}
@@ -0,0 +1,186 @@
+// Copyright 2016 Jonathan Anderson <jonathan.anderson@mun.ca>
+//
+// This software was developed by BAE Systems, the University of Cambridge
+// Computer Laboratory, and Memorial University under DARPA/AFRL contract
+// FA8650-15-C-7558 ("CADETS"), as part of the DARPA Transparent Computing
+// (TC) research program.
+//
+// Licensed under the Apache License, Version 2.0,
+// <LICENSE-APACHE or http://apache.org/licenses/LICENSE-2.0>
+// or the MIT license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+extern crate nix;
+
+use self::nix::sys::time::TimeVal;
+use std::collections::{BTreeMap,HashMap};
+use std::io::{self,Write};
+use ::{ByteString,CallTracker,Header,Record};
+
+
+/// A period during which a thread was switched off the CPU
+#[derive(Clone,Debug)]
+pub struct OffCpuInterval {
+    pub pid: u32,
+    pub tid: usize,
+    pub command: ByteString,
+
+    pub start: TimeVal,
+    pub end: TimeVal,
+
+    /// the wait channel message (e.g., `select` or `biord`), which is
+    /// empty if the thread was preempted rather than put to sleep
+    pub wmesg: String,
+
+    /// whether the thread was stopped while running in user mode
+    pub user: bool,
+
+    /// the system call that the thread was in when it stopped
+    pub syscall: Option<String>,
+}
+
+/// The number and total length of off-CPU intervals with something in common
+#[derive(Clone,Debug,Default)]
+pub struct OffCpuTotal {
+    pub count: u64,
+
+    /// total and longest time off the CPU (µs)
+    pub total: i64,
+    pub max: i64,
+}
+
+/// Pairs context switches into off-CPU intervals and accounts for the time
+/// that threads spend waiting, by wait channel and by system call
+#[derive(Default)]
+pub struct OffCpu {
+    pub by_wmesg: BTreeMap<String, OffCpuTotal>,
+    pub by_syscall: BTreeMap<String, OffCpuTotal>,
+
+    /// time off the CPU, by (system call, wait channel) pair
+    pub stacks: BTreeMap<(String, String), OffCpuTotal>,
+
+    calls: CallTracker,
+
+    /// the interval that each stopped thread is in, without its end
+    stopped: HashMap<(u32, usize), OffCpuInterval>,
+}
+
+
+impl OffCpuInterval {
+    /// The length of the interval (µs).
+    pub fn duration(&self) -> i64 {
+        ::microseconds(&self.end).saturating_sub(::microseconds(&self.start))
+    }
+
+    /// The wait channel, or `[preempted]` for involuntary switches.
+    pub fn wait_channel(&self) -> &str {
+        if self.wmesg.is_empty() { "[preempted]" } else { &self.wmesg }
+    }
+
+    /// The system call, or whether the thread was in user mode or handling
+    /// something else (e.g., a page fault) in the kernel.
+    pub fn context(&self) -> &str {
+        match self.syscall {
+            Some(ref name) => name,
+            None if self.user => "[user]",
+            None => "[kernel]",
+        }
+    }
+}
+
+impl OffCpuTotal {
+    fn add(&mut self, duration: i64) {
+        self.count += 1;
+        self.total = self.total.saturating_add(duration);
+        self.max = self.max.max(duration);
+    }
+}
+
+
+impl OffCpu {
+    pub fn new() -> OffCpu {
+        OffCpu::default()
+    }
+
+    /// Account for the next record in a trace, returning the interval that
+    /// it ends (if it is a context switch back onto the CPU).
+    pub fn record(&mut self, header: &Header, record: &Record)
+        -> Option<OffCpuInterval>
+    {
+        self.calls.record(header, record);
+        let key = (header.pid, header.tid);
+
+        let (out, user, message) = match *record {
+            Record::ContextSwitch{out, user, ref message} => (out, user, message),
+            _ => return None,
+        };
+
+        if out {
+            let syscall = self.calls.pending(header.pid, header.tid)
+                                    .map(|c| c.name());
+
+            self.stopped.insert(key, OffCpuInterval {
+                pid: header.pid,
+                tid: header.tid,
+                command: header.command.clone(),
+                start: header.timestamp,
+                end: header.timestamp,
+                wmesg: message.to_string(),
+                user,
+                syscall,
+            });
+
+            return None;
+        }
+
+        // A resume without a stop began before the trace did.
+        let mut interval = self.stopped.remove(&key)?;
+        interval.end = header.timestamp;
+
+        let duration = interval.duration();
+        let wchan = interval.wait_channel().to_string();
+        let context = interval.context().to_string();
+
+        self.by_wmesg.entry(wchan.clone()).or_default().add(duration);
+        self.by_syscall.entry(context.clone()).or_default().add(duration);
+        self.stacks.entry((context, wchan)).or_default().add(duration);
+
+        Some(interval)
+    }
+
+    /// Write tables of off-CPU time by wait channel and by system call,
+    /// longest total first.
+    pub fn write_table(&self, out: &mut dyn Write) -> io::Result<()> {
+        write_table(out, "wait channel", &self.by_wmesg)?;
+        writeln![out]?;
+        write_table(out, "syscall", &self.by_syscall)
+    }
+
+    /// Write off-CPU time (µs) as folded stacks of `syscall;wmesg`, the
+    /// input format of flame graph tools like `flamegraph.pl`.
+    pub fn write_folded(&self, out: &mut dyn Write) -> io::Result<()> {
+        for ((syscall, wmesg), t) in &self.stacks {
+            writeln![out, "{};{} {}", syscall, wmesg, t.total]?;
+        }
+
+        Ok(())
+    }
+}
+
+fn write_table(out: &mut dyn Write, what: &str,
+               totals: &BTreeMap<String, OffCpuTotal>) -> io::Result<()>
+{
+    writeln![out, "{:<24} {:>8} {:>12} {:>10} {:>10}",
+             what, "count", "total (µs)", "mean", "max"]?;
+
+    let mut sorted: Vec<_> = totals.iter().collect();
+    sorted.sort_by(|a, b| b.1.total.cmp(&a.1.total).then(a.0.cmp(b.0)));
+
+    for (name, t) in sorted {
+        writeln![out, "{:<24} {:>8} {:>12} {:>10} {:>10}",
+                 name, t.count, t.total, t.total / t.count.max(1) as i64, t.max]?;
+    }
+
+    Ok(())
+}
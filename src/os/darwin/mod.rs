@@ -0,0 +1,232 @@
+// Copyright 2016 Jonathan Anderson <jonathan.anderson@mun.ca>
+//
+// This software was developed by BAE Systems, the University of Cambridge
+// Computer Laboratory, and Memorial University under DARPA/AFRL contract
+// FA8650-15-C-7558 ("CADETS"), as part of the DARPA Transparent Computing
+// (TC) research program.
+//
+// Licensed under the Apache License, Version 2.0,
+// <LICENSE-APACHE or http://apache.org/licenses/LICENSE-2.0>
+// or the MIT license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+
+extern crate nix;
+
+use byteorder::{BigEndian,ByteOrder,LittleEndian};
+use self::nix::sys::time::TimeVal;
+use ::{ByteString,Error,IODir,Record,RecordType,Result};
+use super::{Conventions,Entry,Extra,RawHeader,Syscalls};
+
+pub mod syscalls;
+
+
+const HEADER_SIZE: usize = 44;
+
+const KTR_SYSCALL: u32 = 1;
+const KTR_SYSRET: u32 = 2;
+const KTR_NAMEI: u32 = 3;
+const KTR_GENIO: u32 = 4;
+const KTR_PSIG: u32 = 5;
+const KTR_CSW: u32 = 6;
+const KTR_USER: u32 = 7;
+
+const CONVENTIONS: Conventions = Conventions {
+    open_flags: &[
+        (0x0002_0000, 0x0000_8000),     // O_NOCTTY
+        (0x0010_0000, 0x0002_0000),     // O_DIRECTORY
+        (0x0040_0000, 0x0100_0000),     // O_DSYNC
+        (0x0100_0000, 0x0010_0000),     // O_CLOEXEC
+    ],
+    sock_flags: &[],
+    af_inet6: 30,
+    fcntl: &[
+        (67, 17),                       // F_DUPFD_CLOEXEC
+    ],
+    errnos: &[
+        (-1, "ERESTART"),
+        (-2, "EJUSTRETURN"),
+        (84, "EOVERFLOW"),
+        (89, "ECANCELED"),
+        (90, "EIDRM"),
+        (91, "ENOMSG"),
+        (92, "EILSEQ"),
+        (93, "ENOATTR"),
+        (94, "EBADMSG"),
+        (95, "EMULTIHOP"),
+        (97, "ENOLINK"),
+        (100, "EPROTO"),
+        (102, "EOPNOTSUPP"),
+        (104, "ENOTRECOVERABLE"),
+        (105, "EOWNERDEAD"),
+    ],
+    padded: &[],
+};
+
+
+/// Parse a trace written by Darwin's `ktrace(1)` (up to Mac OS X 10.4).
+///
+/// Darwin has no thread IDs in its traces, so each record's `tid` is its
+/// process ID.
+pub fn parse<E>(data: &[u8]) -> Result<Vec<Entry>>
+    where E: ByteOrder
+{
+    let mut calls = Syscalls::new(syscalls::name, &CONVENTIONS);
+    super::parse_records(data, HEADER_SIZE, header::<E>,
+                         |h, payload| decode::<E>(h, payload, &mut calls))
+}
+
+/// Whether a trace looks like it was written by Darwin (in either byte
+/// order: traces from PowerPC systems are big-endian).
+pub fn detect(data: &[u8]) -> bool {
+    super::chains(data, HEADER_SIZE, length::<LittleEndian>)
+        || big_endian(data)
+}
+
+/// Whether a Darwin trace was written by a big-endian (PowerPC) system.
+pub fn big_endian(data: &[u8]) -> bool {
+    super::chains(data, HEADER_SIZE, length::<BigEndian>)
+        && !super::chains(data, HEADER_SIZE, length::<LittleEndian>)
+}
+
+/// The payload length of a plausible Darwin header.
+fn length<E>(data: &[u8]) -> Option<usize>
+    where E: ByteOrder
+{
+    let length = E::read_i32(&data[0..4]);
+    let record_type = E::read_u16(&data[4..6]) as u32;
+    let padding = E::read_u16(&data[6..8]);
+
+    if length >= 0 && padding == 0
+       && (KTR_SYSCALL..=KTR_USER).contains(&record_type)
+    {
+        Some(length as usize)
+    } else {
+        None
+    }
+}
+
+/// Parse a (32-bit) `struct ktr_header`.
+fn header<E>(data: &[u8]) -> Result<Option<RawHeader>>
+    where E: ByteOrder
+{
+    let length = E::read_i32(&data[0..4]);
+    if length < 0 {
+        return Err(Error::msg(format!["negative record length: {}", length]));
+    }
+
+    let pid = E::read_u32(&data[8..12]);
+
+    Ok(Some(RawHeader {
+        record_type: E::read_u16(&data[4..6]) as u32,
+        length: length as usize,
+        pid,
+        tid: pid as usize,
+        command: super::c_bytes(&data[12..29]).into(),
+        timestamp: TimeVal {
+            tv_sec: E::read_i32(&data[32..36]) as i64,
+            tv_usec: E::read_i32(&data[36..40]) as i64,
+        },
+    }))
+}
+
+fn decode<E>(h: &RawHeader, data: &[u8], calls: &mut Syscalls) -> Entry
+    where E: ByteOrder
+{
+    let common = |t: RecordType, r: Result<Record>| {
+        Entry::Common(h.common(t.clone()), r.map_err(|e| e.of_type(&t)))
+    };
+
+    match h.record_type {
+        KTR_SYSCALL => {
+            if data.len() < 4 {
+                return common(RecordType::SystemCall, Err(Error::bad_length(
+                    "at least 4 B (2*short)", data.len())));
+            }
+
+            let code = E::read_u16(&data[0..2]) as u32;
+            let narg = E::read_u16(&data[2..4]) as usize;
+
+            // Arguments were register_t on older systems and u_int64_t
+            // (possibly aligned to 8 B) on newer ones.
+            let args = if data.len() == 4 + 4 * narg {
+                data[4..].chunks(4).map(|a| E::read_u32(a) as u64).collect()
+            } else if data.len() == 4 + 8 * narg {
+                data[4..].chunks(8).map(E::read_u64).collect()
+            } else if data.len() == 8 + 8 * narg {
+                data[8..].chunks(8).map(E::read_u64).collect()
+            } else {
+                return common(RecordType::SystemCall, Err(Error::bad_length(
+                    format!["4 + {} * 4 or 8 B ({} arguments)", narg, narg],
+                    data.len())));
+            };
+
+            calls.call(h, code, args)
+        },
+
+        KTR_SYSRET => {
+            let retval = match data.len() {
+                12 => E::read_u32(&data[8..12]) as u64,
+                16 => E::read_u64(&data[8..16]),
+                n => return common(RecordType::SystemCallReturn, Err(
+                    Error::bad_length("12 or 16 B (2*short + int + register_t)", n))),
+            };
+
+            calls.ret(h, E::read_u16(&data[0..2]) as u32, E::read_u16(&data[2..4]),
+                      E::read_i32(&data[4..8]), retval)
+        },
+
+        KTR_NAMEI => common(RecordType::Namei,
+                            Record::parse::<E>(data, &RecordType::Namei)),
+
+        KTR_GENIO => {
+            if data.len() < 8 {
+                return common(RecordType::GenericIO, Err(Error::bad_length(
+                    "at least 8 B (2*int)", data.len())));
+            }
+
+            common(RecordType::GenericIO, match E::read_u32(&data[4..8]) {
+                0 => Ok(IODir::Read),
+                1 => Ok(IODir::Write),
+                x => Err(Error::unknown_value("uio_rw", x)),
+            }.map(|rw| Record::GenericIO {
+                fd: E::read_i32(&data[0..4]),
+                rw,
+                data: data[8..].to_vec(),
+            }))
+        },
+
+        KTR_PSIG => {
+            // int signo, sig_t action, sigset_t mask (u32), int code
+            if data.len() < 16 {
+                return common(RecordType::Signal, Err(Error::bad_length(
+                    "at least 16 B (int + sig_t + sigset_t + int)", data.len())));
+            }
+
+            common(RecordType::Signal, Ok(Record::Signal {
+                signo: E::read_i32(&data[0..4]),
+                handler: E::read_u32(&data[4..8]) as u64,
+                code: E::read_i32(&data[12..16]),
+                mask: vec![E::read_u32(&data[8..12])],
+            }))
+        },
+
+        KTR_CSW => {
+            if data.len() < 8 {
+                return common(RecordType::ContextSwitch, Err(Error::bad_length(
+                    "at least 8 B (2*int)", data.len())));
+            }
+
+            common(RecordType::ContextSwitch, Ok(Record::ContextSwitch {
+                out: E::read_u32(&data[0..4]) != 0,
+                user: E::read_u32(&data[4..8]) != 0,
+                message: ByteString::default(),
+            }))
+        },
+
+        KTR_USER => common(RecordType::UserData, Ok(Record::UserData(data.to_vec()))),
+
+        t => h.specific(Ok(Extra::Unknown { record_type: t, data: data.to_vec() })),
+    }
+}
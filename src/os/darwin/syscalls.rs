@@ -0,0 +1,174 @@
+// Copyright 2016 Jonathan Anderson <jonathan.anderson@mun.ca>
+//
+// This software was developed by BAE Systems, the University of Cambridge
+// Computer Laboratory, and Memorial University under DARPA/AFRL contract
+// FA8650-15-C-7558 ("CADETS"), as part of the DARPA Transparent Computing
+// (TC) research program.
+//
+// Licensed under the Apache License, Version 2.0,
+// <LICENSE-APACHE or http://apache.org/licenses/LICENSE-2.0>
+// or the MIT license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+
+use ::{Error,Result};
+
+
+/// The name of a Darwin system call (from xnu's `bsd/kern/syscalls.master`).
+///
+/// Only calls whose numbers were stable across the releases that shipped
+/// `ktrace(1)` (up to Mac OS X 10.4) are listed: others are reported by
+/// number.
+pub fn name(id: usize) -> Result<String> {
+    match id {
+        0 => Ok("nosys"),
+        1 => Ok("exit"),
+        2 => Ok("fork"),
+        3 => Ok("read"),
+        4 => Ok("write"),
+        5 => Ok("open"),
+        6 => Ok("close"),
+        7 => Ok("wait4"),
+        9 => Ok("link"),
+        10 => Ok("unlink"),
+        12 => Ok("chdir"),
+        13 => Ok("fchdir"),
+        14 => Ok("mknod"),
+        15 => Ok("chmod"),
+        16 => Ok("chown"),
+        17 => Ok("break"),
+        20 => Ok("getpid"),
+        23 => Ok("setuid"),
+        24 => Ok("getuid"),
+        25 => Ok("geteuid"),
+        26 => Ok("ptrace"),
+        27 => Ok("recvmsg"),
+        28 => Ok("sendmsg"),
+        29 => Ok("recvfrom"),
+        30 => Ok("accept"),
+        31 => Ok("getpeername"),
+        32 => Ok("getsockname"),
+        33 => Ok("access"),
+        34 => Ok("chflags"),
+        35 => Ok("fchflags"),
+        36 => Ok("sync"),
+        37 => Ok("kill"),
+        39 => Ok("getppid"),
+        41 => Ok("dup"),
+        42 => Ok("pipe"),
+        43 => Ok("getegid"),
+        44 => Ok("profil"),
+        45 => Ok("ktrace"),
+        46 => Ok("sigaction"),
+        47 => Ok("getgid"),
+        48 => Ok("sigprocmask"),
+        49 => Ok("getlogin"),
+        50 => Ok("setlogin"),
+        51 => Ok("acct"),
+        52 => Ok("sigpending"),
+        53 => Ok("sigaltstack"),
+        54 => Ok("ioctl"),
+        55 => Ok("reboot"),
+        56 => Ok("revoke"),
+        57 => Ok("symlink"),
+        58 => Ok("readlink"),
+        59 => Ok("execve"),
+        60 => Ok("umask"),
+        61 => Ok("chroot"),
+        65 => Ok("msync"),
+        66 => Ok("vfork"),
+        73 => Ok("munmap"),
+        74 => Ok("mprotect"),
+        75 => Ok("madvise"),
+        78 => Ok("mincore"),
+        79 => Ok("getgroups"),
+        80 => Ok("setgroups"),
+        81 => Ok("getpgrp"),
+        82 => Ok("setpgid"),
+        83 => Ok("setitimer"),
+        85 => Ok("swapon"),
+        86 => Ok("getitimer"),
+        89 => Ok("getdtablesize"),
+        90 => Ok("dup2"),
+        92 => Ok("fcntl"),
+        93 => Ok("select"),
+        95 => Ok("fsync"),
+        96 => Ok("setpriority"),
+        97 => Ok("socket"),
+        98 => Ok("connect"),
+        100 => Ok("getpriority"),
+        104 => Ok("bind"),
+        105 => Ok("setsockopt"),
+        106 => Ok("listen"),
+        111 => Ok("sigsuspend"),
+        116 => Ok("gettimeofday"),
+        117 => Ok("getrusage"),
+        118 => Ok("getsockopt"),
+        120 => Ok("readv"),
+        121 => Ok("writev"),
+        122 => Ok("settimeofday"),
+        123 => Ok("fchown"),
+        124 => Ok("fchmod"),
+        126 => Ok("setreuid"),
+        127 => Ok("setregid"),
+        128 => Ok("rename"),
+        131 => Ok("flock"),
+        132 => Ok("mkfifo"),
+        133 => Ok("sendto"),
+        134 => Ok("shutdown"),
+        135 => Ok("socketpair"),
+        136 => Ok("mkdir"),
+        137 => Ok("rmdir"),
+        138 => Ok("utimes"),
+        139 => Ok("futimes"),
+        140 => Ok("adjtime"),
+        147 => Ok("setsid"),
+        151 => Ok("getpgid"),
+        152 => Ok("setprivexec"),
+        153 => Ok("pread"),
+        154 => Ok("pwrite"),
+        157 => Ok("statfs"),
+        158 => Ok("fstatfs"),
+        159 => Ok("unmount"),
+        165 => Ok("quotactl"),
+        167 => Ok("mount"),
+        181 => Ok("setgid"),
+        182 => Ok("setegid"),
+        183 => Ok("seteuid"),
+        184 => Ok("sigreturn"),
+        188 => Ok("stat"),
+        189 => Ok("fstat"),
+        190 => Ok("lstat"),
+        191 => Ok("pathconf"),
+        192 => Ok("fpathconf"),
+        194 => Ok("getrlimit"),
+        195 => Ok("setrlimit"),
+        196 => Ok("getdirentries"),
+        197 => Ok("mmap"),
+        199 => Ok("lseek"),
+        200 => Ok("truncate"),
+        201 => Ok("ftruncate"),
+        202 => Ok("__sysctl"),
+        203 => Ok("mlock"),
+        204 => Ok("munlock"),
+        205 => Ok("undelete"),
+        220 => Ok("getattrlist"),
+        221 => Ok("setattrlist"),
+        222 => Ok("getdirentriesattr"),
+        223 => Ok("exchangedata"),
+        225 => Ok("searchfs"),
+        226 => Ok("delete"),
+        227 => Ok("copyfile"),
+        230 => Ok("poll"),
+        250 => Ok("minherit"),
+        310 => Ok("getsid"),
+        324 => Ok("mlockall"),
+        325 => Ok("munlockall"),
+        327 => Ok("issetugid"),
+        362 => Ok("kqueue"),
+        363 => Ok("kevent"),
+        x => Err(Error::unknown_value("system call number", x as i64)),
+    }
+    .map(str::to_string)
+}
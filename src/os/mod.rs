@@ -0,0 +1,585 @@
+// Copyright 2016 Jonathan Anderson <jonathan.anderson@mun.ca>
+//
+// This software was developed by BAE Systems, the University of Cambridge
+// Computer Laboratory, and Memorial University under DARPA/AFRL contract
+// FA8650-15-C-7558 ("CADETS"), as part of the DARPA Transparent Computing
+// (TC) research program.
+//
+// Licensed under the Apache License, Version 2.0,
+// <LICENSE-APACHE or http://apache.org/licenses/LICENSE-2.0>
+// or the MIT license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+
+extern crate nix;
+
+use byteorder::ByteOrder;
+use self::nix::sys::time::TimeVal;
+use std::collections::HashMap;
+use std::fmt;
+use std::str;
+use ::{ByteString,Error,Header,Record,RecordType,Result,errno,syscalls};
+
+pub mod darwin;
+pub mod netbsd;
+pub mod openbsd;
+
+/// `KTR_USER_MAXIDLEN` on both OpenBSD and NetBSD
+const USER_LABEL_LEN: usize = 20;
+
+
+/// Operating systems whose `ktrace` files we can read
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum Os {
+    FreeBSD,
+    OpenBSD,
+    NetBSD,
+    Darwin,
+
+    /// Linux, as recorded by `strace(1)`
+    Linux,
+}
+
+/// A record from a trace written by any supported OS
+#[derive(Debug)]
+pub enum Entry {
+    /// A record with an equivalent in the common (FreeBSD) model
+    Common(Header, Result<Record>),
+
+    /// A record that only exists in another OS's format
+    Specific {
+        pid: u32,
+        tid: usize,
+        command: ByteString,
+        timestamp: TimeVal,
+        record: Result<Extra>,
+    },
+}
+
+/// Records that have no equivalent in FreeBSD traces
+#[derive(Clone,Debug)]
+pub enum Extra {
+    /// a system call that FreeBSD doesn't have
+    SystemCall { name: String, args: Vec<u64> },
+
+    /// return from a system call that FreeBSD doesn't have
+    SystemCallReturn { name: String, error: i32, retval: u64 },
+
+    /// `utrace(2)` data, with the label that OpenBSD and NetBSD attach
+    User { label: ByteString, data: Vec<u8> },
+
+    /// the arguments or environment passed to `execve(2)`
+    ExecArgs(Vec<ByteString>),
+    ExecEnv(Vec<ByteString>),
+
+    /// OpenBSD `KTR_PLEDGE`: a pledge(2) violation
+    Pledge { error: i32, syscall: i32, code: u64 },
+
+    /// NetBSD `KTR_EMUL`: the emulation that a process is running under
+    Emulation(ByteString),
+
+    /// NetBSD `KTR_MIB`: a numeric sysctl name
+    Mib(Vec<i32>),
+
+    /// NetBSD `KTR_EXEC_FD`: a descriptor opened by `execve(2)`
+    ExecFd { fd: i32, dtype: u32 },
+
+    /// a record type that we don't know how to decode
+    Unknown { record_type: u32, data: Vec<u8> },
+}
+
+
+impl Os {
+    /// Guess which OS wrote a trace, given (at least) its first few
+    /// kilobytes.
+    ///
+    /// OpenBSD traces begin with a `KTR_START` record. NetBSD and recent
+    /// FreeBSD headers both carry a version number, so NetBSD traces are
+    /// those whose first few records line up with NetBSD headers whose
+    /// thread IDs are implausibly small for FreeBSD (whose thread IDs start
+    /// above `PID_MAX`). Darwin's
+    /// headers are smaller than anyone else's, so Darwin traces are those
+    /// whose records only line up with Darwin-sized headers. Linux traces are
+    /// strace logs, which are text.
+    pub fn detect(data: &[u8]) -> Os {
+        if ::strace::is_strace(data) {
+            return Os::Linux;
+        }
+
+        if data.len() >= 4 && (::byteorder::LittleEndian::read_u32(&data[0..4])
+                                   == openbsd::KTR_START
+                               || ::byteorder::BigEndian::read_u32(&data[0..4])
+                                   == openbsd::KTR_START)
+        {
+            return Os::OpenBSD;
+        }
+
+        if netbsd::detect::<::byteorder::NativeEndian>(data) {
+            return Os::NetBSD;
+        }
+
+        if darwin::detect(data) && !chains(data, 56, freebsd_length) {
+            return Os::Darwin;
+        }
+
+        Os::FreeBSD
+    }
+
+    pub fn from_name(name: &str) -> Option<Os> {
+        match name.to_lowercase().as_str() {
+            "freebsd" => Some(Os::FreeBSD),
+            "openbsd" => Some(Os::OpenBSD),
+            "netbsd" => Some(Os::NetBSD),
+            "darwin" => Some(Os::Darwin),
+            "linux" => Some(Os::Linux),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Os {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match *self {
+            Os::FreeBSD => "FreeBSD",
+            Os::OpenBSD => "OpenBSD",
+            Os::NetBSD => "NetBSD",
+            Os::Darwin => "Darwin",
+            Os::Linux => "Linux",
+        };
+
+        write![f, "{}", name]
+    }
+}
+
+impl fmt::Display for Extra {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Extra::SystemCall{ref name, ref args} => {
+                write![f, "CALL  {}({})", name,
+                       args.iter()
+                           .map(|x| format!["0x{:x}", x])
+                           .collect::<Vec<_>>()
+                           .join(", ")]
+            },
+            Extra::SystemCallReturn{ref name, error, retval} => {
+                write![f, "RET   {} 0x{:x}", name, retval]?;
+                if error != 0 {
+                    write![f, " errno {}", error]?;
+                }
+                Ok(())
+            },
+            Extra::User{ref label, ref data} => {
+                write![f, "USER  {} {:?}", label, data]
+            },
+            Extra::ExecArgs(ref args) => write![f, "ARGS  {:?}", args],
+            Extra::ExecEnv(ref env) => write![f, "ENV   {:?}", env],
+            Extra::Pledge{error, syscall, code} => {
+                write![f, "PLDG  syscall {} code 0x{:x} errno {}",
+                       syscall, code, error]
+            },
+            Extra::Emulation(ref name) => write![f, "EMUL  \"{}\"", name],
+            Extra::Mib(ref mib) => {
+                write![f, "MIB   {}",
+                       mib.iter()
+                          .map(|x| x.to_string())
+                          .collect::<Vec<_>>()
+                          .join(".")]
+            },
+            Extra::ExecFd{fd, dtype} => write![f, "EXFD  fd {} type {}", fd, dtype],
+            Extra::Unknown{record_type, ref data} => {
+                write![f, "????  type {}: {} B", record_type, data.len()]
+            },
+        }
+    }
+}
+
+
+/// Parse a trace written by `os`.
+pub fn parse_os<E>(data: &[u8], os: Os) -> Result<Vec<Entry>>
+    where E: ByteOrder
+{
+    match os {
+        Os::FreeBSD => {
+            ::parse_slice::<E>(data)
+                .map(|r| r.map(|(h, r)| {
+                    Entry::Common(h.into_owned(), r.map(|r| r.into_owned()))
+                }))
+                .collect()
+        },
+        Os::OpenBSD => openbsd::parse::<E>(data),
+        Os::NetBSD => netbsd::parse::<E>(data),
+        Os::Darwin => darwin::parse::<E>(data),
+        Os::Linux => {
+            Ok(::parse_strace(&mut &data[..])?
+                   .into_iter()
+                   .map(|(h, r)| Entry::Common(h, r))
+                   .collect())
+        },
+    }
+}
+
+/// Parse a trace from any supported OS, detecting which one wrote it.
+pub fn parse_any<E>(data: &[u8]) -> Result<(Os, Vec<Entry>)>
+    where E: ByteOrder
+{
+    let os = Os::detect(data);
+    parse_os::<E>(data, os).map(|entries| (os, entries))
+}
+
+
+/// The fields that every OS's record header has in common
+struct RawHeader {
+    record_type: u32,
+    length: usize,
+    pid: u32,
+    tid: usize,
+    command: ByteString,
+    timestamp: TimeVal,
+}
+
+impl RawHeader {
+    /// A header in the common model, for a record of type `t`.
+    fn common(&self, t: RecordType) -> Header {
+        Header {
+            length: self.length,
+            record_type: t,
+            pid: self.pid,
+            command: self.command.clone(),
+            timestamp: self.timestamp,
+            tid: self.tid,
+        }
+    }
+
+    fn specific(&self, record: Result<Extra>) -> Entry {
+        Entry::Specific {
+            pid: self.pid,
+            tid: self.tid,
+            command: self.command.clone(),
+            timestamp: self.timestamp,
+            record,
+        }
+    }
+}
+
+/// Split a trace into records, given the size of its headers and how to
+/// parse them, and decode each record.
+fn parse_records<H, D>(data: &[u8], header_size: usize, parse_header: H,
+                       mut decode: D) -> Result<Vec<Entry>>
+    where H: Fn(&[u8]) -> Result<Option<RawHeader>>,
+          D: FnMut(&RawHeader, &[u8]) -> Entry
+{
+    let mut entries = vec![];
+    let mut offset = 0;
+    let mut index = 0;
+
+    while offset < data.len() {
+        let remaining = &data[offset..];
+        let at = |e: Error| e.at(index, offset as u64);
+
+        if remaining.len() < header_size {
+            return Err(at(Error::truncated(header_size, remaining.len())));
+        }
+
+        let header = parse_header(&remaining[..header_size]).map_err(at)?;
+        let length = header.as_ref().map(|h| h.length).unwrap_or(0);
+
+        let payload = &remaining[header_size..];
+        if payload.len() < length {
+            return Err(at(Error::truncated(length, payload.len())));
+        }
+
+        // Some headers (e.g., OpenBSD's KTR_START) don't describe records.
+        if let Some(ref header) = header {
+            entries.push(match decode(header, &payload[..length]) {
+                Entry::Common(h, r) => Entry::Common(h, r.map_err(at)),
+                Entry::Specific{pid, tid, command, timestamp, record} => {
+                    Entry::Specific {
+                        pid, tid, command, timestamp,
+                        record: record.map_err(at),
+                    }
+                },
+            });
+        }
+
+        offset += header_size + length;
+        index += 1;
+    }
+
+    Ok(entries)
+}
+
+/// Whether the first few records of a trace (or of the start of a trace)
+/// line up, given the size of its headers and a function that
+/// returns the payload length of a plausible header. A record that is cut
+/// off by the end of the data doesn't count against the trace.
+fn chains<F>(data: &[u8], header_size: usize, length: F) -> bool
+    where F: Fn(&[u8]) -> Option<usize>
+{
+    let mut offset = 0;
+    let mut records = 0;
+
+    while records < 8 && offset + header_size <= data.len() {
+        match length(&data[offset..offset + header_size]) {
+            Some(len) => offset = offset.saturating_add(header_size + len),
+            None => return false,
+        }
+        records += 1;
+    }
+
+    records > 0
+}
+
+/// The payload length of a plausible FreeBSD header.
+fn freebsd_length(data: &[u8]) -> Option<usize> {
+    let mut header = [0; 56];
+    header.copy_from_slice(data);
+    Header::parse::<::byteorder::NativeEndian>(&header).ok().map(|h| h.length)
+}
+
+/// A NUL-terminated (or NUL-padded) string that need not be UTF-8,
+/// e.g., a command name.
+fn c_bytes(data: &[u8]) -> &[u8] {
+    let len = data.iter().position(|&c| c == 0).unwrap_or(data.len());
+    &data[..len]
+}
+
+/// NUL-separated strings, e.g., arguments to `execve(2)`.
+fn c_strings(data: &[u8]) -> Vec<ByteString> {
+    data.split(|&c| c == 0)
+        .filter(|s| !s.is_empty())
+        .map(ByteString::from)
+        .collect()
+}
+
+/// `utrace(2)` data, preceded by a NUL-padded label.
+fn user(data: &[u8]) -> Result<Extra> {
+    if data.len() < USER_LABEL_LEN {
+        return Err(Error::bad_length(
+            format!["at least {} B (label)", USER_LABEL_LEN], data.len()));
+    }
+
+    Ok(Extra::User {
+        label: c_bytes(&data[..USER_LABEL_LEN]).into(),
+        data: data[USER_LABEL_LEN..].to_vec(),
+    })
+}
+
+/// A system call argument array: the arguments as 64-bit words.
+fn args<E>(data: &[u8]) -> Result<Vec<u64>>
+    where E: ByteOrder
+{
+    if !data.len().is_multiple_of(8) {
+        return Err(Error::bad_length("a multiple of 8 B (register_t)", data.len()));
+    }
+
+    Ok(data.chunks(8).map(E::read_u64).collect())
+}
+
+fn timestamp(sec: i64, nsec: i64) -> TimeVal {
+    TimeVal { tv_sec: sec, tv_usec: nsec / 1000 }
+}
+
+
+/// How another OS's system call arguments and `errno` values differ from
+/// FreeBSD's
+struct Conventions {
+    /// `open(2)` flags beyond those that all BSDs share, as (native,
+    /// FreeBSD) pairs: other bits have no FreeBSD equivalent
+    open_flags: &'static [(u64, u64)],
+
+    /// `SOCK_*` flags that can be or'ed into a socket type
+    sock_flags: &'static [(u64, u64)],
+
+    af_inet6: u64,
+
+    /// `fcntl(2)` commands beyond `F_SETFL`
+    fcntl: &'static [(u64, u64)],
+
+    /// `errno` values beyond those that all BSDs share, with the names of
+    /// their FreeBSD equivalents
+    errnos: &'static [(i32, &'static str)],
+
+    /// Calls with a padding word before a 64-bit argument (which FreeBSD's
+    /// current equivalents don't have), as (native number, pad index) pairs
+    padded: &'static [(u32, usize)],
+}
+
+/// `O_RDONLY` to `O_EXCL`, which have the same values on every BSD
+const SHARED_OPEN_FLAGS: u64 = 0x0fff;
+const SOCK_TYPE_MASK: u64 = 0x00ff;
+const AF_INET6: u64 = 28;
+const F_SETFL: u64 = 4;
+
+/// `EPERM` to `ENEEDAUTH`, which have the same values on every BSD
+const SHARED_ERRNOS: i32 = 81;
+
+impl Conventions {
+    /// Translate the arguments of a call to FreeBSD system call `name`.
+    fn args(&self, name: &str, args: &mut [u64]) {
+        let open = |flags| translate(self.open_flags, SHARED_OPEN_FLAGS, flags);
+        let sock_type = |t| translate(self.sock_flags, SOCK_TYPE_MASK, t);
+        let domain = |d| if d == self.af_inet6 { AF_INET6 } else { d };
+        let fcntl = |cmd| match self.fcntl.iter().find(|&&(native, _)| native == cmd) {
+            Some(&(_, freebsd)) => freebsd,
+            None => cmd,
+        };
+
+        let mut map = |n: usize, f: &dyn Fn(u64) -> u64| {
+            if let Some(arg) = args.get_mut(n) {
+                *arg = f(*arg);
+            }
+        };
+
+        match name {
+            "open" | "pipe2" => map(1, &open),
+            "openat" => map(2, &open),
+            "socket" | "socketpair" => {
+                map(0, &domain);
+                map(1, &sock_type);
+            },
+            "accept4" => map(3, &sock_type),
+            "fcntl" => map(1, &|cmd| if cmd > F_SETFL { fcntl(cmd) } else { cmd }),
+            _ => {},
+        }
+    }
+
+    /// The FreeBSD equivalent of an `errno` value, if there is one.
+    fn errno(&self, error: i32) -> Option<i32> {
+        if (0..=SHARED_ERRNOS).contains(&error) {
+            return Some(error);
+        }
+
+        self.errnos.iter()
+                   .find(|&&(native, _)| native == error)
+                   .and_then(|&(_, name)| errno::number(name))
+    }
+
+    /// Remove the padding from the arguments of a native call, if it has any.
+    fn unpad(&self, number: u32, args: &mut Vec<u64>) {
+        let pad = self.padded.iter().find(|&&(n, _)| n == number);
+        if let Some(&(_, i)) = pad {
+            if i < args.len() {
+                args.remove(i);
+            }
+        }
+    }
+
+    /// Translate the address family of a `struct sockaddr`.
+    fn sockaddr(&self, data: &mut [u8]) {
+        if data.len() >= 2 && data[1] as u64 == self.af_inet6 {
+            data[1] = AF_INET6 as u8;
+        }
+    }
+}
+
+/// Translate flags into FreeBSD's, keeping `shared` bits as they are.
+fn translate(table: &[(u64, u64)], shared: u64, flags: u64) -> u64 {
+    table.iter()
+         .filter(|&&(native, _)| flags & native != 0)
+         .fold(flags & shared, |f, &(_, freebsd)| f | freebsd)
+}
+
+
+/// Translates other OSes' system calls into FreeBSD's
+///
+/// Calls with FreeBSD equivalents become common records, with the flags
+/// and `errno` values that analyses interpret translated too. A return
+/// whose `errno` has no FreeBSD equivalent stays OS-specific.
+struct Syscalls {
+    name: fn(usize) -> Result<String>,
+    conventions: &'static Conventions,
+    freebsd: HashMap<u32, Option<u16>>,
+}
+
+impl Syscalls {
+    fn new(name: fn(usize) -> Result<String>, conventions: &'static Conventions)
+        -> Syscalls
+    {
+        Syscalls { name, conventions, freebsd: HashMap::new() }
+    }
+
+    fn name(&self, number: u32) -> String {
+        (self.name)(number as usize).unwrap_or(format!["#{}", number])
+    }
+
+    /// The FreeBSD system call with the same name (ignoring the version
+    /// suffixes of names like NetBSD's `__stat50`), if there is one.
+    fn freebsd(&mut self, number: u32) -> Option<u16> {
+        let name = self.name;
+        *self.freebsd.entry(number).or_insert_with(|| {
+            let name = name(number as usize).ok()?;
+            // Only strip the version from compat names (e.g., `___lstat50`):
+            // the digits in names like `dup3` and `wait4` are significant.
+            let base = if name.starts_with("__") {
+                name.trim_start_matches('_')
+                    .trim_end_matches(|c: char| c.is_ascii_digit())
+            } else {
+                name.as_str()
+            };
+
+            let candidates = match base {
+                "exit" => vec!["sys_exit"],
+                "break" => vec!["obreak"],
+                "sysctl" => vec!["__sysctl"],
+                _ => vec![name.as_str(), base],
+            };
+
+            // Prefer the current number of calls that have been renumbered.
+            (0..600_u16).rev().find(|&n| match syscalls::name(n as usize) {
+                Ok(ref freebsd) => candidates.contains(&freebsd.as_str()),
+                Err(_) => false,
+            })
+        })
+    }
+
+    /// Decode a system call, given its (native) number and arguments.
+    fn call(&mut self, header: &RawHeader, number: u32, mut args: Vec<u64>) -> Entry {
+        match self.freebsd(number) {
+            Some(n) => {
+                self.conventions.unpad(number, &mut args);
+                if let Ok(name) = syscalls::name(n as usize) {
+                    self.conventions.args(&name, &mut args);
+                }
+
+                Entry::Common(header.common(RecordType::SystemCall),
+                              Ok(Record::SystemCall { number: n, args }))
+            },
+            None => header.specific(Ok(Extra::SystemCall {
+                name: self.name(number),
+                args,
+            })),
+        }
+    }
+
+    /// Translate a structure copied in or out by a call.
+    fn structure(&self, mut record: Result<Record>) -> Result<Record> {
+        if let Ok(Record::Struct{ref name, ref mut content}) = record {
+            if name == "sockaddr" {
+                self.conventions.sockaddr(content);
+            }
+        }
+
+        record
+    }
+
+    fn ret(&mut self, header: &RawHeader, number: u32, eosys: u16, error: i32,
+           retval: u64) -> Entry
+    {
+        match (self.freebsd(number), self.conventions.errno(error)) {
+            (Some(n), Some(error)) => Entry::Common(
+                header.common(RecordType::SystemCallReturn),
+                Ok(Record::SystemCallReturn {
+                    code: n,
+                    eosys,
+                    error: error as u32,
+                    retval,
+                })),
+            _ => header.specific(Ok(Extra::SystemCallReturn {
+                name: self.name(number),
+                error,
+                retval,
+            })),
+        }
+    }
+}
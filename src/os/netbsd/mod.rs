@@ -0,0 +1,249 @@
+// Copyright 2016 Jonathan Anderson <jonathan.anderson@mun.ca>
+//
+// This software was developed by BAE Systems, the University of Cambridge
+// Computer Laboratory, and Memorial University under DARPA/AFRL contract
+// FA8650-15-C-7558 ("CADETS"), as part of the DARPA Transparent Computing
+// (TC) research program.
+//
+// Licensed under the Apache License, Version 2.0,
+// <LICENSE-APACHE or http://apache.org/licenses/LICENSE-2.0>
+// or the MIT license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+extern crate nix;
+
+use byteorder::ByteOrder;
+use ::{ByteString,Error,Record,RecordType,Result};
+use super::{Conventions,Entry,Extra,RawHeader,Syscalls};
+
+pub mod syscalls;
+
+
+const HEADER_SIZE: usize = 56;
+
+const KTR_SYSCALL: u32 = 1;
+const KTR_SYSRET: u32 = 2;
+const KTR_NAMEI: u32 = 3;
+const KTR_GENIO: u32 = 4;
+const KTR_PSIG: u32 = 5;
+const KTR_CSW: u32 = 6;
+const KTR_EMUL: u32 = 7;
+const KTR_USER: u32 = 8;
+const KTR_EXEC_ARG: u32 = 10;
+const KTR_EXEC_ENV: u32 = 11;
+const KTR_MIB: u32 = 14;
+const KTR_EXEC_FD: u32 = 15;
+
+const CONVENTIONS: Conventions = Conventions {
+    open_flags: &[
+        (0x0000_8000, 0x0000_8000),     // O_NOCTTY
+        (0x0001_0000, 0x0100_0000),     // O_DSYNC
+        (0x0008_0000, 0x0001_0000),     // O_DIRECT
+        (0x0020_0000, 0x0002_0000),     // O_DIRECTORY
+        (0x0040_0000, 0x0010_0000),     // O_CLOEXEC
+        (0x0080_0000, 0x0004_0000),     // O_SEARCH
+    ],
+    sock_flags: &[
+        (0x1000_0000, 0x1000_0000),     // SOCK_CLOEXEC
+        (0x2000_0000, 0x2000_0000),     // SOCK_NONBLOCK
+    ],
+    af_inet6: 24,
+    fcntl: &[
+        (12, 17),                       // F_DUPFD_CLOEXEC
+    ],
+    errnos: &[
+        (-2, "EJUSTRETURN"),
+        (-3, "ERESTART"),
+        (82, "EIDRM"),
+        (83, "ENOMSG"),
+        (84, "EOVERFLOW"),
+        (85, "EILSEQ"),
+        (86, "EOPNOTSUPP"),             // ENOTSUP
+        (87, "ECANCELED"),
+        (88, "EBADMSG"),
+        (93, "ENOATTR"),
+        (94, "EMULTIHOP"),
+        (95, "ENOLINK"),
+        (96, "EPROTO"),
+        (97, "EOWNERDEAD"),
+        (98, "ENOTRECOVERABLE"),
+    ],
+    padded: &[
+        (173, 3),                       // pread(fd, buf, nbyte, PAD, offset)
+        (174, 3),                       // pwrite(fd, buf, nbyte, PAD, offset)
+        (197, 5),                       // mmap(addr, len, prot, flags, fd, PAD, pos)
+        (199, 1),                       // lseek(fd, PAD, offset, whence)
+        (200, 1),                       // truncate(path, PAD, length)
+        (201, 1),                       // ftruncate(fd, PAD, length)
+    ],
+};
+
+
+/// Parse a trace written by NetBSD's `ktrace(1)`.
+pub fn parse<E>(data: &[u8]) -> Result<Vec<Entry>>
+    where E: ByteOrder
+{
+    let mut calls = Syscalls::new(syscalls::name, &CONVENTIONS);
+    super::parse_records(data, HEADER_SIZE, header::<E>,
+                         |h, payload| decode::<E>(h, payload, &mut calls))
+}
+
+/// Whether a trace looks like it was written by NetBSD.
+///
+/// Recent FreeBSD headers carry a version number too, so NetBSD traces are
+/// those whose records line up with version 1 or 2 headers and whose thread
+/// (LWP) IDs are implausibly small for FreeBSD (whose thread IDs start above
+/// `PID_MAX`).
+pub fn detect<E>(data: &[u8]) -> bool
+    where E: ByteOrder
+{
+    super::chains(data, HEADER_SIZE, length::<E>)
+}
+
+/// The payload length of a plausible NetBSD header.
+fn length<E>(data: &[u8]) -> Option<usize>
+    where E: ByteOrder
+{
+    let record_type = E::read_u16(&data[4..6]) as u32;
+    let tid = E::read_u32(&data[48..52]);
+
+    match header::<E>(data) {
+        Ok(Some(h)) if tid < 100000 && (KTR_SYSCALL..=KTR_EXEC_FD).contains(&record_type) => {
+            Some(h.length)
+        },
+        _ => None,
+    }
+}
+
+/// Parse a (version 1 or 2) `struct ktr_header`.
+fn header<E>(data: &[u8]) -> Result<Option<RawHeader>>
+    where E: ByteOrder
+{
+    let length = E::read_i32(&data[0..4]);
+    if length < 0 {
+        return Err(Error::msg(format!["negative record length: {}", length]));
+    }
+
+    // Version 1 has a timespec-like struct with a 32-bit tv_sec (followed
+    // by padding on LP64 systems), version 2 a struct timespec.
+    let version = E::read_i16(&data[6..8]);
+    let timestamp = match version {
+        1 => super::timestamp(E::read_i32(&data[32..36]) as i64, E::read_i64(&data[40..48])),
+        2 => super::timestamp(E::read_i64(&data[32..40]), E::read_i64(&data[40..48])),
+        v => return Err(Error::unknown_value("ktr_version", v as i64)),
+    };
+
+    Ok(Some(RawHeader {
+        record_type: E::read_u16(&data[4..6]) as u32,
+        length: length as usize,
+        pid: E::read_u32(&data[8..12]),
+        tid: E::read_u32(&data[48..52]) as usize,
+        command: super::c_bytes(&data[12..29]).into(),
+        timestamp,
+    }))
+}
+
+fn decode<E>(h: &RawHeader, data: &[u8], calls: &mut Syscalls) -> Entry
+    where E: ByteOrder
+{
+    let common = |t: RecordType, r: Result<Record>| {
+        Entry::Common(h.common(t.clone()), r.map_err(|e| e.of_type(&t)))
+    };
+
+    match h.record_type {
+        KTR_SYSCALL => {
+            if data.len() < 8 {
+                return common(RecordType::SystemCall, Err(Error::bad_length(
+                    "at least 8 B (2*int)", data.len())));
+            }
+
+            let code = E::read_u32(&data[0..4]);
+            let argsize = E::read_u32(&data[4..8]) as usize;
+            if data.len() != 8 + argsize {
+                return common(RecordType::SystemCall, Err(Error::bad_length(
+                    format!["{} B (2*int + {} B of arguments)", 8 + argsize, argsize],
+                    data.len())));
+            }
+
+            match super::args::<E>(&data[8..]) {
+                Ok(args) => calls.call(h, code, args),
+                Err(e) => common(RecordType::SystemCall, Err(e)),
+            }
+        },
+
+        KTR_SYSRET => {
+            if data.len() < 16 {
+                return common(RecordType::SystemCallReturn, Err(Error::bad_length(
+                    "at least 16 B (2*short + int + register_t)", data.len())));
+            }
+
+            calls.ret(h, E::read_u16(&data[0..2]) as u32, E::read_u16(&data[2..4]),
+                      E::read_i32(&data[4..8]), E::read_u64(&data[8..16]))
+        },
+
+        KTR_NAMEI => common(RecordType::Namei,
+                            Record::parse::<E>(data, &RecordType::Namei)),
+
+        KTR_GENIO => common(RecordType::GenericIO,
+                            Record::parse::<E>(data, &RecordType::GenericIO)),
+
+        KTR_PSIG => {
+            // int signo, sig_t action, sigset_t mask (4*u32), int code
+            if data.len() < 36 {
+                return common(RecordType::Signal, Err(Error::bad_length(
+                    "at least 36 B (int + sig_t + sigset_t + int)", data.len())));
+            }
+
+            common(RecordType::Signal, Ok(Record::Signal {
+                signo: E::read_i32(&data[0..4]),
+                handler: E::read_u64(&data[8..16]),
+                code: E::read_i32(&data[32..36]),
+                mask: data[16..32].chunks(4).map(E::read_u32).collect(),
+            }))
+        },
+
+        KTR_CSW => {
+            if data.len() < 8 {
+                return common(RecordType::ContextSwitch, Err(Error::bad_length(
+                    "at least 8 B (2*int)", data.len())));
+            }
+
+            common(RecordType::ContextSwitch, Ok(Record::ContextSwitch {
+                out: E::read_u32(&data[0..4]) != 0,
+                user: E::read_u32(&data[4..8]) != 0,
+                message: ByteString::default(),
+            }))
+        },
+
+        KTR_EMUL => h.specific(Ok(Extra::Emulation(super::c_bytes(data).into()))),
+
+        KTR_USER => h.specific(super::user(data)),
+
+        KTR_EXEC_ARG => h.specific(Ok(Extra::ExecArgs(super::c_strings(data)))),
+        KTR_EXEC_ENV => h.specific(Ok(Extra::ExecEnv(super::c_strings(data)))),
+
+        KTR_MIB => {
+            if !data.len().is_multiple_of(4) {
+                return h.specific(Err(Error::bad_length(
+                    "a multiple of 4 B (int)", data.len())));
+            }
+
+            h.specific(Ok(Extra::Mib(data.chunks(4).map(E::read_i32).collect())))
+        },
+
+        KTR_EXEC_FD => {
+            if data.len() < 8 {
+                return h.specific(Err(Error::bad_length(
+                    "at least 8 B (int + u_int)", data.len())));
+            }
+
+            h.specific(Ok(Extra::ExecFd {
+                fd: E::read_i32(&data[0..4]),
+                dtype: E::read_u32(&data[4..8]),
+            }))
+        },
+
+        t => h.specific(Ok(Extra::Unknown { record_type: t, data: data.to_vec() })),
+    }
+}
@@ -0,0 +1,153 @@
+// Copyright 2016 Jonathan Anderson <jonathan.anderson@mun.ca>
+//
+// This software was developed by BAE Systems, the University of Cambridge
+// Computer Laboratory, and Memorial University under DARPA/AFRL contract
+// FA8650-15-C-7558 ("CADETS"), as part of the DARPA Transparent Computing
+// (TC) research program.
+//
+// Licensed under the Apache License, Version 2.0,
+// <LICENSE-APACHE or http://apache.org/licenses/LICENSE-2.0>
+// or the MIT license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+
+use ::{Error,Result};
+
+
+/// The name of a NetBSD system call (from `sys/kern/syscalls.master`).
+///
+/// Only calls whose numbers have been stable across recent releases are
+/// listed: others are reported by number.
+pub fn name(id: usize) -> Result<String> {
+    match id {
+        0 => Ok("syscall"),
+        1 => Ok("exit"),
+        2 => Ok("fork"),
+        3 => Ok("read"),
+        4 => Ok("write"),
+        5 => Ok("open"),
+        6 => Ok("close"),
+        9 => Ok("link"),
+        10 => Ok("unlink"),
+        12 => Ok("chdir"),
+        13 => Ok("fchdir"),
+        15 => Ok("chmod"),
+        16 => Ok("chown"),
+        17 => Ok("break"),
+        20 => Ok("getpid"),
+        23 => Ok("setuid"),
+        24 => Ok("getuid"),
+        25 => Ok("geteuid"),
+        26 => Ok("ptrace"),
+        27 => Ok("recvmsg"),
+        28 => Ok("sendmsg"),
+        29 => Ok("recvfrom"),
+        30 => Ok("accept"),
+        31 => Ok("getpeername"),
+        32 => Ok("getsockname"),
+        33 => Ok("access"),
+        34 => Ok("chflags"),
+        35 => Ok("fchflags"),
+        36 => Ok("sync"),
+        37 => Ok("kill"),
+        39 => Ok("getppid"),
+        41 => Ok("dup"),
+        42 => Ok("pipe"),
+        43 => Ok("getegid"),
+        44 => Ok("profil"),
+        45 => Ok("ktrace"),
+        47 => Ok("getgid"),
+        49 => Ok("__getlogin"),
+        50 => Ok("__setlogin"),
+        51 => Ok("acct"),
+        54 => Ok("ioctl"),
+        56 => Ok("revoke"),
+        57 => Ok("symlink"),
+        58 => Ok("readlink"),
+        59 => Ok("execve"),
+        60 => Ok("umask"),
+        61 => Ok("chroot"),
+        66 => Ok("vfork"),
+        73 => Ok("munmap"),
+        74 => Ok("mprotect"),
+        75 => Ok("madvise"),
+        78 => Ok("mincore"),
+        79 => Ok("getgroups"),
+        80 => Ok("setgroups"),
+        81 => Ok("getpgrp"),
+        82 => Ok("setpgid"),
+        90 => Ok("dup2"),
+        92 => Ok("fcntl"),
+        95 => Ok("fsync"),
+        96 => Ok("setpriority"),
+        98 => Ok("connect"),
+        100 => Ok("getpriority"),
+        104 => Ok("bind"),
+        105 => Ok("setsockopt"),
+        106 => Ok("listen"),
+        118 => Ok("getsockopt"),
+        120 => Ok("readv"),
+        121 => Ok("writev"),
+        123 => Ok("fchown"),
+        124 => Ok("fchmod"),
+        126 => Ok("setreuid"),
+        127 => Ok("setregid"),
+        128 => Ok("rename"),
+        131 => Ok("flock"),
+        132 => Ok("mkfifo"),
+        133 => Ok("sendto"),
+        134 => Ok("shutdown"),
+        135 => Ok("socketpair"),
+        136 => Ok("mkdir"),
+        137 => Ok("rmdir"),
+        147 => Ok("setsid"),
+        173 => Ok("pread"),
+        174 => Ok("pwrite"),
+        181 => Ok("setgid"),
+        182 => Ok("setegid"),
+        183 => Ok("seteuid"),
+        191 => Ok("pathconf"),
+        192 => Ok("fpathconf"),
+        194 => Ok("getrlimit"),
+        195 => Ok("setrlimit"),
+        197 => Ok("mmap"),
+        199 => Ok("lseek"),
+        200 => Ok("truncate"),
+        201 => Ok("ftruncate"),
+        202 => Ok("__sysctl"),
+        209 => Ok("poll"),
+        286 => Ok("getsid"),
+        344 => Ok("kqueue"),
+        390 => Ok("__getdents30"),
+        394 => Ok("__socket30"),
+        418 => Ok("__gettimeofday50"),
+        435 => Ok("__kevent50"),
+        439 => Ok("__stat50"),
+        440 => Ok("__fstat50"),
+        441 => Ok("__lstat50"),
+        449 => Ok("__wait450"),
+        453 => Ok("pipe2"),
+        454 => Ok("dup3"),
+        455 => Ok("kqueue1"),
+        456 => Ok("paccept"),
+        457 => Ok("linkat"),
+        458 => Ok("renameat"),
+        459 => Ok("mkfifoat"),
+        460 => Ok("mknodat"),
+        461 => Ok("mkdirat"),
+        462 => Ok("faccessat"),
+        463 => Ok("fchmodat"),
+        464 => Ok("fchownat"),
+        465 => Ok("fexecve"),
+        466 => Ok("fstatat"),
+        467 => Ok("utimensat"),
+        468 => Ok("openat"),
+        469 => Ok("readlinkat"),
+        470 => Ok("symlinkat"),
+        471 => Ok("unlinkat"),
+        472 => Ok("futimens"),
+        x => Err(Error::unknown_value("system call number", x as i64)),
+    }
+    .map(str::to_string)
+}
@@ -0,0 +1,191 @@
+// Copyright 2016 Jonathan Anderson <jonathan.anderson@mun.ca>
+//
+// This software was developed by BAE Systems, the University of Cambridge
+// Computer Laboratory, and Memorial University under DARPA/AFRL contract
+// FA8650-15-C-7558 ("CADETS"), as part of the DARPA Transparent Computing
+// (TC) research program.
+//
+// Licensed under the Apache License, Version 2.0,
+// <LICENSE-APACHE or http://apache.org/licenses/LICENSE-2.0>
+// or the MIT license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+
+use byteorder::ByteOrder;
+use ::{Error,Record,RecordType,Result};
+use super::{Conventions,Entry,Extra,RawHeader,Syscalls};
+
+pub mod syscalls;
+
+
+/// The type of the header that begins every OpenBSD trace
+pub const KTR_START: u32 = 0x4b545200;
+
+const HEADER_SIZE: usize = 64;
+
+const KTR_SYSCALL: u32 = 1;
+const KTR_SYSRET: u32 = 2;
+const KTR_NAMEI: u32 = 3;
+const KTR_GENIO: u32 = 4;
+const KTR_PSIG: u32 = 5;
+const KTR_STRUCT: u32 = 8;
+const KTR_USER: u32 = 9;
+const KTR_EXECARGS: u32 = 10;
+const KTR_EXECENV: u32 = 11;
+const KTR_PLEDGE: u32 = 12;
+
+const CONVENTIONS: Conventions = Conventions {
+    open_flags: &[
+        (0x0000_8000, 0x0000_8000),     // O_NOCTTY
+        (0x0001_0000, 0x0010_0000),     // O_CLOEXEC
+        (0x0002_0000, 0x0002_0000),     // O_DIRECTORY
+    ],
+    sock_flags: &[
+        (0x4000, 0x2000_0000),          // SOCK_NONBLOCK
+        (0x8000, 0x1000_0000),          // SOCK_CLOEXEC
+    ],
+    af_inet6: 24,
+    fcntl: &[
+        (10, 17),                       // F_DUPFD_CLOEXEC
+    ],
+    errnos: &[
+        (-1, "ERESTART"),
+        (-2, "EJUSTRETURN"),
+        (83, "ENOATTR"),
+        (84, "EILSEQ"),
+        (87, "EOVERFLOW"),
+        (88, "ECANCELED"),
+        (89, "EIDRM"),
+        (90, "ENOMSG"),
+        (91, "EOPNOTSUPP"),             // ENOTSUP
+        (92, "EBADMSG"),
+        (93, "ENOTRECOVERABLE"),
+        (94, "EOWNERDEAD"),
+        (95, "EPROTO"),
+    ],
+    padded: &[
+        (173, 3),                       // pread(fd, buf, nbyte, PAD, offset)
+        (174, 3),                       // pwrite(fd, buf, nbyte, PAD, offset)
+        (197, 5),                       // mmap(addr, len, prot, flags, fd, PAD, pos)
+        (199, 1),                       // lseek(fd, PAD, offset, whence)
+        (200, 1),                       // truncate(path, PAD, length)
+        (201, 1),                       // ftruncate(fd, PAD, length)
+    ],
+};
+
+
+/// Parse a trace written by OpenBSD's `ktrace(1)`.
+pub fn parse<E>(data: &[u8]) -> Result<Vec<Entry>>
+    where E: ByteOrder
+{
+    let mut calls = Syscalls::new(syscalls::name, &CONVENTIONS);
+    super::parse_records(data, HEADER_SIZE, header::<E>,
+                         |h, payload| decode::<E>(h, payload, &mut calls))
+}
+
+/// Parse a `struct ktr_header`, skipping `KTR_START` markers.
+fn header<E>(data: &[u8]) -> Result<Option<RawHeader>>
+    where E: ByteOrder
+{
+    let record_type = E::read_u32(&data[0..4]);
+    let length = E::read_u64(&data[56..64]) as usize;
+
+    if record_type == KTR_START {
+        return Ok(None);
+    }
+
+    Ok(Some(RawHeader {
+        record_type,
+        length,
+        pid: E::read_u32(&data[4..8]),
+        tid: E::read_u32(&data[8..12]) as usize,
+        command: super::c_bytes(&data[32..56]).into(),
+        timestamp: super::timestamp(E::read_i64(&data[16..24]),
+                                    E::read_i64(&data[24..32])),
+    }))
+}
+
+fn decode<E>(h: &RawHeader, data: &[u8], calls: &mut Syscalls) -> Entry
+    where E: ByteOrder
+{
+    let common = |t: RecordType, r: Result<Record>| {
+        Entry::Common(h.common(t.clone()), r.map_err(|e| e.of_type(&t)))
+    };
+
+    match h.record_type {
+        KTR_SYSCALL => {
+            if data.len() < 8 {
+                return common(RecordType::SystemCall, Err(Error::bad_length(
+                    "at least 8 B (2*int)", data.len())));
+            }
+
+            let code = E::read_u32(&data[0..4]);
+            let argsize = E::read_u32(&data[4..8]) as usize;
+            if data.len() != 8 + argsize {
+                return common(RecordType::SystemCall, Err(Error::bad_length(
+                    format!["{} B (2*int + {} B of arguments)", 8 + argsize, argsize],
+                    data.len())));
+            }
+
+            match super::args::<E>(&data[8..]) {
+                Ok(args) => calls.call(h, code, args),
+                Err(e) => common(RecordType::SystemCall, Err(e)),
+            }
+        },
+
+        KTR_SYSRET => {
+            if data.len() < 16 {
+                return common(RecordType::SystemCallReturn, Err(Error::bad_length(
+                    "at least 16 B (2*int + register_t)", data.len())));
+            }
+
+            calls.ret(h, E::read_u32(&data[0..4]), 0, E::read_i32(&data[4..8]),
+                      E::read_u64(&data[8..16]))
+        },
+
+        KTR_NAMEI => common(RecordType::Namei,
+                            Record::parse::<E>(data, &RecordType::Namei)),
+
+        KTR_GENIO => common(RecordType::GenericIO,
+                            Record::parse::<E>(data, &RecordType::GenericIO)),
+
+        KTR_PSIG => {
+            if data.len() < 24 {
+                return common(RecordType::Signal, Err(Error::bad_length(
+                    "at least 24 B (int + sig_t + 2*int)", data.len())));
+            }
+
+            common(RecordType::Signal, Ok(Record::Signal {
+                signo: E::read_i32(&data[0..4]),
+                handler: E::read_u64(&data[8..16]),
+                code: E::read_i32(&data[20..24]),
+                mask: vec![E::read_u32(&data[16..20])],
+            }))
+        },
+
+        KTR_STRUCT => common(RecordType::Struct, calls.structure(
+                             Record::parse::<E>(data, &RecordType::Struct))),
+
+        KTR_USER => h.specific(super::user(data)),
+
+        KTR_EXECARGS => h.specific(Ok(Extra::ExecArgs(super::c_strings(data)))),
+        KTR_EXECENV => h.specific(Ok(Extra::ExecEnv(super::c_strings(data)))),
+
+        KTR_PLEDGE => {
+            if data.len() < 16 {
+                return h.specific(Err(Error::bad_length(
+                    "at least 16 B (2*int + uint64_t)", data.len())));
+            }
+
+            h.specific(Ok(Extra::Pledge {
+                error: E::read_i32(&data[0..4]),
+                syscall: E::read_i32(&data[4..8]),
+                code: E::read_u64(&data[8..16]),
+            }))
+        },
+
+        t => h.specific(Ok(Extra::Unknown { record_type: t, data: data.to_vec() })),
+    }
+}
+
@@ -0,0 +1,179 @@
+// Copyright 2016 Jonathan Anderson <jonathan.anderson@mun.ca>
+//
+// This software was developed by BAE Systems, the University of Cambridge
+// Computer Laboratory, and Memorial University under DARPA/AFRL contract
+// FA8650-15-C-7558 ("CADETS"), as part of the DARPA Transparent Computing
+// (TC) research program.
+//
+// Licensed under the Apache License, Version 2.0,
+// <LICENSE-APACHE or http://apache.org/licenses/LICENSE-2.0>
+// or the MIT license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+
+use ::{Error,Result};
+
+
+/// The name of a OpenBSD system call (from `sys/kern/syscalls.master`).
+///
+/// Only calls whose numbers have been stable across recent releases are
+/// listed: others are reported by number.
+pub fn name(id: usize) -> Result<String> {
+    match id {
+        0 => Ok("syscall"),
+        1 => Ok("exit"),
+        2 => Ok("fork"),
+        3 => Ok("read"),
+        4 => Ok("write"),
+        5 => Ok("open"),
+        6 => Ok("close"),
+        7 => Ok("getentropy"),
+        8 => Ok("__tfork"),
+        9 => Ok("link"),
+        10 => Ok("unlink"),
+        11 => Ok("wait4"),
+        12 => Ok("chdir"),
+        13 => Ok("fchdir"),
+        14 => Ok("mknod"),
+        15 => Ok("chmod"),
+        16 => Ok("chown"),
+        17 => Ok("break"),
+        18 => Ok("getdtablecount"),
+        19 => Ok("getrusage"),
+        20 => Ok("getpid"),
+        21 => Ok("mount"),
+        22 => Ok("unmount"),
+        23 => Ok("setuid"),
+        24 => Ok("getuid"),
+        25 => Ok("geteuid"),
+        26 => Ok("ptrace"),
+        27 => Ok("recvmsg"),
+        28 => Ok("sendmsg"),
+        29 => Ok("recvfrom"),
+        30 => Ok("accept"),
+        31 => Ok("getpeername"),
+        32 => Ok("getsockname"),
+        33 => Ok("access"),
+        34 => Ok("chflags"),
+        35 => Ok("fchflags"),
+        36 => Ok("sync"),
+        38 => Ok("stat"),
+        39 => Ok("getppid"),
+        40 => Ok("lstat"),
+        41 => Ok("dup"),
+        42 => Ok("fstatat"),
+        43 => Ok("getegid"),
+        44 => Ok("profil"),
+        45 => Ok("ktrace"),
+        46 => Ok("sigaction"),
+        47 => Ok("getgid"),
+        48 => Ok("sigprocmask"),
+        50 => Ok("setlogin"),
+        51 => Ok("acct"),
+        52 => Ok("sigpending"),
+        53 => Ok("fstat"),
+        54 => Ok("ioctl"),
+        55 => Ok("reboot"),
+        56 => Ok("revoke"),
+        57 => Ok("symlink"),
+        58 => Ok("readlink"),
+        59 => Ok("execve"),
+        60 => Ok("umask"),
+        61 => Ok("chroot"),
+        62 => Ok("getfsstat"),
+        63 => Ok("statfs"),
+        64 => Ok("fstatfs"),
+        65 => Ok("fhstatfs"),
+        66 => Ok("vfork"),
+        67 => Ok("gettimeofday"),
+        68 => Ok("settimeofday"),
+        69 => Ok("setitimer"),
+        70 => Ok("getitimer"),
+        71 => Ok("select"),
+        72 => Ok("kevent"),
+        73 => Ok("munmap"),
+        74 => Ok("mprotect"),
+        75 => Ok("madvise"),
+        76 => Ok("utimes"),
+        77 => Ok("futimes"),
+        79 => Ok("getgroups"),
+        80 => Ok("setgroups"),
+        81 => Ok("getpgrp"),
+        82 => Ok("setpgid"),
+        83 => Ok("futex"),
+        84 => Ok("utimensat"),
+        85 => Ok("futimens"),
+        86 => Ok("kbind"),
+        87 => Ok("clock_gettime"),
+        88 => Ok("clock_settime"),
+        89 => Ok("clock_getres"),
+        90 => Ok("dup2"),
+        91 => Ok("nanosleep"),
+        92 => Ok("fcntl"),
+        93 => Ok("accept4"),
+        94 => Ok("__thrsleep"),
+        95 => Ok("fsync"),
+        96 => Ok("setpriority"),
+        97 => Ok("socket"),
+        98 => Ok("connect"),
+        99 => Ok("getdents"),
+        100 => Ok("getpriority"),
+        101 => Ok("pipe2"),
+        102 => Ok("dup3"),
+        103 => Ok("sigreturn"),
+        104 => Ok("bind"),
+        105 => Ok("setsockopt"),
+        106 => Ok("listen"),
+        107 => Ok("chflagsat"),
+        108 => Ok("pledge"),
+        109 => Ok("ppoll"),
+        110 => Ok("pselect"),
+        111 => Ok("sigsuspend"),
+        112 => Ok("sendsyslog"),
+        114 => Ok("unveil"),
+        118 => Ok("getsockopt"),
+        119 => Ok("thrkill"),
+        120 => Ok("readv"),
+        121 => Ok("writev"),
+        122 => Ok("kill"),
+        123 => Ok("fchown"),
+        124 => Ok("fchmod"),
+        126 => Ok("setreuid"),
+        127 => Ok("setregid"),
+        128 => Ok("rename"),
+        131 => Ok("flock"),
+        132 => Ok("mkfifo"),
+        133 => Ok("sendto"),
+        134 => Ok("shutdown"),
+        135 => Ok("socketpair"),
+        136 => Ok("mkdir"),
+        137 => Ok("rmdir"),
+        140 => Ok("adjtime"),
+        141 => Ok("getlogin_r"),
+        147 => Ok("setsid"),
+        148 => Ok("quotactl"),
+        155 => Ok("nfssvc"),
+        161 => Ok("getfh"),
+        165 => Ok("sysarch"),
+        173 => Ok("pread"),
+        174 => Ok("pwrite"),
+        181 => Ok("setgid"),
+        182 => Ok("setegid"),
+        183 => Ok("seteuid"),
+        191 => Ok("pathconf"),
+        192 => Ok("fpathconf"),
+        193 => Ok("swapctl"),
+        194 => Ok("getrlimit"),
+        195 => Ok("setrlimit"),
+        197 => Ok("mmap"),
+        199 => Ok("lseek"),
+        200 => Ok("truncate"),
+        201 => Ok("ftruncate"),
+        202 => Ok("sysctl"),
+        203 => Ok("mlock"),
+        204 => Ok("munlock"),
+        x => Err(Error::unknown_value("system call number", x as i64)),
+    }
+    .map(str::to_string)
+}
@@ -0,0 +1,184 @@
+// Copyright 2016 Jonathan Anderson <jonathan.anderson@mun.ca>
+//
+// This software was developed by BAE Systems, the University of Cambridge
+// Computer Laboratory, and Memorial University under DARPA/AFRL contract
+// FA8650-15-C-7558 ("CADETS"), as part of the DARPA Transparent Computing
+// (TC) research program.
+//
+// Licensed under the Apache License, Version 2.0,
+// <LICENSE-APACHE or http://apache.org/licenses/LICENSE-2.0>
+// or the MIT license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+
+use byteorder::ByteOrder;
+use std::collections::BTreeMap;
+use std::io::Read;
+use std::marker::PhantomData;
+use std::sync::{Arc,Mutex};
+use std::sync::mpsc::{Receiver,SyncSender,sync_channel};
+use std::thread;
+use std::vec;
+use ::{Error,Header,Record,Result};
+
+/// Number of records framed together and handed to one decoding thread
+const BATCH_SIZE: usize = 1024;
+
+type Item = Result<(Header, Result<Record>)>;
+type Jobs = Arc<Mutex<Receiver<(usize, Vec<Framed>)>>>;
+
+
+/// Parse a trace using a pool of threads to decode record payloads.
+///
+/// Headers are framed sequentially on a dedicated thread, and batches of
+/// payloads are decoded by `threads` worker threads. Records are yielded
+/// in their original order. As with `parse_slice`, an error in framing the
+/// trace is yielded as an `Err` item, after which iteration stops.
+pub fn parse_parallel<E, R>(reader: R, threads: usize) -> ParallelRecords<E>
+    where E: ByteOrder + Send + 'static, R: Read + Send + 'static
+{
+    let threads = threads.max(1);
+
+    let (work_tx, work_rx) = sync_channel(2 * threads);
+    let (result_tx, result_rx) = sync_channel(2 * threads);
+    let work_rx = Arc::new(Mutex::new(work_rx));
+
+    let mut handles = vec![];
+    handles.push(thread::spawn(move || frame::<E, _>(reader, work_tx)));
+
+    for _ in 0..threads {
+        let work = work_rx.clone();
+        let results = result_tx.clone();
+        handles.push(thread::spawn(move || decode::<E>(work, results)));
+    }
+
+    ParallelRecords {
+        results: result_rx,
+        pending: BTreeMap::new(),
+        next_batch: 0,
+        current: vec![].into_iter(),
+        threads: handles,
+        byte_order: PhantomData,
+    }
+}
+
+/// An iterator over records decoded in parallel
+pub struct ParallelRecords<E> {
+    results: Receiver<(usize, Vec<Item>)>,
+
+    /// batches that were decoded before the ones preceding them
+    pending: BTreeMap<usize, Vec<Item>>,
+    next_batch: usize,
+    current: vec::IntoIter<Item>,
+
+    threads: Vec<thread::JoinHandle<()>>,
+    byte_order: PhantomData<E>,
+}
+
+impl<E> Iterator for ParallelRecords<E> {
+    type Item = Item;
+
+    fn next(&mut self) -> Option<Item> {
+        loop {
+            if let Some(item) = self.current.next() {
+                return Some(item);
+            }
+
+            if let Some(batch) = self.pending.remove(&self.next_batch) {
+                self.current = batch.into_iter();
+                self.next_batch += 1;
+                continue;
+            }
+
+            match self.results.recv() {
+                Ok((n, batch)) => { self.pending.insert(n, batch); },
+
+                // All of the threads have finished.
+                Err(_) => {
+                    for t in self.threads.drain(..) {
+                        let _ = t.join();
+                    }
+                    return None;
+                },
+            }
+        }
+    }
+}
+
+
+/// A record that has been framed but not yet decoded
+enum Framed {
+    Record { index: usize, offset: u64, header: Header, data: Vec<u8> },
+    Failed(Error),
+}
+
+fn frame<E, R>(mut reader: R, work: SyncSender<(usize, Vec<Framed>)>)
+    where E: ByteOrder, R: Read
+{
+    let mut batch = Vec::with_capacity(BATCH_SIZE);
+    let mut batches = 0;
+    let mut index = 0;
+    let mut offset = 0;
+
+    loop {
+        match ::read_raw::<E>(&mut reader, index, offset) {
+            Ok(Some((header, data))) => {
+                let length = header.length;
+                batch.push(Framed::Record { index, offset, header, data });
+
+                index += 1;
+                offset += (56 + length) as u64;
+            },
+            Ok(None) => break,
+            Err(e) => {
+                batch.push(Framed::Failed(e));
+                break;
+            },
+        }
+
+        if batch.len() == BATCH_SIZE {
+            let full = ::std::mem::replace(&mut batch,
+                                           Vec::with_capacity(BATCH_SIZE));
+            if work.send((batches, full)).is_err() {
+                return;
+            }
+            batches += 1;
+        }
+    }
+
+    if !batch.is_empty() {
+        let _ = work.send((batches, batch));
+    }
+}
+
+fn decode<E>(work: Jobs, results: SyncSender<(usize, Vec<Item>)>)
+    where E: ByteOrder
+{
+    loop {
+        // Only hold the lock while waiting for work, not while decoding.
+        let next = match work.lock() {
+            Ok(rx) => rx.recv(),
+            Err(_) => return,
+        };
+
+        let (n, batch) = match next {
+            Ok(job) => job,
+            Err(_) => return,
+        };
+
+        let decoded = batch.into_iter().map(|framed| match framed {
+            Framed::Record{index, offset, header, data} => {
+                let record = Record::parse::<E>(&data, &header.record_type)
+                                   .map_err(|e| e.in_record(&header)
+                                                 .at(index, offset));
+                Ok((header, record))
+            },
+            Framed::Failed(e) => Err(e),
+        }).collect();
+
+        if results.send((n, decoded)).is_err() {
+            return;
+        }
+    }
+}
@@ -0,0 +1,393 @@
+// Copyright 2016 Jonathan Anderson <jonathan.anderson@mun.ca>
+//
+// This software was developed by BAE Systems, the University of Cambridge
+// Computer Laboratory, and Memorial University under DARPA/AFRL contract
+// FA8650-15-C-7558 ("CADETS"), as part of the DARPA Transparent Computing
+// (TC) research program.
+//
+// Licensed under the Apache License, Version 2.0,
+// <LICENSE-APACHE or http://apache.org/licenses/LICENSE-2.0>
+// or the MIT license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+
+use byteorder::ByteOrder;
+use std::fmt;
+use super::capfail::CapFail;
+use ::{AbiFlags,Error,IODir,Record,RecordType,Result};
+
+
+/// A record that borrows its payload from the buffer it was parsed from
+///
+/// This is the zero-copy equivalent of `Record`: strings and payloads refer
+/// directly into the trace (e.g., a memory-mapped file), and integer arrays
+/// are decoded on demand. Only capability failures are decoded eagerly.
+#[derive(Clone,Debug)]
+pub enum RecordRef<'a> {
+    /// At least one record was dropped.
+    Drop,
+
+    /// KTR_SYSCALL - system call record
+    SystemCall {
+        number: u16,
+        args: Words<'a, u64>,
+    },
+
+    /// KTR_SYSRET - return from system call record
+    SystemCallReturn {
+        code: u16,
+        eosys: u16,
+        error: u32,
+        retval: u64,
+    },
+
+    /// KTR_NAMEI - namei record
+    Namei(&'a [u8]),
+
+    /// KTR_GENIO - trace generic process I/O
+    GenericIO {
+        fd: i32,
+        rw: IODir,
+        data: &'a [u8],
+    },
+
+    /// KTR_PSIG - trace processed signal
+    Signal {
+        signo: i32,
+        handler: u64,
+        code: i32,
+        mask: Words<'a, u32>,
+    },
+
+    /// KTR_CSW - trace context switches
+    ContextSwitch {
+        out: bool,
+        user: bool,
+        message: &'a [u8],
+    },
+
+    /// KTR_USER - data coming from userland
+    UserData(&'a [u8]),
+
+    /// KTR_STRUCT - misc. structs
+    ///
+    /// The structure's name is NUL-terminated in the trace; the NUL is part
+    /// of neither `name` nor `content`.
+    Struct {
+        name: &'a [u8],
+        content: &'a [u8],
+    },
+
+    /// KTR_SYSCTL - name of a sysctl MIB
+    Sysctl(&'a [u8]),
+
+    /// KTR_PROCCTOR - trace process creation (multiple ABI support)
+    ProcessCreation { flags: AbiFlags },
+
+    /// KTR_PROCDTOR - trace process destruction (multiple ABI support)
+    ProcessDestruction,
+
+    /// KTR_CAPFAIL - trace capability check failure
+    CapabilityFailure(CapFail),
+
+    /// KTR_FAULT - page fault record
+    PageFault {
+        virtual_address: u64,
+        fault_type: u32,
+    },
+
+    /// KTR_FAULTEND - end of page fault record
+    PageFaultEnd {
+        result: u32,
+    },
+}
+
+/// An array of integers in a trace, decoded on demand
+#[derive(Clone,Copy)]
+pub struct Words<'a, T> {
+    data: &'a [u8],
+    read: fn(&[u8]) -> T,
+}
+
+
+impl<'a, T> Words<'a, T> {
+    fn new(data: &'a [u8], read: fn(&[u8]) -> T) -> Words<'a, T> {
+        Words { data, read }
+    }
+
+    fn width() -> usize {
+        ::std::mem::size_of::<T>()
+    }
+
+    pub fn len(&self) -> usize {
+        self.data.len() / Words::<T>::width()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn get(&self, i: usize) -> Option<T> {
+        let width = Words::<T>::width();
+        self.data.get(i * width..(i + 1) * width).map(self.read)
+    }
+
+    pub fn iter(&self) -> WordIter<'a, T> {
+        WordIter {
+            chunks: self.data.chunks_exact(Words::<T>::width()),
+            read: self.read,
+        }
+    }
+
+    pub fn to_vec(&self) -> Vec<T> {
+        self.iter().collect()
+    }
+}
+
+impl<'a, T> fmt::Debug for Words<'a, T>
+    where T: fmt::Debug
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<'a, T> IntoIterator for Words<'a, T> {
+    type Item = T;
+    type IntoIter = WordIter<'a, T>;
+
+    fn into_iter(self) -> WordIter<'a, T> {
+        self.iter()
+    }
+}
+
+/// Iterator over the integers in a `Words`
+pub struct WordIter<'a, T> {
+    chunks: ::std::slice::ChunksExact<'a, u8>,
+    read: fn(&[u8]) -> T,
+}
+
+impl<'a, T> Iterator for WordIter<'a, T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.chunks.next().map(self.read)
+    }
+}
+
+
+impl<'a> RecordRef<'a> {
+    /// Parse a record's payload without copying it.
+    pub fn parse<E>(data: &'a [u8], t: &RecordType) -> Result<RecordRef<'a>>
+        where E : ByteOrder
+    {
+        RecordRef::parse_payload::<E>(data, t).map_err(|e| e.of_type(t))
+    }
+
+    /// Copy everything this record refers to into an owned `Record`.
+    pub fn into_owned(self) -> Record {
+        match self {
+            RecordRef::Drop => Record::Drop,
+            RecordRef::SystemCall{number, args} => {
+                Record::SystemCall { number, args: args.to_vec() }
+            },
+            RecordRef::SystemCallReturn{code, eosys, error, retval} => {
+                Record::SystemCallReturn { code, eosys, error, retval }
+            },
+            RecordRef::Namei(name) => Record::Namei(name.into()),
+            RecordRef::GenericIO{fd, rw, data} => {
+                Record::GenericIO { fd, rw, data: data.to_vec() }
+            },
+            RecordRef::Signal{signo, handler, code, mask} => {
+                Record::Signal { signo, handler, code, mask: mask.to_vec() }
+            },
+            RecordRef::ContextSwitch{out, user, message} => {
+                Record::ContextSwitch { out, user, message: message.into() }
+            },
+            RecordRef::UserData(data) => Record::UserData(data.to_vec()),
+            RecordRef::Struct{name, content} => {
+                Record::Struct {
+                    name: name.into(),
+                    content: content.to_vec(),
+                }
+            },
+            RecordRef::Sysctl(name) => Record::Sysctl(name.into()),
+            RecordRef::ProcessCreation{flags} => Record::ProcessCreation{flags},
+            RecordRef::ProcessDestruction => Record::ProcessDestruction,
+            RecordRef::CapabilityFailure(f) => Record::CapabilityFailure(f),
+            RecordRef::PageFault{virtual_address, fault_type} => {
+                Record::PageFault { virtual_address, fault_type }
+            },
+            RecordRef::PageFaultEnd{result} => Record::PageFaultEnd{result},
+        }
+    }
+
+    fn parse_payload<E>(data: &'a [u8], t: &RecordType) -> Result<RecordRef<'a>>
+        where E : ByteOrder
+    {
+        match *t {
+            RecordType::SystemCall => {
+                if data.len() < 8 {
+                    return Err(Error::bad_length("at least 8 B (2*u16 + padding)",
+                                                 data.len()));
+                }
+
+                let code = E::read_u16(&data[0..2]);
+                let num_args = E::read_u16(&data[2..4]);
+
+                // There is padding before the arguments begin
+                let arg_data = &data[8..];
+
+                if arg_data.len() != 8 * num_args as usize {
+                    return Err(Error::bad_length(
+                            format!["{} B ({} 8B arguments)",
+                                    8 + 8 * num_args as usize, num_args],
+                            data.len()));
+                }
+
+                Ok(RecordRef::SystemCall {
+                    number: code,
+                    args: Words::new(arg_data, E::read_u64),
+                })
+            },
+
+            RecordType::SystemCallReturn => {
+                if data.len() != 16 {
+                    return Err(Error::bad_length("16 B", data.len()));
+                }
+
+                Ok(RecordRef::SystemCallReturn {
+                    code: E::read_u16(&data[0..2]),
+                    eosys: E::read_u16(&data[2..4]),
+                    error: E::read_u32(&data[4..8]),
+                    retval: E::read_u64(&data[8..16]),
+                })
+            },
+
+            RecordType::Namei => Ok(RecordRef::Namei(data)),
+
+            RecordType::GenericIO => {
+                if data.len() < 8 {
+                    return Err(Error::bad_length("at least 8 B (2*int)",
+                                                 data.len()));
+                }
+
+                Ok(RecordRef::GenericIO{
+                    fd: E::read_i32(&data[0..4]),
+                    rw: match E::read_u32(&data[4..8]) {
+                        0 => IODir::Read,
+                        1 => IODir::Write,
+                        x => return Err(Error::unknown_value("uio_rw", x))
+                    },
+                    data: &data[8..],
+                })
+            },
+
+            RecordType::Signal => {
+                if data.len() < 20 {
+                    return Err(Error::bad_length(
+                        "at least 20 B (2*int + sig_t + sigset_t + padding)",
+                        data.len()
+                    ));
+                }
+
+                Ok(RecordRef::Signal {
+                    signo: E::read_i32(&data[0..4]),
+                    handler: E::read_u64(&data[8..16]),
+                    code: E::read_i32(&data[16..20]),
+                    mask: Words::new(&data[20..], E::read_u32),
+                })
+            },
+
+            RecordType::ContextSwitch => {
+                if data.len() < 8 {
+                    return Err(Error::bad_length("at least 8 B (2*int)",
+                                                 data.len()));
+                }
+
+                // The wait message is a NUL-padded char[WMESGLEN + 1].
+                let wmesg = &data[8..];
+                let end = wmesg.iter().position(|x| *x == 0).unwrap_or(wmesg.len());
+
+                Ok(RecordRef::ContextSwitch {
+                    out: (E::read_u32(&data[0..4]) != 0),
+                    user: (E::read_u32(&data[4..8]) != 0),
+                    message: &wmesg[..end],
+                })
+            },
+
+            RecordType::UserData => {
+                Ok(RecordRef::UserData(data))
+            },
+
+            RecordType::Struct => {
+                let nul = data.iter()
+                              .position(|x| *x == 0)
+                              .ok_or(Error::msg("no NULL byte in struct name"))?;
+
+                Ok(RecordRef::Struct {
+                    name: &data[..nul],
+                    content: &data[nul + 1..],
+                })
+            },
+
+            RecordType::Sysctl => {
+                if data.is_empty() {
+                    return Err(Error::bad_length("at least 1 B (sysctl MIB)",
+                                                 data.len()));
+                }
+
+                // The kernel includes the name's NUL terminator.
+                let name = data.strip_suffix(&[0]).unwrap_or(data);
+
+                Ok(RecordRef::Sysctl(name))
+            },
+
+            RecordType::ProcessCreation => {
+                if data.len() != 4 {
+                    Err(Error::bad_length("4 B (u32)", data.len()))
+                } else {
+                    Ok(RecordRef::ProcessCreation {
+                        flags: AbiFlags(E::read_u32(data))
+                    })
+                }
+            },
+
+            RecordType::ProcessDestruction => {
+                if !data.is_empty() {
+                    return Err(Error::bad_length("0 B", data.len()));
+                }
+
+                Ok(RecordRef::ProcessDestruction)
+            },
+
+            RecordType::CapabilityFailure => {
+                let failure = CapFail::parse::<E>(data);
+                failure.map(RecordRef::CapabilityFailure)
+            },
+
+            RecordType::PageFault => {
+                if data.len() < 12 {
+                    return Err(Error::bad_length(
+                        "at least 12 B (vm_offset_t + int)", data.len()));
+                }
+
+                Ok(RecordRef::PageFault {
+                    virtual_address: E::read_u64(&data[0..8]),
+                    fault_type: E::read_u32(&data[8..12]),
+                })
+            },
+
+            RecordType::PageFaultEnd => {
+                if data.len() != 4 {
+                    return Err(Error::bad_length("4 B (int)", data.len()));
+                }
+
+                Ok(RecordRef::PageFaultEnd {
+                    result: E::read_u32(data),
+                })
+            },
+        }
+    }
+}
@@ -47,9 +47,9 @@ impl CapFail {
         where E : ByteOrder
     {
         if data.len() < 20 {
-            return Err(Error::bad_value(
-                "enum ktr_cap_fail_type + two cap_rights_t",
-                format!["{} B: {:?}", data.len(), data]
+            return Err(Error::bad_length(
+                "at least 20 B (enum ktr_cap_fail_type + two cap_rights_t)",
+                data.len()
             ));
         }
 
@@ -58,9 +58,9 @@ impl CapFail {
                 // CAPFAIL_NOTCAPABLE
                 let cap_data = &data[8..];  // pad for alignment
                 if !(cap_data.len() % 16 == 0) {
-                    return Err(Error::bad_value(
-                        "two cap_rights_t",
-                        format!["{} B: {:?}", cap_data.len(), cap_data]
+                    return Err(Error::bad_length(
+                        "8 B + a multiple of 16 B (two cap_rights_t)",
+                        data.len()
                     ));
                 }
 
@@ -88,8 +88,7 @@ impl CapFail {
             1 => Ok(CapFail::Increase),  // CAPFAIL_INCREASE
             2 => Ok(CapFail::Syscall),   // CAPFAIL_SYSCALL
             3 => Ok(CapFail::Lookup),    // CAPFAIL_LOOKUP
-            x => Err(Error::bad_value(
-                "ktr_cap_fail_type (integer 0-3)", x.to_string()))
+            x => Err(Error::unknown_value("ktr_cap_fail_type", x))
         }
     }
 }
@@ -114,8 +113,8 @@ impl CapabilityRights {
         where E : ByteOrder
     {
         if data.len() % 8 != 0 {
-            return Err(Error::bad_value(
-                "cap_rights_t", format!["{}B: {:?}", data.len(), data]));
+            return Err(Error::bad_length(
+                "a multiple of 8 B (cap_rights_t)", data.len()));
         }
 
         Ok(CapabilityRights {
@@ -0,0 +1,131 @@
+// Copyright 2016 Jonathan Anderson <jonathan.anderson@mun.ca>
+//
+// This software was developed by BAE Systems, the University of Cambridge
+// Computer Laboratory, and Memorial University under DARPA/AFRL contract
+// FA8650-15-C-7558 ("CADETS"), as part of the DARPA Transparent Computing
+// (TC) research program.
+//
+// Licensed under the Apache License, Version 2.0,
+// <LICENSE-APACHE or http://apache.org/licenses/LICENSE-2.0>
+// or the MIT license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use ::{Error,Result};
+
+
+/// The symbolic name of a FreeBSD `errno` value (or kernel pseudo-error)
+pub fn name(errno: i32) -> Result<String> {
+    match errno {
+        -4 => Ok("EDIRIOCTL"),
+        -3 => Ok("ENOIOCTL"),
+        -2 => Ok("EJUSTRETURN"),
+        -1 => Ok("ERESTART"),
+        1 => Ok("EPERM"),
+        2 => Ok("ENOENT"),
+        3 => Ok("ESRCH"),
+        4 => Ok("EINTR"),
+        5 => Ok("EIO"),
+        6 => Ok("ENXIO"),
+        7 => Ok("E2BIG"),
+        8 => Ok("ENOEXEC"),
+        9 => Ok("EBADF"),
+        10 => Ok("ECHILD"),
+        11 => Ok("EDEADLK"),
+        12 => Ok("ENOMEM"),
+        13 => Ok("EACCES"),
+        14 => Ok("EFAULT"),
+        15 => Ok("ENOTBLK"),
+        16 => Ok("EBUSY"),
+        17 => Ok("EEXIST"),
+        18 => Ok("EXDEV"),
+        19 => Ok("ENODEV"),
+        20 => Ok("ENOTDIR"),
+        21 => Ok("EISDIR"),
+        22 => Ok("EINVAL"),
+        23 => Ok("ENFILE"),
+        24 => Ok("EMFILE"),
+        25 => Ok("ENOTTY"),
+        26 => Ok("ETXTBSY"),
+        27 => Ok("EFBIG"),
+        28 => Ok("ENOSPC"),
+        29 => Ok("ESPIPE"),
+        30 => Ok("EROFS"),
+        31 => Ok("EMLINK"),
+        32 => Ok("EPIPE"),
+        33 => Ok("EDOM"),
+        34 => Ok("ERANGE"),
+        35 => Ok("EAGAIN"),
+        36 => Ok("EINPROGRESS"),
+        37 => Ok("EALREADY"),
+        38 => Ok("ENOTSOCK"),
+        39 => Ok("EDESTADDRREQ"),
+        40 => Ok("EMSGSIZE"),
+        41 => Ok("EPROTOTYPE"),
+        42 => Ok("ENOPROTOOPT"),
+        43 => Ok("EPROTONOSUPPORT"),
+        44 => Ok("ESOCKTNOSUPPORT"),
+        45 => Ok("EOPNOTSUPP"),
+        46 => Ok("EPFNOSUPPORT"),
+        47 => Ok("EAFNOSUPPORT"),
+        48 => Ok("EADDRINUSE"),
+        49 => Ok("EADDRNOTAVAIL"),
+        50 => Ok("ENETDOWN"),
+        51 => Ok("ENETUNREACH"),
+        52 => Ok("ENETRESET"),
+        53 => Ok("ECONNABORTED"),
+        54 => Ok("ECONNRESET"),
+        55 => Ok("ENOBUFS"),
+        56 => Ok("EISCONN"),
+        57 => Ok("ENOTCONN"),
+        58 => Ok("ESHUTDOWN"),
+        59 => Ok("ETOOMANYREFS"),
+        60 => Ok("ETIMEDOUT"),
+        61 => Ok("ECONNREFUSED"),
+        62 => Ok("ELOOP"),
+        63 => Ok("ENAMETOOLONG"),
+        64 => Ok("EHOSTDOWN"),
+        65 => Ok("EHOSTUNREACH"),
+        66 => Ok("ENOTEMPTY"),
+        67 => Ok("EPROCLIM"),
+        68 => Ok("EUSERS"),
+        69 => Ok("EDQUOT"),
+        70 => Ok("ESTALE"),
+        71 => Ok("EREMOTE"),
+        72 => Ok("EBADRPC"),
+        73 => Ok("ERPCMISMATCH"),
+        74 => Ok("EPROGUNAVAIL"),
+        75 => Ok("EPROGMISMATCH"),
+        76 => Ok("EPROCUNAVAIL"),
+        77 => Ok("ENOLCK"),
+        78 => Ok("ENOSYS"),
+        79 => Ok("EFTYPE"),
+        80 => Ok("EAUTH"),
+        81 => Ok("ENEEDAUTH"),
+        82 => Ok("EIDRM"),
+        83 => Ok("ENOMSG"),
+        84 => Ok("EOVERFLOW"),
+        85 => Ok("ECANCELED"),
+        86 => Ok("EILSEQ"),
+        87 => Ok("ENOATTR"),
+        88 => Ok("EDOOFUS"),
+        89 => Ok("EBADMSG"),
+        90 => Ok("EMULTIHOP"),
+        91 => Ok("ENOLINK"),
+        92 => Ok("EPROTO"),
+        93 => Ok("ENOTCAPABLE"),
+        94 => Ok("ECAPMODE"),
+        95 => Ok("ENOTRECOVERABLE"),
+        96 => Ok("EOWNERDEAD"),
+        x => Err(Error::unknown_value("errno", x)),
+    }
+    .map(str::to_string)
+}
+
+/// The FreeBSD `errno` value with a given symbolic name.
+pub fn number(name: &str) -> Option<i32> {
+    (-4..=96).find(|&e| match self::name(e) {
+        Ok(ref n) => n == name,
+        Err(_) => false,
+    })
+}
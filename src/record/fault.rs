@@ -0,0 +1,69 @@
+// Copyright 2016 Jonathan Anderson <jonathan.anderson@mun.ca>
+//
+// This software was developed by BAE Systems, the University of Cambridge
+// Computer Laboratory, and Memorial University under DARPA/AFRL contract
+// FA8650-15-C-7558 ("CADETS"), as part of the DARPA Transparent Computing
+// (TC) research program.
+//
+// Licensed under the Apache License, Version 2.0,
+// <LICENSE-APACHE or http://apache.org/licenses/LICENSE-2.0>
+// or the MIT license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use ::{Error,Result};
+
+
+pub const VM_PROT_READ: u32 = 0x01;
+pub const VM_PROT_WRITE: u32 = 0x02;
+pub const VM_PROT_EXECUTE: u32 = 0x04;
+pub const VM_PROT_COPY: u32 = 0x08;
+
+pub const KERN_SUCCESS: u32 = 0;
+
+
+/// The `VM_PROT_*` flags of a `KTR_FAULT` fault type, as kdump shows them
+/// (e.g., `VM_PROT_READ|VM_PROT_WRITE`).
+pub fn protection(fault_type: u32) -> String {
+    let names = [
+        (VM_PROT_READ, "VM_PROT_READ"),
+        (VM_PROT_WRITE, "VM_PROT_WRITE"),
+        (VM_PROT_EXECUTE, "VM_PROT_EXECUTE"),
+        (VM_PROT_COPY, "VM_PROT_COPY"),
+    ];
+
+    if fault_type == 0 {
+        return "VM_PROT_NONE".to_string();
+    }
+
+    let mut flags: Vec<String> = names.iter()
+                                      .filter(|&&(bit, _)| fault_type & bit != 0)
+                                      .map(|&(_, name)| name.to_string())
+                                      .collect();
+
+    let unknown = fault_type & !names.iter().fold(0, |all, &(bit, _)| all | bit);
+    if unknown != 0 {
+        flags.push(format!["0x{:x}", unknown]);
+    }
+
+    flags.join("|")
+}
+
+/// The symbolic name of a `KTR_FAULTEND` result (a `KERN_*` value).
+pub fn result(result: u32) -> Result<String> {
+    match result {
+        0 => Ok("KERN_SUCCESS"),
+        1 => Ok("KERN_INVALID_ADDRESS"),
+        2 => Ok("KERN_PROTECTION_FAILURE"),
+        3 => Ok("KERN_NO_SPACE"),
+        4 => Ok("KERN_INVALID_ARGUMENT"),
+        5 => Ok("KERN_FAILURE"),
+        6 => Ok("KERN_RESOURCE_SHORTAGE"),
+        7 => Ok("KERN_NOT_RECEIVER"),
+        8 => Ok("KERN_NO_ACCESS"),
+        9 => Ok("KERN_OUT_OF_BOUNDS"),
+        10 => Ok("KERN_RESTART"),
+        x => Err(Error::unknown_value("page fault result", x)),
+    }
+    .map(str::to_string)
+}
@@ -0,0 +1,526 @@
+// Copyright 2016 Jonathan Anderson <jonathan.anderson@mun.ca>
+//
+// This software was developed by BAE Systems, the University of Cambridge
+// Computer Laboratory, and Memorial University under DARPA/AFRL contract
+// FA8650-15-C-7558 ("CADETS"), as part of the DARPA Transparent Computing
+// (TC) research program.
+//
+// Licensed under the Apache License, Version 2.0,
+// <LICENSE-APACHE or http://apache.org/licenses/LICENSE-2.0>
+// or the MIT license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+
+use ::{Error,Result};
+
+
+/// The name of a Linux x86-64 system call.
+pub fn syscall_name(id: usize) -> Result<String> {
+    match id {
+        0 => Ok("read"),
+        1 => Ok("write"),
+        2 => Ok("open"),
+        3 => Ok("close"),
+        4 => Ok("stat"),
+        5 => Ok("fstat"),
+        6 => Ok("lstat"),
+        7 => Ok("poll"),
+        8 => Ok("lseek"),
+        9 => Ok("mmap"),
+        10 => Ok("mprotect"),
+        11 => Ok("munmap"),
+        12 => Ok("brk"),
+        13 => Ok("rt_sigaction"),
+        14 => Ok("rt_sigprocmask"),
+        15 => Ok("rt_sigreturn"),
+        16 => Ok("ioctl"),
+        17 => Ok("pread64"),
+        18 => Ok("pwrite64"),
+        19 => Ok("readv"),
+        20 => Ok("writev"),
+        21 => Ok("access"),
+        22 => Ok("pipe"),
+        23 => Ok("select"),
+        24 => Ok("sched_yield"),
+        25 => Ok("mremap"),
+        26 => Ok("msync"),
+        27 => Ok("mincore"),
+        28 => Ok("madvise"),
+        29 => Ok("shmget"),
+        30 => Ok("shmat"),
+        31 => Ok("shmctl"),
+        32 => Ok("dup"),
+        33 => Ok("dup2"),
+        34 => Ok("pause"),
+        35 => Ok("nanosleep"),
+        36 => Ok("getitimer"),
+        37 => Ok("alarm"),
+        38 => Ok("setitimer"),
+        39 => Ok("getpid"),
+        40 => Ok("sendfile"),
+        41 => Ok("socket"),
+        42 => Ok("connect"),
+        43 => Ok("accept"),
+        44 => Ok("sendto"),
+        45 => Ok("recvfrom"),
+        46 => Ok("sendmsg"),
+        47 => Ok("recvmsg"),
+        48 => Ok("shutdown"),
+        49 => Ok("bind"),
+        50 => Ok("listen"),
+        51 => Ok("getsockname"),
+        52 => Ok("getpeername"),
+        53 => Ok("socketpair"),
+        54 => Ok("setsockopt"),
+        55 => Ok("getsockopt"),
+        56 => Ok("clone"),
+        57 => Ok("fork"),
+        58 => Ok("vfork"),
+        59 => Ok("execve"),
+        60 => Ok("exit"),
+        61 => Ok("wait4"),
+        62 => Ok("kill"),
+        63 => Ok("uname"),
+        64 => Ok("semget"),
+        65 => Ok("semop"),
+        66 => Ok("semctl"),
+        67 => Ok("shmdt"),
+        68 => Ok("msgget"),
+        69 => Ok("msgsnd"),
+        70 => Ok("msgrcv"),
+        71 => Ok("msgctl"),
+        72 => Ok("fcntl"),
+        73 => Ok("flock"),
+        74 => Ok("fsync"),
+        75 => Ok("fdatasync"),
+        76 => Ok("truncate"),
+        77 => Ok("ftruncate"),
+        78 => Ok("getdents"),
+        79 => Ok("getcwd"),
+        80 => Ok("chdir"),
+        81 => Ok("fchdir"),
+        82 => Ok("rename"),
+        83 => Ok("mkdir"),
+        84 => Ok("rmdir"),
+        85 => Ok("creat"),
+        86 => Ok("link"),
+        87 => Ok("unlink"),
+        88 => Ok("symlink"),
+        89 => Ok("readlink"),
+        90 => Ok("chmod"),
+        91 => Ok("fchmod"),
+        92 => Ok("chown"),
+        93 => Ok("fchown"),
+        94 => Ok("lchown"),
+        95 => Ok("umask"),
+        96 => Ok("gettimeofday"),
+        97 => Ok("getrlimit"),
+        98 => Ok("getrusage"),
+        99 => Ok("sysinfo"),
+        100 => Ok("times"),
+        101 => Ok("ptrace"),
+        102 => Ok("getuid"),
+        103 => Ok("syslog"),
+        104 => Ok("getgid"),
+        105 => Ok("setuid"),
+        106 => Ok("setgid"),
+        107 => Ok("geteuid"),
+        108 => Ok("getegid"),
+        109 => Ok("setpgid"),
+        110 => Ok("getppid"),
+        111 => Ok("getpgrp"),
+        112 => Ok("setsid"),
+        113 => Ok("setreuid"),
+        114 => Ok("setregid"),
+        115 => Ok("getgroups"),
+        116 => Ok("setgroups"),
+        117 => Ok("setresuid"),
+        118 => Ok("getresuid"),
+        119 => Ok("setresgid"),
+        120 => Ok("getresgid"),
+        121 => Ok("getpgid"),
+        122 => Ok("setfsuid"),
+        123 => Ok("setfsgid"),
+        124 => Ok("getsid"),
+        125 => Ok("capget"),
+        126 => Ok("capset"),
+        127 => Ok("rt_sigpending"),
+        128 => Ok("rt_sigtimedwait"),
+        129 => Ok("rt_sigqueueinfo"),
+        130 => Ok("rt_sigsuspend"),
+        131 => Ok("sigaltstack"),
+        132 => Ok("utime"),
+        133 => Ok("mknod"),
+        134 => Ok("uselib"),
+        135 => Ok("personality"),
+        136 => Ok("ustat"),
+        137 => Ok("statfs"),
+        138 => Ok("fstatfs"),
+        139 => Ok("sysfs"),
+        140 => Ok("getpriority"),
+        141 => Ok("setpriority"),
+        142 => Ok("sched_setparam"),
+        143 => Ok("sched_getparam"),
+        144 => Ok("sched_setscheduler"),
+        145 => Ok("sched_getscheduler"),
+        146 => Ok("sched_get_priority_max"),
+        147 => Ok("sched_get_priority_min"),
+        148 => Ok("sched_rr_get_interval"),
+        149 => Ok("mlock"),
+        150 => Ok("munlock"),
+        151 => Ok("mlockall"),
+        152 => Ok("munlockall"),
+        153 => Ok("vhangup"),
+        154 => Ok("modify_ldt"),
+        155 => Ok("pivot_root"),
+        156 => Ok("_sysctl"),
+        157 => Ok("prctl"),
+        158 => Ok("arch_prctl"),
+        159 => Ok("adjtimex"),
+        160 => Ok("setrlimit"),
+        161 => Ok("chroot"),
+        162 => Ok("sync"),
+        163 => Ok("acct"),
+        164 => Ok("settimeofday"),
+        165 => Ok("mount"),
+        166 => Ok("umount2"),
+        167 => Ok("swapon"),
+        168 => Ok("swapoff"),
+        169 => Ok("reboot"),
+        170 => Ok("sethostname"),
+        171 => Ok("setdomainname"),
+        172 => Ok("iopl"),
+        173 => Ok("ioperm"),
+        174 => Ok("create_module"),
+        175 => Ok("init_module"),
+        176 => Ok("delete_module"),
+        177 => Ok("get_kernel_syms"),
+        178 => Ok("query_module"),
+        179 => Ok("quotactl"),
+        180 => Ok("nfsservctl"),
+        181 => Ok("getpmsg"),
+        182 => Ok("putpmsg"),
+        183 => Ok("afs_syscall"),
+        184 => Ok("tuxcall"),
+        185 => Ok("security"),
+        186 => Ok("gettid"),
+        187 => Ok("readahead"),
+        188 => Ok("setxattr"),
+        189 => Ok("lsetxattr"),
+        190 => Ok("fsetxattr"),
+        191 => Ok("getxattr"),
+        192 => Ok("lgetxattr"),
+        193 => Ok("fgetxattr"),
+        194 => Ok("listxattr"),
+        195 => Ok("llistxattr"),
+        196 => Ok("flistxattr"),
+        197 => Ok("removexattr"),
+        198 => Ok("lremovexattr"),
+        199 => Ok("fremovexattr"),
+        200 => Ok("tkill"),
+        201 => Ok("time"),
+        202 => Ok("futex"),
+        203 => Ok("sched_setaffinity"),
+        204 => Ok("sched_getaffinity"),
+        205 => Ok("set_thread_area"),
+        206 => Ok("io_setup"),
+        207 => Ok("io_destroy"),
+        208 => Ok("io_getevents"),
+        209 => Ok("io_submit"),
+        210 => Ok("io_cancel"),
+        211 => Ok("get_thread_area"),
+        212 => Ok("lookup_dcookie"),
+        213 => Ok("epoll_create"),
+        214 => Ok("epoll_ctl_old"),
+        215 => Ok("epoll_wait_old"),
+        216 => Ok("remap_file_pages"),
+        217 => Ok("getdents64"),
+        218 => Ok("set_tid_address"),
+        219 => Ok("restart_syscall"),
+        220 => Ok("semtimedop"),
+        221 => Ok("fadvise64"),
+        222 => Ok("timer_create"),
+        223 => Ok("timer_settime"),
+        224 => Ok("timer_gettime"),
+        225 => Ok("timer_getoverrun"),
+        226 => Ok("timer_delete"),
+        227 => Ok("clock_settime"),
+        228 => Ok("clock_gettime"),
+        229 => Ok("clock_getres"),
+        230 => Ok("clock_nanosleep"),
+        231 => Ok("exit_group"),
+        232 => Ok("epoll_wait"),
+        233 => Ok("epoll_ctl"),
+        234 => Ok("tgkill"),
+        235 => Ok("utimes"),
+        236 => Ok("vserver"),
+        237 => Ok("mbind"),
+        238 => Ok("set_mempolicy"),
+        239 => Ok("get_mempolicy"),
+        240 => Ok("mq_open"),
+        241 => Ok("mq_unlink"),
+        242 => Ok("mq_timedsend"),
+        243 => Ok("mq_timedreceive"),
+        244 => Ok("mq_notify"),
+        245 => Ok("mq_getsetattr"),
+        246 => Ok("kexec_load"),
+        247 => Ok("waitid"),
+        248 => Ok("add_key"),
+        249 => Ok("request_key"),
+        250 => Ok("keyctl"),
+        251 => Ok("ioprio_set"),
+        252 => Ok("ioprio_get"),
+        253 => Ok("inotify_init"),
+        254 => Ok("inotify_add_watch"),
+        255 => Ok("inotify_rm_watch"),
+        256 => Ok("migrate_pages"),
+        257 => Ok("openat"),
+        258 => Ok("mkdirat"),
+        259 => Ok("mknodat"),
+        260 => Ok("fchownat"),
+        261 => Ok("futimesat"),
+        262 => Ok("newfstatat"),
+        263 => Ok("unlinkat"),
+        264 => Ok("renameat"),
+        265 => Ok("linkat"),
+        266 => Ok("symlinkat"),
+        267 => Ok("readlinkat"),
+        268 => Ok("fchmodat"),
+        269 => Ok("faccessat"),
+        270 => Ok("pselect6"),
+        271 => Ok("ppoll"),
+        272 => Ok("unshare"),
+        273 => Ok("set_robust_list"),
+        274 => Ok("get_robust_list"),
+        275 => Ok("splice"),
+        276 => Ok("tee"),
+        277 => Ok("sync_file_range"),
+        278 => Ok("vmsplice"),
+        279 => Ok("move_pages"),
+        280 => Ok("utimensat"),
+        281 => Ok("epoll_pwait"),
+        282 => Ok("signalfd"),
+        283 => Ok("timerfd_create"),
+        284 => Ok("eventfd"),
+        285 => Ok("fallocate"),
+        286 => Ok("timerfd_settime"),
+        287 => Ok("timerfd_gettime"),
+        288 => Ok("accept4"),
+        289 => Ok("signalfd4"),
+        290 => Ok("eventfd2"),
+        291 => Ok("epoll_create1"),
+        292 => Ok("dup3"),
+        293 => Ok("pipe2"),
+        294 => Ok("inotify_init1"),
+        295 => Ok("preadv"),
+        296 => Ok("pwritev"),
+        297 => Ok("rt_tgsigqueueinfo"),
+        298 => Ok("perf_event_open"),
+        299 => Ok("recvmmsg"),
+        300 => Ok("fanotify_init"),
+        301 => Ok("fanotify_mark"),
+        302 => Ok("prlimit64"),
+        303 => Ok("name_to_handle_at"),
+        304 => Ok("open_by_handle_at"),
+        305 => Ok("clock_adjtime"),
+        306 => Ok("syncfs"),
+        307 => Ok("sendmmsg"),
+        308 => Ok("setns"),
+        309 => Ok("getcpu"),
+        310 => Ok("process_vm_readv"),
+        311 => Ok("process_vm_writev"),
+        312 => Ok("kcmp"),
+        313 => Ok("finit_module"),
+        314 => Ok("sched_setattr"),
+        315 => Ok("sched_getattr"),
+        316 => Ok("renameat2"),
+        317 => Ok("seccomp"),
+        318 => Ok("getrandom"),
+        319 => Ok("memfd_create"),
+        320 => Ok("kexec_file_load"),
+        321 => Ok("bpf"),
+        322 => Ok("execveat"),
+        323 => Ok("userfaultfd"),
+        324 => Ok("membarrier"),
+        325 => Ok("mlock2"),
+        326 => Ok("copy_file_range"),
+        327 => Ok("preadv2"),
+        328 => Ok("pwritev2"),
+        329 => Ok("pkey_mprotect"),
+        330 => Ok("pkey_alloc"),
+        331 => Ok("pkey_free"),
+        332 => Ok("statx"),
+        333 => Ok("io_pgetevents"),
+        334 => Ok("rseq"),
+        424 => Ok("pidfd_send_signal"),
+        425 => Ok("io_uring_setup"),
+        426 => Ok("io_uring_enter"),
+        427 => Ok("io_uring_register"),
+        428 => Ok("open_tree"),
+        429 => Ok("move_mount"),
+        430 => Ok("fsopen"),
+        431 => Ok("fsconfig"),
+        432 => Ok("fsmount"),
+        433 => Ok("fspick"),
+        434 => Ok("pidfd_open"),
+        435 => Ok("clone3"),
+        436 => Ok("close_range"),
+        437 => Ok("openat2"),
+        438 => Ok("pidfd_getfd"),
+        439 => Ok("faccessat2"),
+        440 => Ok("process_madvise"),
+        441 => Ok("epoll_pwait2"),
+        442 => Ok("mount_setattr"),
+        443 => Ok("quotactl_fd"),
+        444 => Ok("landlock_create_ruleset"),
+        445 => Ok("landlock_add_rule"),
+        446 => Ok("landlock_restrict_self"),
+        447 => Ok("memfd_secret"),
+        448 => Ok("process_mrelease"),
+        449 => Ok("futex_waitv"),
+        450 => Ok("set_mempolicy_home_node"),
+        451 => Ok("cachestat"),
+        452 => Ok("fchmodat2"),
+        x => Err(Error::unknown_value("Linux system call number", x as i64)),
+    }
+    .map(str::to_string)
+}
+
+/// The name of a Linux i386 system call.
+///
+/// Only calls that are commonly seen in traces of 32-bit programs are
+/// listed: others are reported by number.
+pub fn syscall_name_i386(id: usize) -> Result<String> {
+    match id {
+        1 => Ok("exit"),
+        2 => Ok("fork"),
+        3 => Ok("read"),
+        4 => Ok("write"),
+        5 => Ok("open"),
+        6 => Ok("close"),
+        7 => Ok("waitpid"),
+        8 => Ok("creat"),
+        9 => Ok("link"),
+        10 => Ok("unlink"),
+        11 => Ok("execve"),
+        12 => Ok("chdir"),
+        13 => Ok("time"),
+        14 => Ok("mknod"),
+        15 => Ok("chmod"),
+        16 => Ok("lchown"),
+        19 => Ok("lseek"),
+        20 => Ok("getpid"),
+        21 => Ok("mount"),
+        22 => Ok("umount"),
+        23 => Ok("setuid"),
+        24 => Ok("getuid"),
+        26 => Ok("ptrace"),
+        27 => Ok("alarm"),
+        29 => Ok("pause"),
+        30 => Ok("utime"),
+        33 => Ok("access"),
+        34 => Ok("nice"),
+        36 => Ok("sync"),
+        37 => Ok("kill"),
+        38 => Ok("rename"),
+        39 => Ok("mkdir"),
+        40 => Ok("rmdir"),
+        41 => Ok("dup"),
+        42 => Ok("pipe"),
+        43 => Ok("times"),
+        45 => Ok("brk"),
+        46 => Ok("setgid"),
+        47 => Ok("getgid"),
+        48 => Ok("signal"),
+        49 => Ok("geteuid"),
+        50 => Ok("getegid"),
+        51 => Ok("acct"),
+        52 => Ok("umount2"),
+        54 => Ok("ioctl"),
+        55 => Ok("fcntl"),
+        57 => Ok("setpgid"),
+        60 => Ok("umask"),
+        61 => Ok("chroot"),
+        63 => Ok("dup2"),
+        64 => Ok("getppid"),
+        65 => Ok("getpgrp"),
+        66 => Ok("setsid"),
+        67 => Ok("sigaction"),
+        85 => Ok("readlink"),
+        90 => Ok("mmap"),
+        91 => Ok("munmap"),
+        92 => Ok("truncate"),
+        93 => Ok("ftruncate"),
+        94 => Ok("fchmod"),
+        95 => Ok("fchown"),
+        96 => Ok("getpriority"),
+        97 => Ok("setpriority"),
+        99 => Ok("statfs"),
+        100 => Ok("fstatfs"),
+        102 => Ok("socketcall"),
+        103 => Ok("syslog"),
+        104 => Ok("setitimer"),
+        105 => Ok("getitimer"),
+        106 => Ok("stat"),
+        107 => Ok("lstat"),
+        108 => Ok("fstat"),
+        114 => Ok("wait4"),
+        116 => Ok("sysinfo"),
+        117 => Ok("ipc"),
+        118 => Ok("fsync"),
+        119 => Ok("sigreturn"),
+        120 => Ok("clone"),
+        122 => Ok("uname"),
+        125 => Ok("mprotect"),
+        140 => Ok("_llseek"),
+        141 => Ok("getdents"),
+        142 => Ok("_newselect"),
+        143 => Ok("flock"),
+        144 => Ok("msync"),
+        145 => Ok("readv"),
+        146 => Ok("writev"),
+        162 => Ok("nanosleep"),
+        168 => Ok("poll"),
+        172 => Ok("prctl"),
+        173 => Ok("rt_sigreturn"),
+        174 => Ok("rt_sigaction"),
+        175 => Ok("rt_sigprocmask"),
+        180 => Ok("pread64"),
+        181 => Ok("pwrite64"),
+        183 => Ok("getcwd"),
+        190 => Ok("vfork"),
+        191 => Ok("ugetrlimit"),
+        192 => Ok("mmap2"),
+        195 => Ok("stat64"),
+        196 => Ok("lstat64"),
+        197 => Ok("fstat64"),
+        199 => Ok("getuid32"),
+        200 => Ok("getgid32"),
+        201 => Ok("geteuid32"),
+        202 => Ok("getegid32"),
+        220 => Ok("getdents64"),
+        221 => Ok("fcntl64"),
+        224 => Ok("gettid"),
+        240 => Ok("futex"),
+        243 => Ok("set_thread_area"),
+        252 => Ok("exit_group"),
+        258 => Ok("set_tid_address"),
+        265 => Ok("clock_gettime"),
+        270 => Ok("tgkill"),
+        295 => Ok("openat"),
+        300 => Ok("fstatat64"),
+        301 => Ok("unlinkat"),
+        311 => Ok("set_robust_list"),
+        355 => Ok("getrandom"),
+        359 => Ok("socket"),
+        361 => Ok("bind"),
+        362 => Ok("connect"),
+        363 => Ok("listen"),
+        364 => Ok("accept4"),
+        383 => Ok("statx"),
+        384 => Ok("arch_prctl"),
+        x => Err(Error::unknown_value("Linux system call number", x as i64)),
+    }
+    .map(str::to_string)
+}
@@ -0,0 +1,65 @@
+// Copyright 2016 Jonathan Anderson <jonathan.anderson@mun.ca>
+//
+// This software was developed by BAE Systems, the University of Cambridge
+// Computer Laboratory, and Memorial University under DARPA/AFRL contract
+// FA8650-15-C-7558 ("CADETS"), as part of the DARPA Transparent Computing
+// (TC) research program.
+//
+// Licensed under the Apache License, Version 2.0,
+// <LICENSE-APACHE or http://apache.org/licenses/LICENSE-2.0>
+// or the MIT license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+
+use ::{Error,Result};
+
+
+/// The symbolic name of a FreeBSD signal number
+pub fn name(signo: i32) -> Result<String> {
+    match signo {
+        1 => Ok("SIGHUP"),
+        2 => Ok("SIGINT"),
+        3 => Ok("SIGQUIT"),
+        4 => Ok("SIGILL"),
+        5 => Ok("SIGTRAP"),
+        6 => Ok("SIGABRT"),
+        7 => Ok("SIGEMT"),
+        8 => Ok("SIGFPE"),
+        9 => Ok("SIGKILL"),
+        10 => Ok("SIGBUS"),
+        11 => Ok("SIGSEGV"),
+        12 => Ok("SIGSYS"),
+        13 => Ok("SIGPIPE"),
+        14 => Ok("SIGALRM"),
+        15 => Ok("SIGTERM"),
+        16 => Ok("SIGURG"),
+        17 => Ok("SIGSTOP"),
+        18 => Ok("SIGTSTP"),
+        19 => Ok("SIGCONT"),
+        20 => Ok("SIGCHLD"),
+        21 => Ok("SIGTTIN"),
+        22 => Ok("SIGTTOU"),
+        23 => Ok("SIGIO"),
+        24 => Ok("SIGXCPU"),
+        25 => Ok("SIGXFSZ"),
+        26 => Ok("SIGVTALRM"),
+        27 => Ok("SIGPROF"),
+        28 => Ok("SIGWINCH"),
+        29 => Ok("SIGINFO"),
+        30 => Ok("SIGUSR1"),
+        31 => Ok("SIGUSR2"),
+        32 => Ok("SIGTHR"),
+        33 => Ok("SIGLIBRT"),
+        x => Err(Error::unknown_value("signal number", x)),
+    }
+    .map(str::to_string)
+}
+
+/// The FreeBSD signal number with a given symbolic name.
+pub fn number(name: &str) -> Option<i32> {
+    (1..=33).find(|&s| match self::name(s) {
+        Ok(ref n) => n == name,
+        Err(_) => false,
+    })
+}
@@ -0,0 +1,112 @@
+// Copyright 2016 Jonathan Anderson <jonathan.anderson@mun.ca>
+//
+// This software was developed by BAE Systems, the University of Cambridge
+// Computer Laboratory, and Memorial University under DARPA/AFRL contract
+// FA8650-15-C-7558 ("CADETS"), as part of the DARPA Transparent Computing
+// (TC) research program.
+//
+// Licensed under the Apache License, Version 2.0,
+// <LICENSE-APACHE or http://apache.org/licenses/LICENSE-2.0>
+// or the MIT license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use byteorder::{BigEndian,ByteOrder};
+use std::fmt;
+use std::net::{Ipv4Addr,Ipv6Addr,SocketAddrV4,SocketAddrV6};
+use ::{ByteString,Error,Result};
+
+const AF_UNIX: u8 = 1;
+const AF_INET: u8 = 2;
+const AF_INET6: u8 = 28;
+
+
+/// A socket address, as carried by a `KTR_STRUCT` "sockaddr" record
+#[derive(Clone,Debug,PartialEq)]
+pub enum SockAddr {
+    /// AF_UNIX - local (filesystem) socket
+    Unix(ByteString),
+
+    /// AF_INET - IPv4 address and port
+    Inet(SocketAddrV4),
+
+    /// AF_INET6 - IPv6 address and port
+    Inet6(SocketAddrV6),
+
+    /// Any other address family
+    Other { family: u8, data: Vec<u8> },
+}
+
+impl SockAddr {
+    /// Parse a FreeBSD `struct sockaddr` (which begins with `sa_len`).
+    pub fn parse<E>(data: &[u8]) -> Result<SockAddr>
+        where E: ByteOrder
+    {
+        if data.len() < 2 {
+            return Err(Error::bad_length("at least 2 B (sa_len + sa_family)",
+                                         data.len()));
+        }
+
+        let len = (data[0] as usize).min(data.len());
+        let family = data[1];
+        let data = &data[..len.max(2)];
+
+        match family {
+            AF_UNIX => {
+                let path = &data[2..];
+                let end = path.iter().position(|x| *x == 0).unwrap_or(path.len());
+
+                Ok(SockAddr::Unix(path[..end].into()))
+            },
+
+            AF_INET => {
+                if data.len() < 8 {
+                    return Err(Error::bad_length("8 B (sockaddr_in)",
+                                                 data.len()));
+                }
+
+                let addr = Ipv4Addr::new(data[4], data[5], data[6], data[7]);
+                let port = BigEndian::read_u16(&data[2..4]);
+
+                Ok(SockAddr::Inet(SocketAddrV4::new(addr, port)))
+            },
+
+            AF_INET6 => {
+                if data.len() < 24 {
+                    return Err(Error::bad_length("24 B (sockaddr_in6)",
+                                                 data.len()));
+                }
+
+                let mut octets = [0; 16];
+                octets.copy_from_slice(&data[8..24]);
+
+                let port = BigEndian::read_u16(&data[2..4]);
+                let flowinfo = BigEndian::read_u32(&data[4..8]);
+                let scope = if data.len() >= 28 {
+                    E::read_u32(&data[24..28])
+                } else {
+                    0
+                };
+
+                Ok(SockAddr::Inet6(SocketAddrV6::new(
+                    Ipv6Addr::from(octets), port, flowinfo, scope)))
+            },
+
+            _ => Ok(SockAddr::Other {
+                family,
+                data: data[2..].to_vec(),
+            }),
+        }
+    }
+}
+
+impl fmt::Display for SockAddr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SockAddr::Unix(ref path) => write![f, "unix:{}", path],
+            SockAddr::Inet(ref addr) => write![f, "{}", addr],
+            SockAddr::Inet6(ref addr) => write![f, "{}", addr],
+            SockAddr::Other{family, ..} => write![f, "<address family {}>", family],
+        }
+    }
+}
@@ -473,7 +473,39 @@ pub fn name(id: usize) -> Result<String> {
         548 => Ok("numa_getaffinity"),
         549 => Ok("numa_setaffinity"),
         550 => Ok("fdatasync"),
-        x => Err(Error::bad_value("system call number", x.to_string())),
+        551 => Ok("fstat"),
+        552 => Ok("fstatat"),
+        553 => Ok("fhstat"),
+        554 => Ok("getdirentries"),
+        555 => Ok("statfs"),
+        556 => Ok("fstatfs"),
+        557 => Ok("getfsstat"),
+        558 => Ok("fhstatfs"),
+        559 => Ok("mknodat"),
+        560 => Ok("kevent"),
+        561 => Ok("cpuset_getdomain"),
+        562 => Ok("cpuset_setdomain"),
+        563 => Ok("getrandom"),
+        564 => Ok("getfhat"),
+        565 => Ok("fhlink"),
+        566 => Ok("fhlinkat"),
+        567 => Ok("fhreadlink"),
+        568 => Ok("funlinkat"),
+        569 => Ok("copy_file_range"),
+        570 => Ok("__sysctlbyname"),
+        571 => Ok("shm_open2"),
+        572 => Ok("shm_rename"),
+        573 => Ok("sigfastblock"),
+        574 => Ok("__realpathat"),
+        575 => Ok("close_range"),
+        576 => Ok("rpctls_syscall"),
+        577 => Ok("__specialfd"),
+        578 => Ok("aio_writev"),
+        579 => Ok("aio_readv"),
+        580 => Ok("fspacectl"),
+        581 => Ok("sched_getcpu"),
+        582 => Ok("swapoff"),
+        x => Err(Error::unknown_value("system call number", x as i64)),
     }
     .map(str::to_string)
 }
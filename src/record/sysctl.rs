@@ -0,0 +1,208 @@
+// Copyright 2016 Jonathan Anderson <jonathan.anderson@mun.ca>
+//
+// This software was developed by BAE Systems, the University of Cambridge
+// Computer Laboratory, and Memorial University under DARPA/AFRL contract
+// FA8650-15-C-7558 ("CADETS"), as part of the DARPA Transparent Computing
+// (TC) research program.
+//
+// Licensed under the Apache License, Version 2.0,
+// <LICENSE-APACHE or http://apache.org/licenses/LICENSE-2.0>
+// or the MIT license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::fmt;
+
+
+/// The type of value that a sysctl OID holds
+#[derive(Clone,Copy,Debug,Eq,PartialEq)]
+pub enum SysctlType {
+    /// a node whose children are other OIDs
+    Node,
+    Int,
+    UInt,
+    Long,
+    ULong,
+    U64,
+    String,
+
+    /// a C structure (or array of them) of the named type
+    Struct(&'static str),
+
+    /// uninterpreted bytes
+    Opaque,
+}
+
+/// A well-known sysctl OID
+#[derive(Clone,Debug)]
+pub struct Oid {
+    pub name: &'static str,
+    pub value: SysctlType,
+    pub description: &'static str,
+
+    /// the OID's handler takes parameters as further name components
+    /// (e.g., the PID in `kern.proc.pid.1234`)
+    pub parameters: bool,
+}
+
+
+/// The components of a MIB name (e.g., `["kern", "proc", "pid", "42"]`).
+pub fn components(name: &str) -> Vec<&str> {
+    name.split('.').collect()
+}
+
+/// The well-known OID named by `name`, or that `name` passes parameters
+/// to (e.g., `kern.proc.pid` for `kern.proc.pid.1234`).
+pub fn lookup(name: &str) -> Option<&'static Oid> {
+    known_prefix(name).filter(|oid| oid.name == name || oid.parameters)
+}
+
+/// The well-known OID with the longest name that is a prefix of `name`
+/// (by whole components), which may be a node above an unknown OID.
+pub fn known_prefix(name: &str) -> Option<&'static Oid> {
+    let parts = components(name);
+
+    (1..parts.len() + 1).rev()
+                        .map(|n| parts[..n].join("."))
+                        .filter_map(|prefix| OIDS.iter().find(|o| o.name == prefix))
+                        .next()
+}
+
+impl fmt::Display for SysctlType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SysctlType::Node => write![f, "node"],
+            SysctlType::Int => write![f, "int"],
+            SysctlType::UInt => write![f, "u_int"],
+            SysctlType::Long => write![f, "long"],
+            SysctlType::ULong => write![f, "u_long"],
+            SysctlType::U64 => write![f, "uint64_t"],
+            SysctlType::String => write![f, "string"],
+            SysctlType::Struct(name) => write![f, "struct {}", name],
+            SysctlType::Opaque => write![f, "opaque"],
+        }
+    }
+}
+
+
+macro_rules! oid {
+    ($name:expr, $value:expr, $description:expr) => {
+        Oid { name: $name, value: $value, description: $description, parameters: false }
+    };
+    ($name:expr, $value:expr, $description:expr, parameters) => {
+        Oid { name: $name, value: $value, description: $description, parameters: true }
+    };
+}
+
+static OIDS: &[Oid] = &[
+    oid!("kern", SysctlType::Node, "High kernel, proc, limits &c"),
+    oid!("kern.ostype", SysctlType::String, "Operating system type"),
+    oid!("kern.osrelease", SysctlType::String, "Operating system release"),
+    oid!("kern.osrevision", SysctlType::Int, "Operating system revision"),
+    oid!("kern.osreldate", SysctlType::Int, "Kernel release date"),
+    oid!("kern.version", SysctlType::String, "Kernel version"),
+    oid!("kern.hostname", SysctlType::String, "Hostname"),
+    oid!("kern.domainname", SysctlType::String, "Name of the current YP/NIS domain"),
+    oid!("kern.hostid", SysctlType::ULong, "Host ID"),
+    oid!("kern.hostuuid", SysctlType::String, "Host UUID"),
+    oid!("kern.bootfile", SysctlType::String, "Name of kernel file booted"),
+    oid!("kern.boottime", SysctlType::Struct("timeval"), "Estimated system boottime"),
+    oid!("kern.clockrate", SysctlType::Struct("clockinfo"),
+         "Rate and period of various kernel clocks"),
+    oid!("kern.maxproc", SysctlType::Int, "Maximum number of processes"),
+    oid!("kern.maxprocperuid", SysctlType::Int, "Maximum processes allowed per userid"),
+    oid!("kern.maxfiles", SysctlType::Int, "Maximum number of files"),
+    oid!("kern.maxfilesperproc", SysctlType::Int, "Maximum files allowed open per process"),
+    oid!("kern.maxvnodes", SysctlType::ULong, "Target for maximum number of vnodes"),
+    oid!("kern.argmax", SysctlType::Int, "Maximum bytes of argument to execve(2)"),
+    oid!("kern.ngroups", SysctlType::Int,
+         "Maximum number of supplemental groups a user can belong to"),
+    oid!("kern.job_control", SysctlType::Int, "Whether job control is available"),
+    oid!("kern.saved_ids", SysctlType::Int, "Whether saved set-group/user ID is available"),
+    oid!("kern.securelevel", SysctlType::Int, "Current secure level"),
+    oid!("kern.iov_max", SysctlType::Int, "Maximum number of elements in an I/O vector"),
+    oid!("kern.usrstack", SysctlType::ULong, "Top of process stack"),
+    oid!("kern.ps_strings", SysctlType::ULong, "Location of process' ps_strings structure"),
+    oid!("kern.arandom", SysctlType::Opaque, "arc4rand"),
+    oid!("kern.cp_time", SysctlType::Long, "CPU time statistics"),
+    oid!("kern.cp_times", SysctlType::Long, "per-CPU time statistics"),
+    oid!("kern.module_path", SysctlType::String, "module load search path"),
+    oid!("kern.randompid", SysctlType::Int, "Random PID modulus"),
+    oid!("kern.smp.cpus", SysctlType::Int, "Number of CPUs online"),
+    oid!("kern.smp.maxcpus", SysctlType::Int, "Max number of CPUs that the system was compiled for"),
+    oid!("kern.features", SysctlType::Node, "Kernel Features"),
+    oid!("kern.ipc.somaxconn", SysctlType::Int,
+         "Maximum listen socket pending connection accept queue size"),
+    oid!("kern.ipc.maxsockbuf", SysctlType::ULong, "Maximum socket buffer size"),
+    oid!("kern.proc", SysctlType::Node, "Process table"),
+    oid!("kern.proc.all", SysctlType::Struct("kinfo_proc"), "Return entire process table"),
+    oid!("kern.proc.pid", SysctlType::Struct("kinfo_proc"), "Process table entry", parameters),
+    oid!("kern.proc.pgrp", SysctlType::Struct("kinfo_proc"),
+         "Process table entries by process group", parameters),
+    oid!("kern.proc.uid", SysctlType::Struct("kinfo_proc"),
+         "Process table entries by effective uid", parameters),
+    oid!("kern.proc.args", SysctlType::Opaque, "Process argument list", parameters),
+    oid!("kern.proc.env", SysctlType::Opaque, "Process environment", parameters),
+    oid!("kern.proc.auxv", SysctlType::Opaque, "Process ELF auxiliary vector", parameters),
+    oid!("kern.proc.pathname", SysctlType::String, "Process executable path", parameters),
+    oid!("kern.proc.filedesc", SysctlType::Struct("kinfo_file"),
+         "Process filedesc entries", parameters),
+    oid!("kern.proc.vmmap", SysctlType::Struct("kinfo_vmentry"),
+         "Process vm map entries", parameters),
+    oid!("kern.proc.kstack", SysctlType::Struct("kinfo_kstack"),
+         "Process kernel stacks", parameters),
+    oid!("kern.proc.rlimit", SysctlType::Struct("rlimit"), "Process resource limits", parameters),
+    oid!("kern.proc.umask", SysctlType::Opaque, "Process umask", parameters),
+    oid!("kern.proc.osrel", SysctlType::Int, "Process binary osreldate", parameters),
+    oid!("kern.proc.cwd", SysctlType::Struct("kinfo_file"),
+         "Process current working directory", parameters),
+
+    oid!("hw", SysctlType::Node, "hardware"),
+    oid!("hw.machine", SysctlType::String, "Machine class"),
+    oid!("hw.machine_arch", SysctlType::String, "System architecture"),
+    oid!("hw.model", SysctlType::String, "Machine model"),
+    oid!("hw.ncpu", SysctlType::Int, "Number of active CPUs"),
+    oid!("hw.byteorder", SysctlType::Int, "System byte order"),
+    oid!("hw.physmem", SysctlType::ULong, "Amount of physical memory (in bytes)"),
+    oid!("hw.realmem", SysctlType::ULong, "Amount of memory (in bytes) reported by the firmware"),
+    oid!("hw.usermem", SysctlType::ULong, "Amount of memory (in bytes) which is not wired"),
+    oid!("hw.pagesize", SysctlType::Int, "System memory page size"),
+    oid!("hw.pagesizes", SysctlType::Opaque, "Supported page sizes"),
+    oid!("hw.floatingpoint", SysctlType::Int, "Floating point instructions executed in hardware"),
+
+    oid!("vm", SysctlType::Node, "Virtual memory"),
+    oid!("vm.loadavg", SysctlType::Struct("loadavg"), "Machine loadaverage history"),
+    oid!("vm.vmtotal", SysctlType::Struct("vmtotal"), "System virtual memory statistics"),
+    oid!("vm.swap_info", SysctlType::Struct("xswdev"), "Swap statistics by device"),
+    oid!("vm.overcommit", SysctlType::Int, "Configure virtual memory overcommit behavior"),
+    oid!("vm.kmem_size", SysctlType::ULong, "Size of kernel memory"),
+    oid!("vm.stats.vm.v_page_count", SysctlType::UInt, "Total number of pages in system"),
+    oid!("vm.stats.vm.v_free_count", SysctlType::UInt, "Free pages"),
+    oid!("vm.stats.vm.v_page_size", SysctlType::UInt, "Page size in bytes"),
+    oid!("vm.pmap.pg_ps_enabled", SysctlType::Int, "Are large page mappings enabled?"),
+
+    oid!("security", SysctlType::Node, "Security"),
+    oid!("security.jail.jailed", SysctlType::Int, "Process in jail?"),
+    oid!("security.bsd.see_other_uids", SysctlType::Int,
+         "Unprivileged processes may see subjects/objects with different real uid"),
+    oid!("security.bsd.see_other_gids", SysctlType::Int,
+         "Unprivileged processes may see subjects/objects with different real gid"),
+    oid!("security.bsd.unprivileged_proc_debug", SysctlType::Int,
+         "Unprivileged processes may use process debugging facilities"),
+    oid!("security.bsd.unprivileged_read_msgbuf", SysctlType::Int,
+         "Unprivileged processes may read the kernel message buffer"),
+    oid!("security.bsd.hardlink_check_uid", SysctlType::Int,
+         "Unprivileged processes cannot create hard links to files owned by other users"),
+    oid!("security.mac", SysctlType::Node, "TrustedBSD MAC policy controls"),
+
+    oid!("user", SysctlType::Node, "user-level"),
+    oid!("user.cs_path", SysctlType::String, "PATH that finds all the standard utilities"),
+    oid!("user.localbase", SysctlType::String, "Prefix used to install and locate add-on packages"),
+
+    oid!("sysctl", SysctlType::Node, "Sysctl internal magic"),
+    oid!("sysctl.name", SysctlType::Opaque, "Name of an OID"),
+    oid!("sysctl.next", SysctlType::Opaque, "Next OID (for iterating over the MIB)"),
+    oid!("sysctl.name2oid", SysctlType::Opaque, "OID of a name"),
+    oid!("sysctl.oidfmt", SysctlType::Opaque, "Format of an OID"),
+    oid!("sysctl.oiddescr", SysctlType::Opaque, "Description of an OID"),
+];
@@ -0,0 +1,456 @@
+// Copyright 2016 Jonathan Anderson <jonathan.anderson@mun.ca>
+//
+// This software was developed by BAE Systems, the University of Cambridge
+// Computer Laboratory, and Memorial University under DARPA/AFRL contract
+// FA8650-15-C-7558 ("CADETS"), as part of the DARPA Transparent Computing
+// (TC) research program.
+//
+// Licensed under the Apache License, Version 2.0,
+// <LICENSE-APACHE or http://apache.org/licenses/LICENSE-2.0>
+// or the MIT license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+
+use byteorder::ByteOrder;
+use std::collections::HashMap;
+use std::io::{Read,Write};
+use std::marker::PhantomData;
+use ::{AbiTracker,ByteString,Error,Header,Record,RecordType,Result,sysctl};
+
+const AF_UNIX: u8 = 1;
+const AF_INET: u8 = 2;
+const AF_INET6: u8 = 28;
+
+
+/// How to redact the paths in `Namei` records
+#[derive(Clone,Debug,PartialEq)]
+pub enum PathPolicy {
+    Keep,
+
+    /// Replace each path component with a (salted) hash of its name.
+    Hash,
+
+    /// Replace each path component with a name assigned in order of
+    /// appearance (e.g., `/n1/n2`).
+    Map,
+}
+
+/// How to redact the data in `GenericIO` and `UserData` records
+#[derive(Clone,Debug,PartialEq)]
+pub enum DataPolicy {
+    Keep,
+
+    /// Overwrite the data with zeros, keeping its length.
+    Zero,
+
+    /// Keep at most this many bytes of data.
+    Truncate(usize),
+}
+
+/// What a `Redactor` should remove from a trace
+#[derive(Clone,Debug)]
+pub struct RedactOptions {
+    pub paths: PathPolicy,
+    pub data: DataPolicy,
+
+    /// replace command names with pseudonyms (e.g., `cmd1`)
+    pub commands: bool,
+
+    /// replace process and thread IDs with pseudonyms
+    pub pids: bool,
+
+    /// mixed into path hashes, so that they cannot be reversed by hashing
+    /// likely names
+    pub salt: String,
+}
+
+/// Rewrites trace records to remove information that shouldn't be shared
+///
+/// Paths, commands and IDs are replaced consistently throughout a trace,
+/// so the same file or process always has the same pseudonym. Process IDs
+/// are also replaced where system calls take or return them (e.g., the
+/// child PID returned by `fork`), keeping parent/child relationships.
+///
+/// Socket addresses in `KTR_STRUCT` records are redacted like paths
+/// (`AF_UNIX`) or replaced with private addresses (`AF_INET`, `AF_INET6`),
+/// and the contents of other structures (e.g., `stat`'s owner and inode)
+/// are zeroed. Sysctl names keep their well-known prefix (e.g.,
+/// `kern.proc.pid`) and have the rest redacted like path components.
+pub struct Redactor<E> {
+    options: RedactOptions,
+
+    components: HashMap<Vec<u8>, String>,
+    commands: HashMap<ByteString, ByteString>,
+    pids: HashMap<u32, u32>,
+    tids: HashMap<usize, usize>,
+
+    /// pseudonyms for IPv4 and IPv6 addresses
+    addresses: HashMap<Vec<u8>, Vec<u8>>,
+
+    /// system call in progress on each (original) thread
+    calls: HashMap<(u32, usize), u16>,
+
+    /// ABI of each (original) process, which determines system call names
+    abis: AbiTracker,
+
+    byte_order: PhantomData<E>,
+}
+
+
+impl Default for RedactOptions {
+    fn default() -> RedactOptions {
+        RedactOptions {
+            paths: PathPolicy::Hash,
+            data: DataPolicy::Zero,
+            commands: true,
+            pids: true,
+            salt: String::new(),
+        }
+    }
+}
+
+
+impl<E> Redactor<E>
+    where E: ByteOrder
+{
+    pub fn new(options: RedactOptions) -> Redactor<E> {
+        Redactor {
+            options,
+            components: HashMap::new(),
+            commands: HashMap::new(),
+            pids: HashMap::new(),
+            tids: HashMap::new(),
+            addresses: HashMap::new(),
+            calls: HashMap::new(),
+            abis: AbiTracker::new(),
+            byte_order: PhantomData,
+        }
+    }
+
+    /// Redact a record, given its header and undecoded payload.
+    pub fn record(&mut self, header: &Header, payload: &[u8])
+        -> Result<(Header, Vec<u8>)>
+    {
+        let mut payload = payload.to_vec();
+        let key = (header.pid, header.tid);
+
+        if let RecordType::ProcessCreation = header.record_type {
+            if let Ok(record) = Record::parse::<E>(&payload, &header.record_type) {
+                self.abis.record(header, &record);
+            }
+        }
+        let abi = self.abis.abi(header.pid);
+
+        match header.record_type {
+            RecordType::SystemCall => {
+                if payload.len() < 8 {
+                    return Err(Error::bad_length("at least 8 B", payload.len()));
+                }
+
+                let number = E::read_u16(&payload[0..2]);
+                self.calls.insert(key, number);
+
+                if self.options.pids {
+                    let name = abi.syscall_name(number).unwrap_or_default();
+                    for arg in pid_args(&name) {
+                        let start = 8 + 8 * arg;
+                        if let Some(value) = payload.get_mut(start..start + 8) {
+                            let pid = E::read_u64(value) as i64;
+                            E::write_u64(value, self.pid_value(pid) as u64);
+                        }
+                    }
+                }
+            },
+
+            RecordType::SystemCallReturn => {
+                if payload.len() < 16 {
+                    return Err(Error::bad_length("16 B", payload.len()));
+                }
+
+                let number = E::read_u16(&payload[0..2]);
+                let error = E::read_i32(&payload[4..8]);
+                let call = self.calls.remove(&key).unwrap_or(number);
+                let returns_pid = abi.syscall_name(call)
+                                     .is_ok_and(|name| returns_pid(&name));
+
+                if self.options.pids && error == 0 && returns_pid {
+                    let pid = E::read_u64(&payload[8..16]) as i64;
+                    E::write_u64(&mut payload[8..16], self.pid_value(pid) as u64);
+                }
+            },
+
+            RecordType::Namei => {
+                payload = self.path(&payload);
+            },
+
+            RecordType::GenericIO if payload.len() >= 8 => {
+                self.data(&mut payload, 8)
+            },
+
+            RecordType::UserData => self.data(&mut payload, 0),
+
+            RecordType::Struct => {
+                let nul = payload.iter()
+                                 .position(|x| *x == 0)
+                                 .ok_or(Error::msg("no NULL byte in struct name"))?;
+                let (name, content) = payload.split_at(nul + 1);
+
+                let mut redacted = name.to_vec();
+                redacted.extend(match name {
+                    b"sockaddr\0" => self.sockaddr(content),
+                    _ => vec![0; content.len()],
+                });
+                payload = redacted;
+            },
+
+            RecordType::Sysctl => {
+                // The kernel includes the name's NUL terminator.
+                let nul = payload.last() == Some(&0);
+                let name = if nul { &payload[..payload.len() - 1] } else { &payload[..] };
+
+                let mut redacted = self.sysctl(name);
+                if nul {
+                    redacted.push(0);
+                }
+                payload = redacted;
+            },
+
+            _ => {},
+        }
+
+        let mut header = header.clone();
+        header.length = payload.len();
+
+        if self.options.commands {
+            let next = format!["cmd{}", self.commands.len() + 1].into();
+            header.command = self.commands.entry(header.command)
+                                          .or_insert(next)
+                                          .clone();
+        }
+
+        if self.options.pids {
+            header.pid = self.pid(header.pid);
+
+            let next = self.tids.len() + 100001;
+            header.tid = *self.tids.entry(header.tid).or_insert(next);
+        }
+
+        Ok((header, payload))
+    }
+
+    /// Redact a path, keeping its structure (e.g., `/a/b/../c`).
+    ///
+    /// Paths are bytes rather than UTF-8, so they are split and kept as such.
+    fn path(&mut self, path: &[u8]) -> Vec<u8> {
+        if self.options.paths == PathPolicy::Keep {
+            return path.to_vec();
+        }
+
+        path.split(|&b| b == b'/')
+            .map(|c| match c {
+                b"" | b"." | b".." => c.to_vec(),
+                _ => self.component(c).into_bytes(),
+            })
+            .collect::<Vec<_>>()
+            .join(&b'/')
+    }
+
+    /// Redact a FreeBSD `struct sockaddr` (which begins with `sa_len`).
+    fn sockaddr(&mut self, data: &[u8]) -> Vec<u8> {
+        let family = match data.get(1) {
+            Some(&f) => f,
+            None => return vec![0; data.len()],
+        };
+
+        let mut redacted = data.to_vec();
+        match family {
+            AF_UNIX => {
+                let path = &data[2..];
+                let end = path.iter().position(|x| *x == 0).unwrap_or(path.len());
+                let new = self.path(&path[..end]);
+
+                // Keep the structure's size, truncating the path if needed.
+                let len = new.len().min(path.len().saturating_sub(1));
+                for b in &mut redacted[2..] {
+                    *b = 0;
+                }
+                redacted[2..2 + len].copy_from_slice(&new[..len]);
+            },
+
+            AF_INET if data.len() >= 8 => {
+                let addr = self.address(&data[4..8]);
+                redacted[4..8].copy_from_slice(&addr);
+            },
+
+            AF_INET6 if data.len() >= 24 => {
+                let addr = self.address(&data[8..24]);
+                redacted[8..24].copy_from_slice(&addr);
+            },
+
+            _ => {
+                for b in &mut redacted[2..] {
+                    *b = 0;
+                }
+            },
+        }
+
+        redacted
+    }
+
+    /// Replace an IPv4 or IPv6 address with a private one (`10.0.0.0/8` or
+    /// `fd00::/8`), keeping unspecified and loopback addresses.
+    fn address(&mut self, addr: &[u8]) -> Vec<u8> {
+        let special = addr.iter().all(|&b| b == 0)
+            || (addr.len() == 4 && addr[0] == 127)
+            || (addr.len() == 16 && addr[..15].iter().all(|&b| b == 0) && addr[15] == 1);
+        if special {
+            return addr.to_vec();
+        }
+
+        if let Some(a) = self.addresses.get(addr) {
+            return a.clone();
+        }
+
+        let n = self.addresses.len() as u32 + 1;
+        let mut new = vec![0; addr.len()];
+        new[0] = if addr.len() == 4 { 10 } else { 0xfd };
+        let len = new.len();
+        new[len - 3] = (n >> 16) as u8;
+        new[len - 2] = (n >> 8) as u8;
+        new[len - 1] = n as u8;
+
+        self.addresses.insert(addr.to_vec(), new.clone());
+        new
+    }
+
+    /// Redact a sysctl MIB name, keeping the longest well-known prefix
+    /// (e.g., `kern.proc.pid` in `kern.proc.pid.1234`).
+    fn sysctl(&mut self, name: &[u8]) -> Vec<u8> {
+        let known = ::std::str::from_utf8(name).ok()
+                            .and_then(sysctl::known_prefix)
+                            .map(|oid| sysctl::components(oid.name).len())
+                            .unwrap_or(0);
+
+        // The components after kern.proc.<node> are process IDs.
+        let pids = self.options.pids && name.starts_with(b"kern.proc.");
+
+        name.split(|&b| b == b'.')
+            .enumerate()
+            .map(|(i, c)| {
+                let pid = ::std::str::from_utf8(c).ok()
+                                  .and_then(|c| c.parse::<u32>().ok());
+
+                match pid {
+                    _ if i < known => c.to_vec(),
+                    Some(pid) if pids => self.pid(pid).to_string().into_bytes(),
+                    _ if self.options.paths == PathPolicy::Keep => c.to_vec(),
+                    _ => self.component(c).into_bytes(),
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(&b'.')
+    }
+
+    fn component(&mut self, name: &[u8]) -> String {
+        if let Some(c) = self.components.get(name) {
+            return c.clone();
+        }
+
+        let redacted = match self.options.paths {
+            PathPolicy::Hash => {
+                // 64-bit FNV-1a: stable across builds and platforms
+                let hash = self.options.salt.bytes()
+                               .chain(name.iter().cloned())
+                               .fold(0xcbf29ce484222325_u64, |h, b| {
+                                   (h ^ b as u64).wrapping_mul(0x100000001b3)
+                               });
+
+                format!["{:016x}", hash]
+            },
+            _ => format!["n{}", self.components.len() + 1],
+        };
+
+        self.components.insert(name.to_vec(), redacted.clone());
+        redacted
+    }
+
+    /// Redact the data that starts at `start` within a payload.
+    fn data(&self, payload: &mut Vec<u8>, start: usize) {
+        match self.options.data {
+            DataPolicy::Keep => {},
+            DataPolicy::Zero => {
+                for b in &mut payload[start..] {
+                    *b = 0;
+                }
+            },
+            DataPolicy::Truncate(len) => payload.truncate(start + len),
+        }
+    }
+
+    /// Redact a PID, keeping 0 and 1 (the kernel and `init`) in headers as
+    /// well as in arguments and return values.
+    fn pid(&mut self, pid: u32) -> u32 {
+        if pid <= 1 {
+            return pid;
+        }
+
+        // Start above the IDs that mean something in their own right.
+        let next = self.pids.len() as u32 + 100;
+        *self.pids.entry(pid).or_insert(next)
+    }
+
+    /// Redact a PID used as a system call argument or return value, where
+    /// negative values refer to process groups and 0, 1 and -1 have
+    /// special meanings.
+    fn pid_value(&mut self, value: i64) -> i64 {
+        match value {
+            -1..=1 => value,
+            v if v < 0 => -(self.pid((-v) as u32) as i64),
+            v => self.pid(v as u32) as i64,
+        }
+    }
+}
+
+
+/// Copy a trace from `input` to `output`, redacting every record.
+/// Returns the number of records copied.
+pub fn redact<E>(input: &mut dyn Read, output: &mut dyn Write,
+                 redactor: &mut Redactor<E>) -> Result<usize>
+    where E: ByteOrder
+{
+    let mut index = 0;
+    let mut offset = 0;
+
+    while let Some((header, payload)) = ::read_raw::<E>(input, index, offset)? {
+        let (h, p) = redactor.record(&header, &payload)
+                             .map_err(|e| e.in_record(&header).at(index, offset))?;
+
+        output.write_all(&h.to_bytes::<E>())?;
+        output.write_all(&p)?;
+
+        index += 1;
+        offset += (56 + header.length) as u64;
+    }
+
+    Ok(index)
+}
+
+
+/// Arguments of a system call that are process (or process group) IDs.
+fn pid_args(name: &str) -> &'static [usize] {
+    match name {
+        "kill" | "wait4" | "getpgid" | "getsid" => &[0],
+        "setpgid" => &[0, 1],
+        "ptrace" => &[1],
+        _ => &[],
+    }
+}
+
+/// System calls that return a process (or process group) ID.
+pub(crate) fn returns_pid(name: &str) -> bool {
+    matches!(name,
+        "fork" | "vfork" | "rfork" | "getpid" | "getppid" | "wait4" |
+        "wait6" | "getpgid" | "getpgrp" | "getsid" | "setsid" | "clone" |
+        "clone3")
+}
@@ -0,0 +1,102 @@
+// Copyright 2016 Jonathan Anderson <jonathan.anderson@mun.ca>
+//
+// This software was developed by BAE Systems, the University of Cambridge
+// Computer Laboratory, and Memorial University under DARPA/AFRL contract
+// FA8650-15-C-7558 ("CADETS"), as part of the DARPA Transparent Computing
+// (TC) research program.
+//
+// Licensed under the Apache License, Version 2.0,
+// <LICENSE-APACHE or http://apache.org/licenses/LICENSE-2.0>
+// or the MIT license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+
+use byteorder::ByteOrder;
+use std::convert::TryInto;
+use std::marker::PhantomData;
+use ::{Error,HeaderRef,RecordRef,Result};
+
+
+/// Parse records directly out of an in-memory trace (e.g., a mapped file)
+/// without copying their headers or payloads.
+pub fn parse_slice<E>(data: &[u8]) -> RecordRefs<'_, E>
+    where E: ByteOrder
+{
+    RecordRefs {
+        data,
+        offset: 0,
+        index: 0,
+        failed: false,
+        byte_order: PhantomData,
+    }
+}
+
+/// An iterator over the records in an in-memory trace
+///
+/// Each item is a header and the result of parsing its record, or else an
+/// error in the framing of the trace itself (after which iteration stops).
+pub struct RecordRefs<'a, E> {
+    data: &'a [u8],
+    offset: usize,
+    index: usize,
+    failed: bool,
+    byte_order: PhantomData<E>,
+}
+
+impl<'a, E> RecordRefs<'a, E> {
+    /// Byte offset of the next header within the trace.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    fn fail(&mut self, e: Error) -> Option<<Self as Iterator>::Item>
+        where E: ByteOrder
+    {
+        self.failed = true;
+        Some(Err(e.at(self.index, self.offset as u64)))
+    }
+}
+
+impl<'a, E> Iterator for RecordRefs<'a, E>
+    where E: ByteOrder
+{
+    type Item = Result<(HeaderRef<'a>, Result<RecordRef<'a>>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let remaining = &self.data[self.offset..];
+        if self.failed || remaining.is_empty() {
+            return None;
+        }
+
+        if remaining.len() < 56 {
+            return self.fail(Error::truncated(56, remaining.len()));
+        }
+
+        let raw = remaining[..56].try_into().unwrap();
+        let header = match HeaderRef::parse::<E>(raw) {
+            Ok(h) => h,
+            Err(e) => return self.fail(e),
+        };
+
+        let payload = &remaining[56..];
+        if payload.len() < header.length {
+            let e = Error::truncated(header.length, payload.len())
+                          .in_record(&header.into_owned());
+            return self.fail(e);
+        }
+
+        let (index, offset) = (self.index, self.offset as u64);
+        let record = RecordRef::parse::<E>(&payload[..header.length],
+                                           &header.record_type)
+                               .map_err(|e| {
+                                   e.in_record(&header.clone().into_owned())
+                                    .at(index, offset)
+                               });
+
+        self.offset += 56 + header.length;
+        self.index += 1;
+
+        Some(Ok((header, record)))
+    }
+}
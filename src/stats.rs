@@ -0,0 +1,280 @@
+// Copyright 2016 Jonathan Anderson <jonathan.anderson@mun.ca>
+//
+// This software was developed by BAE Systems, the University of Cambridge
+// Computer Laboratory, and Memorial University under DARPA/AFRL contract
+// FA8650-15-C-7558 ("CADETS"), as part of the DARPA Transparent Computing
+// (TC) research program.
+//
+// Licensed under the Apache License, Version 2.0,
+// <LICENSE-APACHE or http://apache.org/licenses/LICENSE-2.0>
+// or the MIT license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+
+use std::collections::BTreeMap;
+use std::io::{self,Write};
+use ::{ByteString,CallTracker,Header,IODir,Record,errno,json};
+
+
+/// Count, error and latency statistics for one system call
+#[derive(Clone,Debug,Default)]
+pub struct SyscallStats {
+    pub name: String,
+    pub calls: u64,
+
+    /// number of failures, by errno
+    pub errors: BTreeMap<i32, u64>,
+
+    /// bytes read and written via `KTR_GENIO` records
+    pub bytes_read: u64,
+    pub bytes_written: u64,
+
+    /// latencies (in µs) of calls whose entry and return were both seen
+    latencies: Vec<i64>,
+    sorted: bool,
+}
+
+/// Per-process system call statistics
+#[derive(Clone,Debug,Default)]
+pub struct ProcessStats {
+    pub pid: u32,
+    pub command: ByteString,
+    pub syscalls: BTreeMap<String, SyscallStats>,
+}
+
+/// Accumulates `truss -c`-style system call statistics for a trace
+#[derive(Default)]
+pub struct Stats {
+    pub syscalls: BTreeMap<String, SyscallStats>,
+    pub processes: BTreeMap<u32, ProcessStats>,
+    calls: CallTracker,
+}
+
+
+impl SyscallStats {
+    fn new(name: &str) -> SyscallStats {
+        SyscallStats {
+            name: name.to_string(),
+            ..SyscallStats::default()
+        }
+    }
+
+    /// Total number of failed calls.
+    pub fn error_count(&self) -> u64 {
+        self.errors.values().sum()
+    }
+
+    /// Total time spent in the call (µs).
+    pub fn total(&self) -> i64 {
+        self.latencies.iter().fold(0, |sum, &t| sum.saturating_add(t))
+    }
+
+    pub fn min(&self) -> Option<i64> {
+        self.latencies.iter().cloned().min()
+    }
+
+    pub fn max(&self) -> Option<i64> {
+        self.latencies.iter().cloned().max()
+    }
+
+    /// The latency (µs) below which `p` percent of calls completed.
+    pub fn percentile(&mut self, p: f64) -> Option<i64> {
+        if self.latencies.is_empty() {
+            return None;
+        }
+
+        if !self.sorted {
+            self.latencies.sort();
+            self.sorted = true;
+        }
+
+        // nearest-rank method
+        let n = self.latencies.len();
+        let rank = ((p / 100.0) * n as f64).ceil() as usize;
+        Some(self.latencies[rank.max(1).min(n) - 1])
+    }
+
+    fn add_latency(&mut self, latency: i64) {
+        self.latencies.push(latency);
+        self.sorted = false;
+    }
+
+    fn write_json(&mut self, out: &mut dyn Write) -> io::Result<()> {
+        let errors = self.errors.iter()
+                         .map(|(e, n)| format!["{}:{}", json::string(&errno_name(*e)), n])
+                         .collect::<Vec<_>>()
+                         .join(",");
+
+        let opt = |x: Option<i64>| x.map(|x| x.to_string())
+                                    .unwrap_or("null".to_string());
+
+        let (p50, p95, p99) = (self.percentile(50.0), self.percentile(95.0),
+                               self.percentile(99.0));
+
+        write![out, "{{\"name\":{},\"calls\":{},\"errors\":{{{}}},\
+                     \"total_us\":{},\"min_us\":{},\"max_us\":{},\
+                     \"p50_us\":{},\"p95_us\":{},\"p99_us\":{},\
+                     \"bytes_read\":{},\"bytes_written\":{}}}",
+               json::string(&self.name), self.calls, errors, self.total(),
+               opt(self.min()), opt(self.max()), opt(p50), opt(p95), opt(p99),
+               self.bytes_read, self.bytes_written]
+    }
+}
+
+
+impl Stats {
+    pub fn new() -> Stats {
+        Stats::default()
+    }
+
+    /// Account for the next record in a trace.
+    pub fn record(&mut self, header: &Header, record: &Record) {
+        let completed = self.calls.record(header, record);
+
+        if let Record::GenericIO{ref rw, ref data, ..} = *record {
+            let name = match self.calls.pending(header.pid, header.tid) {
+                Some(call) => call.name(),
+                None => return,
+            };
+
+            for s in self.counters(header, &name) {
+                match *rw {
+                    IODir::Read => s.bytes_read += data.len() as u64,
+                    IODir::Write => s.bytes_written += data.len() as u64,
+                }
+            }
+
+            return;
+        }
+
+        let call = match completed {
+            Some(call) => call,
+            None => return,
+        };
+
+        let name = call.name();
+        let latency = call.start.map(|start| {
+            ::microseconds(&call.end).saturating_sub(::microseconds(&start))
+        });
+        for s in self.counters(header, &name) {
+            s.calls += 1;
+
+            if !call.succeeded() {
+                *s.errors.entry(call.error).or_insert(0) += 1;
+            }
+
+            if let Some(latency) = latency {
+                s.add_latency(latency);
+            }
+        }
+    }
+
+    /// Write a table of per-syscall statistics, optionally followed by
+    /// a table for each process.
+    pub fn write_table(&mut self, out: &mut dyn Write, per_process: bool)
+        -> io::Result<()>
+    {
+        write_table(out, self.syscalls.values_mut())?;
+
+        if per_process {
+            for p in self.processes.values_mut() {
+                writeln![out]?;
+                writeln![out, "PID {} ({}):", p.pid, p.command]?;
+                write_table(out, p.syscalls.values_mut())?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Write statistics as a single JSON object.
+    pub fn write_json(&mut self, out: &mut dyn Write) -> io::Result<()> {
+        write![out, "{{\"syscalls\":["]?;
+        write_json_list(out, self.syscalls.values_mut())?;
+        write![out, "],\"processes\":["]?;
+
+        for (i, p) in self.processes.values_mut().enumerate() {
+            if i > 0 {
+                write![out, ","]?;
+            }
+
+            write![out, "{{\"pid\":{},\"command\":{},\"syscalls\":[",
+                   p.pid, json::string(&p.command.to_string())]?;
+            write_json_list(out, p.syscalls.values_mut())?;
+            write![out, "]}}"]?;
+        }
+
+        writeln![out, "]}}"]
+    }
+
+    /// The global and per-process statistics for a system call.
+    fn counters(&mut self, header: &Header, name: &str) -> [&mut SyscallStats; 2] {
+        let p = self.processes.entry(header.pid).or_insert_with(|| ProcessStats {
+            pid: header.pid,
+            ..ProcessStats::default()
+        });
+
+        // A process' command changes when it calls execve(2).
+        if p.command != header.command {
+            p.command = header.command.clone();
+        }
+
+        [
+            self.syscalls.entry(name.to_string())
+                         .or_insert_with(|| SyscallStats::new(name)),
+            p.syscalls.entry(name.to_string())
+                      .or_insert_with(|| SyscallStats::new(name)),
+        ]
+    }
+}
+
+fn write_table<'a, I>(out: &mut dyn Write, stats: I) -> io::Result<()>
+    where I: Iterator<Item = &'a mut SyscallStats>
+{
+    writeln![out, "{:<24} {:>8} {:>7} {:>12} {:>9} {:>9} {:>9} {:>9} {:>10} {:>11}",
+             "syscall", "calls", "errors", "total (µs)", "min", "p50", "p95",
+             "max", "read (B)", "written (B)"]?;
+
+    let mut total_calls = 0;
+    let mut total_errors = 0;
+    let mut total_time = 0;
+    let opt = |x: Option<i64>| x.map(|x| x.to_string()).unwrap_or("-".to_string());
+
+    for s in stats {
+        let (p50, p95) = (s.percentile(50.0), s.percentile(95.0));
+
+        writeln![out, "{:<24} {:>8} {:>7} {:>12} {:>9} {:>9} {:>9} {:>9} {:>10} {:>11}",
+                 s.name, s.calls, s.error_count(), s.total(), opt(s.min()),
+                 opt(p50), opt(p95), opt(s.max()), s.bytes_read,
+                 s.bytes_written]?;
+
+        for (e, n) in &s.errors {
+            writeln![out, "    {:<20} {:>8}", errno_name(*e), n]?;
+        }
+
+        total_calls += s.calls;
+        total_errors += s.error_count();
+        total_time = s.total().saturating_add(total_time);
+    }
+
+    writeln![out, "{:<24} {:>8} {:>7} {:>12}", "total", total_calls,
+             total_errors, total_time]
+}
+
+fn write_json_list<'a, I>(out: &mut dyn Write, stats: I) -> io::Result<()>
+    where I: Iterator<Item = &'a mut SyscallStats>
+{
+    for (i, s) in stats.enumerate() {
+        if i > 0 {
+            write![out, ","]?;
+        }
+        s.write_json(out)?;
+    }
+
+    Ok(())
+}
+
+fn errno_name(e: i32) -> String {
+    errno::name(e).unwrap_or(e.to_string())
+}
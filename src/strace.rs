@@ -0,0 +1,1067 @@
+// Copyright 2016 Jonathan Anderson <jonathan.anderson@mun.ca>
+//
+// This software was developed by BAE Systems, the University of Cambridge
+// Computer Laboratory, and Memorial University under DARPA/AFRL contract
+// FA8650-15-C-7558 ("CADETS"), as part of the DARPA Transparent Computing
+// (TC) research program.
+//
+// Licensed under the Apache License, Version 2.0,
+// <LICENSE-APACHE or http://apache.org/licenses/LICENSE-2.0>
+// or the MIT license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+
+extern crate nix;
+
+use self::nix::sys::time::TimeVal;
+use std::collections::{HashMap,HashSet};
+use std::io::BufRead;
+use std::net::{Ipv4Addr,Ipv6Addr};
+use std::str;
+use ::{Abi,ByteString,Error,Header,IODir,Record,RecordType,Result};
+use ::{errno,signal};
+
+/// `AT_FDCWD` on Linux
+const AT_FDCWD: i64 = -100;
+
+/// `MAXCOMLEN`: the longest command name that a header can hold
+const MAXCOMLEN: usize = 19;
+
+
+/// Converts the output of `strace -f -tt -T -xx` into ktrace records
+///
+/// Each process is introduced by a `ProcessCreation` record whose flags
+/// name its (Linux) ABI, just as FreeBSD traces Linux binaries: system call
+/// numbers are Linux's, while error and signal numbers are translated into
+/// FreeBSD's. Threads created by `clone(CLONE_THREAD)` are attributed to
+/// their process, and command names are taken from `execve(2)`.
+///
+/// Timestamps from `-t` or `-tt` are times of day (with midnight rollover
+/// handled), as strace doesn't log the date; `-ttt` gives full timestamps.
+/// Hex dumps from `-e read=` and `-e write=` become `GenericIO` records.
+#[derive(Default)]
+pub struct StraceImporter {
+    records: Vec<(Header, Result<Record>)>,
+    line: usize,
+
+    /// process (thread group) ID of each thread
+    processes: HashMap<u32, u32>,
+    commands: HashMap<u32, ByteString>,
+    abis: HashMap<u32, Abi>,
+
+    /// calls that have been `<unfinished ...>` on each thread
+    unfinished: HashMap<u32, Unfinished>,
+
+    /// where to insert I/O from a hex dump: the return record of the last
+    /// call (if it was an I/O call), with the descriptor and direction
+    io: Option<(usize, i32, IODir)>,
+
+    /// processes that have been introduced with a `ProcessCreation` record
+    introduced: HashSet<u32>,
+
+    /// the last timestamp, time of day and number of midnights seen
+    last: Option<TimeVal>,
+    last_time: i64,
+    days: i64,
+
+    numbers: HashMap<(Abi, String), u16>,
+}
+
+/// A system call that has started but not yet returned
+struct Unfinished {
+    name: String,
+    args: String,
+}
+
+
+/// Import a complete strace log.
+pub fn parse_strace(r: &mut dyn BufRead) -> Result<Vec<(Header, Result<Record>)>> {
+    let mut importer = StraceImporter::new();
+
+    for line in r.lines() {
+        importer.line(&line?);
+    }
+
+    Ok(importer.finish())
+}
+
+/// Whether the start of a file looks like an strace log.
+pub(crate) fn is_strace(data: &[u8]) -> bool {
+    let first = match data.split(|&c| c == b'\n').next() {
+        Some(line) => line,
+        None => return false,
+    };
+
+    match str::from_utf8(first) {
+        Ok(line) => {
+            let (_, _, body) = split_line(line);
+            body.starts_with("+++ ") || body.starts_with("--- ")
+                || body.starts_with("strace: ")
+                || (body.contains('(') && call_name(body).is_some())
+        },
+        Err(_) => false,
+    }
+}
+
+
+impl StraceImporter {
+    pub fn new() -> StraceImporter {
+        let mut numbers = HashMap::new();
+        for &abi in &[Abi::Linux, Abi::LinuxI386] {
+            for n in 0..512 {
+                if let Ok(name) = abi.syscall_name(n) {
+                    numbers.insert((abi, name), n);
+                }
+            }
+        }
+
+        StraceImporter { numbers, ..StraceImporter::default() }
+    }
+
+    /// Convert one line of strace output.
+    pub fn line(&mut self, line: &str) {
+        self.line += 1;
+
+        let (tid, time, body) = split_line(line);
+        let body = body.trim_end();
+
+        if let Some(dump) = body.strip_prefix("| ") {
+            return self.hexdump(dump);
+        }
+
+        if body.is_empty() || body.starts_with("strace: ")
+           || body.starts_with("Process ")
+        {
+            return;
+        }
+
+        let tid = tid.unwrap_or(0);
+        let time = match time {
+            Some(t) => self.timestamp(t),
+            None => self.last.unwrap_or(TimeVal { tv_sec: 0, tv_usec: 0 }),
+        };
+        self.last = Some(time);
+
+        if let Some(rest) = body.strip_prefix("[ Process PID=") {
+            // e.g., "[ Process PID=1234 runs in 32 bit mode. ]"
+            let pid = rest.split_whitespace().next().and_then(|p| p.parse().ok());
+            let abi = if rest.contains("32 bit") { Abi::LinuxI386 } else { Abi::Linux };
+            if let Some(pid) = pid {
+                let pid = self.process(pid);
+                self.abis.insert(pid, abi);
+                self.push(pid, time, Ok(Record::ProcessCreation { flags: abi.flags() }));
+            }
+            return;
+        }
+
+        self.io = None;
+
+        if let Some(signal) = body.strip_prefix("--- ") {
+            self.signal(tid, time, signal);
+        } else if body.starts_with("+++ ") {
+            self.unfinished.remove(&tid);
+            if self.process(tid) == tid {
+                self.push(tid, time, Ok(Record::ProcessDestruction));
+            }
+        } else if let Some(resumed) = body.strip_prefix("<... ") {
+            self.resumed(tid, time, resumed);
+        } else {
+            self.call(tid, time, body);
+        }
+    }
+
+    /// The records converted so far.
+    pub fn finish(self) -> Vec<(Header, Result<Record>)> {
+        self.records
+    }
+
+    fn call(&mut self, tid: u32, time: TimeVal, body: &str) {
+        let name = match call_name(body) {
+            Some(name) => name,
+            None => return self.error(tid, time, RecordType::SystemCall,
+                                      "unrecognised strace output"),
+        };
+
+        let rest = &body[name.len() + 1..];
+        if let Some(args) = rest.strip_suffix("<unfinished ...>") {
+            self.unfinished.insert(tid, Unfinished {
+                name: name.to_string(),
+                args: args.to_string(),
+            });
+            self.start(tid, time, name, args);
+            return;
+        }
+
+        let (args, result) = match close_paren(rest) {
+            Some(i) => (&rest[..i], rest[i + 1..].trim_start()),
+            None => return self.error(tid, time, RecordType::SystemCall,
+                                      "no end to system call arguments"),
+        };
+
+        self.start(tid, time, name, args);
+
+        // Without a resumed line, -T gives us the time of the return.
+        let end = match duration(result) {
+            Some(d) => {
+                let usec = time.tv_usec + d;
+                TimeVal {
+                    tv_sec: time.tv_sec + usec / 1_000_000,
+                    tv_usec: usec % 1_000_000,
+                }
+            },
+            None => time,
+        };
+
+        self.finish_call(tid, end, name, args, result);
+    }
+
+    fn resumed(&mut self, tid: u32, time: TimeVal, resumed: &str) {
+        // e.g., "<... read resumed>"..."..., 4096) = 12 <0.000010>"
+        let (name, rest) = match resumed.find(" resumed>") {
+            Some(i) => (&resumed[..i], &resumed[i + " resumed>".len()..]),
+            None => return self.error(tid, time, RecordType::SystemCallReturn,
+                                      "unrecognised strace output"),
+        };
+
+        let mut args = match self.unfinished.remove(&tid) {
+            Some(ref u) if u.name == name => u.args.clone(),
+            _ => String::new(),
+        };
+
+        let result = match close_paren(rest) {
+            Some(i) => {
+                args += &rest[..i];
+                rest[i + 1..].trim_start()
+            },
+            None => return self.error(tid, time, RecordType::SystemCallReturn,
+                                      "no end to system call arguments"),
+        };
+
+        self.finish_call(tid, time, name, &args, result);
+    }
+
+    /// Convert the start of a system call, including any paths it looks up.
+    fn start(&mut self, tid: u32, time: TimeVal, name: &str, args: &str) {
+        let pid = self.process(tid);
+        let abi = self.abis.get(&pid).cloned().unwrap_or(Abi::Linux);
+
+        let number = match self.numbers.get(&(abi, name.to_string())) {
+            Some(&n) => n,
+            None => return self.error(tid, time, RecordType::SystemCall,
+                format!["unknown {:?} system call: {}", abi, name]),
+        };
+
+        let args = split_args(args);
+        self.push(tid, time, Ok(Record::SystemCall {
+            number,
+            args: args.iter().map(|a| value(a)).collect(),
+        }));
+
+        if takes_path(name) {
+            for path in args.iter().filter_map(|a| string(a)) {
+                self.push(tid, time, Ok(Record::Namei(path.into())));
+            }
+        }
+    }
+
+    /// Convert the return from a system call.
+    fn finish_call(&mut self, tid: u32, time: TimeVal, name: &str, args: &str,
+                   result: &str)
+    {
+        let pid = self.process(tid);
+        let abi = self.abis.get(&pid).cloned().unwrap_or(Abi::Linux);
+
+        let code = match self.numbers.get(&(abi, name.to_string())) {
+            Some(&n) => n,
+            None => return self.error(tid, time, RecordType::SystemCallReturn,
+                format!["unknown {:?} system call: {}", abi, name]),
+        };
+
+        // e.g., "= 3 <0.000012>", "= -1 ENOENT (No such file...)" or "= ?"
+        let result = match result.strip_prefix("=") {
+            Some(r) => r.trim_start(),
+            None => return self.error(tid, time, RecordType::SystemCallReturn,
+                                      "no system call result"),
+        };
+
+        // The call never returned (e.g., exit or a successful execve).
+        if result.starts_with('?') {
+            return;
+        }
+
+        let mut tokens = result.split_whitespace();
+        let retval = tokens.next().map(value).unwrap_or(0);
+        let error = match tokens.next().filter(|t| is_errno(t)) {
+            Some(name) => match errno_number(name) {
+                Some(e) => e,
+                None => return self.error(tid, time, RecordType::SystemCallReturn,
+                    format!["no FreeBSD equivalent of {}", name]),
+            },
+            None => 0,
+        };
+
+        if error == 0 {
+            self.returned(tid, name, args, retval);
+        }
+
+        // Socket addresses are copied in or out before the call returns.
+        let addr = sockaddr_arg(name)
+                       .and_then(|i| split_args(args).get(i).and_then(|a| sockaddr(a)));
+        if let Some(content) = addr {
+            self.push(tid, time, Ok(Record::Struct {
+                name: "sockaddr".into(),
+                content,
+            }));
+        }
+
+        self.push(tid, time, Ok(Record::SystemCallReturn {
+            code,
+            eosys: 0,
+            error: error as u32,
+            retval,
+        }));
+
+        let fd = || split_args(args).first().map(|a| value(a) as i32);
+        self.io = match io_direction(name) {
+            Some(dir) => fd().map(|fd| (self.records.len() - 1, fd, dir)),
+            None => None,
+        };
+    }
+
+    /// Track the effects of a successful call on processes and threads.
+    fn returned(&mut self, tid: u32, name: &str, args: &str, retval: u64) {
+        let pid = self.process(tid);
+
+        match name {
+            "clone" | "clone3" | "fork" | "vfork" if retval != 0 => {
+                let child = retval as u32;
+                if args.contains("CLONE_THREAD") {
+                    self.processes.insert(child, pid);
+                } else {
+                    if let Some(command) = self.commands.get(&pid).cloned() {
+                        self.commands.insert(child, command);
+                    }
+                    if let Some(&abi) = self.abis.get(&pid) {
+                        self.abis.insert(child, abi);
+                    }
+                }
+            },
+
+            "execve" | "execveat" => {
+                let path = split_args(args).iter().filter_map(|a| string(a)).next();
+                if let Some(path) = path {
+                    let name = path.rsplit(|&b| b == b'/').next().unwrap_or(&[]);
+                    let command = &name[..name.len().min(MAXCOMLEN)];
+                    self.commands.insert(pid, command.into());
+                }
+            },
+
+            _ => {},
+        }
+    }
+
+    /// Convert a signal delivery, e.g.,
+    /// "--- SIGCHLD {si_signo=SIGCHLD, si_code=CLD_EXITED, ...} ---".
+    fn signal(&mut self, tid: u32, time: TimeVal, body: &str) {
+        let name = body.split_whitespace().next().unwrap_or("");
+        if !name.starts_with("SIG") {
+            return;     // e.g., "--- stopped by SIGSTOP ---"
+        }
+
+        let signo = match signal_number(name) {
+            Some(s) => s,
+            None => return self.error(tid, time, RecordType::Signal,
+                format!["no FreeBSD equivalent of {}", name]),
+        };
+
+        let code = body.find("si_code=")
+                       .map(|i| &body[i + "si_code=".len()..])
+                       .and_then(|c| c.split([',', '}']).next())
+                       .and_then(|c| c.trim().parse().ok())
+                       .unwrap_or(0);
+
+        self.push(tid, time, Ok(Record::Signal {
+            signo,
+            handler: 0,
+            code,
+            mask: vec![],
+        }));
+    }
+
+    /// Add a line of a hex dump to the I/O of the last call, e.g.,
+    /// "| 00000  72 6f 6f 74 3a 78 3a 30  3a 30 3a 72 6f 6f 74 3a  root:x:0:0:root: |".
+    fn hexdump(&mut self, dump: &str) {
+        let (index, fd, ref dir) = match self.io {
+            Some(ref io) => io.clone(),
+            None => return,
+        };
+
+        // After the offset: 16 bytes (and a gap) in 49 columns, then text.
+        let hex = dump.split_once("  ")
+                      .map(|(_, rest)| rest.get(..49).unwrap_or(rest))
+                      .unwrap_or("");
+        let bytes = hex.split_whitespace()
+                       .map_while(|b| u8::from_str_radix(b, 16).ok());
+
+        if let Some(&mut (_, Ok(Record::GenericIO{ref mut data, ..})))
+                = self.records.get_mut(index.wrapping_sub(1))
+        {
+            data.extend(bytes);
+            return;
+        }
+
+        let header = Header {
+            record_type: RecordType::GenericIO,
+            ..self.records[index].0.clone()
+        };
+        let record = Record::GenericIO { fd, rw: dir.clone(), data: bytes.collect() };
+
+        self.records.insert(index, (header, Ok(record)));
+        self.io = Some((index + 1, fd, dir.clone()));
+    }
+
+    /// The process that a thread belongs to.
+    fn process(&self, tid: u32) -> u32 {
+        self.processes.get(&tid).cloned().unwrap_or(tid)
+    }
+
+    /// Convert an strace time of day (or, with -ttt, epoch time).
+    fn timestamp(&mut self, time: &str) -> TimeVal {
+        let (whole, usec) = match time.split_once('.') {
+            Some((whole, frac)) => {
+                let digits = frac.chars().take(6).collect::<String>();
+                let usec = format!["{:0<6}", digits].parse().unwrap_or(0);
+                (whole, usec)
+            },
+            None => (time, 0),
+        };
+
+        if !whole.contains(':') {
+            return TimeVal { tv_sec: whole.parse().unwrap_or(0), tv_usec: usec };
+        }
+
+        let seconds = whole.split(':')
+                           .fold(0, |acc, x| acc * 60 + x.parse::<i64>().unwrap_or(0));
+
+        if seconds < self.last_time - 12 * 3600 {
+            self.days += 1;
+        }
+        self.last_time = seconds;
+
+        TimeVal { tv_sec: self.days * 86400 + seconds, tv_usec: usec }
+    }
+
+    fn push(&mut self, tid: u32, timestamp: TimeVal, record: Result<Record>) {
+        let pid = self.process(tid);
+
+        // Introduce each process with its ABI, as FreeBSD does.
+        if self.introduced.insert(pid)
+           && !matches!(record, Ok(Record::ProcessCreation{..}))
+        {
+            let abi = self.abis.get(&pid).cloned().unwrap_or(Abi::Linux);
+            self.push(tid, timestamp,
+                      Ok(Record::ProcessCreation { flags: abi.flags() }));
+        }
+
+        let record_type = match record {
+            Ok(ref r) => record_type(r),
+            Err(ref e) => e.record_type().cloned().unwrap_or(RecordType::UserData),
+        };
+
+        let header = Header {
+            length: record.as_ref().map(length).unwrap_or(0),
+            record_type,
+            pid,
+            command: self.commands.get(&pid).cloned().unwrap_or_default(),
+            timestamp,
+            tid: tid as usize,
+        };
+
+        // Errors give their line number rather than a position in a
+        // binary trace (and hex dumps may insert I/O records before them).
+        let record = record.map_err(|e| e.in_record(&header));
+
+        self.records.push((header, record));
+    }
+
+    fn error<S>(&mut self, tid: u32, time: TimeVal, t: RecordType, message: S)
+        where S: Into<String>
+    {
+        let message = format!["line {}: {}", self.line, message.into()];
+        self.push(tid, time, Err(Error::msg(message).of_type(&t)));
+    }
+}
+
+
+/// Split a line into its thread ID (with -f), time (with -t, -tt or -ttt)
+/// and body.
+fn split_line(line: &str) -> (Option<u32>, Option<&str>, &str) {
+    let mut rest = line.trim_start();
+    let mut tid = None;
+
+    if let Some(r) = rest.strip_prefix("[pid") {
+        // "[pid  1234] ..." (when strace writes to a terminal)
+        if let Some((pid, r)) = r.split_once(']') {
+            tid = pid.trim().parse().ok();
+            rest = r.trim_start();
+        }
+    } else if let Some((first, r)) = rest.split_once(char::is_whitespace) {
+        // "1234  ..." (when strace writes to a file)
+        if !first.is_empty() && first.bytes().all(|c| c.is_ascii_digit()) {
+            tid = first.parse().ok();
+            rest = r.trim_start();
+        }
+    }
+
+    let mut time = None;
+    if let Some((first, r)) = rest.split_once(' ') {
+        let is_time = first.starts_with(|c: char| c.is_ascii_digit())
+                      && first.bytes().all(|c| c.is_ascii_digit() || c == b':' || c == b'.');
+        if is_time {
+            time = Some(first);
+            rest = r.trim_start();
+        }
+    }
+
+    (tid, time, rest)
+}
+
+/// The name of the system call at the start of a line, if there is one.
+fn call_name(body: &str) -> Option<&str> {
+    let end = body.find('(')?;
+    let name = &body[..end];
+
+    if !name.is_empty()
+       && name.bytes().all(|c| c.is_ascii_alphanumeric() || c == b'_')
+    {
+        Some(name)
+    } else {
+        None
+    }
+}
+
+/// The position of the parenthesis that closes an argument list.
+fn close_paren(s: &str) -> Option<usize> {
+    let mut depth = 0;
+    let mut quoted = false;
+    let mut escaped = false;
+
+    for (i, c) in s.char_indices() {
+        if quoted {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => quoted = false,
+                _ => {},
+            }
+            continue;
+        }
+
+        match c {
+            '"' => quoted = true,
+            '(' | '[' | '{' => depth += 1,
+            ')' if depth == 0 => return Some(i),
+            ')' | ']' | '}' => depth -= 1,
+            _ => {},
+        }
+    }
+
+    None
+}
+
+/// Split an argument list at its top-level commas.
+fn split_args(args: &str) -> Vec<&str> {
+    let mut v = vec![];
+    let mut depth = 0;
+    let mut quoted = false;
+    let mut escaped = false;
+    let mut start = 0;
+
+    for (i, c) in args.char_indices() {
+        if quoted {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => quoted = false,
+                _ => {},
+            }
+            continue;
+        }
+
+        match c {
+            '"' => quoted = true,
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => depth -= 1,
+            ',' if depth == 0 => {
+                v.push(args[start..i].trim());
+                start = i + 1;
+            },
+            _ => {},
+        }
+    }
+
+    let last = args[start..].trim();
+    if !last.is_empty() {
+        v.push(last);
+    }
+
+    v
+}
+
+/// The numeric value of an argument or return value, if it has one.
+fn value(s: &str) -> u64 {
+    // Named arguments (e.g., clone's "child_stack=0x7f2c9bfff000")
+    let s = match s.split_once('=') {
+        Some((name, value)) if name.bytes().all(|c| c.is_ascii_alphanumeric() || c == b'_') => value,
+        _ => s,
+    };
+
+    let s = s.trim();
+    match s {
+        "NULL" => return 0,
+        "AT_FDCWD" => return AT_FDCWD as u64,
+        _ => {},
+    }
+
+    // Constants and flags, e.g., "SIGUSR1", "O_WRONLY|O_CREAT|O_TRUNC"
+    // or "O_RDONLY|0x800000".
+    if s.starts_with(|c: char| c.is_ascii_uppercase()) {
+        return s.split('|')
+                .map(|f| constant(f.trim()).unwrap_or_else(|| number(f)))
+                .fold(0, |flags, f| flags | f);
+    }
+
+    number(s)
+}
+
+/// The value of a number, which may be negative, hexadecimal or octal.
+fn number(s: &str) -> u64 {
+    let s = s.trim();
+    let (negative, digits) = match s.strip_prefix('-') {
+        Some(d) => (true, d),
+        None => (false, s),
+    };
+
+    // Ignore anything after the number, e.g., -y's "3</etc/passwd>".
+    let parse = |s: &str, radix| {
+        let end = s.find(|c: char| !c.is_digit(radix)).unwrap_or(s.len());
+        u64::from_str_radix(&s[..end], radix).ok()
+    };
+
+    let n = if let Some(hex) = digits.strip_prefix("0x") {
+        parse(hex, 16)
+    } else if digits.len() > 1 && digits.starts_with('0') {
+        parse(&digits[1..], 8)
+    } else {
+        parse(digits, 10)
+    };
+
+    match n {
+        Some(n) if negative => (n as i64).wrapping_neg() as u64,
+        Some(n) => n,
+        None => 0,
+    }
+}
+
+/// The value of a symbolic (generic, x86) Linux constant or flag.
+///
+/// Like other arguments, these keep their Linux values: a FreeBSD trace of
+/// a Linux process records the arguments that the process passed.
+fn constant(name: &str) -> Option<u64> {
+    // Real-time signals are numbered from Linux's SIGRTMIN (32).
+    if let Some(n) = name.strip_prefix("SIGRT_") {
+        return n.parse::<u64>().ok().map(|n| 32 + n);
+    }
+
+    Some(match name {
+        // open(2)
+        "O_RDONLY" | "O_LARGEFILE" => 0,
+        "O_WRONLY" => 0x0000_0001,
+        "O_RDWR" => 0x0000_0002,
+        "O_CREAT" => 0x0000_0040,
+        "O_EXCL" => 0x0000_0080,
+        "O_NOCTTY" => 0x0000_0100,
+        "O_TRUNC" => 0x0000_0200,
+        "O_APPEND" => 0x0000_0400,
+        "O_NONBLOCK" => 0x0000_0800,
+        "O_DSYNC" => 0x0000_1000,
+        "O_ASYNC" => 0x0000_2000,
+        "O_DIRECT" => 0x0000_4000,
+        "O_DIRECTORY" => 0x0001_0000,
+        "O_NOFOLLOW" => 0x0002_0000,
+        "O_NOATIME" => 0x0004_0000,
+        "O_CLOEXEC" => 0x0008_0000,
+        "O_SYNC" => 0x0010_1000,
+        "O_PATH" => 0x0020_0000,
+        "O_TMPFILE" => 0x0041_0000,
+
+        // socket(2)
+        "AF_UNSPEC" | "PF_UNSPEC" => 0,
+        "AF_UNIX" | "AF_LOCAL" | "PF_UNIX" | "PF_LOCAL" => 1,
+        "AF_INET" | "PF_INET" => 2,
+        "AF_INET6" | "PF_INET6" => 10,
+        "AF_NETLINK" | "PF_NETLINK" => 16,
+        "AF_PACKET" | "PF_PACKET" => 17,
+        "AF_ALG" | "PF_ALG" => 38,
+        "AF_VSOCK" | "PF_VSOCK" => 40,
+        "SOCK_STREAM" => 1,
+        "SOCK_DGRAM" => 2,
+        "SOCK_RAW" => 3,
+        "SOCK_RDM" => 4,
+        "SOCK_SEQPACKET" => 5,
+        "SOCK_NONBLOCK" => 0x0000_0800,
+        "SOCK_CLOEXEC" => 0x0008_0000,
+
+        // mmap(2) and mprotect(2)
+        "PROT_NONE" => 0,
+        "PROT_READ" => 0x1,
+        "PROT_WRITE" => 0x2,
+        "PROT_EXEC" => 0x4,
+        "PROT_GROWSDOWN" => 0x0100_0000,
+        "PROT_GROWSUP" => 0x0200_0000,
+        "MAP_SHARED" => 0x1,
+        "MAP_PRIVATE" => 0x2,
+        "MAP_SHARED_VALIDATE" => 0x3,
+        "MAP_FIXED" => 0x10,
+        "MAP_ANONYMOUS" | "MAP_ANON" => 0x20,
+        "MAP_32BIT" => 0x40,
+        "MAP_GROWSDOWN" => 0x100,
+        "MAP_DENYWRITE" => 0x800,
+        "MAP_EXECUTABLE" => 0x1000,
+        "MAP_LOCKED" => 0x2000,
+        "MAP_NORESERVE" => 0x4000,
+        "MAP_POPULATE" => 0x8000,
+        "MAP_NONBLOCK" => 0x1_0000,
+        "MAP_STACK" => 0x2_0000,
+        "MAP_HUGETLB" => 0x4_0000,
+        "MAP_SYNC" => 0x8_0000,
+        "MAP_FIXED_NOREPLACE" => 0x10_0000,
+
+        // fcntl(2) and access(2)
+        "F_DUPFD" => 0,
+        "F_GETFD" => 1,
+        "F_SETFD" => 2,
+        "F_GETFL" => 3,
+        "F_SETFL" => 4,
+        "F_GETLK" => 5,
+        "F_SETLK" => 6,
+        "F_SETLKW" => 7,
+        "F_SETOWN" => 8,
+        "F_GETOWN" => 9,
+        "F_OFD_GETLK" => 36,
+        "F_OFD_SETLK" => 37,
+        "F_OFD_SETLKW" => 38,
+        "F_DUPFD_CLOEXEC" => 1030,
+        "F_SETPIPE_SZ" => 1031,
+        "F_GETPIPE_SZ" => 1032,
+        "F_ADD_SEALS" => 1033,
+        "F_GET_SEALS" => 1034,
+        "FD_CLOEXEC" => 1,
+        "F_OK" => 0,
+        "X_OK" => 1,
+        "W_OK" => 2,
+        "R_OK" => 4,
+
+        // lseek(2) and the *at(2) calls
+        "SEEK_SET" => 0,
+        "SEEK_CUR" => 1,
+        "SEEK_END" => 2,
+        "AT_SYMLINK_NOFOLLOW" => 0x100,
+        "AT_REMOVEDIR" => 0x200,
+        "AT_SYMLINK_FOLLOW" => 0x400,
+        "AT_EMPTY_PATH" => 0x1000,
+
+        // clone(2)
+        "CLONE_VM" => 0x0000_0100,
+        "CLONE_FS" => 0x0000_0200,
+        "CLONE_FILES" => 0x0000_0400,
+        "CLONE_SIGHAND" => 0x0000_0800,
+        "CLONE_PIDFD" => 0x0000_1000,
+        "CLONE_PTRACE" => 0x0000_2000,
+        "CLONE_VFORK" => 0x0000_4000,
+        "CLONE_PARENT" => 0x0000_8000,
+        "CLONE_THREAD" => 0x0001_0000,
+        "CLONE_NEWNS" => 0x0002_0000,
+        "CLONE_SYSVSEM" => 0x0004_0000,
+        "CLONE_SETTLS" => 0x0008_0000,
+        "CLONE_PARENT_SETTID" => 0x0010_0000,
+        "CLONE_CHILD_CLEARTID" => 0x0020_0000,
+        "CLONE_DETACHED" => 0x0040_0000,
+        "CLONE_UNTRACED" => 0x0080_0000,
+        "CLONE_CHILD_SETTID" => 0x0100_0000,
+        "CLONE_NEWCGROUP" => 0x0200_0000,
+        "CLONE_NEWUTS" => 0x0400_0000,
+        "CLONE_NEWIPC" => 0x0800_0000,
+        "CLONE_NEWUSER" => 0x1000_0000,
+        "CLONE_NEWPID" => 0x2000_0000,
+        "CLONE_NEWNET" => 0x4000_0000,
+        "CLONE_IO" => 0x8000_0000,
+
+        // Signals (e.g., kill(2)'s argument or clone(2)'s exit signal)
+        "SIGHUP" => 1,
+        "SIGINT" => 2,
+        "SIGQUIT" => 3,
+        "SIGILL" => 4,
+        "SIGTRAP" => 5,
+        "SIGABRT" | "SIGIOT" => 6,
+        "SIGBUS" => 7,
+        "SIGFPE" => 8,
+        "SIGKILL" => 9,
+        "SIGUSR1" => 10,
+        "SIGSEGV" => 11,
+        "SIGUSR2" => 12,
+        "SIGPIPE" => 13,
+        "SIGALRM" => 14,
+        "SIGTERM" => 15,
+        "SIGSTKFLT" => 16,
+        "SIGCHLD" | "SIGCLD" => 17,
+        "SIGCONT" => 18,
+        "SIGSTOP" => 19,
+        "SIGTSTP" => 20,
+        "SIGTTIN" => 21,
+        "SIGTTOU" => 22,
+        "SIGURG" => 23,
+        "SIGXCPU" => 24,
+        "SIGXFSZ" => 25,
+        "SIGVTALRM" => 26,
+        "SIGPROF" => 27,
+        "SIGWINCH" => 28,
+        "SIGIO" | "SIGPOLL" => 29,
+        "SIGPWR" => 30,
+        "SIGSYS" => 31,
+        "SIGRTMIN" => 32,
+
+        _ => return None,
+    })
+}
+
+/// A FreeBSD `struct sockaddr` from strace's description of one, e.g.,
+/// `{sa_family=AF_INET, sin_port=htons(80), sin_addr=inet_addr("10.0.0.1")}`.
+fn sockaddr(arg: &str) -> Option<Vec<u8>> {
+    let fields = split_args(arg.strip_prefix('{')?.strip_suffix('}')?);
+    let field = |name: &str| {
+        fields.iter()
+              .filter_map(|f| f.split_once('='))
+              .find(|&(n, _)| n == name)
+              .map(|(_, v)| v.trim())
+    };
+
+    // Addresses are given as inet_addr("...") or inet_pton(AF_INET6, "...", ...).
+    let address = || {
+        let start = arg.find("inet_")?;
+        let quote = arg[start..].find('"')?;
+        String::from_utf8(string(&arg[start + quote..])?).ok()
+    };
+
+    // Ports and flow labels are given as htons(...) or htonl(...).
+    let network = |name: &str| {
+        let v = field(name)?;
+        let n = v.strip_prefix("htons(").or_else(|| v.strip_prefix("htonl("))?;
+        n.strip_suffix(')').map(number)
+    };
+
+    let mut data = match field("sa_family")? {
+        "AF_UNIX" | "AF_LOCAL" => {
+            // Abstract socket names are marked with '@'.
+            let path = field("sun_path")?;
+            let (abstract_name, path) = match path.strip_prefix('@') {
+                Some(p) => (true, p),
+                None => (false, path),
+            };
+
+            let mut data = vec![0, 1];
+            if abstract_name {
+                data.push(b'@');
+            }
+            data.extend(string(path)?);
+            data.push(0);
+            data
+        },
+
+        "AF_INET" => {
+            let addr = address()?.parse::<Ipv4Addr>().ok()?;
+
+            let mut data = vec![0, 2];
+            data.extend(&(network("sin_port")? as u16).to_be_bytes());
+            data.extend(&addr.octets());
+            data.extend(&[0; 8]);
+            data
+        },
+
+        "AF_INET6" => {
+            let addr = address()?.parse::<Ipv6Addr>().ok()?;
+            let flowinfo = network("sin6_flowinfo").unwrap_or(0) as u32;
+            let scope = field("sin6_scope_id").map(value).unwrap_or(0) as u32;
+
+            let mut data = vec![0, 28];
+            data.extend(&(network("sin6_port")? as u16).to_be_bytes());
+            data.extend(&flowinfo.to_be_bytes());
+            data.extend(&addr.octets());
+            data.extend(&scope.to_ne_bytes());
+            data
+        },
+
+        _ => return None,
+    };
+
+    data[0] = data.len().min(255) as u8;
+    Some(data)
+}
+
+/// The argument that holds the address of a socket call, if it has one.
+fn sockaddr_arg(name: &str) -> Option<usize> {
+    match name {
+        "connect" | "bind" | "accept" | "accept4" => Some(1),
+        "sendto" | "recvfrom" => Some(4),
+        _ => None,
+    }
+}
+
+/// The contents of a quoted (and possibly truncated) string argument.
+fn string(s: &str) -> Option<Vec<u8>> {
+    let s = s.strip_prefix('"')?;
+    let mut bytes = vec![];
+    let mut chars = s.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => return Some(bytes),
+            '\\' => match chars.next()? {
+                'x' => {
+                    let hex = chars.by_ref().take(2).collect::<String>();
+                    bytes.push(u8::from_str_radix(&hex, 16).ok()?);
+                },
+                'n' => bytes.push(b'\n'),
+                't' => bytes.push(b'\t'),
+                'r' => bytes.push(b'\r'),
+                'v' => bytes.push(0x0b),
+                'f' => bytes.push(0x0c),
+                d @ '0'..='7' => {
+                    let mut n = d.to_digit(8)?;
+                    let rest = chars.clone().take(2).take_while(|c| c.is_digit(8));
+                    for c in rest.collect::<Vec<_>>() {
+                        n = n * 8 + c.to_digit(8)?;
+                        chars.next();
+                    }
+                    bytes.push(n as u8);
+                },
+                c => {
+                    let mut buf = [0; 4];
+                    bytes.extend(c.encode_utf8(&mut buf).as_bytes());
+                },
+            },
+            c => {
+                let mut buf = [0; 4];
+                bytes.extend(c.encode_utf8(&mut buf).as_bytes());
+            },
+        }
+    }
+
+    None
+}
+
+/// The duration of a call in microseconds, from -T's "<0.000012>".
+fn duration(result: &str) -> Option<i64> {
+    let start = result.rfind('<')?;
+    let seconds = result[start + 1..].strip_suffix('>')?;
+    let (whole, frac) = seconds.split_once('.')?;
+
+    let frac = format!["{:0<6}", frac.chars().take(6).collect::<String>()];
+    Some(whole.parse::<i64>().ok()? * 1_000_000 + frac.parse::<i64>().ok()?)
+}
+
+fn is_errno(token: &str) -> bool {
+    token.len() > 1 && token.starts_with('E')
+        && token.bytes().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == b'_')
+}
+
+/// The FreeBSD equivalent of a Linux `errno` name.
+fn errno_number(name: &str) -> Option<i32> {
+    let name = match name {
+        "ERESTARTSYS" | "ERESTARTNOINTR" | "ERESTARTNOHAND"
+            | "ERESTART_RESTARTBLOCK" => "ERESTART",
+        "ENOTSUP" => "EOPNOTSUPP",
+        "EDEADLOCK" => "EDEADLK",
+        "ENODATA" => "ENOATTR",
+        name => name,
+    };
+
+    errno::number(name)
+}
+
+/// The FreeBSD equivalent of a Linux signal name.
+fn signal_number(name: &str) -> Option<i32> {
+    // FreeBSD's real-time signals start at 65.
+    if name == "SIGRTMIN" {
+        return Some(65);
+    }
+    if let Some(n) = name.strip_prefix("SIGRT_") {
+        return n.parse::<i32>().ok().map(|n| 65 + n);
+    }
+
+    let name = match name {
+        "SIGIOT" => "SIGABRT",
+        "SIGCLD" => "SIGCHLD",
+        "SIGPOLL" => "SIGIO",
+        name => name,
+    };
+
+    signal::number(name)
+}
+
+/// Linux system calls whose string arguments are paths.
+fn takes_path(name: &str) -> bool {
+    matches!(name,
+        "open" | "openat" | "openat2" | "creat" | "stat" | "lstat" |
+        "stat64" | "lstat64" | "newfstatat" | "fstatat64" | "statx" |
+        "statfs" | "access" | "faccessat" | "faccessat2" | "execve" |
+        "execveat" | "chdir" | "chroot" | "mkdir" | "mkdirat" | "rmdir" |
+        "unlink" | "unlinkat" | "rename" | "renameat" | "renameat2" |
+        "link" | "linkat" | "symlink" | "symlinkat" | "readlink" |
+        "readlinkat" | "chmod" | "fchmodat" | "chown" | "lchown" |
+        "fchownat" | "truncate" | "mknod" | "mknodat" | "utime" |
+        "utimes" | "utimensat" | "getxattr" | "lgetxattr" | "setxattr" |
+        "lsetxattr" | "listxattr" | "llistxattr" | "removexattr" |
+        "lremovexattr" | "mount" | "umount2" | "inotify_add_watch" |
+        "acct" | "swapon" | "swapoff" | "pivot_root")
+}
+
+/// The direction of I/O that strace can dump for a call.
+fn io_direction(name: &str) -> Option<IODir> {
+    match name {
+        "read" | "readv" | "pread64" | "preadv" | "preadv2" | "recvfrom" |
+        "recvmsg" | "recvmmsg" => Some(IODir::Read),
+        "write" | "writev" | "pwrite64" | "pwritev" | "pwritev2" | "sendto" |
+        "sendmsg" | "sendmmsg" => Some(IODir::Write),
+        _ => None,
+    }
+}
+
+fn record_type(record: &Record) -> RecordType {
+    match *record {
+        Record::SystemCall{..} => RecordType::SystemCall,
+        Record::SystemCallReturn{..} => RecordType::SystemCallReturn,
+        Record::Namei(..) => RecordType::Namei,
+        Record::Struct{..} => RecordType::Struct,
+        Record::GenericIO{..} => RecordType::GenericIO,
+        Record::Signal{..} => RecordType::Signal,
+        Record::ProcessCreation{..} => RecordType::ProcessCreation,
+        Record::ProcessDestruction => RecordType::ProcessDestruction,
+        _ => RecordType::UserData,
+    }
+}
+
+/// The size of a record's payload in a FreeBSD trace.
+fn length(record: &Record) -> usize {
+    match *record {
+        Record::SystemCall{ref args, ..} => 8 + 8 * args.len(),
+        Record::SystemCallReturn{..} => 16,
+        Record::Namei(ref path) => path.len(),
+        Record::Struct{ref name, ref content} => name.len() + 1 + content.len(),
+        Record::GenericIO{ref data, ..} => 8 + data.len(),
+        Record::Signal{ref mask, ..} => 20 + 4 * mask.len(),
+        Record::ProcessCreation{..} => 4,
+        _ => 0,
+    }
+}
@@ -0,0 +1,210 @@
+// Copyright 2016 Jonathan Anderson <jonathan.anderson@mun.ca>
+//
+// This software was developed by BAE Systems, the University of Cambridge
+// Computer Laboratory, and Memorial University under DARPA/AFRL contract
+// FA8650-15-C-7558 ("CADETS"), as part of the DARPA Transparent Computing
+// (TC) research program.
+//
+// Licensed under the Apache License, Version 2.0,
+// <LICENSE-APACHE or http://apache.org/licenses/LICENSE-2.0>
+// or the MIT license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::collections::HashMap;
+use std::fs::{self,File};
+use std::io::Write;
+use std::path::Path;
+use ::{CallTracker,Descriptor,Error,FdTracker,Header,IODir,Record,Result};
+
+
+/// The bytes read from or written to one descriptor, in order
+#[derive(Clone,Debug)]
+pub struct Stream {
+    pub pid: u32,
+    pub command: String,
+    pub fd: i32,
+    pub direction: IODir,
+
+    /// what the descriptor referred to, if known
+    pub descriptor: Option<Descriptor>,
+
+    /// the reconstructed data (less any truncated bytes)
+    pub data: Vec<u8>,
+
+    /// number of `KTR_GENIO` records that contributed to the stream
+    pub chunks: usize,
+
+    /// places where the kernel recorded less data than was transferred,
+    /// as (offset into `data`, number of bytes missing)
+    pub gaps: Vec<(usize, usize)>,
+}
+
+/// Reassembles `GenericIO` records into per-descriptor byte streams
+///
+/// The kernel only records the first `kern.ktrace.genio_size` bytes of each
+/// I/O operation, so data missing from a stream is recorded as a gap.
+#[derive(Default)]
+pub struct StreamExtractor {
+    fds: FdTracker,
+    calls: CallTracker,
+    streams: Vec<Stream>,
+
+    /// currently-open streams, indexed by (pid, fd, direction is read)
+    active: HashMap<(u32, i32, bool), usize>,
+
+    /// the stream and total length of the I/O recorded during each
+    /// thread's current call
+    last_io: HashMap<(u32, usize), (usize, usize)>,
+}
+
+
+impl Stream {
+    /// Total number of bytes missing from the stream.
+    pub fn truncated(&self) -> usize {
+        self.gaps.iter().map(|&(_, len)| len).sum()
+    }
+
+    /// A file name that identifies this stream within an extraction directory.
+    pub fn file_name(&self) -> String {
+        format!["{}-fd{}-{}.bin", self.pid, self.fd,
+                match self.direction {
+                    IODir::Read => "read",
+                    IODir::Write => "write",
+                }]
+    }
+}
+
+
+impl StreamExtractor {
+    pub fn new() -> StreamExtractor {
+        StreamExtractor::default()
+    }
+
+    /// Account for the next record in a trace.
+    pub fn record(&mut self, header: &Header, record: &Record) {
+        let descriptor = self.fds.record(header, record);
+        self.calls.record(header, record);
+
+        match *record {
+            Record::GenericIO{fd, ref rw, ref data} => {
+                let read = match *rw { IODir::Read => true, IODir::Write => false };
+                let key = (header.pid, fd, read);
+
+                // A descriptor that now refers to something else starts a
+                // new stream.
+                let index = match self.active.get(&key) {
+                    Some(&i) if self.streams[i].descriptor == descriptor => i,
+                    _ => {
+                        self.streams.push(Stream {
+                            pid: header.pid,
+                            command: header.command.to_string(),
+                            fd,
+                            direction: rw.clone(),
+                            descriptor,
+                            data: vec![],
+                            chunks: 0,
+                            gaps: vec![],
+                        });
+
+                        let i = self.streams.len() - 1;
+                        self.active.insert(key, i);
+                        i
+                    },
+                };
+
+                let stream = &mut self.streams[index];
+                stream.data.extend_from_slice(data);
+                stream.chunks += 1;
+
+                // A call such as readv(2) can record several chunks.
+                let thread = (header.pid, header.tid);
+                match self.last_io.get_mut(&thread) {
+                    Some(&mut (i, ref mut recorded)) if i == index => {
+                        *recorded += data.len();
+                    },
+                    _ => {
+                        self.last_io.insert(thread, (index, data.len()));
+                    },
+                }
+            },
+
+            Record::SystemCall{ref args, ..} => {
+                let closing = self.calls
+                                  .pending(header.pid, header.tid)
+                                  .is_some_and(|c| c.name() == "close");
+                self.last_io.remove(&(header.pid, header.tid));
+
+                // Later I/O on this descriptor is a new stream.
+                if let (true, Some(&fd)) = (closing, args.first()) {
+                    let (pid, fd) = (header.pid, fd as i32);
+                    self.active.retain(|&(p, f, _), _| p != pid || f != fd);
+                }
+            },
+
+            Record::SystemCallReturn{error, retval, ..} => {
+                let last = self.last_io.remove(&(header.pid, header.tid));
+
+                // Compare what was transferred with what was recorded.
+                if let Some((index, recorded)) = last {
+                    let transferred = retval as usize;
+                    if error == 0 && transferred > recorded {
+                        let stream = &mut self.streams[index];
+                        let offset = stream.data.len();
+                        stream.gaps.push((offset, transferred - recorded));
+                    }
+                }
+            },
+
+            _ => {},
+        }
+    }
+
+    /// The streams reassembled so far, in order of first I/O.
+    pub fn streams(&self) -> &[Stream] {
+        &self.streams
+    }
+
+    pub fn into_streams(self) -> Vec<Stream> {
+        self.streams
+    }
+}
+
+
+/// Write streams to a directory, one file per stream, along with an
+/// `index.txt` that describes each stream.
+pub fn write_streams<P>(streams: &[Stream], dir: P) -> Result<()>
+    where P: AsRef<Path>
+{
+    let dir = dir.as_ref();
+    fs::create_dir_all(dir).map_err(Error::io)?;
+
+    let mut index = File::create(dir.join("index.txt")).map_err(Error::io)?;
+
+    for (i, stream) in streams.iter().enumerate() {
+        // The same descriptor can be reused for several streams.
+        let name = format!["{:04}-{}", i, stream.file_name()];
+
+        File::create(dir.join(&name))
+            .and_then(|mut f| f.write_all(&stream.data))
+            .map_err(Error::io)?;
+
+        writeln![index, "{} pid={} command={} fd={} dir={:?} bytes={} \
+                         chunks={} truncated={} target={}",
+                 name, stream.pid, stream.command, stream.fd,
+                 stream.direction, stream.data.len(), stream.chunks,
+                 stream.truncated(),
+                 stream.descriptor.as_ref()
+                       .map(|d| d.to_string())
+                       .unwrap_or("<unknown>".to_string())]
+            .map_err(Error::io)?;
+
+        for &(offset, len) in &stream.gaps {
+            writeln![index, "    gap at offset {}: {} B not recorded",
+                     offset, len]
+                .map_err(Error::io)?;
+        }
+    }
+
+    Ok(())
+}
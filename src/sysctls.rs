@@ -0,0 +1,118 @@
+// Copyright 2016 Jonathan Anderson <jonathan.anderson@mun.ca>
+//
+// This software was developed by BAE Systems, the University of Cambridge
+// Computer Laboratory, and Memorial University under DARPA/AFRL contract
+// FA8650-15-C-7558 ("CADETS"), as part of the DARPA Transparent Computing
+// (TC) research program.
+//
+// Licensed under the Apache License, Version 2.0,
+// <LICENSE-APACHE or http://apache.org/licenses/LICENSE-2.0>
+// or the MIT license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::collections::BTreeMap;
+use std::io::{self,Write};
+use ::{ByteString,CallTracker,Header,Record,errno,sysctl};
+
+
+/// How a program used one sysctl OID
+#[derive(Clone,Debug,Default)]
+pub struct SysctlUse {
+    /// the system call used to access the OID (`__sysctl` or
+    /// `__sysctlbyname`)
+    pub syscall: String,
+
+    /// calls that only read the value, and calls that also set it
+    pub reads: u64,
+    pub writes: u64,
+
+    /// number of failures, by errno
+    pub errors: BTreeMap<i32, u64>,
+}
+
+/// The sysctl OIDs that each program in a trace queried or set
+///
+/// `KTR_SYSCTL` records are attributed to the `__sysctl` or
+/// `__sysctlbyname` call that emitted them, so that a call that passes a
+/// new value counts as a write.
+#[derive(Default)]
+pub struct Sysctls {
+    /// OID uses, by command and then MIB name
+    pub commands: BTreeMap<ByteString, BTreeMap<ByteString, SysctlUse>>,
+
+    calls: CallTracker,
+}
+
+
+impl Sysctls {
+    pub fn new() -> Sysctls {
+        Sysctls::default()
+    }
+
+    /// Account for the next record in a trace.
+    pub fn record(&mut self, header: &Header, record: &Record) {
+        let call = match self.calls.record(header, record) {
+            Some(call) => call,
+            None => return,
+        };
+
+        // __sysctl(name, namelen, old, oldlenp, new, newlen), and likewise
+        // for __sysctlbyname
+        let write = call.args.get(4).is_some_and(|&new| new != 0);
+        let syscall = call.name();
+
+        for name in &call.sysctls {
+            let u = self.commands.entry(header.command.clone())
+                                 .or_default()
+                                 .entry(name.clone())
+                                 .or_insert_with(|| SysctlUse {
+                                     syscall: syscall.clone(),
+                                     ..SysctlUse::default()
+                                 });
+
+            if write {
+                u.writes += 1;
+            } else {
+                u.reads += 1;
+            }
+
+            if !call.succeeded() {
+                *u.errors.entry(call.error).or_insert(0) += 1;
+            }
+        }
+    }
+
+    /// Write a table of the OIDs used by each command, with their types
+    /// and descriptions (when they are well-known).
+    pub fn write_table(&self, out: &mut dyn Write) -> io::Result<()> {
+        for (i, (command, oids)) in self.commands.iter().enumerate() {
+            if i > 0 {
+                writeln![out]?;
+            }
+
+            writeln![out, "{}:", command]?;
+            writeln![out, "  {:<32} {:>6} {:>6} {:<16} {:<20} description",
+                     "name", "reads", "writes", "syscall", "type"]?;
+
+            for (name, u) in oids {
+                let (value, description) = match name.to_str().and_then(sysctl::lookup) {
+                    Some(oid) => (oid.value.to_string(), oid.description),
+                    None => ("?".to_string(), ""),
+                };
+
+                let line = format!["  {:<32} {:>6} {:>6} {:<16} {:<20} {}",
+                                   name, u.reads, u.writes, u.syscall, value,
+                                   description];
+                writeln![out, "{}", line.trim_end()]?;
+
+                for (e, n) in &u.errors {
+                    writeln![out, "    {:<30} {:>6}",
+                             errno::name(*e).unwrap_or(e.to_string()), n]?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
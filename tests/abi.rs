@@ -0,0 +1,80 @@
+// Copyright 2016 Jonathan Anderson <jonathan.anderson@mun.ca>
+//
+// This software was developed by BAE Systems, the University of Cambridge
+// Computer Laboratory, and Memorial University under DARPA/AFRL contract
+// FA8650-15-C-7558 ("CADETS"), as part of the DARPA Transparent Computing
+// (TC) research program.
+//
+// Licensed under the Apache License, Version 2.0,
+// <LICENSE-APACHE or http://apache.org/licenses/LICENSE-2.0>
+// or the MIT license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+
+extern crate byteorder;
+extern crate ktrace;
+
+mod common;
+
+use byteorder::{BigEndian,LittleEndian};
+use ktrace::{Abi,AbiFamily,AbiFlags,AbiTracker,Record,RecordType};
+
+
+#[test]
+fn freebsd_amd64_flags() {
+    // elf64_freebsd_sysvec on FreeBSD 14/amd64
+    let flags = AbiFlags(0x9 | 0x200 | 0x10000 | 0x40000 | 0x80000 | 0x100000
+                         | 0x200000 | 0x400000 | 0x800000);
+
+    assert_eq!(flags.family(), AbiFamily::FreeBSD);
+    assert_eq!(flags.pointer_width(), Some(64));
+    assert_eq!(flags.abi(), Abi::FreeBSD);
+    assert_eq!(flags.to_string(),
+               "SV_ABI_FREEBSD|SV_LP64|SV_SHP|SV_TIMEKEEP|SV_ASLR|\
+                SV_RNG_SEED_VER|SV_SIG_DISCIGN|SV_SIG_WAITNDQ|SV_DSO_SIG");
+}
+
+#[test]
+fn linux_flags() {
+    // Linux binaries run by the Linuxulator on amd64 and on i386
+    let lp64 = AbiFlags(3 | 0x200 | 0x10000);
+    let ia32 = AbiFlags(3 | 0x100 | 0x4000 | 0x10000);
+
+    assert_eq!((lp64.family(), lp64.abi()), (AbiFamily::Linux, Abi::Linux));
+    assert_eq!((ia32.pointer_width(), ia32.abi()), (Some(32), Abi::LinuxI386));
+    assert!(ia32.contains(0x100 | 0x4000));
+    assert_eq!(Abi::LinuxI386.flags().abi(), Abi::LinuxI386);
+
+    // Unknown families and bits are kept.
+    let odd = AbiFlags(42 | 0x0100_0000);
+    assert_eq!(odd.family(), AbiFamily::Other(42));
+    assert_eq!(odd.names(), ["0x1000000"]);
+    assert_eq!(odd.to_string(), "SV_ABI(42)|0x1000000");
+}
+
+#[test]
+fn process_creation_sets_abi() {
+    let linux = AbiFlags(3 | 0x200);
+    let record = Record::ProcessCreation { flags: linux };
+
+    let (t, payload) = common::encode_as::<LittleEndian>(&record);
+    match Record::parse::<LittleEndian>(&payload, &t).unwrap() {
+        Record::ProcessCreation{flags} => assert_eq!(flags, linux),
+        r => panic!["expected a ProcessCreation record, got {:?}", r],
+    }
+
+    let (t, payload) = common::encode_as::<BigEndian>(&record);
+    match Record::parse::<BigEndian>(&payload, &t).unwrap() {
+        Record::ProcessCreation{flags} => assert_eq!(flags, linux),
+        r => panic!["expected a ProcessCreation record, got {:?}", r],
+    }
+
+    // Linux's system call 2 is open(2), FreeBSD's is fork(2).
+    let mut abis = AbiTracker::new();
+    let h = common::header(RecordType::ProcessCreation, 4);
+    assert_eq!(abis.abi(h.pid), Abi::FreeBSD);
+    assert_eq!(abis.record(&h, &record), Abi::Linux);
+    assert_eq!(abis.abi(h.pid).syscall_name(2).unwrap(), "open");
+    assert_eq!(Abi::FreeBSD.syscall_name(2).unwrap(), "fork");
+}
@@ -0,0 +1,141 @@
+// Copyright 2016 Jonathan Anderson <jonathan.anderson@mun.ca>
+//
+// This software was developed by BAE Systems, the University of Cambridge
+// Computer Laboratory, and Memorial University under DARPA/AFRL contract
+// FA8650-15-C-7558 ("CADETS"), as part of the DARPA Transparent Computing
+// (TC) research program.
+//
+// Licensed under the Apache License, Version 2.0,
+// <LICENSE-APACHE or http://apache.org/licenses/LICENSE-2.0>
+// or the MIT license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+
+extern crate ktrace;
+
+mod common;
+
+use common::{call,failed_call,namei};
+use ktrace::{Access,FileAccesses,Record,RecordType};
+
+const OPEN: u16 = 5;
+const UNLINK: u16 = 10;
+const CHDIR: u16 = 12;
+const RENAME: u16 = 128;
+const OPENAT: u16 = 499;
+
+const O_WRONLY: u64 = 0x0001;
+const O_CREAT: u64 = 0x0200;
+const O_DIRECTORY: u64 = 0x0002_0000;
+const ENOENT: u32 = 2;
+
+
+#[test]
+fn resolved_accesses() {
+    let mut files = FileAccesses::new();
+
+    // Before the working directory is known, relative paths stay relative.
+    failed_call(&mut files, OPEN, &[0x1000, 0], namei(&["./a/../b"]), ENOENT);
+
+    call(&mut files, CHDIR, &[0x1000], namei(&["/tmp"]), 0);
+    call(&mut files, OPEN, &[0x1000, O_WRONLY | O_CREAT], namei(&["log"]), 3);
+    call(&mut files, OPEN, &[0x1000, 0], namei(&["../etc/passwd"]), 4);
+    call(&mut files, RENAME, &[0x1000, 0x2000], namei(&["log", "log.0"]), 0);
+    failed_call(&mut files, UNLINK, &[0x1000], namei(&["/tmp/log.0"]), ENOENT);
+
+    // openat(2) resolves relative to its directory descriptor.
+    call(&mut files, OPEN, &[0x1000, O_DIRECTORY], namei(&["/var/db"]), 5);
+    call(&mut files, OPENAT, &[5, 0x1000, 0], namei(&["pkg/local.sqlite"]), 6);
+
+    let p = &files.processes[&100];
+    assert_eq!(p.command.to_string(), "test");
+
+    let paths: Vec<_> = p.files.keys().map(|k| k.to_string()).collect();
+    assert_eq!(paths, ["/etc/passwd", "/tmp", "/tmp/log", "/tmp/log.0", "/var/db",
+                       "/var/db/pkg/local.sqlite", "b"]);
+
+    let log = &p.files[&"/tmp/log".into()];
+    assert_eq!(log.accesses.iter().cloned().collect::<Vec<_>>(),
+               [Access::Write, Access::Create, Access::Rename]);
+    assert_eq!(log.syscalls.iter().collect::<Vec<_>>(), ["open", "rename"]);
+    assert_eq!((log.calls, log.failures), (2, 0));
+
+    let rotated = &p.files[&"/tmp/log.0".into()];
+    assert_eq!(rotated.accesses.iter().cloned().collect::<Vec<_>>(),
+               [Access::Unlink, Access::Rename]);
+    assert_eq!((rotated.calls, rotated.failures), (2, 1));
+
+    assert!(p.files[&"/tmp".into()].accesses.contains(&Access::Chdir));
+    assert!(p.files[&"/var/db/pkg/local.sqlite".into()].accesses.contains(&Access::Read));
+
+    // Failed lookups are kept.
+    assert_eq!(p.files[&"b".into()].failures, 1);
+
+    let mut text = vec![];
+    files.write_text(&mut text).unwrap();
+    let text = String::from_utf8(text).unwrap();
+    assert!(text.starts_with("PID 100 (test):\n"));
+    assert!(text.contains("  /tmp/log.0 [unlink,rename] (1 of 2 calls failed)\n"));
+}
+
+#[test]
+fn linux_open_flags() {
+    const LINUX_OPEN: u16 = 2;
+    const LINUX_OPENAT: u16 = 257;
+    const LINUX_O_CREAT: u64 = 0x0040;
+    const LINUX_O_TRUNC: u64 = 0x0200;
+    const AT_FDCWD: u64 = -100_i64 as u64;
+
+    let mut files = FileAccesses::new();
+    files.record(&common::header(RecordType::ProcessCreation, 4),
+                 &Record::ProcessCreation { flags: ktrace::Abi::Linux.flags() });
+
+    // Linux's O_TRUNC is FreeBSD's O_CREAT.
+    call(&mut files, LINUX_OPEN, &[0x1000, LINUX_O_TRUNC], namei(&["/tmp/a"]), 3);
+    call(&mut files, LINUX_OPENAT, &[AT_FDCWD, 0x1000, O_WRONLY | LINUX_O_CREAT],
+         namei(&["/tmp/b"]), 4);
+
+    let p = &files.processes[&100];
+    assert_eq!(p.files[&"/tmp/a".into()].accesses.iter().cloned().collect::<Vec<_>>(),
+               [Access::Read, Access::Write]);
+    assert_eq!(p.files[&"/tmp/b".into()].accesses.iter().cloned().collect::<Vec<_>>(),
+               [Access::Write, Access::Create]);
+}
+
+#[test]
+fn linux_at_calls() {
+    const LINUX_OPEN: u16 = 2;
+    const LINUX_O_DIRECTORY: u64 = 0x0001_0000;
+    const LINUX_NEWFSTATAT: u16 = 262;
+    const LINUX_RENAMEAT2: u16 = 316;
+    const LINUX_STATX: u16 = 332;
+    const LINUX_FACCESSAT2: u16 = 439;
+    const LINUX_FCHMODAT2: u16 = 452;
+
+    let mut files = FileAccesses::new();
+    files.record(&common::header(RecordType::ProcessCreation, 4),
+                 &Record::ProcessCreation { flags: ktrace::Abi::Linux.flags() });
+
+    // Each call's paths are relative to its directory descriptors.
+    call(&mut files, LINUX_OPEN, &[0x1000, LINUX_O_DIRECTORY], namei(&["/etc"]), 3);
+    call(&mut files, LINUX_OPEN, &[0x1000, LINUX_O_DIRECTORY], namei(&["/var"]), 4);
+    call(&mut files, LINUX_NEWFSTATAT, &[3, 0x1000, 0x2000, 0], namei(&["passwd"]), 0);
+    call(&mut files, LINUX_STATX, &[3, 0x1000, 0, 0x7ff, 0x2000], namei(&["group"]), 0);
+    call(&mut files, LINUX_FACCESSAT2, &[3, 0x1000, 4, 0], namei(&["shadow"]), 0);
+    call(&mut files, LINUX_FCHMODAT2, &[3, 0x1000, 0o644, 0], namei(&["hosts"]), 0);
+    call(&mut files, LINUX_RENAMEAT2, &[3, 0x1000, 4, 0x2000, 0],
+         namei(&["motd", "tmp/motd"]), 0);
+
+    let p = &files.processes[&100];
+    let accesses = |path: &str| {
+        p.files[&path.into()].accesses.iter().cloned().collect::<Vec<_>>()
+    };
+
+    for path in &["/etc/passwd", "/etc/group", "/etc/shadow"] {
+        assert_eq!(accesses(path), [Access::Stat]);
+    }
+    assert_eq!(accesses("/etc/hosts"), [Access::Attributes]);
+    assert_eq!(accesses("/etc/motd"), [Access::Rename]);
+    assert_eq!(accesses("/var/tmp/motd"), [Access::Rename]);
+}
@@ -0,0 +1,72 @@
+// Copyright 2016 Jonathan Anderson <jonathan.anderson@mun.ca>
+//
+// This software was developed by BAE Systems, the University of Cambridge
+// Computer Laboratory, and Memorial University under DARPA/AFRL contract
+// FA8650-15-C-7558 ("CADETS"), as part of the DARPA Transparent Computing
+// (TC) research program.
+//
+// Licensed under the Apache License, Version 2.0,
+// <LICENSE-APACHE or http://apache.org/licenses/LICENSE-2.0>
+// or the MIT license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+
+extern crate ktrace;
+extern crate nix;
+
+mod common;
+
+use ktrace::{ChromeTrace,Record,RecordType};
+use nix::sys::time::TimeVal;
+
+const READ: u16 = 3;
+const SELECT: u16 = 93;
+
+
+/// Feed `chrome` a record from `usec` µs after the first second.
+fn at(chrome: &mut ChromeTrace<Vec<u8>>, usec: i64, record: Record) {
+    let mut h = common::header(RecordType::SystemCall, 0);
+    h.timestamp = TimeVal { tv_sec: 1, tv_usec: usec };
+    chrome.record(&h, &record).unwrap();
+}
+
+#[test]
+fn slices_and_instants() {
+    let mut chrome = ChromeTrace::new(vec![]).unwrap();
+
+    // A return whose call began before tracing did
+    at(&mut chrome, 10, Record::SystemCallReturn { code: READ, eosys: 0, error: 4, retval: !0 });
+
+    // select(2), with the thread off the CPU for part of it
+    at(&mut chrome, 100, Record::SystemCall { number: SELECT, args: vec![4, 0x1000] });
+    at(&mut chrome, 150, Record::ContextSwitch { out: true, user: false, message: "select".into() });
+    at(&mut chrome, 400, Record::ContextSwitch { out: false, user: false, message: "".into() });
+    at(&mut chrome, 450, Record::SystemCallReturn { code: SELECT, eosys: 0, error: 0, retval: 1 });
+
+    at(&mut chrome, 500, Record::PageFault { virtual_address: 0x1000, fault_type: 1 });
+    at(&mut chrome, 520, Record::PageFaultEnd { result: 0 });
+
+    let json = String::from_utf8(chrome.finish().unwrap()).unwrap();
+    let events: Vec<_> = json.lines().collect();
+
+    assert_eq!(events, [
+        "{\"displayTimeUnit\":\"ns\",\"traceEvents\":[",
+        "{\"name\":\"process_name\",\"ph\":\"M\",\"pid\":100,\"tid\":100100,\
+          \"args\":{\"name\":\"test (100)\"}},",
+        "{\"name\":\"thread_name\",\"ph\":\"M\",\"pid\":100,\"tid\":100100,\
+          \"args\":{\"name\":\"test 100100\"}},",
+        "{\"name\":\"read (return)\",\"cat\":\"syscall\",\"ph\":\"i\",\"s\":\"t\",\
+          \"ts\":1000010,\"pid\":100,\"tid\":100100,\
+          \"args\":{\"args\":\"\",\"retval\":\"0xffffffffffffffff\",\"error\":\"EINTR\"}},",
+        "{\"name\":\"off-CPU: select\",\"cat\":\"sched\",\"ph\":\"X\",\
+          \"ts\":1000150,\"dur\":250,\"pid\":100,\"tid\":100100,\"args\":{}},",
+        "{\"name\":\"select\",\"cat\":\"syscall\",\"ph\":\"X\",\
+          \"ts\":1000100,\"dur\":350,\"pid\":100,\"tid\":100100,\
+          \"args\":{\"args\":\"0x4, 0x1000\",\"retval\":\"0x1\",\"error\":\"0\"}},",
+        "{\"name\":\"page fault\",\"cat\":\"fault\",\"ph\":\"X\",\
+          \"ts\":1000500,\"dur\":20,\"pid\":100,\"tid\":100100,\
+          \"args\":{\"address\":\"0x1000\",\"type\":\"VM_PROT_READ\",\"result\":\"KERN_SUCCESS\"}}",
+        "]}",
+    ]);
+}
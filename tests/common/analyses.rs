@@ -0,0 +1,151 @@
+// Copyright 2016 Jonathan Anderson <jonathan.anderson@mun.ca>
+//
+// This software was developed by BAE Systems, the University of Cambridge
+// Computer Laboratory, and Memorial University under DARPA/AFRL contract
+// FA8650-15-C-7558 ("CADETS"), as part of the DARPA Transparent Computing
+// (TC) research program.
+//
+// Licensed under the Apache License, Version 2.0,
+// <LICENSE-APACHE or http://apache.org/licenses/LICENSE-2.0>
+// or the MIT license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+
+// The analyses that the no-panic tests and the fuzz targets run, from
+// records to output: a new analysis needs only a new entry here.
+
+use byteorder::ByteOrder;
+use ktrace::{Entry,Error,Header,Record};
+use std::io;
+
+
+/// The records that a foreign importer produces with FreeBSD equivalents
+/// (displaying the others).
+pub fn common(entries: Vec<Entry>) -> Vec<(Header, Result<Record, Error>)> {
+    let mut records = vec![];
+    for entry in entries {
+        match entry {
+            Entry::Common(header, record) => records.push((header, record)),
+            Entry::Specific{record, ..} => {
+                let _ = record.map(|extra| extra.to_string());
+            },
+        }
+    }
+    records
+}
+
+/// Run every analysis over the records that could be decoded.
+pub fn analyze<E: ByteOrder>(records: Vec<(Header, Result<Record, Error>)>) {
+    let decoded: Vec<_> = records.iter()
+                                 .filter_map(|(h, r)| r.as_ref().ok().map(|r| (h, r)))
+                                 .collect();
+
+    for analysis in analyses::<E>() {
+        analysis(&decoded).unwrap();
+    }
+}
+
+/// Run an analysis over decoded records and write out its results.
+pub type Analysis = fn(&[(&Header, &Record)]) -> io::Result<()>;
+
+/// Every analysis, reading structures (e.g., in `annotate`) with byte order `E`
+pub fn analyses<E: ByteOrder>() -> Vec<Analysis> {
+    vec![
+        |records| {
+            for &(_, r) in records {
+                let _ = r.to_string();
+            }
+            Ok(())
+        },
+
+        |records| {
+            let mut stats = ktrace::Stats::new();
+            for &(h, r) in records {
+                stats.record(h, r);
+            }
+            stats.write_table(&mut io::sink(), true)?;
+            stats.write_json(&mut io::sink())
+        },
+
+        |records| {
+            let mut streams = ktrace::StreamExtractor::new();
+            for &(h, r) in records {
+                streams.record(h, r);
+            }
+            let _ = streams.streams().iter().map(ktrace::Stream::truncated).sum::<usize>();
+            Ok(())
+        },
+
+        |records| {
+            let mut graph = ktrace::ProvenanceGraph::new();
+            for &(h, r) in records {
+                graph.record(h, r);
+            }
+            graph.write_dot(&mut io::sink())?;
+            graph.write_json_lines(&mut io::sink())
+        },
+
+        |records| {
+            let mut chrome = ktrace::ChromeTrace::new(io::sink())?;
+            for &(h, r) in records {
+                chrome.record(h, r)?;
+            }
+            chrome.finish().map(|_| ())
+        },
+
+        |records| {
+            let mut pcapng = ktrace::PcapNg::new(io::sink())?;
+            for &(h, r) in records {
+                pcapng.record(h, r)?;
+            }
+            pcapng.finish().map(|_| ())
+        },
+
+        |records| {
+            let mut offcpu = ktrace::OffCpu::new();
+            for &(h, r) in records {
+                offcpu.record(h, r);
+            }
+            offcpu.write_table(&mut io::sink())?;
+            offcpu.write_folded(&mut io::sink())
+        },
+
+        |records| {
+            let mut faults = ktrace::PageFaults::new();
+            for &(h, r) in records {
+                faults.record(h, r);
+            }
+            faults.write_table(&mut io::sink())
+        },
+
+        |records| {
+            let mut sysctls = ktrace::Sysctls::new();
+            for &(h, r) in records {
+                sysctls.record(h, r);
+            }
+            sysctls.write_table(&mut io::sink())
+        },
+
+        |records| {
+            let mut files = ktrace::FileAccesses::new();
+            for &(h, r) in records {
+                files.record(h, r);
+            }
+            files.write_text(&mut io::sink())?;
+            files.write_json_lines(&mut io::sink())
+        },
+
+        |records| {
+            let decoded = records.iter().cloned();
+            ktrace::TraceDiff::new(decoded.clone(), decoded, &Default::default())
+                              .write_text(&mut io::sink())
+        },
+
+        |records| {
+            let owned = records.iter().map(|&(h, r)| (h.clone(), Ok(r.clone())));
+            for _ in ktrace::annotate::<E, _>(owned) {}
+            Ok(())
+        },
+    ]
+}
@@ -0,0 +1,334 @@
+// Copyright 2016 Jonathan Anderson <jonathan.anderson@mun.ca>
+//
+// This software was developed by BAE Systems, the University of Cambridge
+// Computer Laboratory, and Memorial University under DARPA/AFRL contract
+// FA8650-15-C-7558 ("CADETS"), as part of the DARPA Transparent Computing
+// (TC) research program.
+//
+// Licensed under the Apache License, Version 2.0,
+// <LICENSE-APACHE or http://apache.org/licenses/LICENSE-2.0>
+// or the MIT license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+
+// Helpers for building binary traces in tests.
+
+#![allow(dead_code)]
+
+extern crate byteorder;
+extern crate nix;
+extern crate proptest;
+
+use self::byteorder::{BigEndian,ByteOrder,LittleEndian};
+use ktrace::{AbiFlags,Descriptor,FdTracker,FileAccesses,Header,IODir,PageFaultInterval};
+use ktrace::{PageFaults,ProvenanceGraph,Record,RecordType,StreamExtractor};
+use self::nix::sys::time::TimeVal;
+use self::proptest::prelude::*;
+
+
+/// A header for a record with a `length`-byte payload.
+pub fn header(record_type: RecordType, length: usize) -> Header {
+    Header {
+        length,
+        record_type,
+        pid: 100,
+        command: "test".into(),
+        timestamp: TimeVal { tv_sec: 1000, tv_usec: 0 },
+        tid: 100100,
+    }
+}
+
+/// Records of every type, with a bias towards real system call numbers
+/// and small arguments (e.g., file descriptors)
+pub fn record() -> impl Strategy<Value = Record> {
+    prop_oneof![
+        (syscall(), prop::collection::vec(word(), 0..8))
+            .prop_map(|(number, args)| Record::SystemCall { number, args }),
+        (syscall(), any::<u16>(), prop_oneof![Just(0), any::<u32>()], word())
+            .prop_map(|(code, eosys, error, retval)| {
+                Record::SystemCallReturn { code, eosys, error, retval }
+            }),
+        prop::collection::vec(any::<u8>(), 0..64).prop_map(|p| Record::Namei(p.into())),
+        (0..16_i32, any::<bool>(), prop::collection::vec(any::<u8>(), 0..64))
+            .prop_map(|(fd, write, data)| Record::GenericIO {
+                fd,
+                rw: if write { IODir::Write } else { IODir::Read },
+                data,
+            }),
+        (any::<i32>(), any::<u64>(), any::<i32>(),
+         prop::collection::vec(any::<u32>(), 4))
+            .prop_map(|(signo, handler, code, mask)| Record::Signal {
+                signo,
+                handler,
+                code,
+                mask,
+            }),
+        (any::<bool>(), any::<bool>(), c_string(0..8))
+            .prop_map(|(out, user, message)| {
+                Record::ContextSwitch { out, user, message: message.into() }
+            }),
+        prop::collection::vec(any::<u8>(), 0..64).prop_map(Record::UserData),
+        ("[a-z_]{1,16}", prop::collection::vec(any::<u8>(), 0..64))
+            .prop_map(|(name, content)| Record::Struct { name: name.into(), content }),
+        c_string(1..64).prop_map(|name| Record::Sysctl(name.into())),
+        any::<u32>().prop_map(|flags| Record::ProcessCreation { flags: AbiFlags(flags) }),
+        Just(Record::ProcessDestruction),
+        (any::<u64>(), any::<u32>())
+            .prop_map(|(virtual_address, fault_type)| {
+                Record::PageFault { virtual_address, fault_type }
+            }),
+        any::<u32>().prop_map(|result| Record::PageFaultEnd { result }),
+    ]
+}
+
+/// Bytes that need not be UTF-8 but contain no NUL (e.g., a command name)
+pub fn c_string(len: ::std::ops::Range<usize>) -> impl Strategy<Value = Vec<u8>> {
+    prop::collection::vec(1..=255_u8, len)
+}
+
+fn syscall() -> impl Strategy<Value = u16> {
+    prop_oneof![0..600_u16, any::<u16>()]
+}
+
+fn word() -> impl Strategy<Value = u64> {
+    prop_oneof![0..16_u64, any::<u64>()]
+}
+
+/// Append a header and payload to a (little-endian) trace.
+pub fn append(trace: &mut Vec<u8>, header: &Header, payload: &[u8]) {
+    append_as::<LittleEndian>(trace, header, payload)
+}
+
+/// Append a header and payload to a trace with byte order `E`.
+pub fn append_as<E: ByteOrder>(trace: &mut Vec<u8>, header: &Header, payload: &[u8]) {
+    trace.extend_from_slice(&header.to_bytes::<E>());
+    trace.extend_from_slice(payload);
+}
+
+/// Encode a record as a (little-endian, LP64) `ktrace` payload.
+pub fn encode(record: &Record) -> (RecordType, Vec<u8>) {
+    encode_as::<LittleEndian>(record)
+}
+
+/// Encode a record as an LP64 `ktrace` payload with byte order `E`.
+pub fn encode_as<E: ByteOrder>(record: &Record) -> (RecordType, Vec<u8>) {
+    let mut v = vec![];
+
+    let t = match *record {
+        Record::Drop => panic!["dropped records have no encoding"],
+
+        Record::SystemCall{number, ref args} => {
+            put_u16::<E>(&mut v, number);
+            put_u16::<E>(&mut v, args.len() as u16);
+            put_u32::<E>(&mut v, 0);
+            for &arg in args {
+                put_u64::<E>(&mut v, arg);
+            }
+            RecordType::SystemCall
+        },
+
+        Record::SystemCallReturn{code, eosys, error, retval} => {
+            put_u16::<E>(&mut v, code);
+            put_u16::<E>(&mut v, eosys);
+            put_u32::<E>(&mut v, error);
+            put_u64::<E>(&mut v, retval);
+            RecordType::SystemCallReturn
+        },
+
+        Record::Namei(ref path) => {
+            v.extend_from_slice(path.as_bytes());
+            RecordType::Namei
+        },
+
+        Record::GenericIO{fd, ref rw, ref data} => {
+            put_u32::<E>(&mut v, fd as u32);
+            put_u32::<E>(&mut v, match *rw { IODir::Read => 0, IODir::Write => 1 });
+            v.extend_from_slice(data);
+            RecordType::GenericIO
+        },
+
+        Record::Signal{signo, handler, code, ref mask} => {
+            put_u32::<E>(&mut v, signo as u32);
+            put_u32::<E>(&mut v, 0);
+            put_u64::<E>(&mut v, handler);
+            put_u32::<E>(&mut v, code as u32);
+            for &word in mask {
+                put_u32::<E>(&mut v, word);
+            }
+            RecordType::Signal
+        },
+
+        Record::ContextSwitch{out, user, ref message} => {
+            put_u32::<E>(&mut v, out as u32);
+            put_u32::<E>(&mut v, user as u32);
+            v.extend_from_slice(message.as_bytes());
+            RecordType::ContextSwitch
+        },
+
+        Record::UserData(ref data) => {
+            v.extend_from_slice(data);
+            RecordType::UserData
+        },
+
+        Record::Struct{ref name, ref content} => {
+            v.extend_from_slice(name.as_bytes());
+            v.push(0);
+            v.extend_from_slice(content);
+            RecordType::Struct
+        },
+
+        Record::Sysctl(ref name) => {
+            v.extend_from_slice(name.as_bytes());
+            v.push(0);
+            RecordType::Sysctl
+        },
+
+        Record::ProcessCreation{flags} => {
+            put_u32::<E>(&mut v, flags.bits());
+            RecordType::ProcessCreation
+        },
+
+        Record::ProcessDestruction => RecordType::ProcessDestruction,
+
+        Record::CapabilityFailure(_) => {
+            panic!["encode capability failures with capfail()"]
+        },
+
+        Record::PageFault{virtual_address, fault_type} => {
+            put_u64::<E>(&mut v, virtual_address);
+            put_u32::<E>(&mut v, fault_type);
+            put_u32::<E>(&mut v, 0);
+            RecordType::PageFault
+        },
+
+        Record::PageFaultEnd{result} => {
+            put_u32::<E>(&mut v, result);
+            RecordType::PageFaultEnd
+        },
+    };
+
+    (t, v)
+}
+
+/// Anything that takes records one at a time (e.g., a tracker or analysis)
+pub trait Sink {
+    type Output;
+    fn record(&mut self, header: &Header, record: &Record) -> Self::Output;
+}
+
+/// Feed a successful call (and any records logged during it) to `sink`,
+/// giving what it makes of the return.
+pub fn call<S: Sink>(sink: &mut S, number: u16, args: &[u64], during: Vec<Record>,
+                     retval: u64) -> S::Output
+{
+    feed(sink, number, args, during, 0, retval)
+}
+
+/// Feed a call that fails with `error` to `sink`.
+pub fn failed_call<S: Sink>(sink: &mut S, number: u16, args: &[u64], during: Vec<Record>,
+                            error: u32) -> S::Output
+{
+    feed(sink, number, args, during, error, !0)
+}
+
+fn feed<S: Sink>(sink: &mut S, number: u16, args: &[u64], during: Vec<Record>,
+                 error: u32, retval: u64) -> S::Output
+{
+    let mut records = vec![Record::SystemCall { number, args: args.to_vec() }];
+    records.extend(during);
+    records.push(Record::SystemCallReturn { code: number, eosys: 0, error, retval });
+
+    records.iter()
+           .map(|r| sink.record(&header(RecordType::SystemCall, 0), r))
+           .last()
+           .unwrap()
+}
+
+/// `Namei` records for the paths that a call looks up
+pub fn namei(paths: &[&str]) -> Vec<Record> {
+    paths.iter().map(|&p| Record::Namei(p.into())).collect()
+}
+
+/// A FreeBSD `struct sockaddr_in`
+pub fn sockaddr_in(addr: [u8; 4], port: u16) -> Record {
+    let mut content = vec![16, 2, 0, 0];
+    BigEndian::write_u16(&mut content[2..4], port);
+    content.extend_from_slice(&addr);
+    content.extend_from_slice(&[0; 8]);
+
+    Record::Struct { name: "sockaddr".into(), content }
+}
+
+/// Records, collected to be exported later
+impl Sink for Vec<Record> {
+    type Output = ();
+    fn record(&mut self, _: &Header, record: &Record) {
+        self.push(record.clone());
+    }
+}
+
+impl<E: ByteOrder> Sink for FdTracker<E> {
+    type Output = Option<Descriptor>;
+    fn record(&mut self, header: &Header, record: &Record) -> Option<Descriptor> {
+        FdTracker::record(self, header, record)
+    }
+}
+
+impl Sink for FileAccesses {
+    type Output = ();
+    fn record(&mut self, header: &Header, record: &Record) {
+        FileAccesses::record(self, header, record)
+    }
+}
+
+impl Sink for PageFaults {
+    type Output = Option<PageFaultInterval>;
+    fn record(&mut self, header: &Header, record: &Record) -> Option<PageFaultInterval> {
+        PageFaults::record(self, header, record)
+    }
+}
+
+impl Sink for ProvenanceGraph {
+    type Output = ();
+    fn record(&mut self, header: &Header, record: &Record) {
+        ProvenanceGraph::record(self, header, record)
+    }
+}
+
+impl Sink for StreamExtractor {
+    type Output = ();
+    fn record(&mut self, header: &Header, record: &Record) {
+        StreamExtractor::record(self, header, record)
+    }
+}
+
+/// Encode a `struct ktr_cap_fail` with `words` 64-bit words per
+/// `cap_rights_t`.
+pub fn capfail(cap_type: u32, words: &[u64]) -> Vec<u8> {
+    let mut v = vec![];
+    put_u32::<LittleEndian>(&mut v, cap_type);
+    put_u32::<LittleEndian>(&mut v, 0);
+    for &w in words {
+        put_u64::<LittleEndian>(&mut v, w);
+    }
+    v
+}
+
+fn put_u16<E: ByteOrder>(v: &mut Vec<u8>, x: u16) {
+    let mut buf = [0; 2];
+    E::write_u16(&mut buf, x);
+    v.extend_from_slice(&buf);
+}
+
+fn put_u32<E: ByteOrder>(v: &mut Vec<u8>, x: u32) {
+    let mut buf = [0; 4];
+    E::write_u32(&mut buf, x);
+    v.extend_from_slice(&buf);
+}
+
+fn put_u64<E: ByteOrder>(v: &mut Vec<u8>, x: u64) {
+    let mut buf = [0; 8];
+    E::write_u64(&mut buf, x);
+    v.extend_from_slice(&buf);
+}
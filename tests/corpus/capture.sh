@@ -0,0 +1,47 @@
+#!/bin/sh
+#
+# Capture a set of small traces, with reference kdump output, covering every
+# ktrace record type. Run on the FreeBSD system to be added to the corpus.
+
+set -e
+
+dir="$(uname -r | sed 's/-.*//')-$(uname -p)"
+mkdir -p "$dir"
+
+tmp=$(mktemp -d)
+trap 'rm -rf "$tmp"' EXIT
+
+# A program that violates capability mode
+cat > "$tmp/capfail.c" <<'CAPFAIL'
+#include <sys/capsicum.h>
+#include <fcntl.h>
+
+int
+main(void)
+{
+	cap_enter();
+	return (open("/etc/passwd", O_RDONLY) == -1 ? 0 : 1);
+}
+CAPFAIL
+cc -o "$tmp/capfail" "$tmp/capfail.c"
+
+capture() {
+	name=$1
+	points=$2
+	shift 2
+
+	ktrace -i -f "$dir/$name.ktrace" -t "$points" "$@" > /dev/null || true
+	kdump -H -f "$dir/$name.ktrace" > "$dir/$name.kdump"
+}
+
+capture io      cin  cat /etc/hosts
+capture struct  cnt  sh -c 'stat /etc/hosts; nc -z 127.0.0.1 22'
+capture sysctl  cy   sysctl kern.ostype
+capture signal  cs   sh -c 'trap "true" USR1; kill -USR1 $$'
+capture csw     cw   sleep 0.1
+capture user    cu   env LD_UTRACE=1 true
+capture capfail cp   "$tmp/capfail"
+capture fault   f    true
+capture fork    c    sh -c 'true & wait'
+
+echo "captured traces in $dir"
@@ -0,0 +1,95 @@
+// Copyright 2016 Jonathan Anderson <jonathan.anderson@mun.ca>
+//
+// This software was developed by BAE Systems, the University of Cambridge
+// Computer Laboratory, and Memorial University under DARPA/AFRL contract
+// FA8650-15-C-7558 ("CADETS"), as part of the DARPA Transparent Computing
+// (TC) research program.
+//
+// Licensed under the Apache License, Version 2.0,
+// <LICENSE-APACHE or http://apache.org/licenses/LICENSE-2.0>
+// or the MIT license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+
+extern crate ktrace;
+extern crate proptest;
+
+mod common;
+
+use ktrace::{Change,DiffOptions,Header,Record,RecordType,TraceDiff};
+use proptest::prelude::*;
+
+
+/// A single thread's calls to each of `numbers`, all of which succeed.
+fn calls(numbers: &[u16]) -> Vec<(Header, Record)> {
+    numbers.iter()
+           .flat_map(|&number| vec![
+               (common::header(RecordType::SystemCall, 0),
+                Record::SystemCall { number, args: vec![] }),
+               (common::header(RecordType::SystemCallReturn, 0),
+                Record::SystemCallReturn { code: number, eosys: 0, error: 0, retval: 0 }),
+           ])
+           .collect()
+}
+
+/// Length of the longest common subsequence of `a` and `b`.
+fn lcs(a: &[u16], b: &[u16]) -> usize {
+    let mut table = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for i in 0..a.len() {
+        for j in 0..b.len() {
+            table[i + 1][j + 1] = if a[i] == b[j] {
+                table[i][j] + 1
+            } else {
+                table[i][j + 1].max(table[i + 1][j])
+            };
+        }
+    }
+
+    table[a.len()][b.len()]
+}
+
+/// getpid, read and write
+fn numbers() -> impl Strategy<Value = Vec<u16>> {
+    proptest::collection::vec(prop_oneof![Just(20_u16), Just(3), Just(4)], 0..40)
+}
+
+
+proptest! {
+    #[test]
+    fn diff_is_minimal(before in numbers(), after in numbers()) {
+        let (a, b) = (calls(&before), calls(&after));
+        let diff = TraceDiff::new(a.iter().map(|(h, r)| (h, r)),
+                                  b.iter().map(|(h, r)| (h, r)),
+                                  &DiffOptions::default());
+
+        let (mut removed, mut inserted) = (0, 0);
+        for t in &diff.threads {
+            for change in &t.changes {
+                match *change {
+                    Change::Removed(_) => removed += 1,
+                    Change::Inserted(_) => inserted += 1,
+                    Change::Changed(..) => panic!["identical calls marked as changed"],
+                }
+            }
+        }
+
+        let common = lcs(&before, &after);
+        prop_assert_eq!(removed, before.len() - common);
+        prop_assert_eq!(inserted, after.len() - common);
+    }
+}
+
+#[test]
+fn long_divergent_threads() {
+    // Aligning these with a full edit graph would need gigabytes.
+    let before = vec![3; 10000];
+    let after = vec![4; 10000];
+    let (a, b) = (calls(&before), calls(&after));
+
+    let diff = TraceDiff::new(a.iter().map(|(h, r)| (h, r)),
+                              b.iter().map(|(h, r)| (h, r)),
+                              &DiffOptions::default());
+
+    assert_eq!(diff.threads[0].changes.len(), 20000);
+}
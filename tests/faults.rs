@@ -0,0 +1,71 @@
+// Copyright 2016 Jonathan Anderson <jonathan.anderson@mun.ca>
+//
+// This software was developed by BAE Systems, the University of Cambridge
+// Computer Laboratory, and Memorial University under DARPA/AFRL contract
+// FA8650-15-C-7558 ("CADETS"), as part of the DARPA Transparent Computing
+// (TC) research program.
+//
+// Licensed under the Apache License, Version 2.0,
+// <LICENSE-APACHE or http://apache.org/licenses/LICENSE-2.0>
+// or the MIT license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+
+extern crate ktrace;
+
+mod common;
+
+use common::call;
+use ktrace::{PageFaults,Record,RecordType};
+
+const OPEN: u16 = 5;
+const MUNMAP: u16 = 73;
+const MMAP: u16 = 477;
+
+/// PROT_READ and VM_PROT_READ
+const READ: u64 = 1;
+
+
+/// Fault on an address, returning the region that the fault is attributed to.
+fn fault(faults: &mut PageFaults, address: u64) -> String {
+    let h = common::header(RecordType::PageFault, 0);
+    faults.record(&h, &Record::PageFault { virtual_address: address, fault_type: READ as u32 });
+    faults.record(&h, &Record::PageFaultEnd { result: 0 })
+          .unwrap()
+          .region
+}
+
+
+#[test]
+fn mmap_fault_attribution() {
+    let mut faults = PageFaults::new();
+
+    call(&mut faults, OPEN, &[0x1000, 0], vec![Record::Namei("/lib/libc.so.7".into())], 3);
+    call(&mut faults, MMAP, &[0, 0x4000, READ, 0, 3, 0], vec![], 0x10000);
+    call(&mut faults, MMAP, &[0, 0x2000, READ, 0x1000, !0, 0], vec![], 0x20000);
+
+    assert_eq!(fault(&mut faults, 0x10100), "/lib/libc.so.7");
+    assert_eq!(fault(&mut faults, 0x21fff), "[anonymous]");
+    assert_eq!(fault(&mut faults, 0x30000), "[unknown]");
+
+    // Unmapping the middle of a mapping leaves both ends mapped...
+    call(&mut faults, MUNMAP, &[0x11000, 0x1000], vec![], 0);
+    assert_eq!(fault(&mut faults, 0x10fff), "/lib/libc.so.7");
+    assert_eq!(fault(&mut faults, 0x11800), "[unknown]");
+    assert_eq!(fault(&mut faults, 0x12000), "/lib/libc.so.7");
+
+    // ... and unmapping across two mappings trims them both.
+    call(&mut faults, MUNMAP, &[0x13000, 0xe000], vec![], 0);
+    assert_eq!(fault(&mut faults, 0x12fff), "/lib/libc.so.7");
+    assert_eq!(fault(&mut faults, 0x13000), "[unknown]");
+    assert_eq!(fault(&mut faults, 0x20fff), "[unknown]");
+    assert_eq!(fault(&mut faults, 0x21000), "[anonymous]");
+
+    let spans: Vec<_> = faults.mappings(100).iter().map(|m| (m.start, m.length)).collect();
+    assert_eq!(spans, [(0x10000, 0x1000), (0x12000, 0x1000), (0x21000, 0x1000)]);
+
+    assert_eq!(faults.regions["/lib/libc.so.7"].faults, 4);
+    assert_eq!(faults.regions["/lib/libc.so.7"].reads, 4);
+    assert_eq!(faults.regions["[unknown]"].faults, 4);
+}
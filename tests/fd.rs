@@ -0,0 +1,359 @@
+// Copyright 2016 Jonathan Anderson <jonathan.anderson@mun.ca>
+//
+// This software was developed by BAE Systems, the University of Cambridge
+// Computer Laboratory, and Memorial University under DARPA/AFRL contract
+// FA8650-15-C-7558 ("CADETS"), as part of the DARPA Transparent Computing
+// (TC) research program.
+//
+// Licensed under the Apache License, Version 2.0,
+// <LICENSE-APACHE or http://apache.org/licenses/LICENSE-2.0>
+// or the MIT license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+
+extern crate byteorder;
+extern crate ktrace;
+
+mod common;
+
+use byteorder::{BigEndian,ByteOrder,LittleEndian};
+use common::{call,sockaddr_in};
+use ktrace::{Descriptor,FdTracker,IODir,Record,RecordType,SockAddr};
+
+const FORK: u16 = 2;
+const OPEN: u16 = 5;
+const CLOSE: u16 = 6;
+const CHDIR: u16 = 12;
+const ACCEPT: u16 = 30;
+const DUP: u16 = 41;
+const PIPE: u16 = 42;
+const EXECVE: u16 = 59;
+const DUP2: u16 = 90;
+const FCNTL: u16 = 92;
+const SOCKET: u16 = 97;
+const CONNECT: u16 = 98;
+const BIND: u16 = 104;
+const OPENAT: u16 = 499;
+const PIPE2: u16 = 542;
+const CLOSE_RANGE: u16 = 575;
+
+
+fn open<E: ByteOrder>(fds: &mut FdTracker<E>, path: &str, fd: u64) {
+    call(fds, OPEN, &[0x1000, 0], vec![Record::Namei(path.into())], fd);
+}
+
+fn file(path: &str) -> Descriptor {
+    Descriptor::File(path.into())
+}
+
+/// An `fds` structure, as logged by OpenBSD's `ktrfds()`
+fn fds_struct<E: ByteOrder>(fds: &[i32]) -> Record {
+    let mut content = vec![0; 4 * fds.len()];
+    for (i, &fd) in fds.iter().enumerate() {
+        E::write_i32(&mut content[4 * i..4 * i + 4], fd);
+    }
+
+    Record::Struct { name: "fds".into(), content }
+}
+
+
+#[test]
+fn open_close_exec() {
+    let mut fds = FdTracker::<LittleEndian>::new();
+    open(&mut fds, "/etc/passwd", 3);
+    call(&mut fds, OPEN, &[0x1000, 0x0010_0000], vec![Record::Namei("/etc/group".into())], 4);
+    open(&mut fds, "/tmp/log", 5);
+
+    // I/O records and calls on a descriptor refer to what it was opened as.
+    let header = common::header(RecordType::SystemCall, 0);
+    assert_eq!(fds.record(&header, &Record::SystemCall { number: 3, args: vec![4, 0, 1] }),
+               Some(file("/etc/group")));
+    assert_eq!(fds.record(&header, &Record::GenericIO { fd: 4, rw: IODir::Read, data: vec![] }),
+               Some(file("/etc/group")));
+
+    call(&mut fds, CLOSE, &[5], vec![], 0);
+    assert_eq!(fds.lookup(100, 5), None);
+
+    // Only the O_CLOEXEC descriptor is closed on exec.
+    call(&mut fds, EXECVE, &[0x1000, 0x2000, 0x3000], vec![], 0);
+    assert_eq!(fds.table(100), [(3, &file("/etc/passwd"))]);
+}
+
+#[test]
+fn working_directory() {
+    let mut fds = FdTracker::<LittleEndian>::new();
+    assert_eq!(fds.cwd(100), None);
+
+    // Paths are relative until the working directory is known.
+    open(&mut fds, "a", 3);
+    assert_eq!(fds.lookup(100, 3), Some(&file("a")));
+
+    call(&mut fds, CHDIR, &[0x1000], vec![Record::Namei("/home/user".into())], 0);
+    assert_eq!(fds.cwd(100), Some(&"/home/user".into()));
+
+    open(&mut fds, "a/../b", 4);
+    assert_eq!(fds.lookup(100, 4), Some(&file("/home/user/b")));
+
+    // openat(5, "c") is relative to the directory that 5 refers to.
+    open(&mut fds, "/tmp", 5);
+    call(&mut fds, OPENAT, &[5, 0x1000, 0], vec![Record::Namei("c".into())], 6);
+    assert_eq!(fds.lookup(100, 6), Some(&file("/tmp/c")));
+
+    // A child inherits the working directory and descriptor table.
+    call(&mut fds, FORK, &[], vec![], 200);
+    assert_eq!(fds.cwd(200), Some(&"/home/user".into()));
+    assert_eq!(fds.table(200), fds.table(100));
+}
+
+#[test]
+fn sockets() {
+    let mut fds = FdTracker::<LittleEndian>::new();
+
+    // socket(AF_INET, SOCK_STREAM | SOCK_CLOEXEC, 0), bind(3, 0.0.0.0:80)
+    call(&mut fds, SOCKET, &[2, 1 | 0x1000_0000, 0], vec![], 3);
+    call(&mut fds, BIND, &[3, 0x1000, 16], vec![sockaddr_in([0, 0, 0, 0], 80)], 0);
+
+    // An accepted connection has the listener's address and a peer.
+    let accepted = call(&mut fds, ACCEPT, &[3, 0x1000, 0x2000],
+                        vec![sockaddr_in([10, 0, 0, 1], 1234)], 4);
+    let socket = |local: Option<&str>, peer: Option<&str>| Descriptor::Socket {
+        domain: 2,
+        sock_type: 1,
+        protocol: 0,
+        local: local.map(|a| SockAddr::Inet(a.parse().unwrap())),
+        peer: peer.map(|a| SockAddr::Inet(a.parse().unwrap())),
+    };
+    assert_eq!(accepted, Some(socket(Some("0.0.0.0:80"), Some("10.0.0.1:1234"))));
+
+    // socket(AF_INET, SOCK_STREAM, 0), connect(5, 10.0.0.2:443)
+    call(&mut fds, SOCKET, &[2, 1, 0], vec![], 5);
+    call(&mut fds, CONNECT, &[5, 0x1000, 16], vec![sockaddr_in([10, 0, 0, 2], 443)], 0);
+    assert_eq!(fds.lookup(100, 5), Some(&socket(None, Some("10.0.0.2:443"))));
+    assert_eq!(fds.lookup(100, 5).unwrap().to_string(), "socket(2, 1) -> 10.0.0.2:443");
+
+    // Only the listening socket was SOCK_CLOEXEC.
+    call(&mut fds, EXECVE, &[0x1000, 0x2000, 0x3000], vec![], 0);
+    let table: Vec<_> = fds.table(100).into_iter().map(|(fd, _)| fd).collect();
+    assert_eq!(table, [4, 5]);
+}
+
+#[test]
+fn annotate() {
+    let header = common::header(RecordType::SystemCall, 0);
+    let records = vec![
+        Record::SystemCall { number: OPEN, args: vec![0x1000, 0] },
+        Record::Namei("/etc/motd".into()),
+        Record::SystemCallReturn { code: OPEN, eosys: 0, error: 0, retval: 3 },
+        Record::GenericIO { fd: 3, rw: IODir::Read, data: b"hi".to_vec() },
+        Record::GenericIO { fd: 4, rw: IODir::Read, data: b"?".to_vec() },
+    ];
+
+    let descriptors: Vec<_> = ktrace::annotate::<LittleEndian, _>(
+            records.into_iter().map(|r| (header.clone(), Ok(r))))
+        .map(|(_, _, descriptor)| descriptor)
+        .collect();
+
+    assert_eq!(descriptors, [
+        None,
+        None,
+        Some(file("/etc/motd")),
+        Some(file("/etc/motd")),
+        None,
+    ]);
+}
+
+#[test]
+fn dup() {
+    let mut fds = FdTracker::<LittleEndian>::new();
+    open(&mut fds, "/etc/passwd", 3);
+
+    assert_eq!(call(&mut fds, DUP, &[3], vec![], 4), Some(file("/etc/passwd")));
+    assert_eq!(call(&mut fds, DUP2, &[3, 10], vec![], 10), Some(file("/etc/passwd")));
+
+    // F_DUPFD and F_DUPFD_CLOEXEC
+    assert_eq!(call(&mut fds, FCNTL, &[3, 0, 20], vec![], 20), Some(file("/etc/passwd")));
+    assert_eq!(call(&mut fds, FCNTL, &[3, 17, 30], vec![], 30), Some(file("/etc/passwd")));
+
+    let table: Vec<_> = fds.table(100).into_iter().map(|(fd, _)| fd).collect();
+    assert_eq!(table, [3, 4, 10, 20, 30]);
+
+    // Duplicating an unknown descriptor clobbers what the target was.
+    call(&mut fds, DUP2, &[7, 10], vec![], 10);
+    assert_eq!(fds.lookup(100, 10), None);
+}
+
+#[test]
+fn pipe() {
+    let mut fds = FdTracker::<LittleEndian>::new();
+    open(&mut fds, "/tmp/a", 3);
+    call(&mut fds, CLOSE, &[3], vec![], 0);
+
+    // pipe(2) only returns the read end: we don't guess the write end.
+    assert_eq!(call(&mut fds, PIPE, &[], vec![], 3), Some(Descriptor::Pipe(0)));
+    assert_eq!(fds.lookup(100, 3), Some(&Descriptor::Pipe(0)));
+    assert_eq!(fds.lookup(100, 4), None);
+
+    // pipe2(2) returns neither, unless the kernel logs them.
+    assert_eq!(call(&mut fds, PIPE2, &[0x1000, 0], vec![], 0), None);
+    assert_eq!(fds.table(100).len(), 1);
+
+    let logged = vec![fds_struct::<LittleEndian>(&[5, 6])];
+    assert_eq!(call(&mut fds, PIPE2, &[0x1000, 0], logged, 0), Some(Descriptor::Pipe(2)));
+    assert_eq!(fds.lookup(100, 5), Some(&Descriptor::Pipe(2)));
+    assert_eq!(fds.lookup(100, 6), Some(&Descriptor::Pipe(2)));
+}
+
+#[test]
+fn socketpair() {
+    let mut fds = FdTracker::<LittleEndian>::new();
+    let logged = vec![fds_struct::<LittleEndian>(&[3, 4])];
+
+    // AF_UNIX, SOCK_STREAM | SOCK_CLOEXEC
+    let pair = call(&mut fds, 135, &[1, 1 | 0x1000_0000, 0, 0x1000], logged, 0);
+    let socket = Descriptor::Socket {
+        domain: 1,
+        sock_type: 1,
+        protocol: 0,
+        local: None,
+        peer: None,
+    };
+
+    assert_eq!(pair, Some(socket.clone()));
+    assert_eq!(fds.table(100), [(3, &socket), (4, &socket)]);
+
+    // Both ends are closed on exec.
+    call(&mut fds, EXECVE, &[0x1000, 0x2000, 0x3000], vec![], 0);
+    assert!(fds.table(100).is_empty());
+}
+
+#[test]
+fn close_range() {
+    let mut fds = FdTracker::<LittleEndian>::new();
+    for fd in 3..8 {
+        open(&mut fds, &format!["/tmp/{}", fd], fd);
+    }
+
+    call(&mut fds, CLOSE_RANGE, &[4, 5, 0], vec![], 0);
+    let table: Vec<_> = fds.table(100).into_iter().map(|(fd, _)| fd).collect();
+    assert_eq!(table, [3, 6, 7]);
+
+    // CLOSE_RANGE_CLOEXEC marks descriptors (up to ~0U) to close on exec.
+    call(&mut fds, CLOSE_RANGE, &[6, 0xffff_ffff, 1 << 2], vec![], 0);
+    assert_eq!(fds.table(100).len(), 3);
+
+    call(&mut fds, EXECVE, &[0x1000, 0x2000, 0x3000], vec![], 0);
+    assert_eq!(fds.table(100), [(3, &file("/tmp/3"))]);
+
+    // closefrom(3) in libc
+    call(&mut fds, CLOSE_RANGE, &[3, 0xffff_ffff, 0], vec![], 0);
+    assert!(fds.table(100).is_empty());
+}
+
+#[test]
+fn non_utf8_paths() {
+    let mut fds = FdTracker::<LittleEndian>::new();
+    let path = &b"/tmp/./caf\xe9\\x9"[..];
+    call(&mut fds, OPEN, &[0x1000, 0], vec![Record::Namei(path.into())], 3);
+
+    // The path keeps its bytes, and is only escaped when displayed.
+    let descriptor = fds.lookup(100, 3).unwrap();
+    assert_eq!(descriptor.path().unwrap().as_bytes(), b"/tmp/caf\xe9\\x9");
+    assert_eq!(descriptor.to_string(), "/tmp/caf\\xe9\\\\x9");
+}
+
+#[test]
+fn big_endian_structures() {
+    let mut fds = FdTracker::<BigEndian>::new();
+
+    // socketpair(AF_INET6, SOCK_DGRAM, 0, ...), as logged on a big-endian host
+    let logged = vec![fds_struct::<BigEndian>(&[3, 4])];
+    call(&mut fds, 135, &[28, 2, 0, 0x1000], logged, 0);
+    assert_eq!(fds.table(100).into_iter().map(|(fd, _)| fd).collect::<Vec<_>>(), [3, 4]);
+
+    // connect(3, [fe80::1%2]:80): sin6_scope_id is in host byte order
+    let mut sockaddr = vec![28, 28, 0, 80, 0, 0, 0, 0];
+    sockaddr.extend_from_slice(&[0xfe, 0x80, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1]);
+    sockaddr.extend_from_slice(&[0, 0, 0, 2]);
+    let logged = vec![Record::Struct { name: "sockaddr".into(), content: sockaddr }];
+    call(&mut fds, 98, &[3, 0x1000, 28], logged, 0);
+
+    match fds.lookup(100, 3) {
+        Some(&Descriptor::Socket{peer: Some(SockAddr::Inet6(ref addr)), ..}) => {
+            assert_eq!(addr.to_string(), "[fe80::1%2]:80");
+        },
+        other => panic!["expected a connected socket, got {:?}", other],
+    }
+}
+
+#[test]
+fn linux_flags() {
+    const LINUX_OPEN: u16 = 2;
+    const LINUX_SOCKET: u16 = 41;
+    const LINUX_EXECVE: u16 = 59;
+    const LINUX_FCNTL: u16 = 72;
+    const LINUX_DUP3: u16 = 292;
+    const LINUX_O_CLOEXEC: u64 = 0x80000;
+    const LINUX_SOCK_CLOEXEC: u64 = 0x80000;
+    const LINUX_F_DUPFD_CLOEXEC: u64 = 1030;
+
+    let mut fds = FdTracker::<LittleEndian>::new();
+    fds.record(&common::header(RecordType::ProcessCreation, 4),
+               &Record::ProcessCreation { flags: ktrace::Abi::Linux.flags() });
+
+    // open("/etc/passwd", O_RDONLY), open("/etc/group", O_RDONLY | O_CLOEXEC)
+    call(&mut fds, LINUX_OPEN, &[0x1000, 0], vec![Record::Namei("/etc/passwd".into())], 3);
+    call(&mut fds, LINUX_OPEN, &[0x1000, LINUX_O_CLOEXEC],
+         vec![Record::Namei("/etc/group".into())], 4);
+
+    // socket(AF_INET, SOCK_STREAM | SOCK_CLOEXEC, 0)
+    let socket = call(&mut fds, LINUX_SOCKET, &[2, 1 | LINUX_SOCK_CLOEXEC, 0], vec![], 5);
+    assert_eq!(socket, Some(Descriptor::Socket {
+        domain: 2,
+        sock_type: 1,
+        protocol: 0,
+        local: None,
+        peer: None,
+    }));
+
+    // fcntl(3, F_DUPFD_CLOEXEC, 10), dup3(3, 11, O_CLOEXEC), dup3(3, 12, 0)
+    call(&mut fds, LINUX_FCNTL, &[3, LINUX_F_DUPFD_CLOEXEC, 10], vec![], 10);
+    call(&mut fds, LINUX_DUP3, &[3, 11, LINUX_O_CLOEXEC], vec![], 11);
+    call(&mut fds, LINUX_DUP3, &[3, 12, 0], vec![], 12);
+    assert_eq!(fds.table(100).len(), 6);
+
+    call(&mut fds, LINUX_EXECVE, &[0x1000, 0x2000, 0x3000], vec![], 0);
+    let table: Vec<_> = fds.table(100).into_iter().map(|(fd, _)| fd).collect();
+    assert_eq!(table, [3, 12]);
+}
+
+#[test]
+fn pathless_opens() {
+    const FHOPEN: u16 = 298;
+    const POSIX_OPENPT: u16 = 504;
+    const SHM_OPEN2: u16 = 571;
+    const SHM_ANON: u64 = 1;
+
+    let mut fds = FdTracker::<LittleEndian>::new();
+    call(&mut fds, CHDIR, &[0x1000], vec![Record::Namei("/home/user".into())], 0);
+
+    // shm_open2(SHM_ANON, O_RDWR, ...), shm_open2("/shm", O_RDWR | O_CLOEXEC, ...)
+    call(&mut fds, SHM_OPEN2, &[SHM_ANON, 2, 0o600, 0, 0], vec![], 3);
+    call(&mut fds, SHM_OPEN2, &[0x1000, 2 | 0x0010_0000, 0o600, 0, 0],
+         vec![Record::Namei("/shm".into())], 4);
+    assert_eq!(fds.lookup(100, 3), Some(&Descriptor::SharedMemory(None)));
+    assert_eq!(fds.lookup(100, 4), Some(&Descriptor::SharedMemory(Some("/shm".into()))));
+
+    // posix_openpt(O_RDWR | O_CLOEXEC): the flags are the first argument.
+    assert_eq!(call(&mut fds, POSIX_OPENPT, &[2 | 0x0010_0000], vec![], 5),
+               Some(Descriptor::Pty));
+
+    // fhopen(fhp, O_RDONLY) on a descriptor number we knew as something else
+    open(&mut fds, "/tmp/a", 6);
+    assert_eq!(call(&mut fds, FHOPEN, &[0x1000, 0], vec![], 6), None);
+    assert_eq!(fds.lookup(100, 6), None);
+
+    call(&mut fds, EXECVE, &[0x1000, 0x2000, 0x3000], vec![], 0);
+    let table: Vec<_> = fds.table(100).into_iter().map(|(fd, _)| fd).collect();
+    assert_eq!(table, [3]);
+}
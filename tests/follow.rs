@@ -0,0 +1,159 @@
+// Copyright 2016 Jonathan Anderson <jonathan.anderson@mun.ca>
+//
+// This software was developed by BAE Systems, the University of Cambridge
+// Computer Laboratory, and Memorial University under DARPA/AFRL contract
+// FA8650-15-C-7558 ("CADETS"), as part of the DARPA Transparent Computing
+// (TC) research program.
+//
+// Licensed under the Apache License, Version 2.0,
+// <LICENSE-APACHE or http://apache.org/licenses/LICENSE-2.0>
+// or the MIT license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+
+// Following traces that are written while they are being read.
+
+extern crate byteorder;
+extern crate ktrace;
+
+mod common;
+
+use byteorder::LittleEndian;
+use ktrace::{FollowEvent,Follower,Record};
+use std::fs::{self,File,OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::thread;
+use std::time::Duration;
+
+
+/// A path for a test's trace, in a directory of its own.
+fn path(test: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!["ktrace-follow-{}-{}",
+                                                std::process::id(), test]);
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir.join("trace.out")
+}
+
+/// A trace of calls to each of `numbers`.
+fn calls(numbers: &[u16]) -> Vec<u8> {
+    let mut trace = vec![];
+    for &number in numbers {
+        let (t, payload) = common::encode(&Record::SystemCall {
+            number,
+            args: vec![number as u64],
+        });
+        common::append(&mut trace, &common::header(t, payload.len()), &payload);
+    }
+    trace
+}
+
+fn append(path: &PathBuf, data: &[u8]) {
+    OpenOptions::new().append(true).open(path).unwrap().write_all(data).unwrap();
+}
+
+fn follow(path: &PathBuf) -> Follower<LittleEndian> {
+    Follower::open(path).unwrap().with_interval(Duration::from_millis(1))
+}
+
+/// The number of the call in the next event, which must be a record.
+fn next_call(f: &mut Follower<LittleEndian>) -> u16 {
+    match f.next_event().unwrap() {
+        FollowEvent::Record(_, Ok(Record::SystemCall{number, ..})) => number,
+        e => panic!["expected a system call, got {:?}", e],
+    }
+}
+
+
+#[test]
+fn partial_records() {
+    let path = path("partial");
+    let trace = calls(&[1, 2, 3]);
+    let (first, rest) = trace.split_at(72 + 56 + 10);
+
+    // The second record is cut off partway through its payload.
+    File::create(&path).unwrap().write_all(first).unwrap();
+    let mut f = follow(&path);
+    assert_eq!(next_call(&mut f), 1);
+
+    append(&path, &rest[..20]);
+    append(&path, &rest[20..]);
+    assert_eq!(next_call(&mut f), 2);
+    assert_eq!(next_call(&mut f), 3);
+}
+
+#[test]
+fn resume_after_eof() {
+    let path = path("resume");
+    File::create(&path).unwrap().write_all(&calls(&[1])).unwrap();
+
+    let mut f = follow(&path);
+    assert_eq!(next_call(&mut f), 1);
+
+    // The follower waits at the end of the file until more is written.
+    let writer = {
+        let path = path.clone();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            append(&path, &calls(&[2]));
+        })
+    };
+
+    assert_eq!(next_call(&mut f), 2);
+    writer.join().unwrap();
+}
+
+#[test]
+fn truncated() {
+    let path = path("truncated");
+    File::create(&path).unwrap().write_all(&calls(&[1, 2])).unwrap();
+
+    let mut f = follow(&path);
+    assert_eq!(next_call(&mut f), 1);
+    assert_eq!(next_call(&mut f), 2);
+
+    // The trace is truncated and restarted with a shorter one...
+    File::create(&path).unwrap().write_all(&calls(&[3])).unwrap();
+    match f.next_event().unwrap() {
+        FollowEvent::Truncated => {},
+        e => panic!["expected truncation, got {:?}", e],
+    }
+    assert_eq!(next_call(&mut f), 3);
+
+    // ... or rewritten in place with a longer one.
+    let mut longer = calls(&[4, 5]);
+    longer[32] ^= 0xff;
+    File::create(&path).unwrap().write_all(&longer).unwrap();
+    match f.next_event().unwrap() {
+        FollowEvent::Truncated => {},
+        e => panic!["expected truncation, got {:?}", e],
+    }
+    assert_eq!(next_call(&mut f), 4);
+    assert_eq!(next_call(&mut f), 5);
+}
+
+#[test]
+fn rotated() {
+    let path = path("rotated");
+    File::create(&path).unwrap().write_all(&calls(&[1])).unwrap();
+
+    let mut f = follow(&path);
+    assert_eq!(next_call(&mut f), 1);
+
+    // The new trace is the same size as the old one (and has the same
+    // first header), so only its inode shows that it has been replaced.
+    let new = path.with_extension("new");
+    File::create(&new).unwrap().write_all(&calls(&[1])).unwrap();
+    fs::rename(&new, &path).unwrap();
+
+    match f.next_event().unwrap() {
+        FollowEvent::Rotated => {},
+        e => panic!["expected rotation, got {:?}", e],
+    }
+    assert_eq!(next_call(&mut f), 1);
+
+    append(&path, &calls(&[2]));
+    assert_eq!(next_call(&mut f), 2);
+}
@@ -0,0 +1,243 @@
+// Copyright 2016 Jonathan Anderson <jonathan.anderson@mun.ca>
+//
+// This software was developed by BAE Systems, the University of Cambridge
+// Computer Laboratory, and Memorial University under DARPA/AFRL contract
+// FA8650-15-C-7558 ("CADETS"), as part of the DARPA Transparent Computing
+// (TC) research program.
+//
+// Licensed under the Apache License, Version 2.0,
+// <LICENSE-APACHE or http://apache.org/licenses/LICENSE-2.0>
+// or the MIT license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+
+// Compare our decoding of the traces in tests/corpus with the output of
+// kdump(1) for the same traces (see tests/corpus/README.md).
+
+extern crate byteorder;
+extern crate ktrace;
+
+use byteorder::LittleEndian;
+use ktrace::{Header,Record,RecordType,fault,syscalls};
+use std::fs::{self,File};
+use std::io::Read;
+use std::path::{Path,PathBuf};
+
+
+/// One record's line of `kdump -H` output
+#[derive(Debug)]
+struct KdumpLine {
+    pid: u32,
+    tid: usize,
+    command: String,
+    tag: String,
+    rest: String,
+}
+
+impl KdumpLine {
+    /// Parse a line of `kdump -H` output, ignoring continuation lines
+    /// (e.g., the hex dump of a `GIO` record).
+    fn parse(line: &str) -> Option<KdumpLine> {
+        let mut words = line.split_whitespace();
+        let pid = words.next()?.parse().ok()?;
+        let tid = words.next()?.parse().ok()?;
+        let command = words.next()?.to_string();
+        let tag = words.next()?.to_string();
+
+        let start = line.find(&tag)? + tag.len();
+
+        Some(KdumpLine {
+            pid,
+            tid,
+            command,
+            tag,
+            rest: line[start..].trim().to_string(),
+        })
+    }
+}
+
+/// The tag that kdump uses for a record type, if it prints that type.
+fn tag(t: &RecordType) -> Option<&'static str> {
+    match *t {
+        RecordType::SystemCall => Some("CALL"),
+        RecordType::SystemCallReturn => Some("RET"),
+        RecordType::Namei => Some("NAMI"),
+        RecordType::GenericIO => Some("GIO"),
+        RecordType::Signal => Some("PSIG"),
+        RecordType::ContextSwitch => Some("CSW"),
+        RecordType::UserData => Some("USER"),
+        RecordType::Struct => Some("STRU"),
+        RecordType::Sysctl => Some("SCTL"),
+        RecordType::CapabilityFailure => Some("CAP"),
+        RecordType::PageFault => Some("PFLT"),
+        RecordType::PageFaultEnd => Some("PRET"),
+        RecordType::ProcessCreation | RecordType::ProcessDestruction => None,
+    }
+}
+
+/// Check a decoded record against kdump's description of it.
+fn check(header: &Header, record: &Record, line: &KdumpLine) -> Result<(), String> {
+    let expected = tag(&header.record_type).unwrap_or("");
+
+    if (header.pid, header.tid, header.command.to_string(), expected)
+        != (line.pid, line.tid, line.command.clone(), &line.tag[..])
+    {
+        return Err(format!["decoded {} {} {} {}", header.pid, header.tid,
+                           header.command, expected]);
+    }
+
+    let name = |number: u16| syscalls::name(number as usize)
+                                      .unwrap_or(format!["#{}", number]);
+
+    match *record {
+        Record::SystemCall{number, ..} => {
+            let n = name(number);
+            if !line.rest.starts_with(&format!["{}(", n]) && line.rest != n {
+                return Err(format!["decoded call to {}", n]);
+            }
+        },
+
+        Record::SystemCallReturn{code, error, retval, ..} => {
+            let mut words = line.rest.split_whitespace();
+            let n = name(code);
+            if words.next() != Some(&n) {
+                return Err(format!["decoded return from {}", n]);
+            }
+
+            let value = words.next().unwrap_or("");
+            let ok = match error as i32 {
+                0 => value.split('/').next() == Some(&retval.to_string())
+                     || value.split('/').next() == Some(&(retval as i64).to_string()),
+                -2 => value == "JUSTRETURN",
+                -1 => value == "RESTART",
+                e => line.rest.contains(&format!["errno {}", e]),
+            };
+
+            if !ok {
+                return Err(format!["decoded error {}, return value {}",
+                                   error as i32, retval]);
+            }
+        },
+
+        Record::Namei(ref path) if line.rest != format!["\"{}\"", path] => {
+            return Err(format!["decoded path \"{}\"", path]);
+        },
+
+        Record::GenericIO{fd, ref data, ..}
+            if !line.rest.starts_with(&format!["fd {} ", fd])
+                || !line.rest.contains(&format![" {} byte", data.len()]) =>
+        {
+            return Err(format!["decoded {} B of I/O on fd {}", data.len(), fd]);
+        },
+
+        Record::Sysctl(ref name) if !line.rest.contains(&name.to_string()) => {
+            return Err(format!["decoded sysctl {}", name]);
+        },
+
+        Record::Struct{ref name, ..}
+            if !line.rest.starts_with(&format!["struct {}", name]) =>
+        {
+            return Err(format!["decoded struct {}", name]);
+        },
+
+        Record::PageFault{virtual_address, fault_type}
+            if line.rest != format!["0x{:x} {}", virtual_address,
+                                    fault::protection(fault_type)] =>
+        {
+            return Err(format!["decoded fault at 0x{:x}", virtual_address]);
+        },
+
+        Record::PageFaultEnd{result}
+            if fault::result(result).ok().as_ref() != Some(&line.rest) =>
+        {
+            return Err(format!["decoded fault result {}", result]);
+        },
+
+        _ => {},
+    }
+
+    Ok(())
+}
+
+/// Decode a trace and compare every record with the reference output.
+fn compare(trace: &Path, reference: &Path) -> Result<usize, String> {
+    let mut data = vec![];
+    File::open(trace).and_then(|mut f| f.read_to_end(&mut data))
+                     .map_err(|e| e.to_string())?;
+
+    let records = ktrace::parse::<LittleEndian>(&mut data.as_slice())
+                         .map_err(|e| e.to_string())?;
+
+    let kdump = fs::read_to_string(reference).map_err(|e| e.to_string())?;
+    let mut lines = kdump.lines().filter_map(KdumpLine::parse);
+
+    let mut checked = 0;
+    for (i, (header, record)) in records.iter().enumerate() {
+        if tag(&header.record_type).is_none() {
+            continue;
+        }
+
+        let record = record.as_ref()
+                           .map_err(|e| format!["record #{}: {}", i, e])?;
+
+        let line = lines.next().ok_or(format![
+            "record #{}: no more kdump output", i])?;
+
+        check(header, record, &line)
+            .map_err(|e| format!["record #{}: {}; kdump says: {:?}", i, e, line])?;
+
+        checked += 1;
+    }
+
+    if let Some(line) = lines.next() {
+        return Err(format!["kdump output has more records: {:?}", line]);
+    }
+
+    Ok(checked)
+}
+
+/// Every trace in the corpus that has reference output.
+fn corpus() -> Vec<(PathBuf, PathBuf)> {
+    let root = Path::new(env!["CARGO_MANIFEST_DIR"]).join("tests/corpus");
+    let mut traces = vec![];
+
+    for dir in fs::read_dir(root).unwrap() {
+        let dir = dir.unwrap().path();
+        if !dir.is_dir() {
+            continue;
+        }
+
+        for file in fs::read_dir(&dir).unwrap() {
+            let path = file.unwrap().path();
+            let reference = path.with_extension("kdump");
+
+            if path.extension().is_some_and(|e| e == "ktrace") && reference.exists() {
+                traces.push((path, reference));
+            }
+        }
+    }
+
+    traces.sort();
+    traces
+}
+
+
+#[test]
+#[ignore = "no captures are checked in yet: run tests/corpus/capture.sh on \
+            each FreeBSD release and architecture to be supported"]
+fn corpus_matches_kdump() {
+    let corpus = corpus();
+    assert![!corpus.is_empty(), "no traces with kdump output in tests/corpus"];
+
+    let mut failures = vec![];
+
+    for (trace, reference) in corpus {
+        match compare(&trace, &reference) {
+            Ok(n) => println!["{}: {} records match", trace.display(), n],
+            Err(e) => failures.push(format!["{}: {}", trace.display(), e]),
+        }
+    }
+
+    assert![failures.is_empty(), "\n{}", failures.join("\n")];
+}
@@ -0,0 +1,127 @@
+// Copyright 2016 Jonathan Anderson <jonathan.anderson@mun.ca>
+//
+// This software was developed by BAE Systems, the University of Cambridge
+// Computer Laboratory, and Memorial University under DARPA/AFRL contract
+// FA8650-15-C-7558 ("CADETS"), as part of the DARPA Transparent Computing
+// (TC) research program.
+//
+// Licensed under the Apache License, Version 2.0,
+// <LICENSE-APACHE or http://apache.org/licenses/LICENSE-2.0>
+// or the MIT license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+
+extern crate byteorder;
+extern crate ktrace;
+extern crate nix;
+extern crate proptest;
+
+mod common;
+
+use byteorder::LittleEndian;
+use common::record;
+use ktrace::{Index,IndexedReader};
+use nix::sys::time::TimeVal;
+use proptest::prelude::*;
+use std::io::Cursor;
+
+
+/// A trace of `records`, each written at one of `times`.
+fn trace(records: &[(ktrace::Record, TimeVal)]) -> Vec<u8> {
+    let mut trace = vec![];
+    for &(ref r, timestamp) in records {
+        let (t, payload) = common::encode(r);
+        let mut h = common::header(t, payload.len());
+        h.timestamp = timestamp;
+        common::append(&mut trace, &h, &payload);
+    }
+
+    trace
+}
+
+fn timestamp() -> impl Strategy<Value = TimeVal> {
+    (any::<i64>(), 0..1_000_000_i64)
+        .prop_map(|(tv_sec, tv_usec)| TimeVal { tv_sec, tv_usec })
+}
+
+
+proptest! {
+    #[test]
+    fn index_round_trip(records in proptest::collection::vec((record(), timestamp()), 0..20)) {
+        let trace = trace(&records);
+        let index = Index::build::<LittleEndian, _>(&mut Cursor::new(&trace)).unwrap();
+        prop_assert_eq!(index.len(), records.len());
+        prop_assert_eq!(index.trace_length(), trace.len() as u64);
+
+        let mut saved = vec![];
+        index.write(&mut saved).unwrap();
+        let loaded = Index::read(&mut saved.as_slice()).unwrap();
+
+        prop_assert_eq!(loaded.trace_length(), index.trace_length());
+        prop_assert_eq!(loaded.len(), index.len());
+        for ((built, read), &(_, timestamp)) in index.entries().iter()
+                                                    .zip(loaded.entries())
+                                                    .zip(&records)
+        {
+            prop_assert_eq!(format!["{:?}", built], format!["{:?}", read]);
+            prop_assert_eq!((read.timestamp.tv_sec, read.timestamp.tv_usec),
+                            (timestamp.tv_sec, timestamp.tv_usec));
+        }
+
+        // The loaded index finds the same records as a freshly built one.
+        let mut reader = IndexedReader::<_, LittleEndian>::new(Cursor::new(&trace), loaded)
+                                                          .unwrap();
+        for (n, &(_, timestamp)) in records.iter().enumerate() {
+            let (header, _) = reader.record(n).unwrap();
+            prop_assert_eq!(header.timestamp, timestamp);
+        }
+    }
+}
+
+#[test]
+fn stale_index() {
+    let records = vec![
+        (ktrace::Record::SystemCall { number: 3, args: vec![0, 0x1000, 64] },
+         TimeVal { tv_sec: 1000, tv_usec: 500 }),
+    ];
+
+    let mut trace = trace(&records);
+    let index = Index::build::<LittleEndian, _>(&mut Cursor::new(&trace)).unwrap();
+    let mut saved = vec![];
+    index.write(&mut saved).unwrap();
+
+    // The trace grows (e.g., because ktrace is still writing to it).
+    let more = self::trace(&records);
+    trace.extend(more);
+
+    let loaded = Index::read(&mut saved.as_slice()).unwrap();
+    let e = IndexedReader::<_, LittleEndian>::new(Cursor::new(&trace), loaded)
+                                             .err()
+                                             .expect("stale index accepted");
+    assert!(e.to_string().contains("stale index"), "{}", e);
+}
+
+#[test]
+fn corrupt_length() {
+    let records = vec![
+        (ktrace::Record::SystemCall { number: 3, args: vec![0, 0x1000, 64] },
+         TimeVal { tv_sec: 1000, tv_usec: 500 }),
+    ];
+
+    let mut trace = trace(&records);
+    let index = Index::build::<LittleEndian, _>(&mut Cursor::new(&trace)).unwrap();
+
+    // The header now claims a 4 GiB payload; don't try to allocate it.
+    trace[0..4].copy_from_slice(&[0xff; 4]);
+
+    let mut reader = IndexedReader::<_, LittleEndian>::new(Cursor::new(&trace), index)
+                                                      .unwrap();
+    let e = reader.record(0).expect_err("corrupt length accepted");
+    match *e.kind() {
+        ktrace::ErrorKind::Truncated { expected, .. } => {
+            assert_eq!(expected, 0xffff_ffff)
+        },
+        ref k => panic!["unexpected error: {:?}", k],
+    }
+}
@@ -0,0 +1,126 @@
+// Copyright 2016 Jonathan Anderson <jonathan.anderson@mun.ca>
+//
+// This software was developed by BAE Systems, the University of Cambridge
+// Computer Laboratory, and Memorial University under DARPA/AFRL contract
+// FA8650-15-C-7558 ("CADETS"), as part of the DARPA Transparent Computing
+// (TC) research program.
+//
+// Licensed under the Apache License, Version 2.0,
+// <LICENSE-APACHE or http://apache.org/licenses/LICENSE-2.0>
+// or the MIT license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+
+extern crate byteorder;
+extern crate ktrace;
+extern crate nix;
+
+mod common;
+
+use byteorder::LittleEndian;
+use ktrace::{Merge,Record};
+use nix::sys::time::TimeVal;
+use std::io::Cursor;
+
+
+/// A trace of calls to each of `numbers`, made at the given times
+/// (in seconds).
+fn trace(calls: &[(u16, i64)]) -> Vec<u8> {
+    let mut trace = vec![];
+    for &(number, time) in calls {
+        let (t, payload) = common::encode(&Record::SystemCall { number, args: vec![] });
+        let mut h = common::header(t, payload.len());
+        h.timestamp = TimeVal { tv_sec: time, tv_usec: 0 };
+        common::append(&mut trace, &h, &payload);
+    }
+    trace
+}
+
+fn merge(traces: Vec<Vec<u8>>) -> Merge<LittleEndian> {
+    let mut merge = Merge::new();
+    for (i, t) in traces.into_iter().enumerate() {
+        assert_eq!(merge.add(Cursor::new(t)), i);
+    }
+    merge
+}
+
+/// The (source, call number, time) of each merged record.
+fn calls(merge: Merge<LittleEndian>) -> Vec<(usize, u16, i64)> {
+    merge.map(|r| r.unwrap())
+         .map(|r| match r.record {
+             Ok(Record::SystemCall{number, ..}) => {
+                 (r.source, number, r.header.timestamp.tv_sec)
+             },
+             other => panic!["expected a system call, got {:?}", other],
+         })
+         .collect()
+}
+
+
+#[test]
+fn interleaving() {
+    let merged = merge(vec![
+        trace(&[(1, 10), (2, 30), (3, 50)]),
+        trace(&[(4, 20), (5, 40)]),
+        trace(&[(6, 5), (7, 60)]),
+    ]);
+
+    assert_eq!(calls(merged), [
+        (2, 6, 5),
+        (0, 1, 10),
+        (1, 4, 20),
+        (0, 2, 30),
+        (1, 5, 40),
+        (0, 3, 50),
+        (2, 7, 60),
+    ]);
+}
+
+#[test]
+fn equal_timestamps() {
+    // Ties go to the source that was added first, and each source's
+    // records stay in order even if their timestamps go backwards.
+    let merged = merge(vec![
+        trace(&[(1, 10), (2, 10)]),
+        trace(&[(3, 10), (4, 5)]),
+        trace(&[(5, 10)]),
+    ]);
+
+    assert_eq!(calls(merged), [
+        (0, 1, 10),
+        (0, 2, 10),
+        (1, 3, 10),
+        (1, 4, 5),
+        (2, 5, 10),
+    ]);
+}
+
+#[test]
+fn framing_error() {
+    // The second trace ends partway through its second record.
+    let mut broken = trace(&[(3, 15), (4, 25)]);
+    let len = broken.len();
+    broken.truncate(len - 4);
+
+    let mut merged = merge(vec![trace(&[(1, 10), (2, 20), (5, 30)]), broken]);
+
+    let mut sources = vec![];
+    let mut error = None;
+    for r in &mut merged {
+        match r {
+            Ok(r) => sources.push(r.source),
+            Err(e) => {
+                assert!(error.is_none(), "more than one error: {}", e);
+                error = Some(e);
+            },
+        }
+    }
+
+    let e = error.expect("truncated trace accepted");
+    assert_eq!((e.trace(), e.index()), (Some(1), Some(1)));
+    assert!(e.to_string().starts_with("trace #1: record #1"), "{}", e);
+
+    // The other trace is read to the end.
+    assert_eq!(sources, [0, 1, 0, 0]);
+}
@@ -0,0 +1,138 @@
+// Copyright 2016 Jonathan Anderson <jonathan.anderson@mun.ca>
+//
+// This software was developed by BAE Systems, the University of Cambridge
+// Computer Laboratory, and Memorial University under DARPA/AFRL contract
+// FA8650-15-C-7558 ("CADETS"), as part of the DARPA Transparent Computing
+// (TC) research program.
+//
+// Licensed under the Apache License, Version 2.0,
+// <LICENSE-APACHE or http://apache.org/licenses/LICENSE-2.0>
+// or the MIT license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+
+// No input should make the parsers (or the analyses built on them) panic:
+// malformed data must be reported as an `Err`.
+
+extern crate byteorder;
+extern crate ktrace;
+extern crate nix;
+extern crate proptest;
+
+mod common;
+#[path = "common/analyses.rs"]
+mod analyses;
+
+use byteorder::{BigEndian,LittleEndian};
+use ktrace::{Header,Os,Record,RecordRef,RecordType};
+use nix::sys::time::TimeVal;
+use proptest::prelude::*;
+use std::io::Cursor;
+
+
+/// A trace whose headers are well-formed but whose payloads may not be
+fn framed_trace() -> impl Strategy<Value = Vec<u8>> {
+    let payload = prop_oneof![
+        common::record().prop_map(|r| common::encode(&r)),
+        (1..15_u16, prop::collection::vec(any::<u8>(), 0..64))
+            .prop_map(|(t, data)| (RecordType::from_u16(t).unwrap(), data)),
+    ];
+
+    prop::collection::vec((payload, 1..4_u32, 1..4_usize, any::<i64>(), any::<i64>()), 0..64).prop_map(|records| {
+        let mut trace = vec![];
+        for ((t, payload), pid, tid, sec, usec) in records {
+            let mut header = common::header(t, payload.len());
+            header.pid = pid;
+            header.tid = tid;
+            header.timestamp = TimeVal { tv_sec: sec, tv_usec: usec };
+            common::append(&mut trace, &header, &payload);
+        }
+        trace
+    })
+}
+
+/// An strace log made of lines from the test logs, mixed with junk
+fn strace_log() -> impl Strategy<Value = String> {
+    let lines: Vec<String> = [include_str!["strace/cat.log"], include_str!["strace/i386.log"]]
+        .iter()
+        .flat_map(|log| log.lines())
+        .map(String::from)
+        .collect();
+
+    let line = prop_oneof![
+        prop::sample::select(lines),
+        "[0-9]{1,5} +[0-9:.]{0,16} [a-z_]{0,10}\\(.{0,30}\\) *= ?[-0-9a-fx?]{0,8}.{0,20}",
+        ".{0,40}",
+    ];
+
+    prop::collection::vec(line, 0..32).prop_map(|lines| lines.join("\n"))
+}
+
+/// Run every analysis over a trace, ignoring records that fail to parse.
+fn analyze(trace: &[u8]) {
+    if let Ok(records) = ktrace::parse::<LittleEndian>(&mut &trace[..]) {
+        analyses::analyze::<LittleEndian>(records);
+    }
+}
+
+/// Run every analysis over the records that a foreign importer produces.
+fn analyze_os(trace: &[u8], os: Os) {
+    if let Ok(entries) = ktrace::parse_os::<LittleEndian>(trace, os) {
+        analyses::analyze::<LittleEndian>(analyses::common(entries));
+    }
+}
+
+
+proptest! {
+    #[test]
+    fn header_never_panics(data in prop::collection::vec(any::<u8>(), 56)) {
+        let mut raw = [0; 56];
+        raw.copy_from_slice(&data);
+        let _ = Header::parse::<LittleEndian>(&raw);
+        let _ = Header::parse::<BigEndian>(&raw);
+    }
+
+    #[test]
+    fn record_never_panics(t in 1..15_u16,
+                           data in prop::collection::vec(any::<u8>(), 0..128)) {
+        let t = RecordType::from_u16(t).unwrap();
+
+        if let Ok(r) = Record::parse::<LittleEndian>(&data, &t) {
+            let _ = r.to_string();
+        }
+        let _ = Record::parse::<BigEndian>(&data, &t);
+        let _ = RecordRef::parse::<LittleEndian>(&data, &t);
+    }
+
+    #[test]
+    fn trace_never_panics(data in prop::collection::vec(any::<u8>(), 0..512)) {
+        let _ = ktrace::parse::<LittleEndian>(&mut &data[..]);
+        for _ in ktrace::parse_slice::<LittleEndian>(&data) {}
+        for _ in ktrace::parse_parallel::<LittleEndian, _>(Cursor::new(data.clone()), 2) {}
+        let _ = ktrace::Index::build::<LittleEndian, _>(&mut Cursor::new(&data));
+        let _ = ktrace::Index::read(&mut &data[..]);
+
+        for &os in &[Os::FreeBSD, Os::OpenBSD, Os::NetBSD, Os::Darwin, Os::Linux] {
+            let _ = ktrace::parse_os::<BigEndian>(&data, os);
+            analyze_os(&data, os);
+        }
+        let _ = ktrace::parse_any::<LittleEndian>(&data);
+    }
+
+    #[test]
+    fn strace_never_panics(log in strace_log()) {
+        analyze_os(log.as_bytes(), Os::Linux);
+    }
+
+    #[test]
+    fn analyses_never_panic(trace in framed_trace()) {
+        analyze(&trace);
+
+        let mut redactor = ktrace::Redactor::<LittleEndian>::new(Default::default());
+        let mut redacted = vec![];
+        if ktrace::redact(&mut &trace[..], &mut redacted, &mut redactor).is_ok() {
+            analyze(&redacted);
+        }
+    }
+}
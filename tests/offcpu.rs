@@ -0,0 +1,78 @@
+// Copyright 2016 Jonathan Anderson <jonathan.anderson@mun.ca>
+//
+// This software was developed by BAE Systems, the University of Cambridge
+// Computer Laboratory, and Memorial University under DARPA/AFRL contract
+// FA8650-15-C-7558 ("CADETS"), as part of the DARPA Transparent Computing
+// (TC) research program.
+//
+// Licensed under the Apache License, Version 2.0,
+// <LICENSE-APACHE or http://apache.org/licenses/LICENSE-2.0>
+// or the MIT license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+
+extern crate ktrace;
+extern crate nix;
+
+mod common;
+
+use ktrace::{OffCpu,Record,RecordType};
+use nix::sys::time::TimeVal;
+
+const SELECT: u16 = 93;
+
+
+/// Feed `offcpu` a record from thread `tid` at `usec` µs into the trace,
+/// returning the length of any interval that it ends.
+fn at(offcpu: &mut OffCpu, tid: usize, usec: i64, record: Record) -> Option<i64> {
+    let mut h = common::header(RecordType::ContextSwitch, 0);
+    h.tid = tid;
+    h.timestamp = TimeVal { tv_sec: 1000 + usec / 1_000_000, tv_usec: usec % 1_000_000 };
+
+    offcpu.record(&h, &record).map(|i| i.duration())
+}
+
+fn switch(out: bool, user: bool, wmesg: &str) -> Record {
+    Record::ContextSwitch { out, user, message: wmesg.into() }
+}
+
+
+#[test]
+fn intervals() {
+    let mut offcpu = OffCpu::new();
+    let call = Record::SystemCall { number: SELECT, args: vec![4, 0, 0, 0, 0] };
+    let ret = Record::SystemCallReturn { code: SELECT, eosys: 0, error: 0, retval: 1 };
+
+    // A thread that was already off the CPU when tracing began
+    assert_eq!(at(&mut offcpu, 2, 0, switch(false, false, "")), None);
+
+    // Sleeping in select(2), twice, across a second boundary
+    assert_eq!(at(&mut offcpu, 1, 100, call.clone()), None);
+    assert_eq!(at(&mut offcpu, 1, 150, switch(true, false, "select")), None);
+    assert_eq!(at(&mut offcpu, 1, 400, switch(false, false, "select")), Some(250));
+    assert_eq!(at(&mut offcpu, 1, 999_900, switch(true, false, "select")), None);
+    assert_eq!(at(&mut offcpu, 1, 1_000_650, switch(false, false, "select")), Some(750));
+    assert_eq!(at(&mut offcpu, 1, 1_000_700, ret), None);
+
+    // Preempted in user mode, while another thread is stopped
+    assert_eq!(at(&mut offcpu, 1, 2_000_000, switch(true, true, "")), None);
+    assert_eq!(at(&mut offcpu, 2, 2_000_005, switch(true, false, "biord")), None);
+    assert_eq!(at(&mut offcpu, 1, 2_000_020, switch(false, true, "")), Some(20));
+
+    let select = &offcpu.by_wmesg["select"];
+    assert_eq!((select.count, select.total, select.max), (2, 1000, 750));
+    assert_eq!(offcpu.by_syscall["select"].total, 1000);
+
+    let preempted = &offcpu.by_wmesg["[preempted]"];
+    assert_eq!((preempted.count, preempted.total), (1, 20));
+    assert_eq!(offcpu.by_syscall["[user]"].total, 20);
+
+    // Thread 2 never came back, so its wait isn't counted.
+    assert!(!offcpu.by_wmesg.contains_key("biord"));
+
+    let mut folded = vec![];
+    offcpu.write_folded(&mut folded).unwrap();
+    assert_eq!(String::from_utf8(folded).unwrap(),
+               "[user];[preempted] 20\nselect;select 1000\n");
+}
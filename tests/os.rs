@@ -0,0 +1,243 @@
+// Copyright 2016 Jonathan Anderson <jonathan.anderson@mun.ca>
+//
+// This software was developed by BAE Systems, the University of Cambridge
+// Computer Laboratory, and Memorial University under DARPA/AFRL contract
+// FA8650-15-C-7558 ("CADETS"), as part of the DARPA Transparent Computing
+// (TC) research program.
+//
+// Licensed under the Apache License, Version 2.0,
+// <LICENSE-APACHE or http://apache.org/licenses/LICENSE-2.0>
+// or the MIT license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+
+// Traces written by OSes other than FreeBSD.
+
+extern crate byteorder;
+extern crate ktrace;
+
+use byteorder::{ByteOrder,NativeEndian};
+use ktrace::{Entry,Extra,Os,Record,syscalls};
+
+
+/// A NetBSD `struct ktr_header` with timestamp fields `a` and `b`.
+fn netbsd_header(record_type: u16, version: i16, length: usize, a: i64, b: i64)
+    -> Vec<u8>
+{
+    let mut h = vec![0; 56];
+    NativeEndian::write_i32(&mut h[0..4], length as i32);
+    NativeEndian::write_u16(&mut h[4..6], record_type);
+    NativeEndian::write_i16(&mut h[6..8], version);
+    NativeEndian::write_u32(&mut h[8..12], 42);
+    h[12..16].copy_from_slice(b"ls\0\0");
+
+    if version == 1 {
+        NativeEndian::write_i32(&mut h[32..36], a as i32);
+    } else {
+        NativeEndian::write_i64(&mut h[32..40], a);
+    }
+    NativeEndian::write_i64(&mut h[40..48], b);
+    NativeEndian::write_u32(&mut h[48..52], 1);
+
+    h
+}
+
+/// A NetBSD trace of calls to each of `numbers` (without arguments).
+fn netbsd_calls(version: i16, numbers: &[u32]) -> Vec<u8> {
+    let mut trace = vec![];
+    for &n in numbers {
+        let mut payload = vec![0; 8];
+        NativeEndian::write_u32(&mut payload[0..4], n);
+
+        trace.extend(netbsd_header(1, version, payload.len(), 1500000000, 250000000));
+        trace.extend(payload);
+    }
+    trace
+}
+
+fn calls(entries: &[Entry]) -> Vec<String> {
+    entries.iter()
+           .map(|e| match *e {
+               Entry::Common(_, Ok(Record::SystemCall{number, ..})) => {
+                   syscalls::name(number as usize).unwrap()
+               },
+               Entry::Specific{record: Ok(Extra::SystemCall{ref name, ..}), ..} => {
+                   format!["netbsd:{}", name]
+               },
+               ref e => panic!["expected a system call, got {:?}", e],
+           })
+           .collect()
+}
+
+
+#[test]
+fn netbsd_timestamps() {
+    // Version 1: a 32-bit tv_sec and a long tv_nsec
+    let mut v1 = netbsd_header(1, 1, 8, 1500000000, 999999999);
+    v1.extend(vec![1, 0, 0, 0, 0, 0, 0, 0]);
+    // Version 2: struct timespec
+    let mut v2 = netbsd_header(1, 2, 8, 1500000000, 999999999);
+    v2.extend(vec![1, 0, 0, 0, 0, 0, 0, 0]);
+
+    for trace in &[v1, v2] {
+        let entries = ktrace::parse_os::<NativeEndian>(trace, Os::NetBSD).unwrap();
+        match entries[0] {
+            Entry::Common(ref h, _) => {
+                assert_eq!((h.timestamp.tv_sec, h.timestamp.tv_usec), (1500000000, 999999));
+                assert_eq!((h.pid, h.tid), (42, 1));
+                assert_eq!(h.command, "ls");
+            },
+            ref e => panic!["expected a FreeBSD-equivalent record, got {:?}", e],
+        }
+    }
+}
+
+#[test]
+fn netbsd_detection() {
+    for version in 1..3 {
+        let trace = netbsd_calls(version, &[1, 1, 1]);
+        assert_eq!(Os::detect(&trace), Os::NetBSD);
+
+        // A header cut off by the end of the data (e.g., of a file's first
+        // few kilobytes) doesn't stop a trace from being recognised.
+        assert_eq!(Os::detect(&trace[..64 + 20]), Os::NetBSD);
+    }
+
+    // FreeBSD headers have versions too, but their thread IDs are large.
+    for version in 1..3 {
+        let mut trace = netbsd_calls(version, &[1, 1]);
+        for h in [0, 64].iter() {
+            NativeEndian::write_u32(&mut trace[h + 48..h + 52], 100123);
+        }
+        assert_eq!(Os::detect(&trace), Os::FreeBSD);
+    }
+}
+
+#[test]
+fn netbsd_syscall_names() {
+    // dup3 (454) has no FreeBSD equivalent, pipe2 (453) does, and __stat50
+    // (439) is a versioned stat(2).
+    let trace = netbsd_calls(2, &[454, 453, 439]);
+    let entries = ktrace::parse_os::<NativeEndian>(&trace, Os::NetBSD).unwrap();
+
+    assert_eq!(calls(&entries), ["netbsd:dup3", "pipe2", "stat"]);
+}
+
+/// A NetBSD trace of a call to `number` and its return.
+fn netbsd_call(number: u32, args: &[u64], error: i32, retval: u64) -> Vec<u8> {
+    let mut call = vec![0; 8];
+    NativeEndian::write_u32(&mut call[0..4], number);
+    NativeEndian::write_u32(&mut call[4..8], 8 * args.len() as u32);
+    for &arg in args {
+        let mut a = [0; 8];
+        NativeEndian::write_u64(&mut a, arg);
+        call.extend(&a);
+    }
+
+    let mut ret = vec![0; 16];
+    NativeEndian::write_u16(&mut ret[0..2], number as u16);
+    NativeEndian::write_i32(&mut ret[4..8], error);
+    NativeEndian::write_u64(&mut ret[8..16], retval);
+
+    let mut trace = netbsd_header(1, 2, call.len(), 1500000000, 0);
+    trace.extend(call);
+    trace.extend(netbsd_header(2, 2, ret.len(), 1500000000, 0));
+    trace.extend(ret);
+    trace
+}
+
+#[test]
+fn netbsd_arguments() {
+    // open("x", O_WRONLY | O_CREAT | O_CLOEXEC), socket(AF_INET6,
+    // SOCK_STREAM | SOCK_CLOEXEC, 0) and fcntl(3, F_DUPFD_CLOEXEC, 0)
+    let mut trace = netbsd_call(5, &[0x1000, 0x0040_0201], 0, 3);
+    trace.extend(netbsd_call(394, &[24, 0x1000_0001, 0], 0, 4));
+    trace.extend(netbsd_call(92, &[3, 12, 0], 0, 5));
+
+    let entries = ktrace::parse_os::<NativeEndian>(&trace, Os::NetBSD).unwrap();
+    let args = entries.iter()
+                      .filter_map(|e| match *e {
+                          Entry::Common(_, Ok(Record::SystemCall{ref args, ..})) => {
+                              Some(args.clone())
+                          },
+                          _ => None,
+                      })
+                      .collect::<Vec<_>>();
+
+    assert_eq!(args, [
+        vec![0x1000, 0x0010_0201],
+        vec![28, 0x1000_0001, 0],
+        vec![3, 17, 0],
+    ]);
+}
+
+#[test]
+fn netbsd_padded_arguments() {
+    // pread(3, buf, 512, PAD, 4096), mmap(0, 8192, PROT_READ, MAP_SHARED,
+    // 3, PAD, 0) and lseek(3, PAD, 100, SEEK_SET)
+    let mut trace = netbsd_call(173, &[3, 0x1000, 512, 0, 4096], 0, 512);
+    trace.extend(netbsd_call(197, &[0, 8192, 1, 1, 3, 0, 0], 0, 0x2000));
+    trace.extend(netbsd_call(199, &[3, 0, 100, 0], 0, 100));
+
+    let entries = ktrace::parse_os::<NativeEndian>(&trace, Os::NetBSD).unwrap();
+    let calls = entries.iter()
+                       .filter_map(|e| match *e {
+                           Entry::Common(_, Ok(Record::SystemCall{number, ref args})) => {
+                               Some((syscalls::name(number as usize).unwrap(), args.clone()))
+                           },
+                           _ => None,
+                       })
+                       .collect::<Vec<_>>();
+
+    assert_eq!(calls, [
+        ("pread".to_string(), vec![3, 0x1000, 512, 4096]),
+        ("mmap".to_string(), vec![0, 8192, 1, 1, 3, 0]),
+        ("lseek".to_string(), vec![3, 100, 0]),
+    ]);
+}
+
+#[test]
+fn netbsd_strings_need_not_be_utf8() {
+    // KTR_EMUL and KTR_EXEC_ARG
+    let mut trace = netbsd_header(7, 2, 7, 1500000000, 0);
+    trace.extend(b"netbsd\0");
+    trace.extend(netbsd_header(10, 2, 10, 1500000000, 0));
+    trace.extend(b"ls\0caf\xe9\0\xff\0");
+
+    let entries = ktrace::parse_os::<NativeEndian>(&trace, Os::NetBSD).unwrap();
+    match entries[0] {
+        Entry::Specific{record: Ok(Extra::Emulation(ref name)), ..} => {
+            assert_eq!(name, "netbsd")
+        },
+        ref e => panic!["expected an emulation name, got {:?}", e],
+    }
+    match entries[1] {
+        Entry::Specific{record: Ok(Extra::ExecArgs(ref args)), ..} => {
+            let args = args.iter().map(|a| a.as_bytes()).collect::<Vec<_>>();
+            assert_eq!(args, [&b"ls"[..], b"caf\xe9", b"\xff"]);
+        },
+        ref e => panic!["expected exec arguments, got {:?}", e],
+    }
+}
+
+#[test]
+fn netbsd_errnos() {
+    // ENOTSUP has a different value in FreeBSD; ENOSR has no equivalent.
+    let mut trace = netbsd_call(5, &[0x1000, 0], 86, 0);
+    trace.extend(netbsd_call(5, &[0x1000, 0], 90, 0));
+
+    let entries = ktrace::parse_os::<NativeEndian>(&trace, Os::NetBSD).unwrap();
+    match entries[1] {
+        Entry::Common(_, Ok(Record::SystemCallReturn{error, ..})) => {
+            assert_eq!(ktrace::errno::name(error as i32).unwrap(), "EOPNOTSUPP")
+        },
+        ref e => panic!["expected a FreeBSD-equivalent return, got {:?}", e],
+    }
+    match entries[3] {
+        Entry::Specific{record: Ok(Extra::SystemCallReturn{ref name, error, ..}), ..} => {
+            assert_eq!((name.as_str(), error), ("open", 90))
+        },
+        ref e => panic!["expected a NetBSD-specific return, got {:?}", e],
+    }
+}
@@ -0,0 +1,117 @@
+// Copyright 2016 Jonathan Anderson <jonathan.anderson@mun.ca>
+//
+// This software was developed by BAE Systems, the University of Cambridge
+// Computer Laboratory, and Memorial University under DARPA/AFRL contract
+// FA8650-15-C-7558 ("CADETS"), as part of the DARPA Transparent Computing
+// (TC) research program.
+//
+// Licensed under the Apache License, Version 2.0,
+// <LICENSE-APACHE or http://apache.org/licenses/LICENSE-2.0>
+// or the MIT license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+
+extern crate byteorder;
+extern crate ktrace;
+
+mod common;
+
+use byteorder::{BigEndian,ByteOrder,LittleEndian};
+use common::{call,sockaddr_in};
+use ktrace::{IODir,PcapNg,Record,RecordType};
+
+const SOCKET: u16 = 97;
+const CONNECT: u16 = 98;
+const WRITE: u16 = 4;
+const RECVFROM: u16 = 29;
+
+
+fn genio(fd: i32, rw: IODir, data: &[u8]) -> Record {
+    Record::GenericIO { fd, rw, data: data.to_vec() }
+}
+
+fn capture(records: &[Record]) -> Vec<u8> {
+    let mut pcap = PcapNg::new(vec![]).unwrap();
+    for r in records {
+        pcap.record(&common::header(RecordType::SystemCall, 0), r).unwrap();
+    }
+
+    pcap.finish().unwrap()
+}
+
+/// Split a (little-endian) pcapng file into (type, body) blocks, checking
+/// that each block's length is repeated at its end.
+fn blocks(mut data: &[u8]) -> Vec<(u32, Vec<u8>)> {
+    let mut blocks = vec![];
+
+    while !data.is_empty() {
+        let block_type = LittleEndian::read_u32(&data[0..4]);
+        let length = LittleEndian::read_u32(&data[4..8]) as usize;
+        assert!(length >= 12 && length.is_multiple_of(4), "bad block length {}", length);
+        assert_eq!(LittleEndian::read_u32(&data[length - 4..length]) as usize, length);
+
+        blocks.push((block_type, data[8..length - 4].to_vec()));
+        data = &data[length..];
+    }
+
+    blocks
+}
+
+/// The IPv4 packets in a capture's Enhanced Packet Blocks.
+fn packets(blocks: &[(u32, Vec<u8>)]) -> Vec<Vec<u8>> {
+    blocks.iter()
+          .filter(|&&(t, _)| t == 6)
+          .map(|(_, body)| {
+              let length = LittleEndian::read_u32(&body[12..16]) as usize;
+              assert_eq!(LittleEndian::read_u32(&body[16..20]) as usize, length);
+              body[20..20 + length].to_vec()
+          })
+          .collect()
+}
+
+
+#[test]
+fn block_structure() {
+    let mut records = vec![];
+    call(&mut records, SOCKET, &[2, 1, 0], vec![], 3);
+    call(&mut records, CONNECT, &[3, 0x1000, 16],
+         vec![sockaddr_in([192, 0, 2, 1], 80)], 0);
+    call(&mut records, WRITE, &[3, 0x2000, 4],
+         vec![genio(3, IODir::Write, b"GET ")], 4);
+
+    let blocks = blocks(&capture(&records));
+    let types: Vec<_> = blocks.iter().map(|&(t, _)| t).collect();
+
+    // Section header, inet and unix interfaces, handshake and data
+    assert_eq!(types, [0x0a0d_0d0a, 1, 1, 6, 6, 6, 6]);
+    assert_eq!(LittleEndian::read_u32(&blocks[0].1[0..4]), 0x1a2b_3c4d);
+
+    let packets = packets(&blocks);
+    let data = &packets[3];
+    assert_eq!(data[9], 6);                             // TCP
+    assert_eq!(&data[16..20], &[192, 0, 2, 1]);         // to the peer
+    assert_eq!(BigEndian::read_u16(&data[22..24]), 80);
+    assert_eq!(&data[40..], b"GET ");
+}
+
+#[test]
+fn unconnected_recvfrom() {
+    let mut records = vec![];
+    call(&mut records, SOCKET, &[2, 2, 0], vec![], 3);
+
+    // The sender's address is copied out after the data.
+    call(&mut records, RECVFROM, &[3, 0x2000, 512, 0, 0x3000, 0x4000],
+         vec![genio(3, IODir::Read, b"answer"),
+              sockaddr_in([198, 51, 100, 7], 53)],
+         6);
+
+    let packets = packets(&blocks(&capture(&records)));
+    assert_eq!(packets.len(), 1);
+
+    let datagram = &packets[0];
+    assert_eq!(datagram[9], 17);                        // UDP
+    assert_eq!(&datagram[12..16], &[198, 51, 100, 7]);  // from the sender
+    assert_eq!(BigEndian::read_u16(&datagram[20..22]), 53);
+    assert_eq!(&datagram[28..], b"answer");
+}
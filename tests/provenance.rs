@@ -0,0 +1,119 @@
+// Copyright 2016 Jonathan Anderson <jonathan.anderson@mun.ca>
+//
+// This software was developed by BAE Systems, the University of Cambridge
+// Computer Laboratory, and Memorial University under DARPA/AFRL contract
+// FA8650-15-C-7558 ("CADETS"), as part of the DARPA Transparent Computing
+// (TC) research program.
+//
+// Licensed under the Apache License, Version 2.0,
+// <LICENSE-APACHE or http://apache.org/licenses/LICENSE-2.0>
+// or the MIT license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+
+extern crate ktrace;
+
+mod common;
+
+use common::call;
+use ktrace::{EdgeKind,Node,ProvenanceGraph,Record};
+
+
+fn edges(graph: &ProvenanceGraph) -> Vec<(Node, Node, EdgeKind, u64)> {
+    let nodes = graph.nodes();
+    graph.edges()
+         .iter()
+         .map(|e| (nodes[e.from].clone(), nodes[e.to].clone(), e.kind, e.count))
+         .collect()
+}
+
+
+#[test]
+fn io_edges_need_data() {
+    let mut graph = ProvenanceGraph::new();
+    let file = || Node::File("/tmp/log".into());
+    let process = Node::Process { pid: 100 };
+
+    call(&mut graph, 5, &[0x1000, 2], vec![Record::Namei("/tmp/log".into())], 3);
+
+    // Zero-byte reads and writes don't carry information.
+    call(&mut graph, 3, &[3, 0x2000, 0], vec![], 0);
+    call(&mut graph, 4, &[3, 0x2000, 0], vec![], 0);
+    assert!(graph.edges().is_empty());
+
+    call(&mut graph, 4, &[3, 0x2000, 5], vec![], 5);
+    call(&mut graph, 4, &[3, 0x2000, 5], vec![], 5);
+    call(&mut graph, 3, &[3, 0x2000, 5], vec![], 1);
+
+    assert_eq!(edges(&graph), [
+        (process.clone(), file(), EdgeKind::Write, 2),
+        (file(), process, EdgeKind::Read, 1),
+    ]);
+}
+
+#[test]
+fn fexecve_of_close_on_exec_descriptor() {
+    let mut graph = ProvenanceGraph::new();
+
+    // open("/bin/sh", O_RDONLY | O_CLOEXEC)
+    call(&mut graph, 5, &[0x1000, 0x0010_0000], vec![Record::Namei("/bin/sh".into())], 3);
+    call(&mut graph, 492, &[3, 0x2000, 0x3000], vec![], 0);
+
+    assert_eq!(edges(&graph), [
+        (Node::File("/bin/sh".into()), Node::Process { pid: 100 }, EdgeKind::Exec, 1),
+    ]);
+}
+
+#[test]
+fn relative_paths() {
+    let mut graph = ProvenanceGraph::new();
+    let process = || Node::Process { pid: 100 };
+    let file = |path: &str| Node::File(path.into());
+
+    // chdir("/home/user"), then open("/tmp", O_DIRECTORY) as descriptor 3
+    call(&mut graph, 12, &[0x1000], vec![Record::Namei("/home/user".into())], 0);
+    call(&mut graph, 5, &[0x1000, 0x0002_0000], vec![Record::Namei("/tmp".into())], 3);
+
+    // execve("bin/../bin/sh", ...)
+    call(&mut graph, 59, &[0x1000, 0x2000, 0x3000],
+         vec![Record::Namei("bin/../bin/sh".into())], 0);
+
+    // renameat(3, "a", AT_FDCWD, "b")
+    call(&mut graph, 501, &[3, 0x1000, -100_i64 as u64, 0x2000],
+         vec![Record::Namei("a".into()), Record::Namei("b".into())], 0);
+
+    // unlinkat(3, "./c", 0), unlink("d")
+    call(&mut graph, 503, &[3, 0x1000, 0], vec![Record::Namei("./c".into())], 0);
+    call(&mut graph, 10, &[0x1000], vec![Record::Namei("d".into())], 0);
+
+    assert_eq!(edges(&graph), [
+        (file("/home/user/bin/sh"), process(), EdgeKind::Exec, 1),
+        (file("/tmp/a"), file("/home/user/b"), EdgeKind::Rename, 1),
+        (process(), file("/tmp/c"), EdgeKind::Unlink, 1),
+        (process(), file("/home/user/d"), EdgeKind::Unlink, 1),
+    ]);
+}
+
+#[test]
+fn unnamed_sockets() {
+    let mut graph = ProvenanceGraph::new();
+    let process = || Node::Process { pid: 100 };
+
+    // socket(PF_LOCAL, SOCK_STREAM, 0) as descriptors 3 and 4
+    call(&mut graph, 97, &[1, 1, 0], vec![], 3);
+    call(&mut graph, 97, &[1, 1, 0], vec![], 4);
+
+    call(&mut graph, 4, &[3, 0x2000, 5], vec![], 5);
+    call(&mut graph, 3, &[4, 0x2000, 5], vec![], 5);
+
+    assert_eq!(edges(&graph), [
+        (process(), Node::UnnamedSocket { pid: 100, fd: 3 }, EdgeKind::Write, 1),
+        (Node::UnnamedSocket { pid: 100, fd: 4 }, process(), EdgeKind::Read, 1),
+    ]);
+
+    let mut json = vec![];
+    graph.write_json_lines(&mut json).unwrap();
+    assert!(String::from_utf8(json).unwrap()
+                .contains("{\"type\":\"node\",\"id\":1,\"kind\":\"socket\",\"pid\":100,\"fd\":3}\n"));
+}
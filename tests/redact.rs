@@ -0,0 +1,190 @@
+// Copyright 2016 Jonathan Anderson <jonathan.anderson@mun.ca>
+//
+// This software was developed by BAE Systems, the University of Cambridge
+// Computer Laboratory, and Memorial University under DARPA/AFRL contract
+// FA8650-15-C-7558 ("CADETS"), as part of the DARPA Transparent Computing
+// (TC) research program.
+//
+// Licensed under the Apache License, Version 2.0,
+// <LICENSE-APACHE or http://apache.org/licenses/LICENSE-2.0>
+// or the MIT license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+
+extern crate byteorder;
+extern crate ktrace;
+
+mod common;
+
+use byteorder::{BigEndian,ByteOrder,LittleEndian};
+use ktrace::{Header,Record,RedactOptions,Redactor,SockAddr};
+
+
+/// A parent (PID 4321) that forks a child (PID 4322), which looks up its
+/// parent, connects to a local socket and reads a sysctl about its parent.
+fn trace<E: ByteOrder>() -> Vec<u8> {
+    let mut sockaddr = vec![106, 1];
+    sockaddr.extend_from_slice(b"/var/run/secret.sock");
+    sockaddr.resize(106, 0);
+
+    let records = vec![
+        (4321, Record::SystemCall { number: 2, args: vec![] }),
+        (4321, Record::SystemCallReturn { code: 2, eosys: 0, error: 0, retval: 4322 }),
+        (4322, Record::SystemCall { number: 39, args: vec![] }),
+        (4322, Record::SystemCallReturn { code: 39, eosys: 0, error: 0, retval: 4321 }),
+        (4322, Record::SystemCall { number: 98, args: vec![3, 0x1000, 106] }),
+        (4322, Record::Struct { name: "sockaddr".into(), content: sockaddr }),
+        (4322, Record::SystemCallReturn { code: 98, eosys: 0, error: 0, retval: 0 }),
+        (4322, Record::SystemCall { number: 188, args: vec![0x2000, 0x3000] }),
+        (4322, Record::Struct { name: "stat".into(), content: vec![0xff; 224] }),
+        (4322, Record::SystemCallReturn { code: 188, eosys: 0, error: 0, retval: 0 }),
+        (4322, Record::Sysctl("kern.proc.pid.4321".into())),
+        (4321, Record::SystemCall { number: 37, args: vec![4322, 15] }),
+        (4321, Record::SystemCallReturn { code: 37, eosys: 0, error: 0, retval: 0 }),
+    ];
+
+    let mut trace = vec![];
+    for (pid, r) in records {
+        let (t, payload) = common::encode_as::<E>(&r);
+        let mut h = common::header(t, payload.len());
+        h.pid = pid;
+        h.tid = pid as usize * 1000;
+        common::append_as::<E>(&mut trace, &h, &payload);
+    }
+
+    trace
+}
+
+fn redact<E: ByteOrder>() -> Vec<(Header, Record)> {
+    let mut redacted = vec![];
+    let mut redactor = Redactor::<E>::new(RedactOptions::default());
+    let n = ktrace::redact(&mut trace::<E>().as_slice(), &mut redacted, &mut redactor)
+                   .unwrap();
+    assert_eq!(n, 13);
+
+    ktrace::parse::<E>(&mut redacted.as_slice())
+        .unwrap()
+        .into_iter()
+        .map(|(h, r)| (h, r.unwrap()))
+        .collect()
+}
+
+fn check(records: &[(Header, Record)]) {
+    let parent = records[0].0.pid;
+    let child = records[2].0.pid;
+    assert!(parent != 4321 && child != 4322 && parent != child);
+
+    // fork(2) returns the child's new PID, getppid(2) the parent's...
+    match records[1].1 {
+        Record::SystemCallReturn{retval, ..} => assert_eq!(retval, child as u64),
+        ref r => panic!["expected fork's return, got {:?}", r],
+    }
+    match records[3].1 {
+        Record::SystemCallReturn{retval, ..} => assert_eq!(retval, parent as u64),
+        ref r => panic!["expected getppid's return, got {:?}", r],
+    }
+
+    // ... and kill(2) is sent to the child's new PID.
+    match records[11].1 {
+        Record::SystemCall{ref args, ..} => assert_eq!(args[..], [child as u64, 15]),
+        ref r => panic!["expected a call to kill, got {:?}", r],
+    }
+
+    for (h, _) in records {
+        assert_eq!(h.command, "cmd1");
+    }
+
+    match records[5].1 {
+        Record::Struct{ref content, ..} => {
+            assert_eq!(content.len(), 106);
+            match SockAddr::parse::<LittleEndian>(content).unwrap() {
+                SockAddr::Unix(path) => {
+                    let path = path.to_str().unwrap();
+                    assert!(path.starts_with('/') && !path.contains("secret"));
+                },
+                a => panic!["expected a local socket address, got {}", a],
+            }
+        },
+        ref r => panic!["expected a sockaddr, got {:?}", r],
+    }
+
+    match records[8].1 {
+        Record::Struct{ref content, ..} => assert!(content.iter().all(|&b| b == 0)),
+        ref r => panic!["expected a stat structure, got {:?}", r],
+    }
+
+    match records[10].1 {
+        Record::Sysctl(ref name) => {
+            assert_eq!(name.to_string(), format!["kern.proc.pid.{}", parent]);
+        },
+        ref r => panic!["expected a sysctl, got {:?}", r],
+    }
+}
+
+
+#[test]
+fn redacted_trace_parses() {
+    check(&redact::<LittleEndian>());
+}
+
+#[test]
+fn redacted_big_endian_trace_parses() {
+    check(&redact::<BigEndian>());
+}
+
+#[test]
+fn linux_calls_and_init() {
+    const LINUX_OPEN: u16 = 2;
+    const LINUX_ALARM: u16 = 37;
+    const LINUX_KILL: u16 = 62;
+
+    let records = vec![
+        (4321, Record::ProcessCreation { flags: ktrace::Abi::Linux.flags() }),
+        (4321, Record::SystemCall { number: LINUX_OPEN, args: vec![0x1000, 0] }),
+        (4321, Record::SystemCallReturn { code: LINUX_OPEN, eosys: 0, error: 0, retval: 3 }),
+        (4321, Record::SystemCall { number: LINUX_ALARM, args: vec![4321] }),
+        (4321, Record::SystemCall { number: LINUX_KILL, args: vec![4321, 15] }),
+        (4321, Record::SystemCall { number: LINUX_KILL, args: vec![1, 1] }),
+        (1, Record::SystemCall { number: 37, args: vec![4321, 15] }),
+    ];
+
+    let mut trace = vec![];
+    for (pid, r) in records {
+        let (t, payload) = common::encode(&r);
+        let mut h = common::header(t, payload.len());
+        h.pid = pid;
+        common::append(&mut trace, &h, &payload);
+    }
+
+    let mut redacted = vec![];
+    let mut redactor = Redactor::<LittleEndian>::new(RedactOptions::default());
+    ktrace::redact(&mut trace.as_slice(), &mut redacted, &mut redactor).unwrap();
+
+    let records: Vec<_> = ktrace::parse::<LittleEndian>(&mut redacted.as_slice())
+                                .unwrap()
+                                .into_iter()
+                                .map(|(h, r)| (h.pid, r.unwrap()))
+                                .collect();
+    let pid = records[0].0;
+    assert_ne!(pid, 4321);
+
+    let args = |r: &Record| match *r {
+        Record::SystemCall{ref args, ..} => args.clone(),
+        ref r => panic!["expected a call, got {:?}", r],
+    };
+
+    // Linux's open(2) and alarm(2) have the numbers of FreeBSD's fork(2)
+    // and kill(2), but only Linux's kill(2) involves PIDs.
+    match records[2].1 {
+        Record::SystemCallReturn{retval, ..} => assert_eq!(retval, 3),
+        ref r => panic!["expected open's return, got {:?}", r],
+    }
+    assert_eq!(args(&records[3].1), [4321]);
+    assert_eq!(args(&records[4].1), [pid as u64, 15]);
+
+    // init is PID 1 wherever it appears.
+    assert_eq!(args(&records[5].1), [1, 1]);
+    assert_eq!(records[6].0, 1);
+    assert_eq!(args(&records[6].1), [pid as u64, 15]);
+}
@@ -0,0 +1,183 @@
+// Copyright 2016 Jonathan Anderson <jonathan.anderson@mun.ca>
+//
+// This software was developed by BAE Systems, the University of Cambridge
+// Computer Laboratory, and Memorial University under DARPA/AFRL contract
+// FA8650-15-C-7558 ("CADETS"), as part of the DARPA Transparent Computing
+// (TC) research program.
+//
+// Licensed under the Apache License, Version 2.0,
+// <LICENSE-APACHE or http://apache.org/licenses/LICENSE-2.0>
+// or the MIT license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+
+extern crate byteorder;
+extern crate ktrace;
+extern crate nix;
+extern crate proptest;
+
+mod common;
+
+use byteorder::{BigEndian,LittleEndian};
+use common::{c_string,record};
+use ktrace::{CapFail,Header,Record,RecordType,SockAddr};
+use nix::sys::time::TimeVal;
+use proptest::prelude::*;
+use std::io::Cursor;
+
+
+fn header() -> impl Strategy<Value = Header> {
+    (1..15_u16, any::<u32>(), any::<u32>(), c_string(0..20),
+     any::<i64>(), 0..1_000_000_i64, any::<usize>())
+        .prop_map(|(t, length, pid, command, sec, usec, tid)| Header {
+            length: length as usize,
+            record_type: RecordType::from_u16(t).unwrap(),
+            pid,
+            command: command.into(),
+            timestamp: TimeVal { tv_sec: sec, tv_usec: usec },
+            tid,
+        })
+}
+
+
+proptest! {
+    #[test]
+    fn header_round_trip(h in header()) {
+        let parsed = Header::parse::<LittleEndian>(&h.to_bytes::<LittleEndian>()).unwrap();
+        prop_assert_eq!(format!["{:?}", parsed], format!["{:?}", h]);
+    }
+
+    #[test]
+    fn record_round_trip(r in record()) {
+        let (t, payload) = common::encode(&r);
+        let parsed = Record::parse::<LittleEndian>(&payload, &t).unwrap();
+        prop_assert_eq!(format!["{:?}", parsed], format!["{:?}", r]);
+    }
+
+    #[test]
+    fn capfail_round_trip(cap_type in 0..4_u32,
+                          words in prop::collection::vec(any::<u64>(), 1..4)) {
+        // Two cap_rights_t of the same size
+        let mut rights = words.clone();
+        rights.extend_from_slice(&words);
+
+        let payload = common::capfail(cap_type, &rights);
+        let parsed = Record::parse::<LittleEndian>(&payload,
+                                                   &RecordType::CapabilityFailure)
+                            .unwrap();
+
+        let fail = match parsed {
+            Record::CapabilityFailure(fail) => fail,
+            other => panic!["expected a capability failure, got {:?}", other],
+        };
+
+        match (cap_type, fail) {
+            (0, CapFail::NotCapable{held, needed}) => {
+                prop_assert_eq!(held.to_string(), needed.to_string());
+            },
+            (1, CapFail::Increase) | (2, CapFail::Syscall) | (3, CapFail::Lookup) => {},
+            (t, fail) => panic!["type {} parsed as {:?}", t, fail],
+        }
+    }
+
+    #[test]
+    fn trace_round_trip(records in prop::collection::vec(record(), 0..32)) {
+        let mut trace = vec![];
+        for r in &records {
+            let (t, payload) = common::encode(r);
+            common::append(&mut trace, &common::header(t, payload.len()), &payload);
+        }
+
+        let parsed = ktrace::parse::<LittleEndian>(&mut trace.as_slice()).unwrap();
+        prop_assert_eq!(parsed.len(), records.len());
+
+        for ((_, parsed), r) in parsed.iter().zip(&records) {
+            let parsed = parsed.as_ref().unwrap();
+            prop_assert_eq!(format!["{:?}", parsed], format!["{:?}", r]);
+        }
+
+        // The zero-copy and parallel parsers agree with `parse`.
+        let borrowed: Vec<_> = ktrace::parse_slice::<LittleEndian>(&trace)
+                                      .map(|r| r.unwrap())
+                                      .map(|(h, r)| (h.into_owned(), r.unwrap().into_owned()))
+                                      .collect();
+        for ((h, _), (parsed, _)) in borrowed.iter().zip(&parsed) {
+            prop_assert_eq!(format!["{:?}", h], format!["{:?}", parsed]);
+        }
+        let borrowed: Vec<_> = borrowed.into_iter().map(|(_, r)| r).collect();
+        let parallel: Vec<_> = ktrace::parse_parallel::<LittleEndian, _>(Cursor::new(trace), 3)
+                                      .map(|r| r.unwrap().1.unwrap())
+                                      .collect();
+
+        prop_assert_eq!(format!["{:?}", borrowed], format!["{:?}", records]);
+        prop_assert_eq!(format!["{:?}", parallel], format!["{:?}", records]);
+    }
+
+    #[test]
+    fn big_endian_merge_round_trip(records in prop::collection::vec(record(), 0..16)) {
+        let mut trace = vec![];
+        for r in &records {
+            let (t, payload) = common::encode_as::<BigEndian>(r);
+            common::append_as::<BigEndian>(&mut trace, &common::header(t, payload.len()),
+                                           &payload);
+        }
+
+        let mut merge = ktrace::Merge::<BigEndian>::new();
+        merge.add(Cursor::new(trace.clone()));
+
+        let mut merged = vec![];
+        for r in merge {
+            merged.extend(r.unwrap().to_bytes::<BigEndian>());
+        }
+        prop_assert_eq!(merged, trace);
+    }
+}
+
+#[test]
+fn non_utf8_strings() {
+    let mut h = common::header(RecordType::Namei, 10);
+    h.command = b"caf\xe9"[..].into();
+
+    let parsed = Header::parse::<LittleEndian>(&h.to_bytes::<LittleEndian>()).unwrap();
+    assert_eq!(parsed.command.as_bytes(), b"caf\xe9");
+    assert_eq!(parsed.command.to_string(), "caf\\xe9");
+
+    let path = Record::parse::<LittleEndian>(b"/tmp/\xff\xfe.txt", &RecordType::Namei)
+                      .unwrap();
+    match path {
+        Record::Namei(ref p) => assert_eq!(p.to_string(), "/tmp/\\xff\\xfe.txt"),
+        other => panic!["expected a namei record, got {:?}", other],
+    }
+}
+
+#[test]
+fn struct_content_follows_name() {
+    let data = b"sockaddr\0\x10\x1c\x00\x50";
+
+    match Record::parse::<LittleEndian>(data, &RecordType::Struct).unwrap() {
+        Record::Struct{ref name, ref content} => {
+            assert_eq!(name.as_bytes(), b"sockaddr");
+            assert_eq!(content, b"\x10\x1c\x00\x50");
+        },
+        other => panic!["expected a struct record, got {:?}", other],
+    }
+
+    match Record::parse::<LittleEndian>(b"sockaddr\0", &RecordType::Struct).unwrap() {
+        Record::Struct{ref content, ..} => assert!(content.is_empty()),
+        other => panic!["expected a struct record, got {:?}", other],
+    }
+}
+
+#[test]
+fn unix_socket_paths_need_not_be_utf8() {
+    let data = b"\x0c\x01/tmp/\xff\xfes\0";
+
+    match SockAddr::parse::<LittleEndian>(data).unwrap() {
+        SockAddr::Unix(ref path) => {
+            assert_eq!(path.as_bytes(), b"/tmp/\xff\xfes");
+            assert_eq!(path.to_string(), "/tmp/\\xff\\xfes");
+        },
+        other => panic!["expected a local socket address, got {}", other],
+    }
+}
@@ -0,0 +1,77 @@
+// Copyright 2016 Jonathan Anderson <jonathan.anderson@mun.ca>
+//
+// This software was developed by BAE Systems, the University of Cambridge
+// Computer Laboratory, and Memorial University under DARPA/AFRL contract
+// FA8650-15-C-7558 ("CADETS"), as part of the DARPA Transparent Computing
+// (TC) research program.
+//
+// Licensed under the Apache License, Version 2.0,
+// <LICENSE-APACHE or http://apache.org/licenses/LICENSE-2.0>
+// or the MIT license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+
+extern crate ktrace;
+extern crate nix;
+
+mod common;
+
+use ktrace::{IODir,Record,RecordType,Stats};
+use nix::sys::time::TimeVal;
+
+const READ: u16 = 3;
+const EINTR: u32 = 4;
+
+
+/// A read(2) that starts at `start` µs into the trace and takes `latency`
+/// µs, recording `data`.
+fn read(stats: &mut Stats, start: i64, latency: i64, data: &[u8], error: u32) {
+    let at = |usec: i64| {
+        let mut h = common::header(RecordType::SystemCall, 0);
+        h.timestamp = TimeVal { tv_sec: 1000 + usec / 1_000_000, tv_usec: usec % 1_000_000 };
+        h
+    };
+
+    let retval = if error == 0 { data.len() as u64 } else { !0 };
+    stats.record(&at(start), &Record::SystemCall { number: READ, args: vec![3, 0x1000, 64] });
+    stats.record(&at(start), &Record::GenericIO { fd: 3, rw: IODir::Read, data: data.to_vec() });
+    stats.record(&at(start + latency),
+                 &Record::SystemCallReturn { code: READ, eosys: 0, error, retval });
+}
+
+
+#[test]
+fn percentiles() {
+    let mut stats = Stats::new();
+
+    // Latencies of 100, 200, ..., 1000 µs, in a shuffled order and across
+    // a second boundary
+    for (i, &n) in [7, 3, 10, 1, 5, 9, 2, 8, 4, 6].iter().enumerate() {
+        read(&mut stats, 999_000 + 2000 * i as i64, 100 * n, b"data", 0);
+    }
+    read(&mut stats, 1_100_000, 50, b"", EINTR);
+
+    let s = stats.syscalls.get_mut("read").unwrap();
+    assert_eq!((s.calls, s.error_count(), s.errors.get(&(EINTR as i32))), (11, 1, Some(&1)));
+    assert_eq!((s.min(), s.max(), s.total()), (Some(50), Some(1000), 5550));
+    assert_eq!(s.bytes_read, 40);
+
+    // nearest-rank percentiles over 11 samples
+    assert_eq!(s.percentile(0.0), Some(50));
+    assert_eq!(s.percentile(50.0), Some(500));
+    assert_eq!(s.percentile(90.0), Some(900));
+    assert_eq!(s.percentile(95.0), Some(1000));
+    assert_eq!(s.percentile(100.0), Some(1000));
+
+    assert_eq!(stats.processes[&100].syscalls["read"].calls, 11);
+
+    let mut json = vec![];
+    stats.write_json(&mut json).unwrap();
+    let json = String::from_utf8(json).unwrap();
+    assert!(json.starts_with("{\"syscalls\":[{\"name\":\"read\",\"calls\":11,\
+                              \"errors\":{\"EINTR\":1},\"total_us\":5550,\
+                              \"min_us\":50,\"max_us\":1000,\"p50_us\":500,\
+                              \"p95_us\":1000,\"p99_us\":1000,\
+                              \"bytes_read\":40,\"bytes_written\":0}],"));
+}
@@ -0,0 +1,312 @@
+// Copyright 2016 Jonathan Anderson <jonathan.anderson@mun.ca>
+//
+// This software was developed by BAE Systems, the University of Cambridge
+// Computer Laboratory, and Memorial University under DARPA/AFRL contract
+// FA8650-15-C-7558 ("CADETS"), as part of the DARPA Transparent Computing
+// (TC) research program.
+//
+// Licensed under the Apache License, Version 2.0,
+// <LICENSE-APACHE or http://apache.org/licenses/LICENSE-2.0>
+// or the MIT license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+
+extern crate byteorder;
+extern crate ktrace;
+
+use byteorder::NativeEndian;
+use ktrace::{Abi,Call,CallTracker,Header,IODir,Os,Record,SockAddr,Stats};
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+
+fn import(name: &str) -> Vec<(Header, Record)> {
+    let path = Path::new(env!["CARGO_MANIFEST_DIR"]).join("tests/strace").join(name);
+    let mut log = BufReader::new(File::open(path).unwrap());
+
+    ktrace::parse_strace(&mut log)
+        .unwrap()
+        .into_iter()
+        .map(|(h, r)| (h, r.unwrap()))
+        .collect()
+}
+
+fn calls(records: &[(Header, Record)]) -> Vec<Call> {
+    let mut tracker = CallTracker::new();
+    records.iter()
+           .filter_map(|(h, r)| tracker.record(h, r))
+           .collect()
+}
+
+
+#[test]
+fn processes_are_tagged_with_linux_abi() {
+    let records = import("cat.log");
+
+    let abis = records.iter()
+                      .filter_map(|(h, r)| match *r {
+                          Record::ProcessCreation{flags} => {
+                              Some((h.pid, Abi::from_flags(flags)))
+                          },
+                          _ => None,
+                      })
+                      .collect::<Vec<_>>();
+
+    assert_eq!(abis, vec![(4100, Abi::Linux), (4200, Abi::Linux)]);
+    assert!(matches!(records[0].1, Record::ProcessCreation{..}));
+}
+
+#[test]
+fn calls_use_linux_numbers() {
+    let records = import("cat.log");
+    let names = calls(&records).iter()
+                               .filter(|c| c.pid == 4100 && c.tid == 4100)
+                               .map(|c| c.name())
+                               .collect::<Vec<_>>();
+
+    assert_eq!(names, vec!["execve", "brk", "openat", "openat", "read",
+                           "clone", "write", "clone", "read", "close"]);
+
+    // openat is 257 on x86-64 (and 499 on FreeBSD).
+    assert!(records.iter().any(|(_, r)| match *r {
+        Record::SystemCall{number, ..} => number == 257,
+        _ => false,
+    }));
+}
+
+#[test]
+fn results_and_errors() {
+    let records = import("cat.log");
+    let calls = calls(&records);
+
+    let failed = calls.iter().filter(|c| !c.succeeded()).collect::<Vec<_>>();
+    assert_eq!(failed.len(), 2);
+
+    assert_eq!(failed[0].name(), "openat");
+    assert_eq!(ktrace::errno::name(failed[0].error).unwrap(), "ENOENT");
+    assert_eq!(failed[0].paths, vec!["/etc/nope"]);
+    assert_eq!(failed[0].args[0] as i64, -100);
+    assert_eq!(failed[0].args[2], 0x80000);       // O_RDONLY|O_CLOEXEC
+
+    assert_eq!(failed[1].name(), "kill");
+    assert_eq!(ktrace::errno::name(failed[1].error).unwrap(), "EPERM");
+    assert_eq!(failed[1].args[0], 4100);
+    assert_eq!(failed[1].args[1], 10);              // SIGUSR1
+
+    let open = calls.iter().find(|c| c.name() == "openat" && c.succeeded()).unwrap();
+    assert_eq!(open.retval, 3);
+    assert_eq!(open.paths, vec!["/etc/hostname"]);
+}
+
+#[test]
+fn symbolic_arguments() {
+    let net = calls(&import("net.log"));
+    let args = |name: &str| net.iter().find(|c| c.name() == name).unwrap().args.clone();
+
+    assert_eq!(args("socket"), vec![2, 0x80001, 0]);     // AF_INET, SOCK_STREAM|SOCK_CLOEXEC
+    assert_eq!(args("mmap")[2..4], [0x3, 0x22]);          // PROT_READ|PROT_WRITE, MAP_PRIVATE|MAP_ANONYMOUS
+    assert_eq!(args("fcntl")[1..], [4, 0x802]);           // F_SETFL, O_RDWR|O_NONBLOCK
+    assert_eq!(args("kill")[1], 10);                      // SIGUSR1
+
+    // clone(2)'s flags include its exit signal.
+    let clone = calls(&import("cat.log")).into_iter()
+                                         .filter(|c| c.name() == "clone")
+                                         .map(|c| c.args[1])
+                                         .collect::<Vec<_>>();
+    assert_eq!(clone, vec![0x50f00, 0x1200011]);
+}
+
+#[test]
+fn socket_addresses() {
+    let records = import("net.log");
+    let addresses = calls(&records).iter()
+        .filter_map(|c| c.find_struct("sockaddr").map(|s| (c.name(), s.to_vec())))
+        .map(|(name, s)| (name, SockAddr::parse::<NativeEndian>(&s).unwrap().to_string()))
+        .collect::<Vec<_>>();
+
+    assert_eq!(addresses, vec![
+        ("connect".to_string(), "93.184.216.34:80".to_string()),
+        ("bind".to_string(), "[::1]:5353".to_string()),
+        ("recvfrom".to_string(), "[::1]:40000".to_string()),
+        ("connect".to_string(), "unix:/run/nscd/socket".to_string()),
+    ]);
+
+    // Structures are recorded between the call and its return.
+    let i = records.iter()
+                   .position(|(_, r)| matches!(*r, Record::Struct{..}))
+                   .unwrap();
+    assert!(matches!(records[i - 1].1, Record::SystemCall{number: 42, ..}));
+    assert!(matches!(records[i + 1].1, Record::SystemCallReturn{code: 42, ..}));
+    assert_eq!(records[i].0.length, "sockaddr".len() + 1 + 16);
+}
+
+#[test]
+fn durations_and_unfinished_calls() {
+    let records = import("cat.log");
+    let calls = calls(&records);
+    let micros = |c: &Call| {
+        let start = c.start.unwrap();
+        (c.end.tv_sec - start.tv_sec) * 1_000_000 + c.end.tv_usec - start.tv_usec
+    };
+
+    // -T gives the duration of a complete call...
+    let read = calls.iter().find(|c| c.name() == "read").unwrap();
+    assert_eq!(micros(read), 10);
+
+    // ... and a resumed call returns when it's resumed.
+    let write = calls.iter().find(|c| c.name() == "write").unwrap();
+    assert_eq!(micros(write), 500);
+    assert_eq!(write.retval, 7);
+}
+
+#[test]
+fn timestamps_roll_over_at_midnight() {
+    let records = import("cat.log");
+    let times = records.iter()
+                       .map(|(h, _)| h.timestamp.tv_sec * 1_000_000 + h.timestamp.tv_usec)
+                       .collect::<Vec<_>>();
+
+    assert_eq!(times[0], (23 * 3600 + 59 * 60 + 59) * 1_000_000 + 100_000);
+    assert_eq!(*times.last().unwrap(), 86400 * 1_000_000 + 6200);
+    assert!(times.windows(2).all(|w| w[0] <= w[1]));
+}
+
+#[test]
+fn hex_dumps_become_io() {
+    let records = import("cat.log");
+
+    let io = records.iter()
+                    .enumerate()
+                    .filter_map(|(i, (_, r))| match *r {
+                        Record::GenericIO{fd, ref rw, ref data} => {
+                            Some((i, fd, rw.clone(), data.clone()))
+                        },
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>();
+
+    assert_eq!(io.len(), 2);
+    assert_eq!(io[0].1, 3);
+    assert!(matches!(io[0].2, IODir::Read));
+    assert_eq!(io[1].1, 1);
+    assert!(matches!(io[1].2, IODir::Write));
+
+    for (i, _, _, data) in io {
+        assert_eq!(data, b"bsdbox\n");
+
+        // I/O is recorded while the call is in progress.
+        assert!(matches!(records[i + 1].1, Record::SystemCallReturn{..}));
+    }
+}
+
+#[test]
+fn threads_and_children() {
+    let records = import("cat.log");
+
+    // The thread's records belong to its process.
+    let (getpid, _) = records.iter()
+                             .find(|(h, _)| h.tid == 4101)
+                             .unwrap();
+    assert_eq!(getpid.pid, 4100);
+    assert_eq!(getpid.command, "cat");
+
+    // A forked child inherits its parent's command.
+    let (child, _) = records.iter().find(|(h, _)| h.pid == 4200).unwrap();
+    assert_eq!(child.command, "cat");
+
+    // Only processes (not threads) are destroyed.
+    let destroyed = records.iter()
+                           .filter(|(_, r)| matches!(*r, Record::ProcessDestruction))
+                           .map(|(h, _)| h.pid)
+                           .collect::<Vec<_>>();
+    assert_eq!(destroyed, vec![4200, 4100]);
+}
+
+#[test]
+fn signals_use_freebsd_numbers() {
+    let records = import("cat.log");
+    let signals = records.iter()
+                         .filter_map(|(_, r)| match *r {
+                             Record::Signal{signo, ..} => Some(signo),
+                             _ => None,
+                         })
+                         .collect::<Vec<_>>();
+
+    assert_eq!(signals, vec![20]);
+}
+
+#[test]
+fn thirty_two_bit_processes() {
+    let records = import("i386.log");
+    let abi = records.iter()
+                     .filter_map(|(_, r)| match *r {
+                         Record::ProcessCreation{flags} => Some(Abi::from_flags(flags)),
+                         _ => None,
+                     })
+                     .next_back();
+    assert_eq!(abi, Some(Abi::LinuxI386));
+
+    let names = calls(&records).iter().map(|c| c.name()).collect::<Vec<_>>();
+    assert_eq!(names, vec!["execve", "brk", "write"]);
+    assert_eq!(records[0].0.command, "");
+    assert_eq!(records.last().unwrap().0.command, "hello32");
+}
+
+#[test]
+fn analyses_work_on_imported_logs() {
+    let records = import("cat.log");
+
+    let mut stats = Stats::new();
+    for (h, r) in &records {
+        stats.record(h, r);
+    }
+
+    let mut table = vec![];
+    stats.write_table(&mut table, false).unwrap();
+    let table = String::from_utf8(table).unwrap();
+
+    let line = |name: &str| {
+        table.lines()
+             .find(|l| l.split_whitespace().next() == Some(name))
+             .map(|l| l.split_whitespace().map(str::to_string).collect::<Vec<_>>())
+             .unwrap()
+    };
+
+    assert_eq!(line("openat")[1..3], ["2", "1"]);
+    assert_eq!(line("read").last().unwrap(), "0");
+    assert_eq!(line("read")[8], "7");
+    assert_eq!(line("write").last().unwrap(), "7");
+}
+
+#[test]
+fn logs_are_detected() {
+    for name in &["cat.log", "i386.log", "net.log"] {
+        let path = Path::new(env!["CARGO_MANIFEST_DIR"]).join("tests/strace").join(name);
+        let data = std::fs::read(path).unwrap();
+
+        assert_eq!(Os::detect(&data), Os::Linux);
+        let entries = ktrace::parse_os::<NativeEndian>(&data, Os::Linux);
+        assert!(entries.is_ok());
+    }
+}
+
+#[test]
+fn errors_give_line_numbers() {
+    let log = "\
+1234  10:00:00.000001 read(3, \"hi\", 2) = 2 <0.000010>
+ | 00000  68 69                                             hi               |
+1234  10:00:00.000002 frobnicate(3) = 0 <0.000010>
+";
+
+    let records = ktrace::parse_strace(&mut log.as_bytes()).unwrap();
+    let e = records.iter()
+                   .filter_map(|(_, r)| r.as_ref().err())
+                   .next()
+                   .expect("unknown system call accepted");
+
+    // Lines aren't byte offsets (and the hex dump adds a record).
+    assert_eq!((e.index(), e.offset()), (None, None));
+    assert!(e.to_string().starts_with("line 3: unknown"), "{}", e);
+}
@@ -0,0 +1,135 @@
+// Copyright 2016 Jonathan Anderson <jonathan.anderson@mun.ca>
+//
+// This software was developed by BAE Systems, the University of Cambridge
+// Computer Laboratory, and Memorial University under DARPA/AFRL contract
+// FA8650-15-C-7558 ("CADETS"), as part of the DARPA Transparent Computing
+// (TC) research program.
+//
+// Licensed under the Apache License, Version 2.0,
+// <LICENSE-APACHE or http://apache.org/licenses/LICENSE-2.0>
+// or the MIT license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+
+extern crate ktrace;
+
+mod common;
+
+use common::call;
+use ktrace::{Descriptor,IODir,Record,RecordType,StreamExtractor};
+use std::fs;
+
+const READ: u16 = 3;
+const WRITE: u16 = 4;
+const OPEN: u16 = 5;
+const CLOSE: u16 = 6;
+
+
+/// An I/O call on `fd` that transferred `transferred` bytes, of which the
+/// kernel recorded `data`.
+fn io(streams: &mut StreamExtractor, rw: IODir, fd: i32, data: &[u8], transferred: u64) {
+    let number = match rw { IODir::Read => READ, IODir::Write => WRITE };
+    let genio = Record::GenericIO { fd, rw, data: data.to_vec() };
+    call(streams, number, &[fd as u64, 0x1000, transferred], vec![genio], transferred);
+}
+
+
+#[test]
+fn gaps() {
+    let mut streams = StreamExtractor::new();
+
+    call(&mut streams, OPEN, &[0x1000, 2], vec![Record::Namei("/tmp/a".into())], 3);
+    io(&mut streams, IODir::Read, 3, b"abcd", 10);
+    io(&mut streams, IODir::Read, 3, b"efgh", 4);
+    io(&mut streams, IODir::Read, 3, b"", 0);
+    io(&mut streams, IODir::Write, 3, b"xy", 2);
+    io(&mut streams, IODir::Read, 3, b"ij", 5);
+
+    // Reusing the descriptor starts new streams.
+    call(&mut streams, CLOSE, &[3], vec![], 0);
+    call(&mut streams, OPEN, &[0x1000, 0], vec![Record::Namei("/tmp/b".into())], 3);
+    io(&mut streams, IODir::Read, 3, b"new", 3);
+
+    let streams = streams.into_streams();
+    assert_eq!(streams.len(), 3);
+
+    let a = &streams[0];
+    assert_eq!(a.descriptor, Some(Descriptor::File("/tmp/a".into())));
+    assert_eq!(a.data, b"abcdefghij");
+    assert_eq!(a.chunks, 4);
+    assert_eq!(a.gaps, [(4, 6), (10, 3)]);
+    assert_eq!(a.truncated(), 9);
+
+    assert_eq!(streams[1].data, b"xy");
+    assert!(streams[1].gaps.is_empty());
+    assert_eq!(streams[1].file_name(), "100-fd3-write.bin");
+
+    assert_eq!(streams[2].descriptor, Some(Descriptor::File("/tmp/b".into())));
+    assert_eq!(streams[2].data, b"new");
+
+    let dir = std::env::temp_dir().join(format!["ktrace-streams-{}", std::process::id()]);
+    ktrace::write_streams(&streams, &dir).unwrap();
+    let index = fs::read_to_string(dir.join("index.txt")).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert!(index.starts_with("0000-100-fd3-read.bin pid=100 command=test fd=3 dir=Read \
+                               bytes=10 chunks=4 truncated=9 target=/tmp/a\n\
+                               \x20   gap at offset 4: 6 B not recorded\n\
+                               \x20   gap at offset 10: 3 B not recorded\n"));
+}
+
+#[test]
+fn chunked_calls() {
+    const READV: u16 = 120;
+
+    let mut streams = StreamExtractor::new();
+    call(&mut streams, OPEN, &[0x1000, 0], vec![Record::Namei("/tmp/a".into())], 3);
+
+    // A readv(2) that transferred 10 B, of which 4 + 2 B were recorded.
+    call(&mut streams, READV, &[3, 0x1000, 2], vec![
+        Record::GenericIO { fd: 3, rw: IODir::Read, data: b"abcd".to_vec() },
+        Record::GenericIO { fd: 3, rw: IODir::Read, data: b"ef".to_vec() },
+    ], 10);
+
+    // A readv(2) whose chunks were all recorded.
+    call(&mut streams, READV, &[3, 0x1000, 2], vec![
+        Record::GenericIO { fd: 3, rw: IODir::Read, data: b"gh".to_vec() },
+        Record::GenericIO { fd: 3, rw: IODir::Read, data: b"ij".to_vec() },
+    ], 4);
+
+    let streams = streams.into_streams();
+    assert_eq!(streams.len(), 1);
+    assert_eq!(streams[0].data, b"abcdefghij");
+    assert_eq!(streams[0].chunks, 4);
+    assert_eq!(streams[0].gaps, [(6, 4)]);
+}
+
+#[test]
+fn linux_close() {
+    const LINUX_READ: u16 = 0;
+    const LINUX_OPEN: u16 = 2;
+    const LINUX_CLOSE: u16 = 3;
+
+    let mut streams = StreamExtractor::new();
+    streams.record(&common::header(RecordType::ProcessCreation, 4),
+                   &Record::ProcessCreation { flags: ktrace::Abi::Linux.flags() });
+
+    let read = |streams: &mut StreamExtractor, data: &[u8]| {
+        let genio = Record::GenericIO { fd: 3, rw: IODir::Read, data: data.to_vec() };
+        call(streams, LINUX_READ, &[3, 0x1000, 0x1000], vec![genio], data.len() as u64);
+    };
+
+    // Reopening the same file after close(2) starts a new stream, even
+    // though FreeBSD's close(2) has a different number.
+    call(&mut streams, LINUX_OPEN, &[0x1000, 0], vec![Record::Namei("/tmp/a".into())], 3);
+    read(&mut streams, b"abc");
+    call(&mut streams, LINUX_CLOSE, &[3], vec![], 0);
+    call(&mut streams, LINUX_OPEN, &[0x1000, 0], vec![Record::Namei("/tmp/a".into())], 3);
+    read(&mut streams, b"abc");
+
+    let streams = streams.into_streams();
+    assert_eq!(streams.len(), 2);
+    assert_eq!(streams[0].descriptor, Some(Descriptor::File("/tmp/a".into())));
+    assert_eq!(streams[1].descriptor, Some(Descriptor::File("/tmp/a".into())));
+}
@@ -0,0 +1,108 @@
+// Copyright 2016 Jonathan Anderson <jonathan.anderson@mun.ca>
+//
+// This software was developed by BAE Systems, the University of Cambridge
+// Computer Laboratory, and Memorial University under DARPA/AFRL contract
+// FA8650-15-C-7558 ("CADETS"), as part of the DARPA Transparent Computing
+// (TC) research program.
+//
+// Licensed under the Apache License, Version 2.0,
+// <LICENSE-APACHE or http://apache.org/licenses/LICENSE-2.0>
+// or the MIT license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+
+extern crate ktrace;
+
+mod common;
+
+use ktrace::{Record,RecordType,Sysctls};
+
+const SYSCTL: u16 = 202;
+const EPERM: i32 = 1;
+
+
+/// Feed a `__sysctl(name, namelen, old, oldlenp, new, newlen)` call that
+/// logs `names` to `sysctls`.
+fn sysctl(sysctls: &mut Sysctls, names: &[&[u8]], new: u64, error: i32) {
+    let args = vec![0x1000, 2, 0x2000, 0x3000, new, if new == 0 { 0 } else { 4 }];
+    let mut records = vec![Record::SystemCall { number: SYSCTL, args }];
+    records.extend(names.iter().map(|&n| Record::Sysctl(n.into())));
+    records.push(Record::SystemCallReturn {
+        code: SYSCTL,
+        eosys: 0,
+        error: error as u32,
+        retval: if error == 0 { 0 } else { !0 },
+    });
+
+    for r in &records {
+        sysctls.record(&common::header(RecordType::SystemCall, 0), r);
+    }
+}
+
+
+#[test]
+fn reads_and_writes() {
+    let mut sysctls = Sysctls::new();
+
+    sysctl(&mut sysctls, &[b"kern.ostype"], 0, 0);
+    sysctl(&mut sysctls, &[b"kern.ostype"], 0, 0);
+    sysctl(&mut sysctls, &[b"kern.hostname"], 0x4000, 0);
+    sysctl(&mut sysctls, &[b"kern.hostname"], 0x4000, EPERM);
+    sysctl(&mut sysctls, &[b"kern.\xff"], 0, 0);
+
+    // Records outside of a sysctl call aren't attributed to anything.
+    sysctls.record(&common::header(RecordType::Sysctl, 0), &Record::Sysctl("vm.stats".into()));
+
+    let oids = &sysctls.commands[&"test".into()];
+    assert_eq!(oids.len(), 3);
+
+    let ostype = &oids[&"kern.ostype".into()];
+    assert_eq!((ostype.reads, ostype.writes), (2, 0));
+    assert_eq!(ostype.syscall, "__sysctl");
+    assert!(ostype.errors.is_empty());
+
+    let hostname = &oids[&"kern.hostname".into()];
+    assert_eq!((hostname.reads, hostname.writes), (0, 2));
+    assert_eq!(hostname.errors.get(&EPERM), Some(&1));
+
+    // Names that aren't valid UTF-8 keep their bytes.
+    assert_eq!(oids[&b"kern.\xff"[..].into()].reads, 1);
+
+    let mut table = vec![];
+    sysctls.write_table(&mut table).unwrap();
+    let table = String::from_utf8(table).unwrap();
+    assert!(table.starts_with("test:\n"));
+    assert!(table.contains("kern.\\xff"));
+    assert!(table.contains("EPERM"));
+}
+
+#[test]
+fn descriptions() {
+    assert_eq!(ktrace::sysctl::lookup("kern.ostype").map(|o| o.name), Some("kern.ostype"));
+    assert_eq!(ktrace::sysctl::lookup("kern.proc.pid.1234").map(|o| o.name),
+               Some("kern.proc.pid"));
+
+    // Unlisted OIDs aren't described by the nodes above them.
+    assert!(ktrace::sysctl::lookup("kern.ipc.nmbclusters").is_none());
+    assert!(ktrace::sysctl::lookup("kern.proc.all.1234").is_none());
+
+    let mut sysctls = Sysctls::new();
+    sysctl(&mut sysctls, &[b"kern.ipc.nmbclusters"], 0, 0);
+    sysctl(&mut sysctls, &[b"kern.proc.pid.1234"], 0, 0);
+
+    let mut table = vec![];
+    sysctls.write_table(&mut table).unwrap();
+    let table = String::from_utf8(table).unwrap();
+
+    let line = |name: &str| table.lines()
+                                 .find(|l| l.split_whitespace().next() == Some(name))
+                                 .unwrap()
+                                 .split_whitespace()
+                                 .skip(4)
+                                 .collect::<Vec<_>>()
+                                 .join(" ");
+
+    assert_eq!(line("kern.ipc.nmbclusters"), "?");
+    assert_eq!(line("kern.proc.pid.1234"), "struct kinfo_proc Process table entry");
+}